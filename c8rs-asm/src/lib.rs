@@ -0,0 +1,74 @@
+use std::{fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use clap::{Parser, ValueEnum};
+use clap_num::maybe_hex;
+
+pub use octo::assemble_octo;
+pub use program::{assemble_source, Program};
+
+mod octo;
+mod program;
+
+#[derive(Parser, Debug)]
+pub struct AssemblerArgs {
+    /// assembly source
+    file: String,
+
+    #[arg(long, value_enum)]
+    /// source dialect, defaults to Octo for .8o files and c8rs otherwise
+    syntax: Option<Syntax>,
+
+    #[arg(short, value_name = "FILE")]
+    /// ROM to write, defaults to the source with a .ch8 extension
+    output: Option<String>,
+
+    #[arg(long, value_name = "ADDR", default_value_t = 0x200, value_parser = maybe_hex::<u16>)]
+    /// address the ROM is loaded at, for resolving labels
+    base: u16,
+
+    #[arg(long, value_name = "FILE")]
+    /// write labels and source lines to FILE, for `c8rs run --source-map`
+    source_map: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Syntax {
+    /// the syntax `c8rs dis` emits
+    C8rs,
+    /// Octo (.8o) source
+    Octo,
+}
+
+pub fn assemble(args: AssemblerArgs) -> Result<()> {
+    let source = fs::read_to_string(&args.file)
+        .map_err(|err| anyhow!("Failed to read {}: {err}", args.file))?;
+
+    let syntax = args
+        .syntax
+        .unwrap_or_else(|| match Path::new(&args.file).extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("8o") => Syntax::Octo,
+            _ => Syntax::C8rs,
+        });
+    let program = match syntax {
+        Syntax::C8rs => assemble_source(&source, args.base),
+        Syntax::Octo => assemble_octo(&source, args.base),
+    }
+    .map_err(|err| anyhow!("{}: {err}", args.file))?;
+
+    let output = args.output.unwrap_or_else(|| {
+        Path::new(&args.file)
+            .with_extension("ch8")
+            .to_string_lossy()
+            .into_owned()
+    });
+    fs::write(&output, &program.rom).map_err(|err| anyhow!("Failed to write {output}: {err}"))?;
+
+    if let Some(path) = args.source_map {
+        let map = program.source_map(&args.file);
+        fs::write(&path, map.to_string())
+            .map_err(|err| anyhow!("Failed to write {path}: {err}"))?;
+    }
+
+    Ok(())
+}
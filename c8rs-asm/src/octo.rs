@@ -0,0 +1,875 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, bail, Result};
+use c8rs_core::{instructions::Register, Instruction};
+
+use crate::Program;
+
+/// Tokens a macro may expand to before giving up, recursive macros never
+/// finish
+const MAX_TOKENS: usize = 1_000_000;
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+/// Where a forward reference to a label is patched in
+#[derive(Debug, Clone, Copy)]
+enum Patch {
+    /// Low 12 bits of the opcode at the offset
+    Addr,
+    /// 16-bit word at the offset
+    Long,
+    /// `:unpack` high byte, `nibble << 4 | addr >> 8`
+    UnpackHigh { nibble: u8 },
+    /// `:unpack` low byte
+    UnpackLow,
+}
+
+struct Fixup {
+    offset: usize,
+    label: String,
+    patch: Patch,
+    line: usize,
+}
+
+/// Open `if ... begin` or `loop`
+enum Block {
+    /// Offset of the jump to the `else` or `end`, and whether `else` was seen
+    If { jump: usize, has_else: bool },
+    /// Start of the loop and the `while` jumps leaving it
+    Loop { start: u16, exits: Vec<usize> },
+}
+
+/// Condition of `if` and `while`, as the skips testing it
+#[derive(Debug, Clone, Copy)]
+struct Condition {
+    /// Skips the next instruction if the condition holds
+    skip_true: Instruction,
+    /// Skips the next instruction if it doesn't
+    skip_false: Instruction,
+}
+
+/// Assemble Octo (`.8o`) source to be loaded at `base`
+///
+/// Supports labels, `:const`, `:alias`, `:macro`, `:unpack`, `:org`,
+/// `:next`, `:call`, the `if`/`loop` control structures and every
+/// CHIP-8, SCHIP and XO-CHIP statement. `:calc` and the `<`/`>` comparisons
+/// aren't supported.
+pub fn assemble_octo(source: &str, base: u16) -> Result<Program> {
+    let mut tokens: Vec<Token> = source
+        .lines()
+        .enumerate()
+        .flat_map(|(n, line)| {
+            let code = line.split_once('#').map_or(line, |(code, _)| code);
+            code.split_whitespace().map(move |text| Token {
+                text: text.to_string(),
+                line: n + 1,
+            })
+        })
+        .collect();
+    tokens.reverse();
+
+    let mut asm = Assembler {
+        tokens,
+        expanded: 0,
+        base,
+        rom: Vec::new(),
+        here: 0,
+        labels: BTreeMap::new(),
+        lines: BTreeMap::new(),
+        consts: HashMap::new(),
+        aliases: HashMap::new(),
+        macros: HashMap::new(),
+        fixups: Vec::new(),
+        blocks: Vec::new(),
+        line: 0,
+    };
+
+    while !asm.tokens.is_empty() {
+        asm.statement()
+            .map_err(|err| anyhow!("line {}: {err}", asm.line))?;
+    }
+    if !asm.blocks.is_empty() {
+        bail!("unterminated if or loop at the end of the source");
+    }
+    asm.resolve()?;
+
+    Ok(Program {
+        rom: asm.rom,
+        labels: asm.labels,
+        lines: asm.lines,
+    })
+}
+
+struct Assembler {
+    /// Tokens left to assemble, last one first
+    tokens: Vec<Token>,
+    /// Tokens macros have expanded to so far
+    expanded: usize,
+    base: u16,
+    rom: Vec<u8>,
+    /// Offset the next byte is written at
+    here: usize,
+    labels: BTreeMap<String, u16>,
+    /// Source line of each instruction
+    lines: BTreeMap<u16, usize>,
+    consts: HashMap<String, u16>,
+    aliases: HashMap<String, Register>,
+    macros: HashMap<String, (Vec<String>, Vec<Token>)>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    /// Line of the last token read, for errors
+    line: usize,
+}
+
+impl Assembler {
+    fn next(&mut self) -> Result<String> {
+        let token = self
+            .tokens
+            .pop()
+            .ok_or(anyhow!("unexpected end of source"))?;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.last().map(|t| t.text.as_str())
+    }
+
+    fn expect(&mut self, expected: &str) -> Result<()> {
+        let token = self.next()?;
+        if token != expected {
+            bail!("expected '{expected}', found '{token}'");
+        }
+        Ok(())
+    }
+
+    fn addr(&self) -> u16 {
+        self.base.wrapping_add(self.here as u16)
+    }
+
+    fn emit_byte(&mut self, byte: u8) -> Result<()> {
+        if self.base as usize + self.here >= 0x10000 {
+            bail!("program doesn't fit in memory");
+        }
+        if self.here >= self.rom.len() {
+            self.rom.resize(self.here + 1, 0);
+        }
+        self.rom[self.here] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn emit(&mut self, instr: Instruction) -> Result<()> {
+        let [hi, lo] = instr.encode().to_be_bytes();
+        self.lines.insert(self.addr(), self.line);
+        self.emit_byte(hi)?;
+        self.emit_byte(lo)
+    }
+
+    fn statement(&mut self) -> Result<()> {
+        let token = self.next()?;
+
+        match token.as_str() {
+            ":" => {
+                let name = self.next()?;
+                self.define_label(name, self.addr())
+            }
+            ":next" => {
+                let name = self.next()?;
+                self.define_label(name, self.addr().wrapping_add(1))
+            }
+            ":const" => {
+                let name = self.next()?;
+                let value = self.next()?;
+                let value = self.value(&value)?;
+                self.consts.insert(name, value);
+                Ok(())
+            }
+            ":alias" => {
+                let name = self.next()?;
+                let reg = self.next()?;
+                let reg = self.register(&reg)?;
+                self.aliases.insert(name, reg);
+                Ok(())
+            }
+            ":org" => {
+                let addr = self.next()?;
+                let addr = self.value(&addr)?;
+                self.here = addr
+                    .checked_sub(self.base)
+                    .ok_or(anyhow!("{addr:#06X} is before the start of the ROM"))?
+                    as usize;
+                Ok(())
+            }
+            ":macro" => self.define_macro(),
+            ":unpack" => self.unpack(),
+            ":call" => {
+                let target = self.next()?;
+                self.jump(Instruction::Call { addr: 0 }, &target)
+            }
+            ":byte" => {
+                let value = self.next()?;
+                self.byte(&value)
+            }
+            // debugger directives, c8rs has its own breakpoints
+            ":breakpoint" => self.next().map(|_| ()),
+            ":monitor" => self.next().and_then(|_| self.next()).map(|_| ()),
+            ":calc" | ":stringmode" | ":assert" => bail!("{token} isn't supported"),
+
+            "clear" => self.emit(Instruction::Cls),
+            "return" | ";" => self.emit(Instruction::Ret),
+            "exit" => self.emit(Instruction::Exit),
+            "lores" => self.emit(Instruction::Lores),
+            "hires" => self.emit(Instruction::Hires),
+            "scroll-left" => self.emit(Instruction::ScrollLeft),
+            "scroll-right" => self.emit(Instruction::ScrollRight),
+            "audio" => self.emit(Instruction::Audio),
+            "scroll-down" | "scroll-up" | "plane" => {
+                let n = self.next()?;
+                let n = self.nibble(&n)?;
+                self.emit(match token.as_str() {
+                    "scroll-down" => Instruction::ScrollDown { n },
+                    "scroll-up" => Instruction::ScrollUp { n },
+                    _ => Instruction::Plane { mask: n },
+                })
+            }
+            "jump" => {
+                let target = self.next()?;
+                self.jump(Instruction::Jmp { addr: 0 }, &target)
+            }
+            "jump0" => {
+                let target = self.next()?;
+                self.jump(Instruction::JmpReg { addr: 0 }, &target)
+            }
+            "sprite" => {
+                let (x, y, n) = (self.next()?, self.next()?, self.next()?);
+                self.emit(Instruction::Drw {
+                    regx: self.register(&x)?,
+                    regy: self.register(&y)?,
+                    len: self.nibble(&n)?,
+                })
+            }
+            "bcd" | "saveflags" | "loadflags" => {
+                let reg = self.next()?;
+                let reg = self.register(&reg)?;
+                self.emit(match token.as_str() {
+                    "bcd" => Instruction::Bcd { reg },
+                    "saveflags" => Instruction::StoreFlags { reg },
+                    _ => Instruction::LoadFlags { reg },
+                })
+            }
+            "save" | "load" => self.save_load(token == "save"),
+            "i" => self.assign_i(),
+            "delay" | "buzzer" | "pitch" => {
+                self.expect(":=")?;
+                let reg = self.next()?;
+                let reg = self.register(&reg)?;
+                self.emit(match token.as_str() {
+                    "delay" => Instruction::SetDelayTimer { reg },
+                    "buzzer" => Instruction::SetSoundTimer { reg },
+                    _ => Instruction::Pitch { reg },
+                })
+            }
+            "if" => self.if_statement(),
+            "else" => self.else_statement(),
+            "end" => self.end_statement(),
+            "loop" => {
+                self.blocks.push(Block::Loop {
+                    start: self.addr(),
+                    exits: Vec::new(),
+                });
+                Ok(())
+            }
+            "while" => self.while_statement(),
+            "again" => self.again_statement(),
+            _ if self.is_register(&token) => self.register_statement(&token),
+            _ if self.macros.contains_key(&token) => self.expand_macro(&token),
+            _ if self.number(&token).is_some() || self.consts.contains_key(&token) => {
+                self.byte(&token)
+            }
+            // a bare name calls the subroutine it labels
+            _ if is_identifier(&token) => self.jump(Instruction::Call { addr: 0 }, &token),
+            _ => bail!("unexpected '{token}'"),
+        }
+    }
+
+    fn define_label(&mut self, name: String, addr: u16) -> Result<()> {
+        if !is_identifier(&name) {
+            bail!("invalid label name '{name}'");
+        }
+        if self.labels.insert(name.clone(), addr).is_some() {
+            bail!("label '{name}' is defined twice");
+        }
+        Ok(())
+    }
+
+    /// `:macro name args... { body }`
+    fn define_macro(&mut self) -> Result<()> {
+        let name = self.next()?;
+
+        let mut args = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token == "{" {
+                break;
+            }
+            args.push(token);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 0;
+        loop {
+            let token = self
+                .tokens
+                .pop()
+                .ok_or(anyhow!("unterminated macro '{name}'"))?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" if depth == 0 => break,
+                "}" => depth -= 1,
+                _ => (),
+            }
+            body.push(token);
+        }
+
+        self.macros.insert(name, (args, body));
+        Ok(())
+    }
+
+    /// Replace a macro invocation with its body, arguments substituted
+    fn expand_macro(&mut self, name: &str) -> Result<()> {
+        let (params, body) = self.macros[name].clone();
+
+        let mut args = HashMap::new();
+        for param in params {
+            let arg = self.next()?;
+            args.insert(param, arg);
+        }
+
+        let line = self.line;
+        let expansion: Vec<Token> = body
+            .into_iter()
+            .map(|token| Token {
+                text: args.get(&token.text).cloned().unwrap_or(token.text),
+                line,
+            })
+            .collect();
+
+        self.expanded += expansion.len();
+        if self.expanded > MAX_TOKENS {
+            bail!("macro '{name}' expands too much");
+        }
+        self.tokens.extend(expansion.into_iter().rev());
+        Ok(())
+    }
+
+    /// `:unpack nibble label` or `:unpack long label`, loading the address
+    /// into `v0` and `v1`
+    fn unpack(&mut self) -> Result<()> {
+        let kind = self.next()?;
+        let nibble = match kind.as_str() {
+            "long" => 0,
+            _ => self.nibble(&kind)?,
+        };
+        let target = self.next()?;
+
+        let patches = [
+            (Register::V0, Patch::UnpackHigh { nibble }),
+            (Register::V1, Patch::UnpackLow),
+        ];
+        for (reg, patch) in patches {
+            self.fixups.push(Fixup {
+                offset: self.here,
+                label: target.clone(),
+                patch,
+                line: self.line,
+            });
+            self.emit(Instruction::LdImm { reg, byte: 0 })?;
+        }
+        Ok(())
+    }
+
+    /// Emit `instr` with the address `target`, patched in later if it's a
+    /// label
+    fn jump(&mut self, instr: Instruction, target: &str) -> Result<()> {
+        let mut op = instr.encode();
+        match self.value(target) {
+            Ok(addr) if addr > 0xFFF => bail!("{addr:#06X} out of range (max 0xFFF)"),
+            Ok(addr) => op |= addr,
+            Err(_) if is_identifier(target) => self.fixups.push(Fixup {
+                offset: self.here,
+                label: target.to_string(),
+                patch: Patch::Addr,
+                line: self.line,
+            }),
+            Err(err) => return Err(err),
+        }
+
+        self.emit(Instruction::Unknown(op))
+    }
+
+    fn byte(&mut self, token: &str) -> Result<()> {
+        let byte = self.byte_value(token)?;
+        self.emit_byte(byte)
+    }
+
+    /// `save vx`, `save vx - vy` and the `load` equivalents
+    fn save_load(&mut self, save: bool) -> Result<()> {
+        let regx = self.next()?;
+        let regx = self.register(&regx)?;
+
+        if self.peek() != Some("-") {
+            return self.emit(match save {
+                true => Instruction::StoreRegs { reg: regx },
+                false => Instruction::LoadRegs { reg: regx },
+            });
+        }
+
+        self.next()?;
+        let regy = self.next()?;
+        let regy = self.register(&regy)?;
+        self.emit(match save {
+            true => Instruction::StoreRange { regx, regy },
+            false => Instruction::LoadRange { regx, regy },
+        })
+    }
+
+    /// `i := addr`, `i := long addr`, `i := hex vx`, `i := bighex vx` and
+    /// `i += vx`
+    fn assign_i(&mut self) -> Result<()> {
+        let op = self.next()?;
+        let operand = self.next()?;
+
+        match (op.as_str(), operand.as_str()) {
+            ("+=", reg) => {
+                let reg = self.register(reg)?;
+                self.emit(Instruction::AddI { reg })
+            }
+            (":=", "hex") => {
+                let reg = self.next()?;
+                let reg = self.register(&reg)?;
+                self.emit(Instruction::LdFont { reg })
+            }
+            (":=", "bighex") => {
+                let reg = self.next()?;
+                let reg = self.register(&reg)?;
+                self.emit(Instruction::LdHiFont { reg })
+            }
+            (":=", "long") => {
+                let target = self.next()?;
+                self.emit(Instruction::LdILong)?;
+                match self.value(&target) {
+                    Ok(addr) => {
+                        let [hi, lo] = addr.to_be_bytes();
+                        self.emit_byte(hi)?;
+                        self.emit_byte(lo)
+                    }
+                    Err(_) if is_identifier(&target) => {
+                        self.fixups.push(Fixup {
+                            offset: self.here,
+                            label: target,
+                            patch: Patch::Long,
+                            line: self.line,
+                        });
+                        self.emit_byte(0)?;
+                        self.emit_byte(0)
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+            (":=", target) => self.jump(Instruction::LdI { addr: 0 }, target),
+            _ => bail!("unexpected 'i {op}'"),
+        }
+    }
+
+    /// Statements starting with a register, e.g. `v0 += 1`
+    fn register_statement(&mut self, token: &str) -> Result<()> {
+        let regx = self.register(token)?;
+        let op = self.next()?;
+        let operand = self.next()?;
+
+        if op == ":=" {
+            let instr = match operand.as_str() {
+                "key" => Instruction::LdKey { reg: regx },
+                "delay" => Instruction::LdDelayTimer { reg: regx },
+                "random" => {
+                    let mask = self.next()?;
+                    Instruction::Rnd {
+                        reg: regx,
+                        byte: self.byte_value(&mask)?,
+                    }
+                }
+                _ if self.is_register(&operand) => Instruction::LdReg {
+                    regx,
+                    regy: self.register(&operand)?,
+                },
+                _ => Instruction::LdImm {
+                    reg: regx,
+                    byte: self.byte_value(&operand)?,
+                },
+            };
+            return self.emit(instr);
+        }
+
+        if !self.is_register(&operand) {
+            let byte = self.byte_value(&operand)?;
+            return self.emit(match op.as_str() {
+                "+=" => Instruction::AddImm { reg: regx, byte },
+                "-=" => Instruction::AddImm {
+                    reg: regx,
+                    byte: byte.wrapping_neg(),
+                },
+                _ => bail!("unexpected '{op} {operand}'"),
+            });
+        }
+
+        let regy = self.register(&operand)?;
+        self.emit(match op.as_str() {
+            "+=" => Instruction::AddReg { regx, regy },
+            "-=" => Instruction::SubReg { regx, regy },
+            "=-" => Instruction::SubN { regx, regy },
+            "|=" => Instruction::Or { regx, regy },
+            "&=" => Instruction::And { regx, regy },
+            "^=" => Instruction::Xor { regx, regy },
+            ">>=" => Instruction::Shr { regx, regy },
+            "<<=" => Instruction::Shl { regx, regy },
+            _ => bail!("unexpected '{op}'"),
+        })
+    }
+
+    /// `vx == n`, `vx != vy`, `vx key`, `vx -key`, ...
+    fn condition(&mut self) -> Result<Condition> {
+        let reg = self.next()?;
+        let reg = self.register(&reg)?;
+        let op = self.next()?;
+
+        let (eq, ne) = match op.as_str() {
+            "key" => {
+                let pressed = Instruction::SkipPressed { reg };
+                let not_pressed = Instruction::SkipNotPressed { reg };
+                return Ok(Condition {
+                    skip_true: pressed,
+                    skip_false: not_pressed,
+                });
+            }
+            "-key" => {
+                return Ok(Condition {
+                    skip_true: Instruction::SkipNotPressed { reg },
+                    skip_false: Instruction::SkipPressed { reg },
+                });
+            }
+            "==" | "!=" => {
+                let operand = self.next()?;
+                if self.is_register(&operand) {
+                    let regy = self.register(&operand)?;
+                    (
+                        Instruction::SkipEqReg { regx: reg, regy },
+                        Instruction::SkipNEqReg { regx: reg, regy },
+                    )
+                } else {
+                    let byte = self.byte_value(&operand)?;
+                    (
+                        Instruction::SkipEqImm { reg, byte },
+                        Instruction::SkipNEqImm { reg, byte },
+                    )
+                }
+            }
+            _ => bail!("unsupported condition '{op}'"),
+        };
+
+        Ok(match op.as_str() {
+            "==" => Condition {
+                skip_true: eq,
+                skip_false: ne,
+            },
+            _ => Condition {
+                skip_true: ne,
+                skip_false: eq,
+            },
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<()> {
+        let condition = self.condition()?;
+
+        match self.next()?.as_str() {
+            "then" => self.emit(condition.skip_false),
+            "begin" => {
+                // skip the jump past the body when the condition holds
+                self.emit(condition.skip_true)?;
+                self.blocks.push(Block::If {
+                    jump: self.here,
+                    has_else: false,
+                });
+                self.emit(Instruction::Jmp { addr: 0 })
+            }
+            token => bail!("expected 'then' or 'begin', found '{token}'"),
+        }
+    }
+
+    fn else_statement(&mut self) -> Result<()> {
+        let Some(Block::If {
+            jump,
+            has_else: false,
+        }) = self.blocks.pop()
+        else {
+            bail!("'else' without 'if ... begin'");
+        };
+
+        let else_jump = self.here;
+        self.emit(Instruction::Jmp { addr: 0 })?;
+        self.patch_jump(jump, self.addr())?;
+        self.blocks.push(Block::If {
+            jump: else_jump,
+            has_else: true,
+        });
+        Ok(())
+    }
+
+    fn end_statement(&mut self) -> Result<()> {
+        let Some(Block::If { jump, .. }) = self.blocks.pop() else {
+            bail!("'end' without 'if ... begin'");
+        };
+        self.patch_jump(jump, self.addr())
+    }
+
+    fn while_statement(&mut self) -> Result<()> {
+        let condition = self.condition()?;
+        self.emit(condition.skip_true)?;
+
+        let exit = self.here;
+        self.emit(Instruction::Jmp { addr: 0 })?;
+        match self.blocks.iter_mut().rev().find_map(|block| match block {
+            Block::Loop { exits, .. } => Some(exits),
+            Block::If { .. } => None,
+        }) {
+            Some(exits) => exits.push(exit),
+            None => bail!("'while' outside of a loop"),
+        }
+        Ok(())
+    }
+
+    fn again_statement(&mut self) -> Result<()> {
+        let Some(Block::Loop { start, exits }) = self.blocks.pop() else {
+            bail!("'again' without 'loop'");
+        };
+
+        self.emit(Instruction::Jmp { addr: start })?;
+        for exit in exits {
+            self.patch_jump(exit, self.addr())?;
+        }
+        Ok(())
+    }
+
+    fn patch_jump(&mut self, offset: usize, addr: u16) -> Result<()> {
+        if addr > 0xFFF {
+            bail!("{addr:#06X} out of range (max 0xFFF)");
+        }
+        self.rom[offset] |= (addr >> 8) as u8;
+        self.rom[offset + 1] = addr as u8;
+        Ok(())
+    }
+
+    /// Patch in the labels that were referenced before being defined
+    fn resolve(&mut self) -> Result<()> {
+        for fixup in &self.fixups {
+            let err = |msg: String| anyhow!("line {}: {msg}", fixup.line);
+            let addr = *self
+                .labels
+                .get(&fixup.label)
+                .ok_or_else(|| err(format!("unknown label '{}'", fixup.label)))?;
+
+            let (offset, [hi, lo]) = (fixup.offset, addr.to_be_bytes());
+            match fixup.patch {
+                Patch::Addr if addr > 0xFFF => {
+                    return Err(err(format!("{addr:#06X} out of range (max 0xFFF)")));
+                }
+                Patch::Addr => {
+                    self.rom[offset] |= hi;
+                    self.rom[offset + 1] = lo;
+                }
+                Patch::Long => {
+                    self.rom[offset] = hi;
+                    self.rom[offset + 1] = lo;
+                }
+                Patch::UnpackHigh { nibble } => self.rom[offset + 1] = (nibble << 4) | (hi & 0xF),
+                Patch::UnpackLow => self.rom[offset + 1] = lo,
+            }
+        }
+        Ok(())
+    }
+
+    fn is_register(&self, token: &str) -> bool {
+        self.register(token).is_ok()
+    }
+
+    fn register(&self, token: &str) -> Result<Register> {
+        if let Some(reg) = self.aliases.get(token) {
+            return Ok(*reg);
+        }
+        token.parse().map_err(|err: String| anyhow!(err))
+    }
+
+    /// Value of a number, constant or already defined label
+    fn value(&self, token: &str) -> Result<u16> {
+        self.number(token)
+            .or_else(|| self.consts.get(token).copied())
+            .or_else(|| self.labels.get(token).copied())
+            .ok_or_else(|| anyhow!("invalid value '{token}'"))
+    }
+
+    fn byte_value(&self, token: &str) -> Result<u8> {
+        let value = self.value(token)?;
+        // negative numbers are two's complement bytes
+        if value > 0xFF && value < 0xFF80 {
+            bail!("{token} doesn't fit in a byte");
+        }
+        Ok(value as u8)
+    }
+
+    fn nibble(&self, token: &str) -> Result<u8> {
+        match self.value(token)? {
+            value @ 0..=0xF => Ok(value as u8),
+            value => bail!("{value:#X} out of range (max 0xF)"),
+        }
+    }
+
+    /// `0x` hex, `0b` binary or decimal numbers, negative ones wrap
+    fn number(&self, token: &str) -> Option<u16> {
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+
+        let value = if let Some(hex) = digits.strip_prefix("0x") {
+            u16::from_str_radix(hex, 16).ok()?
+        } else if let Some(bin) = digits.strip_prefix("0b") {
+            u16::from_str_radix(bin, 2).ok()?
+        } else {
+            digits.parse().ok()?
+        };
+
+        Some(if negative {
+            value.wrapping_neg()
+        } else {
+            value
+        })
+    }
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assemble(source: &str) -> Result<Vec<u8>> {
+        assemble_octo(source, 0x200).map(|program| program.rom)
+    }
+
+    fn error(source: &str) -> String {
+        assemble(source).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_if_then() {
+        assert_eq!(
+            assemble("if v0 == 5 then v1 := 1").unwrap(),
+            [0x40, 0x05, 0x61, 0x01]
+        );
+        assert_eq!(
+            assemble("if v0 -key then v1 := 1").unwrap(),
+            [0xE0, 0x9E, 0x61, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_if_begin() {
+        assert_eq!(
+            assemble("if v0 != v1 begin v2 := 1 end").unwrap(),
+            [0x90, 0x10, 0x12, 0x06, 0x62, 0x01]
+        );
+        assert_eq!(
+            assemble("if v0 != v1 begin v2 := 1 else v2 := 2 end").unwrap(),
+            [0x90, 0x10, 0x12, 0x08, 0x62, 0x01, 0x12, 0x0A, 0x62, 0x02]
+        );
+
+        assert_eq!(error("else"), "line 1: 'else' without 'if ... begin'");
+        assert_eq!(
+            error("if v0 == 1 begin clear"),
+            "unterminated if or loop at the end of the source"
+        );
+    }
+
+    #[test]
+    fn test_loop() {
+        assert_eq!(
+            assemble("loop v0 += 1 while v0 != 10 again").unwrap(),
+            [0x70, 0x01, 0x40, 0x0A, 0x12, 0x08, 0x12, 0x00]
+        );
+        assert_eq!(error("while v0 == 1"), "line 1: 'while' outside of a loop");
+        assert_eq!(error("again"), "line 1: 'again' without 'loop'");
+    }
+
+    #[test]
+    fn test_unpack() {
+        assert_eq!(
+            assemble(":unpack 0xA data\n: data 0x12 0x34").unwrap(),
+            [0x60, 0xA2, 0x61, 0x04, 0x12, 0x34]
+        );
+        assert_eq!(
+            assemble(":unpack long data\n: data 0x12 0x34").unwrap(),
+            [0x60, 0x02, 0x61, 0x04, 0x12, 0x34]
+        );
+    }
+
+    #[test]
+    fn test_macro() {
+        assert_eq!(
+            assemble(":macro add2 reg { reg += 2 }\nadd2 v3 add2 v4").unwrap(),
+            [0x73, 0x02, 0x74, 0x02]
+        );
+        assert_eq!(
+            error(":macro forever { forever forever forever forever }\nforever"),
+            "line 2: macro 'forever' expands too much"
+        );
+        assert_eq!(
+            error(":macro open { clear"),
+            "line 1: unterminated macro 'open'"
+        );
+    }
+
+    #[test]
+    fn test_const_alias() {
+        assert_eq!(
+            assemble(":const SPEED 3\n:alias score v5\nv0 := SPEED score += 1").unwrap(),
+            [0x60, 0x03, 0x75, 0x01]
+        );
+    }
+
+    #[test]
+    fn test_org() {
+        let rom = assemble("jump start\n:org 0x210\n: start clear").unwrap();
+        assert_eq!(rom.len(), 0x12);
+        assert_eq!(rom[..2], [0x12, 0x10]);
+        assert_eq!(rom[0x10..], [0x00, 0xE0]);
+
+        assert_eq!(
+            error(":org 0x100"),
+            "line 1: 0x0100 is before the start of the ROM"
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(error(":calc x { 1 + 2 }"), "line 1: :calc isn't supported");
+    }
+}
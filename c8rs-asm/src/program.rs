@@ -0,0 +1,386 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use c8rs_core::{instructions::Register, Instruction, SourceMap};
+
+/// Operands that can't be label names
+const KEYWORDS: [&str; 8] = ["I", "DT", "ST", "K", "F", "HF", "R", "LONG"];
+
+/// Assembled ROM
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub rom: Vec<u8>,
+    /// Address of each label
+    pub labels: BTreeMap<String, u16>,
+    /// Source line of each instruction
+    pub lines: BTreeMap<u16, usize>,
+}
+
+impl Program {
+    /// Labels and lines for the debugger, assembled from `file`
+    pub fn source_map(&self, file: &str) -> SourceMap {
+        let mut map = SourceMap::new();
+        map.file = Some(file.to_string());
+        for (name, addr) in &self.labels {
+            map.add_label(name, *addr);
+        }
+        for (addr, line) in &self.lines {
+            map.add_line(*addr, *line);
+        }
+        map
+    }
+}
+
+/// A line of source that emits bytes
+struct Statement<'a> {
+    line: usize,
+    kind: StatementKind<'a>,
+}
+
+enum StatementKind<'a> {
+    /// `db 0x12, 0x34`
+    Bytes(Vec<&'a str>),
+    /// `dw 0x1234, label`
+    Words(Vec<&'a str>),
+    /// `sprite ..####..`
+    Sprite(Vec<u8>),
+    Instruction(&'a str),
+}
+
+/// Assemble `source` to be loaded at `base`
+///
+/// Lines hold an optional `label:`, followed by an instruction in the syntax
+/// of [`Instruction`]'s `Display`, a `db` list of bytes, a `dw` list of
+/// big-endian words or a `sprite` row of `.` and `#` pixels. Labels can be
+/// used wherever an address is expected, `;` starts a comment and the
+/// `0x0200|` address column of `c8rs dis` output is ignored.
+pub fn assemble_source(source: &str, base: u16) -> Result<Program> {
+    let mut labels = BTreeMap::new();
+    let mut statements = Vec::new();
+
+    // first pass: find the address of every label
+    let mut addr = base as usize;
+    for (n, line) in source.lines().enumerate() {
+        let line_no = n + 1;
+        let mut line = strip_address(strip_comment(line)).trim();
+
+        while let Some((label, rest)) = split_label(line) {
+            if labels.insert(label.to_string(), addr as u16).is_some() {
+                bail!("line {line_no}: label '{label}' is defined twice");
+            }
+            line = rest.trim();
+        }
+        if line.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, operands) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let list = || operands.split(',').map(str::trim).collect();
+        let kind = match mnemonic.to_ascii_lowercase().as_str() {
+            "db" => StatementKind::Bytes(list()),
+            "dw" => StatementKind::Words(list()),
+            "sprite" => StatementKind::Sprite(
+                sprite_row(operands.trim()).map_err(|err| anyhow!("line {line_no}: {err}"))?,
+            ),
+            _ => StatementKind::Instruction(line),
+        };
+
+        addr += match &kind {
+            StatementKind::Bytes(bytes) => bytes.len(),
+            StatementKind::Words(words) => words.len() * 2,
+            StatementKind::Sprite(bytes) => bytes.len(),
+            StatementKind::Instruction(line) if is_long(line) => 4,
+            StatementKind::Instruction(_) => 2,
+        };
+        if addr > 0x10000 {
+            bail!("line {line_no}: program doesn't fit in memory");
+        }
+
+        statements.push(Statement {
+            line: line_no,
+            kind,
+        });
+    }
+
+    // second pass: emit bytes with labels resolved
+    let mut rom = Vec::new();
+    let mut lines = BTreeMap::new();
+    for statement in statements {
+        let line = statement.line;
+        let err = |err| anyhow!("line {line}: {err}");
+
+        match statement.kind {
+            StatementKind::Bytes(bytes) => {
+                for byte in bytes {
+                    let value = resolve(byte, &labels).map_err(err)?;
+                    let value = u8::try_from(value)
+                        .map_err(|_| anyhow!("line {line}: {value:#X} doesn't fit in a byte"))?;
+                    rom.push(value);
+                }
+            }
+            StatementKind::Words(words) => {
+                for word in words {
+                    rom.extend(resolve(word, &labels).map_err(err)?.to_be_bytes());
+                }
+            }
+            StatementKind::Sprite(bytes) => rom.extend(bytes),
+            StatementKind::Instruction(text) => {
+                let text = substitute_labels(text, &labels).map_err(err)?;
+                let (instr, long) = parse_instruction(&text).map_err(err)?;
+                lines.insert(base.wrapping_add(rom.len() as u16), line);
+                rom.extend(instr.encode().to_be_bytes());
+                if let Some(long) = long {
+                    rom.extend(long.to_be_bytes());
+                }
+            }
+        }
+    }
+
+    Ok(Program { rom, labels, lines })
+}
+
+fn strip_comment(line: &str) -> &str {
+    line.split_once(';').map_or(line, |(code, _)| code)
+}
+
+/// Drop the `0x0200|` column of `c8rs dis` output
+fn strip_address(line: &str) -> &str {
+    match line.split_once('|') {
+        Some((addr, rest)) if parse_number(addr.trim()).is_ok() => rest,
+        _ => line,
+    }
+}
+
+/// Split `label: rest` into its parts
+fn split_label(line: &str) -> Option<(&str, &str)> {
+    let (label, rest) = line.split_once(':')?;
+    is_identifier(label).then_some((label, rest))
+}
+
+fn is_identifier(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !is_keyword(s)
+}
+
+/// Registers and special operands
+fn is_keyword(s: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(s)) || s.parse::<Register>().is_ok()
+}
+
+/// Whether `line` is the 4-byte `LD I, LONG nnnn`
+fn is_long(line: &str) -> bool {
+    line.split([' ', ','])
+        .any(|word| word.eq_ignore_ascii_case("LONG"))
+}
+
+/// Replace label names in the operands of `text` with their addresses
+fn substitute_labels(text: &str, labels: &BTreeMap<String, u16>) -> Result<String, String> {
+    let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+    let operands = operands
+        .split(',')
+        .map(str::trim)
+        .filter(|operand| !operand.is_empty())
+        .map(|operand| {
+            let words = operand
+                .split_whitespace()
+                .map(|word| match labels.get(word) {
+                    Some(addr) => Ok(format!("{addr:#06X}")),
+                    None if is_identifier(word) => Err(format!("unknown label '{word}'")),
+                    None => Ok(word.to_string()),
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            Ok(words.join(" "))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(format!("{mnemonic} {}", operands.join(", ")))
+}
+
+/// Parse an instruction, with the address following `F000` for
+/// `LD I, LONG nnnn`
+fn parse_instruction(text: &str) -> Result<(Instruction, Option<u16>), String> {
+    if let Some(at) = text.to_ascii_uppercase().find("LONG") {
+        let at = at + "LONG".len();
+        let addr = parse_number(text[at..].trim())?;
+        return Ok((text[..at].parse()?, Some(addr)));
+    }
+
+    Ok((text.parse()?, None))
+}
+
+/// Bytes of a row of `#` (lit) and `.` (unlit) pixels, 8 pixels per byte
+/// with the last byte padded with unlit pixels
+fn sprite_row(row: &str) -> Result<Vec<u8>, String> {
+    if row.is_empty() {
+        return Err("expected a row of '.' and '#' pixels".to_string());
+    }
+
+    let pixels = row
+        .chars()
+        .map(|c| match c {
+            '#' => Ok(true),
+            '.' => Ok(false),
+            _ => Err(format!("invalid sprite pixel '{c}'")),
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(pixels
+        .chunks(8)
+        .map(|bits| {
+            bits.iter()
+                .enumerate()
+                .fold(0u8, |byte, (n, &bit)| byte | ((bit as u8) << (7 - n)))
+        })
+        .collect())
+}
+
+/// Value of a number or label
+fn resolve(s: &str, labels: &BTreeMap<String, u16>) -> Result<u16, String> {
+    match labels.get(s) {
+        Some(addr) => Ok(*addr),
+        None => parse_number(s),
+    }
+}
+
+/// Parse a `0x` prefixed hex or a decimal number
+fn parse_number(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid number: {s}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c8rs_disasm::{disassemble, DisassemblerArgs};
+    use clap::Parser;
+    use std::path::Path;
+
+    fn assemble(source: &str) -> Result<Vec<u8>> {
+        assemble_source(source, 0x200).map(|program| program.rom)
+    }
+
+    #[test]
+    fn test_labels() {
+        let program = assemble_source(
+            "start: CALL draw ; forward reference\n\
+             loop: JMP loop\n\
+             draw:\n\
+             RET\n",
+            0x200,
+        )
+        .unwrap();
+
+        assert_eq!(program.rom, [0x22, 0x04, 0x12, 0x02, 0x00, 0xEE]);
+        assert_eq!(program.labels["start"], 0x200);
+        assert_eq!(program.labels["loop"], 0x202);
+        assert_eq!(program.labels["draw"], 0x204);
+        assert_eq!(program.lines[&0x204], 4);
+    }
+
+    #[test]
+    fn test_label_errors() {
+        let err = assemble("a: CLS\nb: a: RET").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: label 'a' is defined twice");
+
+        let err = assemble("CLS\nJMP nowhere").unwrap_err();
+        assert_eq!(err.to_string(), "line 2: unknown label 'nowhere'");
+    }
+
+    #[test]
+    fn test_address_column() {
+        let rom = assemble("0x0200| LD V0, 0x12\n0x0202|   JMP 0x0200 ; loop\n").unwrap();
+        assert_eq!(rom, [0x60, 0x12, 0x12, 0x00]);
+    }
+
+    #[test]
+    fn test_long() {
+        let program =
+            assemble_source("LD I, LONG 0x1234\nlabel: CLS\nLD I, LONG label", 0x200).unwrap();
+        assert_eq!(
+            program.rom,
+            [0xF0, 0x00, 0x12, 0x34, 0x00, 0xE0, 0xF0, 0x00, 0x02, 0x04]
+        );
+        assert_eq!(program.labels["label"], 0x204);
+    }
+
+    #[test]
+    fn test_db() {
+        assert_eq!(assemble("db 0x12, 255, 0").unwrap(), [0x12, 0xFF, 0x00]);
+        assert_eq!(
+            assemble("CLS\ndb 0x12, 0x100").unwrap_err().to_string(),
+            "line 2: 0x100 doesn't fit in a byte"
+        );
+        // labels past 0xFF don't fit either
+        assert_eq!(
+            assemble("here: db here").unwrap_err().to_string(),
+            "line 1: 0x200 doesn't fit in a byte"
+        );
+    }
+
+    #[test]
+    fn test_dw() {
+        let rom = assemble("dw 0x1234, table, end\ntable: db 1\nend:").unwrap();
+        assert_eq!(rom, [0x12, 0x34, 0x02, 0x06, 0x02, 0x07, 0x01]);
+        assert_eq!(
+            assemble("dw missing").unwrap_err().to_string(),
+            "line 1: invalid number: missing"
+        );
+    }
+
+    #[test]
+    fn test_sprite() {
+        let program = assemble_source(
+            "sprite #..##..#\nsprite ###\nsprite #........#\nafter: JMP after",
+            0x200,
+        )
+        .unwrap();
+        // rows are padded to whole bytes with unlit pixels
+        assert_eq!(program.rom, [0x99, 0xE0, 0x80, 0x40, 0x12, 0x04]);
+        assert_eq!(program.labels["after"], 0x204);
+    }
+
+    #[test]
+    fn test_sprite_errors() {
+        assert_eq!(
+            assemble("sprite ..xx..").unwrap_err().to_string(),
+            "line 1: invalid sprite pixel 'x'"
+        );
+        assert_eq!(
+            assemble("CLS\nsprite").unwrap_err().to_string(),
+            "line 2: expected a row of '.' and '#' pixels"
+        );
+    }
+
+    #[test]
+    fn test_disassembly_round_trip() {
+        let roms = Path::new(env!("CARGO_MANIFEST_DIR")).join("../roms");
+        let out = std::env::temp_dir().join(format!("c8rs-test-{}.asm", std::process::id()));
+
+        let mut tested = 0;
+        for entry in std::fs::read_dir(roms).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "ch8") {
+                continue;
+            }
+
+            let rom = std::fs::read(&path).unwrap();
+            let args = DisassemblerArgs::parse_from([
+                "dis",
+                path.to_str().unwrap(),
+                "-o",
+                out.to_str().unwrap(),
+            ]);
+            disassemble(args, |_| Ok(rom.clone())).unwrap();
+
+            let source = std::fs::read_to_string(&out).unwrap();
+            assert_eq!(assemble(&source).unwrap(), rom, "{}", path.display());
+            tested += 1;
+        }
+        assert!(tested > 0);
+    }
+}
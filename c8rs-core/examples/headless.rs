@@ -0,0 +1,38 @@
+//! Run a ROM without any frontend and print the display as text.
+//!
+//! ```sh
+//! cargo run -p c8rs-core --example headless -- roms/test_opcode.ch8 60
+//! ```
+
+use std::env;
+
+use c8rs_core::Chip8;
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let Some(path) = args.next() else {
+        eprintln!("usage: headless <rom> [frames]");
+        return;
+    };
+    let frames = args.next().and_then(|s| s.parse().ok()).unwrap_or(60);
+
+    let rom = std::fs::read(&path).expect("failed to read ROM");
+    let mut chip8 = Chip8::new(&rom);
+
+    let mut halted = false;
+    for _ in 0..frames {
+        if chip8.frame() {
+            halted = true;
+            break;
+        }
+    }
+
+    let display = chip8.display();
+    let (width, _) = display.get_dimensions();
+    for row in display.get_pixels().chunks(width) {
+        let line: String = row.iter().map(|p| if *p { '█' } else { ' ' }).collect();
+        println!("{line}");
+    }
+
+    println!("halted: {halted}, PC: {:#06X}", chip8.cpu().pc);
+}
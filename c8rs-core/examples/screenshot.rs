@@ -0,0 +1,54 @@
+//! Run each ROM for a fixed number of frames and save the display as a PBM
+//! image.
+//!
+//! ```sh
+//! cargo run -p c8rs-core --example screenshot -- roms/*.ch8
+//! ```
+
+use std::path::Path;
+
+use c8rs_core::Chip8;
+
+const FRAMES: usize = 120;
+
+fn main() {
+    let roms: Vec<String> = std::env::args().skip(1).collect();
+    if roms.is_empty() {
+        eprintln!("usage: screenshot <rom>...");
+        return;
+    }
+
+    for path in roms {
+        let rom = match std::fs::read(&path) {
+            Ok(rom) => rom,
+            Err(err) => {
+                eprintln!("{path}: {err}");
+                continue;
+            }
+        };
+
+        let mut chip8 = Chip8::new(&rom);
+        for _ in 0..FRAMES {
+            if chip8.frame() {
+                break;
+            }
+        }
+
+        let display = chip8.display();
+        let (width, height) = display.get_dimensions();
+
+        let mut pbm = format!("P1\n{width} {height}\n");
+        for row in display.get_pixels().chunks(width) {
+            for pixel in row {
+                pbm.push(if *pixel { '1' } else { '0' });
+            }
+            pbm.push('\n');
+        }
+
+        let out = Path::new(&path).with_extension("pbm");
+        match std::fs::write(&out, pbm) {
+            Ok(()) => println!("{path} -> {}", out.display()),
+            Err(err) => eprintln!("{}: {err}", out.display()),
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Minimal windowed frontend using minifb.
+//!
+//! ```sh
+//! cargo run -p c8rs-core --example window -- roms/test_opcode.ch8
+//! ```
+
+use c8rs_core::{chip8::TIMER_HZ, Chip8};
+use minifb::{Key, Window, WindowOptions};
+
+const SCALE: usize = 10;
+
+fn main() {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: window <rom>");
+        return;
+    };
+
+    let rom = std::fs::read(&path).expect("failed to read ROM");
+    let mut chip8 = Chip8::new(&rom);
+
+    let (width, height) = chip8.display().get_dimensions();
+    let mut window = Window::new(
+        &format!("c8rs - {path}"),
+        width * SCALE,
+        height * SCALE,
+        WindowOptions::default(),
+    )
+    .expect("failed to open window");
+    window.set_target_fps(TIMER_HZ as usize);
+
+    let mut buffer = vec![0u32; width * height];
+    let mut halted = false;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if !halted {
+            halted = chip8.frame();
+        }
+
+        for (dst, pixel) in buffer.iter_mut().zip(chip8.display().get_pixels()) {
+            *dst = if pixel { 0xFFFFFF } else { 0x000000 };
+        }
+
+        window
+            .update_with_buffer(&buffer, width, height)
+            .expect("failed to update window");
+    }
+}
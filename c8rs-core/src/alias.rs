@@ -0,0 +1,163 @@
+use std::collections::BTreeMap;
+
+use clap::CommandFactory;
+
+use crate::DebugCommand;
+
+/// How deep aliases may expand into other aliases
+const MAX_DEPTH: usize = 8;
+
+/// User-defined debugger commands, e.g. `bp200 = break 0x200; continue`
+///
+/// Aliases expand to one or more `;` separated commands before parsing,
+/// arguments after the alias name are appended to the last one.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Aliases {
+    aliases: BTreeMap<String, String>,
+}
+
+impl Aliases {
+    pub fn new() -> Aliases {
+        Aliases::default()
+    }
+
+    pub fn define(&mut self, name: &str, expansion: &str) -> Result<(), String> {
+        if DebugCommand::command().find_subcommand(name).is_some() {
+            return Err(format!("'{name}' is a built-in command"));
+        }
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("invalid alias name '{name}'"));
+        }
+
+        self.aliases
+            .insert(name.to_string(), expansion.trim().to_string());
+        Ok(())
+    }
+
+    /// Returns whether the alias existed
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.aliases.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.aliases.get(name).map(String::as_str)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.aliases
+            .iter()
+            .map(|(name, expansion)| (name.as_str(), expansion.as_str()))
+    }
+
+    /// Parse `name = expansion` lines, as written by the `Display` impl
+    pub fn from_lines(s: &str) -> Result<Aliases, String> {
+        let mut aliases = Aliases::new();
+
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, expansion) = line
+                .split_once('=')
+                .ok_or(format!("line {}: expected 'name = commands'", n + 1))?;
+            aliases
+                .define(name.trim(), expansion)
+                .map_err(|err| format!("line {}: {err}", n + 1))?;
+        }
+
+        Ok(aliases)
+    }
+
+    /// Expand aliases in `line` and parse the resulting commands
+    pub fn parse(&self, line: &str) -> Result<Vec<DebugCommand>, String> {
+        self.parse_depth(line, 0)
+    }
+
+    fn parse_depth(&self, line: &str, depth: usize) -> Result<Vec<DebugCommand>, String> {
+        let line = line.trim();
+        let (name, args) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+
+        let Some(expansion) = self.aliases.get(name) else {
+            return DebugCommand::parse_from(line).map(|cmd| vec![cmd]);
+        };
+
+        if depth >= MAX_DEPTH {
+            return Err(format!("alias '{name}' expands too deeply"));
+        }
+
+        let mut lines: Vec<String> = expansion.split(';').map(str::to_string).collect();
+        if let Some(last) = lines.last_mut() {
+            last.push(' ');
+            last.push_str(args);
+        }
+
+        let mut cmds = Vec::new();
+        for line in lines {
+            cmds.extend(self.parse_depth(&line, depth + 1)?);
+        }
+
+        Ok(cmds)
+    }
+}
+
+impl std::fmt::Display for Aliases {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, expansion) in self.iter() {
+            writeln!(f, "{name} = {expansion}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Expr;
+
+    #[test]
+    fn test_expand() {
+        let mut aliases = Aliases::new();
+        aliases.define("bp200", "break 0x200; continue").unwrap();
+        aliases.define("go", "bp200").unwrap();
+        aliases.define("sn", "step").unwrap();
+
+        let bp200 = vec![
+            DebugCommand::Breakpoint {
+                addr: Expr::Num(0x200),
+                condition: None,
+            },
+            DebugCommand::Continue,
+        ];
+        assert_eq!(aliases.parse("bp200"), Ok(bp200.clone()));
+        assert_eq!(aliases.parse("go"), Ok(bp200));
+        assert_eq!(
+            aliases.parse("sn 10"),
+            Ok(vec![DebugCommand::Step { count: 10 }])
+        );
+        assert_eq!(aliases.parse("c"), Ok(vec![DebugCommand::Continue]));
+    }
+
+    #[test]
+    fn test_define_errors() {
+        let mut aliases = Aliases::new();
+        assert!(aliases.define("break", "continue").is_err());
+        assert!(aliases.define("b", "continue").is_err());
+        assert!(aliases.define("a b", "continue").is_err());
+
+        aliases.define("loop", "loop").unwrap();
+        assert!(aliases.parse("loop").is_err());
+    }
+
+    #[test]
+    fn test_lines_round_trip() {
+        let aliases =
+            Aliases::from_lines("# mine\nbp200 = break 0x200; continue\nsn=step\n").unwrap();
+        assert_eq!(aliases.get("sn"), Some("step"));
+        assert_eq!(Aliases::from_lines(&aliases.to_string()), Ok(aliases));
+
+        assert!(Aliases::from_lines("bp200").is_err());
+    }
+}
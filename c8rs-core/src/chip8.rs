@@ -0,0 +1,343 @@
+use std::{
+    ops::RangeInclusive,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use crate::{display::Display, memory::MEM_SIZE, memory_map::MemoryMap, Cpu, Memory};
+
+/// Rate at which the delay and sound timers count down
+pub const TIMER_HZ: u32 = 60;
+
+/// Handler for an opcode the core doesn't implement, called with the raw
+/// opcode and `pc` already pointing at the next instruction
+pub type OpcodeHandler = Box<dyn FnMut(&mut Cpu, u16) + Send>;
+
+struct OpcodeHook {
+    opcodes: RangeInclusive<u16>,
+    handler: OpcodeHandler,
+}
+
+impl std::fmt::Debug for OpcodeHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpcodeHook")
+            .field("opcodes", &self.opcodes)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Single-threaded CHIP-8 machine driven by the caller's own loop
+///
+/// Frontends either call [`Chip8::frame`] once per 60 Hz frame, or step
+/// instructions and tick timers manually with [`Chip8::step`] and
+/// [`Chip8::tick_timers`].
+///
+/// The display is double buffered, [`Chip8::display`] only changes when a
+/// completed frame is presented so sprites are never seen half drawn.
+#[derive(Debug)]
+pub struct Chip8 {
+    cpu: Cpu,
+    /// Program as loaded, for [`Chip8::reset`]
+    rom: Box<[u8]>,
+    /// Shared with [`EmulatorController`](crate::EmulatorController)s,
+    /// which read it while the machine runs on another thread
+    presented: Arc<Mutex<Display>>,
+    ips: u32,
+    /// `ips` added every frame, a frame runs an instruction per 60 of it
+    /// and carries the rest over so slow machines still hit their IPS
+    instruction_credit: u64,
+    hooks: Vec<OpcodeHook>,
+}
+
+impl Chip8 {
+    pub fn new(rom: &[u8]) -> Chip8 {
+        Chip8::with_mem_size(rom, MEM_SIZE)
+    }
+
+    pub fn with_mem_size(rom: &[u8], mem_size: usize) -> Chip8 {
+        let cpu = Cpu::boot(Memory::init_with_size(mem_size, rom), rom.len());
+
+        Chip8 {
+            presented: Arc::new(Mutex::new(cpu.display.clone())),
+            cpu,
+            rom: rom.into(),
+            ips: 700,
+            instruction_credit: 0,
+            hooks: Vec::new(),
+        }
+    }
+
+    /// Handle unimplemented opcodes in `opcodes` (e.g. `0x0000..=0x0FFF` for
+    /// machine code calls) with `handler` instead of ignoring them. Opcodes
+    /// the core implements never reach a hook, the first matching hook wins.
+    pub fn add_opcode_hook(
+        &mut self,
+        opcodes: RangeInclusive<u16>,
+        handler: impl FnMut(&mut Cpu, u16) + Send + 'static,
+    ) {
+        self.hooks.push(OpcodeHook {
+            opcodes,
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Execute a single instruction, returns `true` if the CPU halted or
+    /// faulted
+    pub fn step(&mut self) -> bool {
+        if self.run_hook() {
+            return self.cpu.fault.is_some();
+        }
+
+        self.cpu.step()
+    }
+
+    /// Run the hook for the current opcode, returns `false` if there is none
+    fn run_hook(&mut self) -> bool {
+        if self.hooks.is_empty() || self.cpu.fault.is_some() {
+            return false;
+        }
+
+        let op = self.cpu.mem.read_u16(self.cpu.pc);
+        if !self.cpu.is_unhandled(op) {
+            return false;
+        }
+
+        let Some(hook) = self.hooks.iter_mut().find(|h| h.opcodes.contains(&op)) else {
+            return false;
+        };
+
+        self.cpu.code_write = None;
+        self.cpu.mark_executed(self.cpu.pc);
+        self.cpu.pc = self.cpu.pc.wrapping_add(2);
+        (hook.handler)(&mut self.cpu, op);
+        true
+    }
+
+    /// Count the delay and sound timers down by one tick
+    pub fn tick_timers(&mut self) {
+        self.cpu.delay_timer = self.cpu.delay_timer.saturating_sub(1);
+        self.cpu.sound_timer = self.cpu.sound_timer.saturating_sub(1);
+    }
+
+    /// Run one 60 Hz frame: `ips / 60` instructions followed by a timer tick,
+    /// then present the display. Below 60 IPS only some frames run an
+    /// instruction. Returns `true` if the CPU halted or faulted during the
+    /// frame.
+    pub fn frame(&mut self) -> bool {
+        self.instruction_credit += self.ips as u64;
+        let count = self.instruction_credit / TIMER_HZ as u64;
+        self.instruction_credit %= TIMER_HZ as u64;
+
+        for _ in 0..count {
+            if self.step() {
+                self.present();
+                return true;
+            }
+        }
+
+        self.tick_timers();
+        self.present();
+
+        false
+    }
+
+    /// Make the current contents of the display visible through
+    /// [`Chip8::display`]
+    pub fn present(&mut self) {
+        self.presented.lock().unwrap().clone_from(&self.cpu.display);
+    }
+
+    /// First address that had already been executed and was overwritten by
+    /// the last instruction, for detecting self-modifying code
+    pub fn last_code_write(&self) -> Option<u16> {
+        self.cpu.code_write
+    }
+
+    /// Replace the program with `rom` and reset, keeping the IPS, opcode
+    /// hooks, quirks and memory protection
+    pub fn load_rom(&mut self, rom: &[u8]) {
+        self.rom = rom.into();
+        self.reset();
+    }
+
+    /// Restore a state saved from [`Chip8::cpu`]
+    pub fn restore(&mut self, cpu: Cpu) {
+        self.cpu.restore(cpu);
+        self.present();
+        self.instruction_credit = 0;
+    }
+
+    /// Power cycle: reload the original ROM and clear registers, timers,
+    /// the keypad and the display. RPL flags, quirks, memory protection and
+    /// the random number generator are kept.
+    pub fn reset(&mut self) {
+        let mem = Memory::init_with_size(self.cpu.mem.size(), &self.rom);
+        let mut cpu = Cpu::boot(mem, self.rom.len());
+        cpu.protect_memory = self.cpu.protect_memory;
+        cpu.quirks = self.cpu.quirks;
+        cpu.flags = self.cpu.flags;
+        cpu.rng = self.cpu.rng;
+        self.restore(cpu);
+    }
+
+    /// Program as loaded, before it ran
+    pub fn rom(&self) -> &[u8] {
+        &self.rom
+    }
+
+    pub fn ips(&self) -> u32 {
+        self.ips
+    }
+
+    pub fn set_ips(&mut self, ips: u32) {
+        self.ips = ips.max(1);
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn cpu_mut(&mut self) -> &mut Cpu {
+        &mut self.cpu
+    }
+
+    pub fn memory(&self) -> &Memory {
+        &self.cpu.mem
+    }
+
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        &mut self.cpu.mem
+    }
+
+    pub fn memory_map(&self) -> &MemoryMap {
+        self.cpu.memory_map()
+    }
+
+    /// Last presented frame
+    pub fn display(&self) -> MutexGuard<'_, Display> {
+        self.presented.lock().unwrap()
+    }
+
+    /// The presented frame, updated in place by every [`Chip8::present`]
+    pub(crate) fn shared_display(&self) -> Arc<Mutex<Display>> {
+        self.presented.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ADD V0, 1; JMP 0x200
+    const COUNTER: [u8; 4] = [0x70, 0x01, 0x12, 0x00];
+
+    #[test]
+    fn test_frame() {
+        let mut chip8 = Chip8::new(&COUNTER);
+        chip8.set_ips(600);
+        chip8.cpu_mut().delay_timer = 2;
+
+        assert!(!chip8.frame());
+
+        assert_eq!(chip8.cpu().registers[0], 5);
+        assert_eq!(chip8.cpu().delay_timer, 1);
+    }
+
+    #[test]
+    fn test_tick_timers() {
+        let mut chip8 = Chip8::new(&[]);
+        chip8.cpu_mut().delay_timer = 1;
+        chip8.cpu_mut().sound_timer = 2;
+
+        chip8.tick_timers();
+        chip8.tick_timers();
+
+        assert_eq!(chip8.cpu().delay_timer, 0);
+        assert_eq!(chip8.cpu().sound_timer, 0);
+    }
+
+    #[test]
+    fn test_frame_below_60_ips() {
+        let mut chip8 = Chip8::new(&COUNTER);
+        chip8.set_ips(20);
+        chip8.cpu_mut().sound_timer = 60;
+
+        for _ in 0..60 {
+            assert!(!chip8.frame());
+        }
+
+        // a second of frames runs 20 instructions and 60 timer ticks
+        assert_eq!(chip8.cpu().registers[0], 10);
+        assert_eq!(chip8.cpu().sound_timer, 0);
+    }
+
+    #[test]
+    fn test_present() {
+        // LD F, V0; DRW V0, V0, 5; JMP 0x204
+        let mut chip8 = Chip8::new(&[0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04]);
+
+        chip8.step();
+        chip8.step();
+        assert_eq!(*chip8.display(), Display::default());
+        assert_ne!(chip8.cpu().display, Display::default());
+
+        chip8.present();
+        assert_eq!(*chip8.display(), chip8.cpu().display);
+    }
+
+    #[test]
+    fn test_opcode_hook() {
+        // SYS 0x123; CLS
+        let mut chip8 = Chip8::new(&[0x01, 0x23, 0x00, 0xE0]);
+        chip8.add_opcode_hook(0x0000..=0x0FFF, |cpu, op| {
+            cpu.registers[0] = (op & 0xFF) as u8;
+        });
+
+        chip8.step();
+        assert_eq!(chip8.cpu().registers[0], 0x23);
+        assert_eq!(chip8.cpu().pc, 0x202);
+
+        chip8.step();
+        assert_eq!(chip8.cpu().registers[0], 0x23);
+        assert_eq!(chip8.cpu().pc, 0x204);
+    }
+
+    #[test]
+    fn test_halt() {
+        // JMP 0x200
+        let mut chip8 = Chip8::new(&[0x12, 0x00]);
+        assert!(chip8.frame());
+    }
+
+    #[test]
+    fn test_load_rom() {
+        let mut chip8 = Chip8::new(&COUNTER);
+        chip8.set_ips(120);
+        chip8.frame();
+
+        chip8.load_rom(&[0x12, 0x60]);
+        assert_eq!(chip8.ips(), 120);
+        assert_eq!(chip8.cpu().pc, 0x2C0);
+        assert_eq!(chip8.cpu().registers[0], 0);
+        assert_eq!(chip8.memory().read_u16(0x202), 0x0000);
+    }
+
+    #[test]
+    fn test_reset() {
+        // LD I, 0x200; LD [I], V0 overwriting itself with V0 = 0x12
+        let mut chip8 = Chip8::new(&[0xA2, 0x00, 0xF0, 0x55]);
+        chip8.cpu_mut().registers[0] = 0x12;
+        chip8.cpu_mut().flags[0] = 0x34;
+        chip8.cpu_mut().delay_timer = 5;
+        chip8.step();
+        chip8.step();
+        assert_eq!(chip8.memory().read_u8(0x200), 0x12);
+
+        chip8.reset();
+        assert_eq!(chip8.memory().read_u8(0x200), 0xA2);
+        assert_eq!(chip8.cpu().registers[0], 0);
+        assert_eq!(chip8.cpu().i, 0);
+        assert_eq!(chip8.cpu().delay_timer, 0);
+        assert_eq!(chip8.cpu().pc, 0x200);
+        assert_eq!(chip8.cpu().flags[0], 0x34);
+    }
+}
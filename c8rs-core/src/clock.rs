@@ -0,0 +1,195 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Time source pacing the emulator thread, called once per instruction
+pub trait Clock: Send {
+    /// Called before the first tick and whenever the instruction rate
+    /// changes, a zero period runs as fast as possible
+    fn set_period(&mut self, period: Duration);
+
+    /// Wait until the next instruction is due
+    fn tick(&mut self);
+
+    /// Time since the clock was created, the delay and sound timers count
+    /// down at 60 Hz of it
+    fn now(&self) -> Duration;
+}
+
+/// Wall clock, runs instructions at the configured IPS
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RealtimeClock {
+    interval: Option<spin_sleep_util::Interval>,
+    start: Instant,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for RealtimeClock {
+    fn default() -> RealtimeClock {
+        RealtimeClock {
+            interval: None,
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clock for RealtimeClock {
+    fn set_period(&mut self, period: Duration) {
+        self.interval = (!period.is_zero()).then(|| spin_sleep_util::interval(period));
+    }
+
+    fn tick(&mut self) {
+        if let Some(interval) = &mut self.interval {
+            interval.tick();
+        }
+    }
+
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Clock that never sleeps, advancing a virtual time by one period per tick
+///
+/// Clones share the same time, keep one to read [`VirtualClock::elapsed`]
+/// after handing the other to the emulator.
+#[derive(Debug, Clone, Default)]
+pub struct VirtualClock {
+    period: Duration,
+    elapsed_nanos: Arc<AtomicU64>,
+}
+
+impl VirtualClock {
+    pub fn new() -> VirtualClock {
+        VirtualClock::default()
+    }
+
+    /// Virtual time passed since the emulator started
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_nanos.load(Ordering::Relaxed))
+    }
+}
+
+impl Clock for VirtualClock {
+    fn set_period(&mut self, period: Duration) {
+        self.period = period;
+    }
+
+    fn tick(&mut self) {
+        self.elapsed_nanos
+            .fetch_add(self.period.as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn now(&self) -> Duration {
+        self.elapsed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Chip8Emulator, DebugCommand, EmulatorCommand, EmulatorEvent, EmulatorState, Speed,
+    };
+
+    #[test]
+    fn test_virtual_clock() {
+        let rom = [
+            0x60, 0x03, // LD V0, 3
+            0xF0, 0x15, // LD DT, V0
+            0xF1, 0x07, // LD V1, DT
+            0x31, 0x00, // SE V1, 0
+            0x12, 0x04, // JMP 0x204
+            0x12, 0x0A, // JMP 0x20A
+        ];
+
+        let clock = VirtualClock::new();
+        let emu = Chip8Emulator::new(&rom).with_clock(clock.clone());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        controller.set_ips(60).unwrap();
+        controller.resume().unwrap();
+
+        let halted = EmulatorEvent::StateChanged(EmulatorState::Halted);
+        while events.recv_timeout(Duration::from_secs(1)).unwrap() != halted {}
+
+        handle.stop().unwrap();
+
+        // one tick per executed instruction, the timer ticks once per frame
+        assert_eq!(clock.elapsed(), Duration::from_secs(1) / 60 * 8);
+        assert_eq!(controller.cpu().registers[1], 0);
+    }
+
+    #[test]
+    fn test_timers_below_60_ips() {
+        let rom = [
+            0x60, 0x3C, // LD V0, 60
+            0xF0, 0x15, // LD DT, V0
+            0xF1, 0x07, // LD V1, DT
+            0x31, 0x00, // SE V1, 0
+            0x12, 0x04, // JMP 0x204
+            0x12, 0x0A, // JMP 0x20A
+        ];
+
+        let clock = VirtualClock::new();
+        let emu = Chip8Emulator::new(&rom).with_clock(clock.clone());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        controller.set_ips(10).unwrap();
+        controller.resume().unwrap();
+
+        let halted = EmulatorEvent::StateChanged(EmulatorState::Halted);
+        while events.recv_timeout(Duration::from_secs(1)).unwrap() != halted {}
+
+        handle.stop().unwrap();
+
+        // the timer still runs at 60 Hz, a second of it takes a few
+        // instructions instead of 60
+        let elapsed = clock.elapsed();
+        assert!(elapsed >= Duration::from_secs(1), "{elapsed:?}");
+        assert!(elapsed < Duration::from_millis(1500), "{elapsed:?}");
+        assert_eq!(controller.cpu().registers[1], 0);
+    }
+
+    #[test]
+    fn test_speed() {
+        let rom = [
+            0x60, 0x01, // LD V0, 1
+            0x12, 0x02, // JMP 0x202
+        ];
+
+        let clock = VirtualClock::new();
+        let emu = Chip8Emulator::new(&rom).with_clock(clock.clone());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        controller.set_ips(60).unwrap();
+        controller
+            .send(EmulatorCommand::DebugCommand(DebugCommand::Speed {
+                speed: Speed::Percent(50),
+            }))
+            .unwrap();
+        controller.resume().unwrap();
+
+        let halted = EmulatorEvent::StateChanged(EmulatorState::Halted);
+        while events.recv_timeout(Duration::from_secs(1)).unwrap() != halted {}
+
+        handle.stop().unwrap();
+
+        // half speed takes twice as long per instruction
+        assert_eq!(clock.elapsed(), Duration::from_secs(1) / 30 * 2);
+        assert_eq!(controller.speed(), Speed::Percent(50));
+    }
+}
@@ -0,0 +1,195 @@
+use std::{
+    sync::mpsc::{Receiver, Sender, TryRecvError},
+    time::Duration,
+};
+
+use crate::EmulatorCommand;
+
+/// Result of a command handled by the emulator thread
+pub type CommandResult = Result<CommandOutput, CommandError>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandOutput {
+    None,
+    Message(String),
+    /// Rows of cells under a header row
+    Table {
+        headers: Vec<String>,
+        rows: Vec<Vec<String>>,
+    },
+}
+
+impl CommandOutput {
+    pub fn table<const N: usize>(headers: [&str; N], rows: Vec<[String; N]>) -> CommandOutput {
+        CommandOutput::Table {
+            headers: headers.map(str::to_string).to_vec(),
+            rows: rows.into_iter().map(|row| row.to_vec()).collect(),
+        }
+    }
+}
+
+/// Text as shown in the debugger panel, tables get aligned columns
+impl std::fmt::Display for CommandOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandOutput::None => Ok(()),
+            CommandOutput::Message(msg) => write!(f, "{msg}"),
+            CommandOutput::Table { headers, rows } => {
+                let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+                for row in rows {
+                    for (width, cell) in widths.iter_mut().zip(row) {
+                        *width = (*width).max(cell.chars().count());
+                    }
+                }
+
+                for (n, row) in std::iter::once(headers).chain(rows).enumerate() {
+                    if n > 0 {
+                        writeln!(f)?;
+                    }
+                    let line = row
+                        .iter()
+                        .zip(&widths)
+                        .map(|(cell, &width)| format!("{cell:width$}"))
+                        .collect::<Vec<_>>()
+                        .join("  ");
+                    write!(f, "{}", line.trim_end())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// The emulator thread is no longer running
+    Disconnected,
+    /// The reply did not arrive in time
+    Timeout,
+    /// The command could not be executed
+    Failed(String),
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::Disconnected => write!(f, "emulator is not running"),
+            CommandError::Timeout => write!(f, "timed out waiting for emulator"),
+            CommandError::Failed(msg) => write!(f, "{msg}"),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Command sent to the emulator thread, with an optional channel for the
+/// result
+pub(crate) struct Request {
+    pub(crate) cmd: EmulatorCommand,
+    pub(crate) reply: Reply,
+}
+
+/// Where to send the result of a [`Request`], if anywhere
+pub(crate) struct Reply(pub(crate) Option<Sender<CommandResult>>);
+
+impl Reply {
+    /// Send `result` back, output nobody is waiting for is logged instead
+    pub(crate) fn send(self, result: CommandResult) {
+        match (self.0, result) {
+            (Some(reply), result) => {
+                let _ = reply.send(result);
+            }
+            (None, Ok(CommandOutput::None)) => (),
+            (None, Ok(output)) => log::info!("{output}"),
+            (None, Err(err)) => log::warn!("Command failed: {err}"),
+        }
+    }
+}
+
+/// Result of a command that has not necessarily been handled yet
+pub struct PendingReply {
+    pub(crate) rx: Receiver<CommandResult>,
+}
+
+impl PendingReply {
+    /// Block until the emulator has handled the command
+    pub fn wait(self) -> CommandResult {
+        self.rx.recv().unwrap_or(Err(CommandError::Disconnected))
+    }
+
+    /// Like [`PendingReply::wait`], giving up after `timeout`
+    pub fn wait_timeout(self, timeout: Duration) -> CommandResult {
+        self.rx
+            .recv_timeout(timeout)
+            .unwrap_or_else(|err| match err {
+                std::sync::mpsc::RecvTimeoutError::Timeout => Err(CommandError::Timeout),
+                std::sync::mpsc::RecvTimeoutError::Disconnected => Err(CommandError::Disconnected),
+            })
+    }
+
+    /// Poll for the result without blocking
+    pub fn try_get(&self) -> Option<CommandResult> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(CommandError::Disconnected)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chip8Emulator, DebugCommand, Expr};
+
+    #[test]
+    fn test_request() {
+        let emu = Chip8Emulator::new(&[0x12, 0x00]);
+        let controller = emu.controller();
+        let handle = emu.start();
+
+        let timeout = Duration::from_secs(1);
+
+        let result = controller
+            .request(EmulatorCommand::DebugCommand(DebugCommand::Breakpoint {
+                addr: Expr::Num(0x200),
+                condition: None,
+            }))
+            .wait_timeout(timeout);
+        assert_eq!(
+            result,
+            Ok(CommandOutput::Message("Breakpoint set: 0x0200".to_string()))
+        );
+
+        let result = controller
+            .request(EmulatorCommand::DebugCommand(DebugCommand::Redo))
+            .wait_timeout(timeout);
+        assert!(matches!(result, Err(CommandError::Failed(_))));
+
+        let result = controller
+            .request(EmulatorCommand::Stop)
+            .wait_timeout(timeout);
+        assert_eq!(result, Ok(CommandOutput::None));
+        handle.join().unwrap();
+
+        let result = controller
+            .request(EmulatorCommand::Stop)
+            .wait_timeout(timeout);
+        assert_eq!(result, Err(CommandError::Disconnected));
+    }
+
+    #[test]
+    fn test_table() {
+        let table = CommandOutput::table(
+            ["addr", "condition"],
+            vec![
+                ["0x0200".to_string(), String::new()],
+                ["0x0300".to_string(), "V0 == 1".to_string()],
+            ],
+        );
+        assert_eq!(
+            table.to_string(),
+            "addr    condition\n0x0200\n0x0300  V0 == 1"
+        );
+    }
+}
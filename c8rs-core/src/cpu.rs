@@ -1,11 +1,28 @@
 use std::ops::{Index, IndexMut};
 
+use bitvec::vec::BitVec;
+
 use crate::{
-    display::Display, instructions::Register, memory::FONT_SPRITE_ADDR, Instruction, Memory,
+    display::Display,
+    instructions::Register,
+    memory::FONT_SPRITE_ADDR,
+    memory_map::{MemoryMap, Region, RegionKind},
+    Instruction, Memory, Quirks,
 };
 
 pub type Registers = [u8; 16];
 
+/// ROMs for the original two-page hi-res interpreter start with `JMP 0x260`,
+/// the interpreter patch lives at 0x260 and the program itself at 0x2C0
+const HIRES_HEADER: u16 = 0x1260;
+pub(crate) const HIRES_ENTRY: u16 = 0x2C0;
+/// Machine code routine of the hi-res interpreter that clears the screen
+pub(crate) const HIRES_CLS: u16 = 0x0230;
+
+/// The stack grows down from here, one 16-bit return address per level
+pub(crate) const STACK_TOP: u16 = 0x1FE;
+pub(crate) const STACK_DEPTH: u16 = 16;
+
 impl Index<Register> for Registers {
     type Output = u8;
 
@@ -20,7 +37,30 @@ impl IndexMut<Register> for Registers {
     }
 }
 
-#[derive(Debug)]
+/// Error that stops the CPU until it is reset
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fault {
+    /// `CALL` with all stack levels in use
+    StackOverflow,
+    /// `RET` with an empty stack
+    StackUnderflow,
+    /// Write outside the program's memory with memory protection enabled
+    ProtectedWrite { addr: u16 },
+}
+
+impl std::fmt::Display for Fault {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fault::StackOverflow => write!(f, "stack overflow"),
+            Fault::StackUnderflow => write!(f, "stack underflow"),
+            Fault::ProtectedWrite { addr } => write!(f, "protected write to {addr:#06X}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cpu {
     pub registers: Registers,
 
@@ -31,6 +71,41 @@ pub struct Cpu {
     pub sp: u16,
     pub i: u16,
 
+    /// Keypad state, indexed by key
+    pub keys: [bool; 16],
+
+    /// Set when the last instruction faulted, cleared on reset
+    pub fault: Option<Fault>,
+
+    /// Legacy 64x64 hi-res mode
+    pub hires: bool,
+    entry: u16,
+
+    /// Fault on writes to regions of the memory map that aren't writable
+    pub protect_memory: bool,
+    pub quirks: Quirks,
+    pub(crate) map: MemoryMap,
+
+    /// State of the generator behind `Rnd`, set it to seed the generator
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub rng: u32,
+
+    /// SCHIP RPL user flags
+    pub flags: Registers,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) flags_dirty: bool,
+
+    /// Addresses fetched as instructions since the last reset
+    #[cfg_attr(feature = "serde", serde(skip))]
+    executed: BitVec,
+    /// Times the instruction at each address was fetched since the last
+    /// reset
+    #[cfg_attr(feature = "serde", serde(skip))]
+    exec_counts: Vec<u32>,
+    /// First executed address overwritten by the last instruction
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) code_write: Option<u16>,
+
     pub(crate) mem: Memory,
     pub(crate) display: Display,
 }
@@ -44,21 +119,94 @@ impl Cpu {
             sound_timer: 0,
 
             pc: 0x200,
-            sp: 0x1FE,
+            sp: STACK_TOP,
             i: 0x000,
 
+            keys: [false; 16],
+
+            fault: None,
+
+            hires: false,
+            entry: 0x200,
+
+            protect_memory: false,
+            quirks: Quirks::default(),
+            map: MemoryMap::new(mem.size(), 0, false),
+
+            rng: 0,
+
+            flags: Default::default(),
+            flags_dirty: false,
+
+            executed: BitVec::repeat(false, mem.size()),
+            exec_counts: vec![0; mem.size()],
+            code_write: None,
+
             mem,
             display,
         }
     }
 
+    /// Create a CPU for the `rom_len` byte ROM loaded in `mem`, detecting the
+    /// legacy hi-res variant from its header
+    pub(crate) fn boot(mem: Memory, rom_len: usize) -> Cpu {
+        let hires = mem.read_u16(0x200) == HIRES_HEADER;
+        let map = MemoryMap::new(mem.size(), rom_len, hires);
+
+        if !hires {
+            return Cpu {
+                map,
+                ..Cpu::new(mem, Display::default())
+            };
+        }
+
+        let mut cpu = Cpu::new(mem, Display::new(64, 64));
+        cpu.hires = true;
+        cpu.entry = HIRES_ENTRY;
+        cpu.pc = HIRES_ENTRY;
+        cpu.map = map;
+        cpu
+    }
+
+    pub fn memory_map(&self) -> &MemoryMap {
+        &self.map
+    }
+
+    /// Replace the whole machine state with `saved`, e.g. a save state
+    pub fn restore(&mut self, mut saved: Cpu) {
+        let mut mem = std::mem::take(&mut self.mem);
+        mem.restore(std::mem::take(&mut saved.mem));
+
+        *self = Cpu { mem, ..saved };
+        self.code_write = None;
+        // not part of serialized states
+        if self.executed.len() != self.mem.size() {
+            self.executed = BitVec::repeat(false, self.mem.size());
+        }
+        if self.exec_counts.len() != self.mem.size() {
+            self.exec_counts = vec![0; self.mem.size()];
+        }
+    }
+
     pub fn reset(&mut self) {
-        self.pc = 0x200;
-        self.sp = 0x1FE;
+        self.pc = self.entry;
+        self.sp = STACK_TOP;
+        self.fault = None;
+        self.executed.fill(false);
+        self.exec_counts.fill(0);
         self.display.clear();
     }
 
+    /// Execute a single instruction, returns `true` if the CPU halted or
+    /// faulted
     pub fn step(&mut self) -> bool {
+        if self.fault.is_some() {
+            return true;
+        }
+
+        self.code_write = None;
+        self.mark_executed(self.pc);
+
         let instr = Instruction::parse(self.mem.read_u16(self.pc));
 
         match self.execute(instr) {
@@ -70,10 +218,25 @@ impl Cpu {
         }
     }
 
+    /// Whether `op` is ignored by [`Cpu::step`]
+    pub(crate) fn is_unhandled(&self, op: u16) -> bool {
+        match Instruction::parse(op) {
+            Instruction::Unknown(HIRES_CLS) => !self.hires,
+            Instruction::Unknown(_) => true,
+            _ => false,
+        }
+    }
+
     fn execute(&mut self, instr: Instruction) -> Option<u16> {
         match instr {
             Instruction::Cls => self.display.clear(),
-            Instruction::Ret => self.pc = self.pop_stack(),
+            Instruction::Ret => match self.pop_stack() {
+                Ok(addr) => self.pc = addr,
+                Err(fault) => {
+                    self.fault = Some(fault);
+                    return None;
+                }
+            },
             Instruction::Jmp { addr } => {
                 if addr == self.pc {
                     return None;
@@ -81,32 +244,40 @@ impl Cpu {
                 self.pc = addr;
             }
             Instruction::Call { addr } => {
-                self.push_stack(self.pc);
+                if let Err(fault) = self.push_stack(self.pc) {
+                    self.fault = Some(fault);
+                    return None;
+                }
                 self.pc = addr;
             }
-            Instruction::SkipEqImm { reg, byte } => {
-                if self.registers[reg] == byte {
-                    self.pc = self.pc.wrapping_add(2);
-                }
+            Instruction::SkipEqImm { reg, byte } if self.registers[reg] == byte => {
+                self.pc = self.pc.wrapping_add(2);
             }
-            Instruction::SkipNEqImm { reg, byte } => {
-                if self.registers[reg] != byte {
-                    self.pc = self.pc.wrapping_add(2);
-                }
+            Instruction::SkipNEqImm { reg, byte } if self.registers[reg] != byte => {
+                self.pc = self.pc.wrapping_add(2);
             }
-            Instruction::SkipEqReg { regx, regy } => {
-                if self.registers[regx] == self.registers[regy] {
-                    self.pc = self.pc.wrapping_add(2);
-                }
+            Instruction::SkipEqReg { regx, regy }
+                if self.registers[regx] == self.registers[regy] =>
+            {
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdImm { reg, byte } => self.registers[reg] = byte,
             Instruction::AddImm { reg, byte } => {
                 self.registers[reg] = self.registers[reg].wrapping_add(byte)
             }
             Instruction::LdReg { regx, regy } => self.registers[regx] = self.registers[regy],
-            Instruction::Or { regx, regy } => self.registers[regx] |= self.registers[regy],
-            Instruction::And { regx, regy } => self.registers[regx] &= self.registers[regy],
-            Instruction::Xor { regx, regy } => self.registers[regx] ^= self.registers[regy],
+            Instruction::Or { regx, regy } => {
+                self.registers[regx] |= self.registers[regy];
+                self.reset_vf();
+            }
+            Instruction::And { regx, regy } => {
+                self.registers[regx] &= self.registers[regy];
+                self.reset_vf();
+            }
+            Instruction::Xor { regx, regy } => {
+                self.registers[regx] ^= self.registers[regy];
+                self.reset_vf();
+            }
             Instruction::AddReg { regx, regy } => {
                 let (val, carry) = self.registers[regx].overflowing_add(self.registers[regy]);
                 self.registers[regx] = val;
@@ -121,8 +292,9 @@ impl Cpu {
                 self.registers[regx] = self.registers[regx].wrapping_sub(self.registers[regy]);
             }
             Instruction::Shr { regx, regy } => {
-                self.registers[Register::VF] = self.registers[regy] & 0x01;
-                self.registers[regx] = self.registers[regy] >> 1;
+                let src = if self.quirks.shift { regx } else { regy };
+                self.registers[Register::VF] = self.registers[src] & 0x01;
+                self.registers[regx] = self.registers[src] >> 1;
             }
             Instruction::SubN { regx, regy } => {
                 self.registers[Register::VF] = if self.registers[regy] > self.registers[regx] {
@@ -133,30 +305,44 @@ impl Cpu {
                 self.registers[regx] = self.registers[regy].wrapping_sub(self.registers[regx]);
             }
             Instruction::Shl { regx, regy } => {
-                self.registers[Register::VF] = self.registers[regy] & 0x80;
-                self.registers[regx] = self.registers[regy] << 1;
+                let src = if self.quirks.shift { regx } else { regy };
+                self.registers[Register::VF] = self.registers[src] & 0x80;
+                self.registers[regx] = self.registers[src] << 1;
             }
-            Instruction::SkipNEqReg { regx, regy } => {
-                if self.registers[regx] != self.registers[regy] {
-                    self.pc = self.pc.saturating_add(2);
-                }
+            Instruction::SkipNEqReg { regx, regy }
+                if self.registers[regx] != self.registers[regy] =>
+            {
+                self.pc = self.pc.wrapping_add(2);
             }
             Instruction::LdI { addr } => self.i = addr,
             Instruction::JmpReg { addr } => {
-                self.pc = addr + self.registers[Register::V0] as u16;
+                let reg = if self.quirks.jump {
+                    (addr >> 8) as usize & 0xF
+                } else {
+                    0
+                };
+                self.pc = addr.wrapping_add(self.registers[reg] as u16);
             }
-            // Instruction::Rnd { reg, byte } => todo!(),
+            Instruction::Rnd { reg, byte } => self.registers[reg] = self.random() & byte,
             Instruction::Drw { regx, regy, len } => {
                 let sprite = self.mem.read(self.i, len as u16);
                 let collision =
                     self.display
-                        .draw_sprite(self.registers[regx], self.registers[regy], sprite);
+                        .draw_sprite(self.registers[regx], self.registers[regy], &sprite);
                 self.registers[Register::VF] = collision as u8;
             }
-            // Instruction::SkipPressed { reg } => todo!(),
-            // Instruction::SkipNotPressed { reg } => todo!(),
+            Instruction::SkipPressed { reg } if self.key_pressed(self.registers[reg]) => {
+                self.pc = self.pc.wrapping_add(2);
+            }
+            Instruction::SkipNotPressed { reg } if !self.key_pressed(self.registers[reg]) => {
+                self.pc = self.pc.wrapping_add(2);
+            }
             Instruction::LdDelayTimer { reg } => self.registers[reg] = self.delay_timer,
-            // Instruction::LdKey { reg } => todo!(),
+            Instruction::LdKey { reg } => match self.keys.iter().position(|&pressed| pressed) {
+                Some(key) => self.registers[reg] = key as u8,
+                // wait by executing this instruction again
+                None => return Some(self.pc),
+            },
             Instruction::SetDelayTimer { reg } => self.delay_timer = self.registers[reg],
             Instruction::SetSoundTimer { reg } => self.sound_timer = self.registers[reg],
             Instruction::AddI { reg } => self.i = self.i.wrapping_add(self.registers[reg] as u16),
@@ -164,22 +350,42 @@ impl Cpu {
                 self.i = FONT_SPRITE_ADDR + self.registers[reg] as u16 * 5
             }
             Instruction::Bcd { reg } => {
+                if let Err(fault) = self.check_write(self.i, 3) {
+                    self.fault = Some(fault);
+                    return None;
+                }
                 let val = self.registers[reg];
-                self.mem.write_u8(self.i, val / 100);
-                self.mem.write_u8(self.i + 1, (val / 10) % 10);
-                self.mem.write_u8(self.i + 2, val % 10);
+                self.write_mem(self.i, val / 100);
+                self.write_mem(self.i.wrapping_add(1), (val / 10) % 10);
+                self.write_mem(self.i.wrapping_add(2), val % 10);
             }
             Instruction::StoreRegs { reg } => {
+                if let Err(fault) = self.check_write(self.i, reg as u16 + 1) {
+                    self.fault = Some(fault);
+                    return None;
+                }
                 for reg in 0..=reg as u16 {
-                    self.mem
-                        .write_u8(self.i + reg, self.registers[reg as usize]);
+                    self.write_mem(self.i.wrapping_add(reg), self.registers[reg as usize]);
                 }
+                self.increment_i(reg);
             }
             Instruction::LoadRegs { reg } => {
                 for reg in 0..=reg as u16 {
-                    self.registers[reg as usize] = self.mem.read_u8(self.i + reg);
+                    self.registers[reg as usize] = self.mem.read_u8(self.i.wrapping_add(reg));
                 }
+                self.increment_i(reg);
+            }
+            Instruction::StoreFlags { reg } => {
+                let n = reg as usize + 1;
+                self.flags[..n].copy_from_slice(&self.registers[..n]);
+                self.flags_dirty = true;
             }
+            Instruction::LoadFlags { reg } => {
+                let n = reg as usize + 1;
+                self.registers[..n].copy_from_slice(&self.flags[..n]);
+            }
+            // clears the 64x64 screen in the hi-res interpreter
+            Instruction::Unknown(HIRES_CLS) if self.hires => self.display.clear(),
             _ => (),
         };
 
@@ -191,14 +397,105 @@ impl Cpu {
         }
     }
 
-    fn push_stack(&mut self, addr: u16) {
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers[Register::VF] = 0;
+        }
+    }
+
+    /// Step I past the registers `Fx55`/`Fx65` copied up to `reg`
+    fn increment_i(&mut self, reg: Register) {
+        if self.quirks.memory_increment {
+            self.i = self.i.wrapping_add(reg as u16 + 1);
+        }
+    }
+
+    /// Next byte of a linear congruential generator, deterministic for a
+    /// given [`Cpu::rng`]
+    fn random(&mut self) -> u8 {
+        self.rng = self.rng.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        (self.rng >> 24) as u8
+    }
+
+    fn key_pressed(&self, key: u8) -> bool {
+        self.keys[(key & 0xF) as usize]
+    }
+
+    /// Whether the byte at `addr` was fetched as part of an instruction
+    /// since the last reset
+    pub fn was_executed(&self, addr: u16) -> bool {
+        self.executed[(addr & self.mem.max_addr()) as usize]
+    }
+
+    /// Bytes of the program that were executed since the last reset, and
+    /// the size of the program
+    pub fn coverage(&self) -> (usize, usize) {
+        self.map
+            .regions()
+            .iter()
+            .filter(|r| r.kind == RegionKind::Program)
+            .fold((0, 0), |(executed, total), r| {
+                let bytes = &self.executed[r.start as usize..=r.end as usize];
+                (executed + bytes.count_ones(), total + bytes.len())
+            })
+    }
+
+    /// Times the instruction at each address was executed since the last
+    /// reset, indexed by address
+    pub fn exec_counts(&self) -> &[u32] {
+        &self.exec_counts
+    }
+
+    /// Mark both bytes of the instruction at `addr` as executed
+    pub(crate) fn mark_executed(&mut self, addr: u16) {
+        let mask = self.mem.max_addr();
+        let count = &mut self.exec_counts[(addr & mask) as usize];
+        *count = count.saturating_add(1);
+        self.executed.set((addr & mask) as usize, true);
+        self.executed
+            .set((addr.wrapping_add(1) & mask) as usize, true);
+    }
+
+    /// Write from a running program, noting writes to code that has run
+    fn write_mem(&mut self, addr: u16, value: u8) {
+        if self.code_write.is_none() && self.executed[(addr & self.mem.max_addr()) as usize] {
+            self.code_write = Some(addr);
+        }
+
+        self.mem.write_u8(addr, value);
+    }
+
+    fn check_write(&self, addr: u16, len: u16) -> Result<(), Fault> {
+        if !self.protect_memory {
+            return Ok(());
+        }
+
+        for addr in (0..len).map(|offset| addr.wrapping_add(offset)) {
+            if !self.map.region(addr).is_some_and(Region::writable) {
+                return Err(Fault::ProtectedWrite { addr });
+            }
+        }
+
+        Ok(())
+    }
+
+    fn push_stack(&mut self, addr: u16) -> Result<(), Fault> {
+        if self.sp <= STACK_TOP - STACK_DEPTH * 2 {
+            return Err(Fault::StackOverflow);
+        }
+
         self.mem.write_u16(self.sp, addr);
-        self.sp = self.sp.saturating_sub(2);
+        self.sp -= 2;
+        Ok(())
     }
 
-    fn pop_stack(&mut self) -> u16 {
-        self.sp = self.sp.saturating_add(2);
-        self.mem.read_u16(self.sp)
+    fn pop_stack(&mut self) -> Result<u16, Fault> {
+        if self.sp >= STACK_TOP {
+            return Err(Fault::StackUnderflow);
+        }
+
+        self.sp += 2;
+        Ok(self.mem.read_u16(self.sp))
     }
 }
 
@@ -260,12 +557,98 @@ mod test {
     #[test]
     fn test_ret() {
         let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
-        cpu.push_stack(0x2A8);
+        cpu.push_stack(0x2A8).unwrap();
 
         let pc = cpu.execute(Ret);
         assert_eq!(pc, Some(0x2AA))
     }
 
+    #[test]
+    fn test_stack_underflow() {
+        let mut cpu = Cpu::new(Memory::init(&[0x00, 0xEE]), Display::default());
+
+        assert!(cpu.step());
+        assert_eq!(cpu.fault, Some(Fault::StackUnderflow));
+        assert_eq!(cpu.pc, 0x200);
+
+        cpu.reset();
+        assert_eq!(cpu.fault, None);
+    }
+
+    #[test]
+    fn test_protect_memory() {
+        // LD [I], V1
+        let mut cpu = Cpu::boot(Memory::init(&[0xF1, 0x55]), 2);
+        cpu.protect_memory = true;
+
+        cpu.i = 0x300;
+        assert!(!cpu.step());
+
+        cpu.pc = 0x200;
+        cpu.i = 0x14F;
+        assert!(cpu.step());
+        assert_eq!(cpu.fault, Some(Fault::ProtectedWrite { addr: 0x14F }));
+    }
+
+    #[test]
+    fn test_code_write() {
+        // LD I, 0x200; LD [I], V0
+        let mut cpu = Cpu::new(Memory::init(&[0xA2, 0x00, 0xF0, 0x55]), Display::default());
+
+        cpu.step();
+        assert_eq!(cpu.code_write, None);
+
+        cpu.step();
+        assert_eq!(cpu.code_write, Some(0x200));
+
+        cpu.reset();
+        cpu.i = 0x200;
+        cpu.pc = 0x202;
+        cpu.step();
+        assert_eq!(cpu.code_write, None);
+    }
+
+    #[test]
+    fn test_exec_counts() {
+        // LD V0, 0x01; JMP 0x202
+        let mut cpu = Cpu::new(Memory::init(&[0x60, 0x01, 0x12, 0x02]), Display::default());
+
+        for _ in 0..4 {
+            cpu.step();
+        }
+        assert_eq!(cpu.exec_counts()[0x200], 1);
+        assert_eq!(cpu.exec_counts()[0x201], 0);
+        assert_eq!(cpu.exec_counts()[0x202], 3);
+
+        cpu.reset();
+        assert!(cpu.exec_counts().iter().all(|&count| count == 0));
+    }
+
+    #[test]
+    fn test_coverage() {
+        // LD V0, 0x01; JMP 0x202; CLS
+        let mut cpu = Cpu::boot(Memory::init(&[0x60, 0x01, 0x12, 0x02, 0x00, 0xE0]), 6);
+        assert_eq!(cpu.coverage(), (0, 6));
+
+        cpu.step();
+        cpu.step();
+        assert!(cpu.was_executed(0x203));
+        assert!(!cpu.was_executed(0x204));
+        assert_eq!(cpu.coverage(), (4, 6));
+    }
+
+    #[test]
+    fn test_stack_overflow() {
+        // CALL 0x200
+        let mut cpu = Cpu::new(Memory::init(&[0x22, 0x00]), Display::default());
+
+        for _ in 0..STACK_DEPTH {
+            assert!(!cpu.step());
+        }
+        assert!(cpu.step());
+        assert_eq!(cpu.fault, Some(Fault::StackOverflow));
+    }
+
     #[test]
     fn test_jmp() {
         let (_, pc) = test_instr!(Jmp { addr: 0x3FA });
@@ -276,7 +659,7 @@ mod test {
     fn test_call() {
         let (mut cpu, pc) = test_instr!(Call { addr: 0x123 });
         assert_eq!(pc, Some(0x123));
-        assert_eq!(cpu.pop_stack(), 0x200);
+        assert_eq!(cpu.pop_stack(), Ok(0x200));
     }
 
     #[test]
@@ -306,6 +689,22 @@ mod test {
         assert_eq!(pc, Some(0x202));
     }
 
+    #[test]
+    fn test_skip_neq_reg() {
+        let (_, pc) = test_instr!(SkipNEqReg { regx: V2, regy: V5 }, V2 => 0x21, V5 => 0x22);
+        assert_eq!(pc, Some(0x204));
+
+        let (_, pc) = test_instr!(SkipNEqReg { regx: V2, regy: V5 }, V2 => 0x21, V5 => 0x21);
+        assert_eq!(pc, Some(0x202));
+
+        // wraps around the end of memory like the other skips
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+        cpu.pc = 0xFFFE;
+        cpu.registers[V2] = 0x21;
+        let pc = cpu.execute(Instruction::SkipNEqReg { regx: V2, regy: V5 });
+        assert_eq!(pc, Some(0x0002));
+    }
+
     #[test]
     fn test_ld_imm() {
         let (cpu, pc) = test_instr!(LdImm {
@@ -428,7 +827,37 @@ mod test {
         assert_eq!(pc, Some(0x354));
     }
 
-    // TODO: test Rnd
+    #[test]
+    fn test_rnd() {
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+        cpu.rng = 1234;
+        let mut values = Vec::new();
+        for _ in 0..8 {
+            cpu.execute(Instruction::Rnd {
+                reg: V1,
+                byte: 0xFF,
+            });
+            values.push(cpu.registers[V1]);
+        }
+        assert!(values.iter().any(|&v| v != values[0]));
+
+        // the same seed gives the same sequence
+        cpu.rng = 1234;
+        for &value in &values {
+            cpu.execute(Instruction::Rnd {
+                reg: V1,
+                byte: 0xFF,
+            });
+            assert_eq!(cpu.registers[V1], value);
+        }
+
+        let (cpu, pc) = test_instr!(Rnd {
+            reg: V2,
+            byte: 0x0F
+        });
+        assert_eq!(cpu.registers[V2] & 0xF0, 0);
+        assert_eq!(pc, Some(0x202));
+    }
 
     // TODO: test Drw
 
@@ -508,4 +937,126 @@ mod test {
         assert_eq!(cpu.registers[V3], 0x00);
         assert_eq!(pc, Some(0x202));
     }
+
+    #[test]
+    fn test_quirks() {
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+        cpu.quirks = Quirks::for_variant(crate::Variant::Schip);
+        cpu.registers[V0] = 0b0110;
+        cpu.registers[V1] = 0xFF;
+        cpu.registers[V3] = 0x10;
+
+        cpu.execute(Shr { regx: V0, regy: V1 });
+        assert_eq!(cpu.registers[V0], 0b0011);
+        assert_eq!(cpu.execute(JmpReg { addr: 0x342 }), Some(0x352));
+
+        cpu.quirks = Quirks::for_variant(crate::Variant::Chip8);
+        cpu.registers[VF] = 1;
+        cpu.execute(Or { regx: V0, regy: V1 });
+        assert_eq!(cpu.registers[VF], 0);
+
+        cpu.i = 0x300;
+        cpu.execute(StoreRegs { reg: V2 });
+        assert_eq!(cpu.i, 0x303);
+        cpu.execute(LoadRegs { reg: V0 });
+        assert_eq!(cpu.i, 0x304);
+    }
+
+    #[test]
+    fn test_store_flags() {
+        let (cpu, pc) =
+            test_instr!(StoreFlags { reg: V2 }, V0 => 0x12, V1 => 0x34, V2 => 0x56, V3 => 0x78);
+        assert_eq!(cpu.flags[..4], [0x12, 0x34, 0x56, 0x00]);
+        assert!(cpu.flags_dirty);
+        assert_eq!(pc, Some(0x202));
+    }
+
+    #[test]
+    fn test_load_flags() {
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+        cpu.flags[..3].copy_from_slice(&[0xAB, 0xCD, 0xEF]);
+        cpu.registers[V2] = 0x12;
+
+        let pc = cpu.execute(LoadFlags { reg: V1 });
+
+        assert_eq!(cpu.registers[V0], 0xAB);
+        assert_eq!(cpu.registers[V1], 0xCD);
+        assert_eq!(cpu.registers[V2], 0x12);
+        assert_eq!(pc, Some(0x202));
+    }
+
+    #[test]
+    fn test_boot_hires() {
+        let cpu = Cpu::boot(Memory::init(&[0x12, 0x60]), 2);
+        assert!(cpu.hires);
+        assert_eq!(cpu.pc, 0x2C0);
+        assert_eq!(cpu.display.get_dimensions(), (64, 64));
+
+        let cpu = Cpu::boot(Memory::init(&[0x12, 0x00]), 2);
+        assert!(!cpu.hires);
+        assert_eq!(cpu.pc, 0x200);
+        assert_eq!(cpu.display.get_dimensions(), (64, 32));
+    }
+
+    #[test]
+    fn test_hires_cls() {
+        let mut cpu = Cpu::boot(Memory::init(&[0x12, 0x60]), 2);
+        cpu.display.draw_sprite(0, 40, &[0xFF]);
+
+        cpu.execute(Unknown(0x0230));
+
+        assert_eq!(cpu.display, Display::new(64, 64));
+    }
+
+    #[test]
+    fn test_restore() {
+        let mut cpu = Cpu::new(Memory::init(&[0x12, 0x00]), Display::default());
+        cpu.step();
+        let saved = cpu.clone();
+
+        cpu.registers[V0] = 0x12;
+        cpu.mem.write_u8(0x300, 0xFF);
+        let generation = cpu.mem.generation();
+
+        cpu.restore(saved);
+        assert_eq!(cpu.registers[V0], 0);
+        assert_eq!(cpu.mem.read_u8(0x300), 0);
+        assert!(cpu.mem.generation() > generation);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_restore_serialized() {
+        let mut cpu = Cpu::new(Memory::init(&[0x70, 0x01]), Display::default());
+        let json = serde_json::to_string(&cpu).unwrap();
+
+        cpu.restore(serde_json::from_str(&json).unwrap());
+        // execution tracking isn't serialized, restoring must not break it
+        assert!(!cpu.step());
+    }
+
+    #[test]
+    fn test_skip_pressed() {
+        let (_, pc) = test_instr!(SkipPressed { reg: V0 }, V0 => 0xA);
+        assert_eq!(pc, Some(0x202));
+        let (_, pc) = test_instr!(SkipNotPressed { reg: V0 }, V0 => 0xA);
+        assert_eq!(pc, Some(0x204));
+
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+        cpu.registers[V0] = 0xA;
+        cpu.keys[0xA] = true;
+        assert_eq!(cpu.execute(SkipNotPressed { reg: V0 }), Some(0x202));
+        assert_eq!(cpu.execute(SkipPressed { reg: V0 }), Some(0x204));
+    }
+
+    #[test]
+    fn test_ld_key() {
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+
+        assert_eq!(cpu.execute(LdKey { reg: V3 }), Some(0x200));
+
+        cpu.keys[0x7] = true;
+        assert_eq!(cpu.execute(LdKey { reg: V3 }), Some(0x202));
+        assert_eq!(cpu.registers[V3], 0x7);
+    }
 }
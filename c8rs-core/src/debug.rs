@@ -1,21 +1,129 @@
-use clap::Parser;
+use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy, PartialEq, Parser)]
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::{alias::Aliases, expr::Expr, instructions::Register, Cpu, Instruction, Memory};
+
+/// Condition for pausing that isn't tied to an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum BreakEvent {
+    /// Any `DRW`
+    Draw,
+    /// Screen cleared
+    Cls,
+    /// `I` register modified
+    I,
+}
+
+impl std::fmt::Display for BreakEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                BreakEvent::Draw => "draw",
+                BreakEvent::Cls => "cls",
+                BreakEvent::I => "I change",
+            }
+        )
+    }
+}
+
+/// How fast the emulator runs relative to its IPS, timers included
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    /// Percentage of real time
+    Percent(u32),
+    /// As fast as the host can go
+    Unlimited,
+}
+
+impl Default for Speed {
+    fn default() -> Speed {
+        Speed::Percent(100)
+    }
+}
+
+impl std::str::FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "max" {
+            return Ok(Speed::Unlimited);
+        }
+        match s.trim_end_matches('%').parse() {
+            Ok(percent) if percent > 0 => Ok(Speed::Percent(percent)),
+            _ => Err(format!("invalid speed '{s}', expected a percentage or max")),
+        }
+    }
+}
+
+impl std::fmt::Display for Speed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Speed::Percent(percent) => write!(f, "{percent}%"),
+            Speed::Unlimited => write!(f, "max"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Parser)]
 #[command(name = "", multicall = true)]
 pub enum DebugCommand {
+    /// Execute `count` instructions, then pause
     #[command(visible_alias = "s")]
-    Step,
+    Step {
+        #[arg(default_value_t = 1)]
+        count: u32,
+    },
 
     #[command(visible_alias = "p")]
     Pause,
 
+    /// Continue until the current subroutine returns
+    #[command(visible_alias = "fin")]
+    Finish,
+
+    /// Continue until PC reaches `addr`
+    #[command(visible_alias = "unt")]
+    Until {
+        #[clap(value_parser=clap_num::maybe_hex::<u16>)]
+        addr: u16,
+    },
+
     #[command(visible_alias = "c")]
     Continue,
 
     #[command(name = "break", visible_alias = "b")]
+    /// Toggle a breakpoint, `break <addr> if <condition>` only pauses when
+    /// the condition is non-zero
     Breakpoint {
+        #[clap(value_parser = parse_expr)]
+        addr: Expr,
+        #[arg(long = "if", value_parser = parse_expr)]
+        condition: Option<Expr>,
+    },
+
+    /// Evaluate an expression, e.g. `print [I] + V0`
+    Print {
+        #[clap(value_parser = parse_expr)]
+        expr: Expr,
+    },
+
+    /// Log `format` whenever PC reaches `addr`, without pausing. `{V0}`-`{VF}`,
+    /// `{I}`, `{PC}`, `{SP}`, `{DT}` and `{ST}` are replaced with their values.
+    /// Removes the tracepoint if no format is given.
+    #[command(visible_alias = "t")]
+    Trace {
         #[clap(value_parser=clap_num::maybe_hex::<u16>)]
         addr: u16,
+        format: Option<String>,
+    },
+
+    /// Toggle pausing after instructions matching `event`
+    #[command(visible_alias = "bo")]
+    BreakOn {
+        event: BreakEvent,
     },
 
     SetPc {
@@ -23,16 +131,581 @@ pub enum DebugCommand {
         addr: u16,
     },
 
+    SetReg {
+        reg: Register,
+        #[clap(value_parser=clap_num::maybe_hex::<u8>)]
+        value: u8,
+    },
+
+    #[command(visible_alias = "w")]
+    WriteMem {
+        #[clap(value_parser=clap_num::maybe_hex::<u16>)]
+        addr: u16,
+        #[clap(value_parser=clap_num::maybe_hex::<u8>)]
+        value: u8,
+    },
+
+    /// Assemble an instruction over the one at `addr`, e.g.
+    /// `patch 0x204 LD V0, 0x05`
+    Patch {
+        #[clap(value_parser=clap_num::maybe_hex::<u16>)]
+        addr: u16,
+        #[clap(value_parser = parse_instruction)]
+        instr: Instruction,
+    },
+
+    #[command(visible_alias = "u")]
+    Undo,
+
+    Redo,
+
     #[command(visible_alias = "rs")]
     Reset,
 
     #[command(name = "ips")]
-    IPS { ips: u32 },
+    IPS {
+        ips: u32,
+    },
+
+    /// Run at a percentage of the IPS, timers included, or as fast as
+    /// possible (`speed 25%`, `speed max`)
+    Speed {
+        #[clap(value_parser = parse_speed)]
+        speed: Speed,
+    },
+
+    /// List breakpoints or tracepoints
+    Info {
+        what: InfoKind,
+    },
+
+    /// Define `alias <name> = <commands>`, show one alias, or list them all
+    Alias {
+        name: Option<String>,
+        #[arg(skip)]
+        expansion: Option<String>,
+    },
+
+    Unalias {
+        name: String,
+    },
+
+    /// Inject keypad input, e.g. `key tap A 5`
+    Key {
+        #[command(subcommand)]
+        action: KeyAction,
+    },
+
+    /// Dump the display to a `.pbm` or `.txt` file, or a PNG if the frontend
+    /// can encode one
+    Screenshot {
+        path: Option<PathBuf>,
+    },
+
+    /// Save the machine state to a slot, or to a file if `name` looks like a
+    /// path (`save slot1`, `save game.c8state`)
+    Save {
+        name: String,
+    },
+
+    /// Restore a state saved with `save`, any file without the `.c8state`
+    /// extension is loaded as a new ROM instead
+    Load {
+        name: String,
+    },
+
+    /// Run the debugger commands in `file`, one per line
+    Source {
+        file: PathBuf,
+    },
+
+    /// Print memory, gdb style: `x/16b 0x300`, `x/8w 0x200`, `x/4i pc`
+    #[command(name = "x")]
+    Examine {
+        #[arg(short = 'n', default_value_t = 1)]
+        count: u16,
+        #[arg(short, value_enum, default_value_t = ExamineUnit::Byte)]
+        unit: ExamineUnit,
+        #[clap(value_parser = parse_expr)]
+        addr: Expr,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum InfoKind {
+    #[value(alias = "b")]
+    Breakpoints,
+    #[value(alias = "t")]
+    Tracepoints,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Subcommand)]
+pub enum KeyAction {
+    /// Hold `key` down until released
+    Press {
+        #[clap(value_parser = parse_key)]
+        key: u8,
+    },
+    Release {
+        #[clap(value_parser = parse_key)]
+        key: u8,
+    },
+    /// Press `key` and release it after `frames` frames have run
+    Tap {
+        #[clap(value_parser = parse_key)]
+        key: u8,
+        #[arg(default_value_t = 1)]
+        frames: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum ExamineUnit {
+    #[value(name = "b")]
+    Byte,
+    #[value(name = "w")]
+    Word,
+    #[value(name = "i")]
+    Instruction,
+}
+
+/// Keypad key as a single hex digit
+fn parse_key(s: &str) -> Result<u8, String> {
+    match u8::from_str_radix(s, 16) {
+        Ok(key) if key <= 0xF => Ok(key),
+        _ => Err(format!("invalid key '{s}', expected 0-F")),
+    }
+}
+
+fn parse_expr(s: &str) -> Result<Expr, String> {
+    s.parse()
+}
+
+fn parse_instruction(s: &str) -> Result<Instruction, String> {
+    s.parse()
+}
+
+fn parse_speed(s: &str) -> Result<Speed, String> {
+    s.parse()
 }
 
 impl DebugCommand {
     pub fn parse_from(s: &str) -> Result<DebugCommand, String> {
-        let s = shlex::split(s).ok_or("Invalid quoting".to_owned())?;
+        // keep the expansion verbatim, it's parsed when the alias is used
+        if let Some((head, expansion)) = s.split_once('=') {
+            if let ["alias", name] = head.split_whitespace().collect::<Vec<_>>()[..] {
+                return Ok(DebugCommand::Alias {
+                    name: Some(name.to_string()),
+                    expansion: Some(expansion.trim().to_string()),
+                });
+            }
+        }
+
+        let mut s = shlex::split(s).ok_or("Invalid quoting".to_owned())?;
+
+        if let Some(spec) = s.first().and_then(|cmd| cmd.strip_prefix("x/")) {
+            let (count, unit) = spec.split_at(
+                spec.find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(spec.len()),
+            );
+
+            let mut args = vec!["x".to_string()];
+            if !count.is_empty() {
+                args.extend(["-n".to_string(), count.to_string()]);
+            }
+            if !unit.is_empty() {
+                args.extend(["-u".to_string(), unit.to_string()]);
+            }
+            s.splice(..1, args);
+        }
+
+        // expressions may contain spaces, pass each one as a single argument
+        match s.first().map(String::as_str) {
+            Some("break" | "b") => {
+                let (addr, condition) = match s.iter().position(|arg| arg == "if") {
+                    Some(n) => (s[1..n].join(" "), Some(s[n + 1..].join(" "))),
+                    None => (s[1..].join(" "), None),
+                };
+                s.truncate(1);
+                s.push(addr);
+                if let Some(condition) = condition {
+                    s.extend(["--if".to_string(), condition]);
+                }
+            }
+            Some("patch") if s.len() > 3 => {
+                let instr = s.split_off(2).join(" ");
+                s.push(instr);
+            }
+            Some("print" | "x") => {
+                let n = s
+                    .iter()
+                    .rposition(|arg| arg == "-n" || arg == "-u")
+                    .map_or(1, |n| n + 2);
+                if s.len() > n {
+                    let expr = s.split_off(n).join(" ");
+                    s.push(expr);
+                }
+            }
+            _ => (),
+        }
+
         DebugCommand::try_parse_from(s).map_err(|err| err.to_string())
     }
 }
+
+/// Parse a debugger script, one command per line. Blank lines and lines
+/// starting with `#` are skipped, aliases defined by the script can be used
+/// on the following lines.
+pub fn parse_script(script: &str, aliases: &Aliases) -> Result<Vec<DebugCommand>, String> {
+    let mut aliases = aliases.clone();
+    let mut cmds = Vec::new();
+
+    for (n, line) in script.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parsed = aliases
+            .parse(line)
+            .map_err(|err| format!("line {}: {}", n + 1, err.lines().next().unwrap_or_default()))?;
+        for cmd in &parsed {
+            if let DebugCommand::Alias {
+                name: Some(name),
+                expansion: Some(expansion),
+            } = cmd
+            {
+                aliases
+                    .define(name, expansion)
+                    .map_err(|err| format!("line {}: {err}", n + 1))?;
+            }
+        }
+        cmds.extend(parsed);
+    }
+
+    Ok(cmds)
+}
+
+/// Format `count` units of memory starting at `addr`, one line per row
+pub(crate) fn examine(mem: &Memory, addr: u16, count: u16, unit: ExamineUnit) -> String {
+    let (per_line, size) = match unit {
+        ExamineUnit::Byte => (16, 1),
+        ExamineUnit::Word => (8, 2),
+        ExamineUnit::Instruction => (1, 2),
+    };
+
+    (0..count)
+        .step_by(per_line as usize)
+        .map(|start| {
+            let line_addr = addr.wrapping_add(start.wrapping_mul(size));
+            let values = (0..per_line.min(count - start))
+                .map(|n| {
+                    let addr = line_addr.wrapping_add(n * size);
+                    match unit {
+                        ExamineUnit::Byte => format!("{:02X}", mem.read_u8(addr)),
+                        ExamineUnit::Word => format!("{:04X}", mem.read_u16(addr)),
+                        ExamineUnit::Instruction => {
+                            let word = mem.read_u16(addr);
+                            format!("{word:04X}  {}", Instruction::parse(word))
+                        }
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            format!("{line_addr:#06X}: {values}")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Expand the placeholders of a [`DebugCommand::Trace`] format
+pub(crate) fn format_trace(format: &str, cpu: &Cpu) -> String {
+    let mut msg = format
+        .replace("{I}", &format!("{:#06X}", cpu.i))
+        .replace("{PC}", &format!("{:#06X}", cpu.pc))
+        .replace("{SP}", &format!("{:#06X}", cpu.sp))
+        .replace("{DT}", &format!("{:#04X}", cpu.delay_timer))
+        .replace("{ST}", &format!("{:#04X}", cpu.sound_timer));
+
+    for (n, value) in cpu.registers.iter().enumerate() {
+        msg = msg.replace(&format!("{{V{n:X}}}"), &format!("{value:#04X}"));
+    }
+
+    msg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_edit_commands() {
+        assert_eq!(
+            DebugCommand::parse_from("set-reg V1 0x12"),
+            Ok(DebugCommand::SetReg {
+                reg: Register::V1,
+                value: 0x12
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("w 0x300 255"),
+            Ok(DebugCommand::WriteMem {
+                addr: 0x300,
+                value: 0xFF
+            })
+        );
+        assert!(DebugCommand::parse_from("set-reg I 0x12").is_err());
+        assert_eq!(
+            DebugCommand::parse_from("patch 0x204 LD V0, 0x05"),
+            Ok(DebugCommand::Patch {
+                addr: 0x204,
+                instr: Instruction::LdImm {
+                    reg: Register::V0,
+                    byte: 0x05
+                }
+            })
+        );
+        assert!(DebugCommand::parse_from("patch 0x204 LD V0").is_err());
+    }
+
+    #[test]
+    fn test_parse_script() {
+        let script = "# setup\nips 500\n\n  break 0x202\ncontinue\n";
+        assert_eq!(
+            parse_script(script, &Aliases::new()),
+            Ok(vec![
+                DebugCommand::IPS { ips: 500 },
+                DebugCommand::Breakpoint {
+                    addr: Expr::Num(0x202),
+                    condition: None
+                },
+                DebugCommand::Continue,
+            ])
+        );
+
+        let err = parse_script("ips 500\nbogus", &Aliases::new()).unwrap_err();
+        assert!(err.starts_with("line 2: "), "{err}");
+
+        let script = "alias sn = step\nsn 3";
+        assert_eq!(
+            parse_script(script, &Aliases::new()).unwrap()[1],
+            DebugCommand::Step { count: 3 }
+        );
+    }
+
+    #[test]
+    fn test_parse_alias() {
+        assert_eq!(
+            DebugCommand::parse_from("alias bp200 = break 0x200; continue"),
+            Ok(DebugCommand::Alias {
+                name: Some("bp200".to_string()),
+                expansion: Some("break 0x200; continue".to_string())
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("alias bp200"),
+            Ok(DebugCommand::Alias {
+                name: Some("bp200".to_string()),
+                expansion: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_key() {
+        assert_eq!(
+            DebugCommand::parse_from("key press a"),
+            Ok(DebugCommand::Key {
+                action: KeyAction::Press { key: 0xA }
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("key tap F 10"),
+            Ok(DebugCommand::Key {
+                action: KeyAction::Tap {
+                    key: 0xF,
+                    frames: 10
+                }
+            })
+        );
+        assert!(DebugCommand::parse_from("key press 10").is_err());
+        assert!(DebugCommand::parse_from("key hold 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_step() {
+        assert_eq!(
+            DebugCommand::parse_from("s"),
+            Ok(DebugCommand::Step { count: 1 })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("step 100"),
+            Ok(DebugCommand::Step { count: 100 })
+        );
+    }
+
+    #[test]
+    fn test_parse_examine() {
+        assert_eq!(
+            DebugCommand::parse_from("x/16b 0x300"),
+            Ok(DebugCommand::Examine {
+                count: 16,
+                unit: ExamineUnit::Byte,
+                addr: Expr::Num(0x300)
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("x/i PC"),
+            Ok(DebugCommand::Examine {
+                count: 1,
+                unit: ExamineUnit::Instruction,
+                addr: Expr::Pc
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("x i"),
+            Ok(DebugCommand::Examine {
+                count: 1,
+                unit: ExamineUnit::Byte,
+                addr: Expr::I
+            })
+        );
+        assert!(DebugCommand::parse_from("x/4q 0x200").is_err());
+    }
+
+    #[test]
+    fn test_parse_expressions() {
+        assert_eq!(
+            DebugCommand::parse_from("b 0x200"),
+            Ok(DebugCommand::Breakpoint {
+                addr: Expr::Num(0x200),
+                condition: None
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("break V0 + 0x200 if [I] == 3"),
+            Ok(DebugCommand::Breakpoint {
+                addr: "V0 + 0x200".parse().unwrap(),
+                condition: Some("[I] == 3".parse().unwrap())
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("print I + 5"),
+            Ok(DebugCommand::Print {
+                expr: "I + 5".parse().unwrap()
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("x/2w I + 2"),
+            Ok(DebugCommand::Examine {
+                count: 2,
+                unit: ExamineUnit::Word,
+                addr: "I + 2".parse().unwrap()
+            })
+        );
+        assert!(DebugCommand::parse_from("print").is_err());
+        assert!(DebugCommand::parse_from("break 0x200 if").is_err());
+    }
+
+    #[test]
+    fn test_parse_until() {
+        assert_eq!(
+            DebugCommand::parse_from("until 0x2A0"),
+            Ok(DebugCommand::Until { addr: 0x2A0 })
+        );
+    }
+
+    #[test]
+    fn test_parse_trace() {
+        assert_eq!(
+            DebugCommand::parse_from("trace 0x200 'V0={V0}'"),
+            Ok(DebugCommand::Trace {
+                addr: 0x200,
+                format: Some("V0={V0}".to_string())
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("t 0x200"),
+            Ok(DebugCommand::Trace {
+                addr: 0x200,
+                format: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_examine() {
+        let mem = Memory::init(&[0x00, 0xE0, 0x12, 0x00]);
+
+        assert_eq!(
+            examine(&mem, 0x200, 3, ExamineUnit::Byte),
+            "0x0200: 00 E0 12"
+        );
+        assert_eq!(
+            examine(&mem, 0x1FC, 5, ExamineUnit::Word),
+            "0x01FC: 0000 0000 00E0 1200 0000"
+        );
+        assert_eq!(
+            examine(&mem, 0x200, 2, ExamineUnit::Instruction),
+            "0x0200: 00E0  CLS\n0x0202: 1200  JMP 0x0200"
+        );
+        assert_eq!(
+            examine(&mem, 0x200, 17, ExamineUnit::Byte).lines().count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_format_trace() {
+        let mut cpu = Cpu::new(Memory::default(), Default::default());
+        cpu.registers[Register::VA] = 0x12;
+        cpu.i = 0x345;
+
+        assert_eq!(
+            format_trace("VA={VA} I={I} PC={PC} {X}", &cpu),
+            "VA=0x12 I=0x0345 PC=0x0200 {X}"
+        );
+    }
+
+    #[test]
+    fn test_parse_break_on() {
+        assert_eq!(
+            DebugCommand::parse_from("break-on draw"),
+            Ok(DebugCommand::BreakOn {
+                event: BreakEvent::Draw
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("bo i"),
+            Ok(DebugCommand::BreakOn {
+                event: BreakEvent::I
+            })
+        );
+        assert!(DebugCommand::parse_from("break-on key").is_err());
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(
+            DebugCommand::parse_from("speed 25%"),
+            Ok(DebugCommand::Speed {
+                speed: Speed::Percent(25)
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("speed 200"),
+            Ok(DebugCommand::Speed {
+                speed: Speed::Percent(200)
+            })
+        );
+        assert_eq!(
+            DebugCommand::parse_from("speed max"),
+            Ok(DebugCommand::Speed {
+                speed: Speed::Unlimited
+            })
+        );
+        assert!(DebugCommand::parse_from("speed 0").is_err());
+        assert!(DebugCommand::parse_from("speed fast").is_err());
+    }
+}
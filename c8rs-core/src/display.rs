@@ -1,28 +1,45 @@
-use bitvec::array::BitArray;
+use bitvec::vec::BitVec;
 
-const DISPLAY_WIDTH: usize = 64;
-const DISPLAY_HEIGHT: usize = 32;
+pub const DISPLAY_WIDTH: usize = 64;
+pub const DISPLAY_HEIGHT: usize = 32;
 
-#[derive(Default, Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Display {
-    buffer: BitArray<[usize; DISPLAY_HEIGHT]>,
+    width: usize,
+    height: usize,
+    buffer: BitVec,
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display::new(DISPLAY_WIDTH, DISPLAY_HEIGHT)
+    }
 }
 
 impl Display {
+    pub fn new(width: usize, height: usize) -> Display {
+        Display {
+            width,
+            height,
+            buffer: BitVec::repeat(false, width * height),
+        }
+    }
+
     pub(crate) fn clear(&mut self) {
-        self.buffer = BitArray::new([0; DISPLAY_HEIGHT]);
+        self.buffer.fill(false);
     }
 
     pub(crate) fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
         let mut collision = false;
 
         for (row, byte) in sprite.iter().enumerate() {
-            let py = (y as usize + row) % DISPLAY_HEIGHT;
+            let py = (y as usize + row) % self.height;
 
             for col in 0..8 {
-                let px = (x as usize + col) % DISPLAY_WIDTH;
+                let px = (x as usize + col) % self.width;
                 let bit = byte & (1 << (7 - col)) != 0;
-                let i = py * DISPLAY_WIDTH + px;
+                let i = py * self.width + px;
                 collision |= self.set_pixel(i, bit);
             }
         }
@@ -43,7 +60,7 @@ impl Display {
     }
 
     pub fn get_dimensions(&self) -> (usize, usize) {
-        (DISPLAY_WIDTH, DISPLAY_HEIGHT)
+        (self.width, self.height)
     }
 
     pub fn get_pixels(&self) -> Vec<bool> {
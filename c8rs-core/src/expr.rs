@@ -0,0 +1,346 @@
+use crate::{instructions::Register, source_map::SourceMap, Cpu};
+
+/// Debugger expression over CPU state, e.g. `V0+0x200` or `[I] == 3`
+///
+/// Arithmetic wraps at 16 bits, comparisons evaluate to 1 or 0 and `[addr]`
+/// reads a byte from memory. Other names are labels from a [`SourceMap`],
+/// replaced with their address by [`Expr::resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Num(u16),
+    Reg(Register),
+    I,
+    Pc,
+    Sp,
+    Dt,
+    St,
+    Label(String),
+    Deref(Box<Expr>),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    And,
+    Or,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Expr {
+    /// Replace labels with their address in `map`
+    pub fn resolve(self, map: &SourceMap) -> Result<Expr, String> {
+        Ok(match self {
+            Expr::Label(name) => {
+                Expr::Num(map.addr(&name).ok_or(format!("unknown label: {name}"))?)
+            }
+            Expr::Deref(addr) => Expr::Deref(Box::new(addr.resolve(map)?)),
+            Expr::Binary(lhs, op, rhs) => {
+                Expr::Binary(Box::new(lhs.resolve(map)?), op, Box::new(rhs.resolve(map)?))
+            }
+            expr => expr,
+        })
+    }
+
+    /// Value of the expression, unresolved labels evaluate to 0
+    pub fn eval(&self, cpu: &Cpu) -> u16 {
+        match self {
+            Expr::Num(n) => *n,
+            Expr::Reg(reg) => cpu.registers[*reg] as u16,
+            Expr::I => cpu.i,
+            Expr::Pc => cpu.pc,
+            Expr::Sp => cpu.sp,
+            Expr::Dt => cpu.delay_timer as u16,
+            Expr::St => cpu.sound_timer as u16,
+            Expr::Label(_) => 0,
+            Expr::Deref(addr) => cpu.mem.read_u8(addr.eval(cpu)) as u16,
+            Expr::Binary(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(cpu), rhs.eval(cpu));
+                match op {
+                    BinOp::Add => lhs.wrapping_add(rhs),
+                    BinOp::Sub => lhs.wrapping_sub(rhs),
+                    BinOp::Mul => lhs.wrapping_mul(rhs),
+                    BinOp::And => lhs & rhs,
+                    BinOp::Or => lhs | rhs,
+                    BinOp::Eq => (lhs == rhs) as u16,
+                    BinOp::Ne => (lhs != rhs) as u16,
+                    BinOp::Lt => (lhs < rhs) as u16,
+                    BinOp::Le => (lhs <= rhs) as u16,
+                    BinOp::Gt => (lhs > rhs) as u16,
+                    BinOp::Ge => (lhs >= rhs) as u16,
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for BinOp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                BinOp::Add => "+",
+                BinOp::Sub => "-",
+                BinOp::Mul => "*",
+                BinOp::And => "&",
+                BinOp::Or => "|",
+                BinOp::Eq => "==",
+                BinOp::Ne => "!=",
+                BinOp::Lt => "<",
+                BinOp::Le => "<=",
+                BinOp::Gt => ">",
+                BinOp::Ge => ">=",
+            }
+        )
+    }
+}
+
+/// Parsable form of the expression, nested operations are parenthesized
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operand = |expr: &Expr| match expr {
+            Expr::Binary(..) => format!("({expr})"),
+            _ => expr.to_string(),
+        };
+
+        match self {
+            Expr::Num(n) => write!(f, "{n:#X}"),
+            Expr::Reg(reg) => write!(f, "{reg}"),
+            Expr::I => write!(f, "I"),
+            Expr::Pc => write!(f, "PC"),
+            Expr::Sp => write!(f, "SP"),
+            Expr::Dt => write!(f, "DT"),
+            Expr::St => write!(f, "ST"),
+            Expr::Label(name) => write!(f, "{name}"),
+            Expr::Deref(addr) => write!(f, "[{addr}]"),
+            Expr::Binary(lhs, op, rhs) => write!(f, "{} {op} {}", operand(lhs), operand(rhs)),
+        }
+    }
+}
+
+impl std::str::FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            pos: 0,
+        };
+
+        let expr = parser.comparison()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected '{token}' in expression")),
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if is_word(c) {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek().filter(|&&c| is_word(c)) {
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(word);
+        } else if "=!<>".contains(c) {
+            chars.next();
+            let mut op = c.to_string();
+            if chars.peek() == Some(&'=') {
+                op.push('=');
+                chars.next();
+            }
+            tokens.push(op);
+        } else if "+-*&|()[]".contains(c) {
+            chars.next();
+            tokens.push(c.to_string());
+        } else {
+            return Err(format!("unexpected '{c}' in expression"));
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn is_word(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Recursive descent parser, lowest precedence first: comparisons, `|`,
+/// `&`, `+`/`-`, `*`
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<&str> {
+        self.pos += 1;
+        self.tokens.get(self.pos - 1).map(String::as_str)
+    }
+
+    fn binary(
+        &mut self,
+        ops: &[(&str, BinOp)],
+        operand: fn(&mut Parser) -> Result<Expr, String>,
+    ) -> Result<Expr, String> {
+        let mut expr = operand(self)?;
+
+        while let Some(&(_, op)) = ops.iter().find(|(s, _)| Some(*s) == self.peek()) {
+            self.next();
+            expr = Expr::Binary(Box::new(expr), op, Box::new(operand(self)?));
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        self.binary(
+            &[
+                ("==", BinOp::Eq),
+                ("!=", BinOp::Ne),
+                ("<", BinOp::Lt),
+                ("<=", BinOp::Le),
+                (">", BinOp::Gt),
+                (">=", BinOp::Ge),
+            ],
+            Parser::or,
+        )
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        self.binary(&[("|", BinOp::Or)], Parser::and)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        self.binary(&[("&", BinOp::And)], Parser::sum)
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        self.binary(&[("+", BinOp::Add), ("-", BinOp::Sub)], Parser::product)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        self.binary(&[("*", BinOp::Mul)], Parser::atom)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        let token = self
+            .next()
+            .ok_or("unexpected end of expression")?
+            .to_string();
+
+        let expr = match token.to_ascii_uppercase().as_str() {
+            "(" => {
+                let expr = self.comparison()?;
+                self.expect(")")?;
+                expr
+            }
+            "[" => {
+                let expr = self.comparison()?;
+                self.expect("]")?;
+                Expr::Deref(Box::new(expr))
+            }
+            "I" => Expr::I,
+            "PC" => Expr::Pc,
+            "SP" => Expr::Sp,
+            "DT" => Expr::Dt,
+            "ST" => Expr::St,
+            t if t.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') => {
+                match token.parse() {
+                    Ok(reg) => Expr::Reg(reg),
+                    Err(_) => Expr::Label(token),
+                }
+            }
+            _ => Expr::Num(
+                clap_num::maybe_hex::<u16>(&token)
+                    .map_err(|_| format!("invalid number: {token}"))?,
+            ),
+        };
+
+        Ok(expr)
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            _ => Err(format!("expected '{token}' in expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{display::Display, Memory};
+
+    fn eval(s: &str) -> Result<u16, String> {
+        let mut cpu = Cpu::new(Memory::init(&[0xAB]), Display::default());
+        cpu.registers[Register::V0] = 0x10;
+        cpu.i = 0x200;
+
+        let mut map = SourceMap::new();
+        map.add_label("main_loop", 0x204);
+
+        s.parse::<Expr>()
+            .and_then(|expr| expr.resolve(&map))
+            .map(|expr| expr.eval(&cpu))
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(eval("V0+0x200"), Ok(0x210));
+        assert_eq!(eval("I + 5"), Ok(0x205));
+        assert_eq!(eval("[I]"), Ok(0xAB));
+        assert_eq!(eval("[0x200] == 0xAB"), Ok(1));
+        assert_eq!(eval("2 + 3 * 4"), Ok(14));
+        assert_eq!(eval("(2 + 3) * 4"), Ok(20));
+        assert_eq!(eval("v0 & 0x18 != 0"), Ok(1));
+        assert_eq!(eval("pc - 0x201"), Ok(0xFFFF));
+        assert_eq!(eval("V0 >= 0x10"), Ok(1));
+        assert_eq!(eval("main_loop + 2"), Ok(0x206));
+    }
+
+    #[test]
+    fn test_display() {
+        for s in [
+            "V0 + 0x200",
+            "[I] == 0x3",
+            "(0x2 + 0x3) * 0x4",
+            "PC - [SP + 0x1]",
+            "main_loop + 0x2",
+        ] {
+            assert_eq!(s.parse::<Expr>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(eval("").is_err());
+        assert!(eval("V0 +").is_err());
+        assert!(eval("[I").is_err());
+        assert!(eval("VG").is_err());
+        assert!(eval("loop").is_err());
+        assert!(eval("2x").is_err());
+        assert!(eval("1 2").is_err());
+        assert!(eval("1 % 2").is_err());
+    }
+}
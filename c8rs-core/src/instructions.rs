@@ -11,6 +11,7 @@ macro_rules! addr {
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Register {
     V0 = 0x0,
     V1 = 0x1,
@@ -57,6 +58,20 @@ impl std::fmt::Display for Register {
     }
 }
 
+impl std::str::FromStr for Register {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digit = s
+            .strip_prefix(['V', 'v'])
+            .filter(|d| d.len() == 1)
+            .and_then(|d| u8::from_str_radix(d, 16).ok())
+            .ok_or_else(|| format!("invalid register: {s}"))?;
+
+        Ok(digit.into())
+    }
+}
+
 impl From<u8> for Register {
     fn from(value: u8) -> Self {
         match value {
@@ -82,6 +97,7 @@ impl From<u8> for Register {
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     /// 00E0
     /// Clear screen
@@ -307,9 +323,106 @@ pub enum Instruction {
         reg: Register,
     },
 
+    /// Fx75 (SCHIP)
+    /// Store registers `V0` through `Vx` in RPL user flags
+    StoreFlags {
+        reg: Register,
+    },
+
+    /// Fx85 (SCHIP)
+    /// Read registers `V0` through `Vx` from RPL user flags
+    LoadFlags {
+        reg: Register,
+    },
+
+    /// 00Cn (SCHIP)
+    /// Scroll the display down `n` pixels
+    ScrollDown {
+        n: u8,
+    },
+
+    /// 00FB (SCHIP)
+    /// Scroll the display right 4 pixels
+    ScrollRight,
+
+    /// 00FC (SCHIP)
+    /// Scroll the display left 4 pixels
+    ScrollLeft,
+
+    /// 00FD (SCHIP)
+    /// Exit the interpreter
+    Exit,
+
+    /// 00FE (SCHIP)
+    /// Switch to 64x32 low resolution
+    Lores,
+
+    /// 00FF (SCHIP)
+    /// Switch to 128x64 high resolution
+    Hires,
+
+    /// Fx30 (SCHIP)
+    /// Set I to the 10-byte sprite for digit `Vx`
+    LdHiFont {
+        reg: Register,
+    },
+
+    /// 00Dn (XO-CHIP)
+    /// Scroll the display up `n` pixels
+    ScrollUp {
+        n: u8,
+    },
+
+    /// 5xy2 (XO-CHIP)
+    /// Store registers `Vx` through `Vy` in memory starting at location `I`
+    StoreRange {
+        regx: Register,
+        regy: Register,
+    },
+
+    /// 5xy3 (XO-CHIP)
+    /// Read registers `Vx` through `Vy` from memory starting at location `I`
+    LoadRange {
+        regx: Register,
+        regy: Register,
+    },
+
+    /// F000 nnnn (XO-CHIP)
+    /// Set I to the 16-bit address in the next two bytes
+    LdILong,
+
+    /// Fn01 (XO-CHIP)
+    /// Select the bit planes drawn to with mask `n`
+    Plane {
+        mask: u8,
+    },
+
+    /// F002 (XO-CHIP)
+    /// Load the 16-byte audio pattern buffer from location `I`
+    Audio,
+
+    /// Fx3A (XO-CHIP)
+    /// Set the audio pattern playback rate from `Vx`
+    Pitch {
+        reg: Register,
+    },
+
     Unknown(u16),
 }
 
+/// Instruction set a ROM is written for, each a superset of the previous
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, clap::ValueEnum)]
+pub enum Variant {
+    /// The original interpreter
+    #[default]
+    #[value(alias = "vip")]
+    Chip8,
+    /// SUPER-CHIP 1.1
+    Schip,
+    /// Octo's XO-CHIP
+    XoChip,
+}
+
 impl std::fmt::Display for Instruction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -347,13 +460,342 @@ impl std::fmt::Display for Instruction {
             Instruction::Bcd { reg } => write!(f, "BCD {reg}"),
             Instruction::StoreRegs { reg } => write!(f, "LD [I], {reg}"),
             Instruction::LoadRegs { reg } => write!(f, "LD {reg}, [I]"),
+            Instruction::StoreFlags { reg } => write!(f, "LD R, {reg}"),
+            Instruction::LoadFlags { reg } => write!(f, "LD {reg}, R"),
+            Instruction::ScrollDown { n } => write!(f, "SCD {n:#04X}"),
+            Instruction::ScrollRight => write!(f, "SCR"),
+            Instruction::ScrollLeft => write!(f, "SCL"),
+            Instruction::Exit => write!(f, "EXIT"),
+            Instruction::Lores => write!(f, "LOW"),
+            Instruction::Hires => write!(f, "HIGH"),
+            Instruction::LdHiFont { reg } => write!(f, "LD HF, {reg}"),
+            Instruction::ScrollUp { n } => write!(f, "SCU {n:#04X}"),
+            Instruction::StoreRange { regx, regy } => write!(f, "SAVE {regx}, {regy}"),
+            Instruction::LoadRange { regx, regy } => write!(f, "LOAD {regx}, {regy}"),
+            Instruction::LdILong => write!(f, "LD I, LONG"),
+            Instruction::Plane { mask } => write!(f, "PLANE {mask:#04X}"),
+            Instruction::Audio => write!(f, "AUDIO"),
+            Instruction::Pitch { reg } => write!(f, "PITCH {reg}"),
             Instruction::Unknown(op) => write!(f, "unknown ({op:#06X})"),
         }
     }
 }
 
+/// Operand in the assembly syntax emitted by `Display`
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Operand {
+    Reg(Register),
+    Imm(u16),
+    I,
+    /// `[I]`
+    IndirectI,
+    DT,
+    ST,
+    K,
+    F,
+    /// `HF`, the SCHIP big font
+    HF,
+    R,
+    /// `LONG`, the address following `F000`
+    Long,
+}
+
+impl std::str::FromStr for Operand {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let operand = match s.to_ascii_uppercase().as_str() {
+            "I" => Operand::I,
+            "[I]" => Operand::IndirectI,
+            "DT" => Operand::DT,
+            "ST" => Operand::ST,
+            "K" => Operand::K,
+            "F" => Operand::F,
+            "HF" => Operand::HF,
+            "R" => Operand::R,
+            "LONG" => Operand::Long,
+            _ if s.starts_with(['V', 'v']) => Operand::Reg(s.parse()?),
+            _ => Operand::Imm(parse_number(s)?),
+        };
+
+        Ok(operand)
+    }
+}
+
+/// Parse a `0x` prefixed hex or a decimal number
+fn parse_number(s: &str) -> Result<u16, String> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => s.parse(),
+    }
+    .map_err(|_| format!("invalid number: {s}"))
+}
+
+fn check_range(value: u16, max: u16) -> Result<u16, String> {
+    if value > max {
+        return Err(format!("{value:#X} out of range (max {max:#X})"));
+    }
+    Ok(value)
+}
+
+impl std::str::FromStr for Instruction {
+    type Err = String;
+
+    /// Parse the syntax emitted by `Display`, e.g. `LD V1, 0x23`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use Operand::*;
+
+        let s = s.trim();
+
+        if let Some(op) = s
+            .strip_prefix("unknown (")
+            .and_then(|op| op.strip_suffix(')'))
+        {
+            return parse_number(op).map(Instruction::Unknown);
+        }
+
+        let (mnemonic, operands) = s.split_once(char::is_whitespace).unwrap_or((s, ""));
+        let operands = operands
+            .split(',')
+            .map(str::trim)
+            .filter(|o| !o.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Operand>, _>>()?;
+
+        let addr = |addr: u16| check_range(addr, 0xFFF);
+        let byte = |byte: u16| check_range(byte, 0xFF).map(|b| b as u8);
+
+        let instr = match (mnemonic.to_ascii_uppercase().as_str(), &operands[..]) {
+            ("CLS", []) => Instruction::Cls,
+            ("RET", []) => Instruction::Ret,
+            ("JMP", &[Imm(a)]) => Instruction::Jmp { addr: addr(a)? },
+            ("JMP", &[Reg(Register::V0), Imm(a)]) => Instruction::JmpReg { addr: addr(a)? },
+            ("CALL", &[Imm(a)]) => Instruction::Call { addr: addr(a)? },
+            ("SE", &[Reg(reg), Imm(b)]) => Instruction::SkipEqImm {
+                reg,
+                byte: byte(b)?,
+            },
+            ("SE", &[Reg(regx), Reg(regy)]) => Instruction::SkipEqReg { regx, regy },
+            ("SNE", &[Reg(reg), Imm(b)]) => Instruction::SkipNEqImm {
+                reg,
+                byte: byte(b)?,
+            },
+            ("SNE", &[Reg(regx), Reg(regy)]) => Instruction::SkipNEqReg { regx, regy },
+            ("LD", &[Reg(reg), Imm(b)]) => Instruction::LdImm {
+                reg,
+                byte: byte(b)?,
+            },
+            ("LD", &[Reg(regx), Reg(regy)]) => Instruction::LdReg { regx, regy },
+            ("LD", &[I, Imm(a)]) => Instruction::LdI { addr: addr(a)? },
+            ("LD", &[Reg(reg), DT]) => Instruction::LdDelayTimer { reg },
+            ("LD", &[Reg(reg), K]) => Instruction::LdKey { reg },
+            ("LD", &[DT, Reg(reg)]) => Instruction::SetDelayTimer { reg },
+            ("LD", &[ST, Reg(reg)]) => Instruction::SetSoundTimer { reg },
+            ("LD", &[F, Reg(reg)]) => Instruction::LdFont { reg },
+            ("LD", &[IndirectI, Reg(reg)]) => Instruction::StoreRegs { reg },
+            ("LD", &[Reg(reg), IndirectI]) => Instruction::LoadRegs { reg },
+            ("LD", &[R, Reg(reg)]) => Instruction::StoreFlags { reg },
+            ("LD", &[Reg(reg), R]) => Instruction::LoadFlags { reg },
+            ("ADD", &[Reg(reg), Imm(b)]) => Instruction::AddImm {
+                reg,
+                byte: byte(b)?,
+            },
+            ("ADD", &[Reg(regx), Reg(regy)]) => Instruction::AddReg { regx, regy },
+            ("ADD", &[I, Reg(reg)]) => Instruction::AddI { reg },
+            ("OR", &[Reg(regx), Reg(regy)]) => Instruction::Or { regx, regy },
+            ("AND", &[Reg(regx), Reg(regy)]) => Instruction::And { regx, regy },
+            ("XOR", &[Reg(regx), Reg(regy)]) => Instruction::Xor { regx, regy },
+            ("SUB", &[Reg(regx), Reg(regy)]) => Instruction::SubReg { regx, regy },
+            ("SHR", &[Reg(regx), Reg(regy)]) => Instruction::Shr { regx, regy },
+            ("SUBN", &[Reg(regx), Reg(regy)]) => Instruction::SubN { regx, regy },
+            ("SHL", &[Reg(regx), Reg(regy)]) => Instruction::Shl { regx, regy },
+            ("RND", &[Reg(reg), Imm(b)]) => Instruction::Rnd {
+                reg,
+                byte: byte(b)?,
+            },
+            ("DRW", &[Reg(regx), Reg(regy), Imm(len)]) => Instruction::Drw {
+                regx,
+                regy,
+                len: check_range(len, 0xF)? as u8,
+            },
+            ("SKP", &[Reg(reg)]) => Instruction::SkipPressed { reg },
+            ("SKNP", &[Reg(reg)]) => Instruction::SkipNotPressed { reg },
+            ("BCD", &[Reg(reg)]) => Instruction::Bcd { reg },
+            ("SCD", &[Imm(n)]) => Instruction::ScrollDown {
+                n: check_range(n, 0xF)? as u8,
+            },
+            ("SCR", []) => Instruction::ScrollRight,
+            ("SCL", []) => Instruction::ScrollLeft,
+            ("EXIT", []) => Instruction::Exit,
+            ("LOW", []) => Instruction::Lores,
+            ("HIGH", []) => Instruction::Hires,
+            ("LD", &[HF, Reg(reg)]) => Instruction::LdHiFont { reg },
+            ("SCU", &[Imm(n)]) => Instruction::ScrollUp {
+                n: check_range(n, 0xF)? as u8,
+            },
+            ("SAVE", &[Reg(regx), Reg(regy)]) => Instruction::StoreRange { regx, regy },
+            ("LOAD", &[Reg(regx), Reg(regy)]) => Instruction::LoadRange { regx, regy },
+            ("LD", &[I, Long]) => Instruction::LdILong,
+            ("PLANE", &[Imm(mask)]) => Instruction::Plane {
+                mask: check_range(mask, 0xF)? as u8,
+            },
+            ("AUDIO", []) => Instruction::Audio,
+            ("PITCH", &[Reg(reg)]) => Instruction::Pitch { reg },
+            _ => return Err(format!("invalid instruction: {s}")),
+        };
+
+        Ok(instr)
+    }
+}
+
 impl Instruction {
+    /// Parse a CHIP-8 opcode, as executed by [`crate::Cpu`]
     pub fn parse(op: u16) -> Instruction {
+        Instruction::parse_variant(op, Variant::Chip8)
+    }
+
+    /// Parse an opcode including the extensions of `variant`
+    pub fn parse_variant(op: u16, variant: Variant) -> Instruction {
+        let instr = Instruction::parse_chip8(op);
+        if instr != Instruction::Unknown(op) {
+            return instr;
+        }
+
+        let op0 = ((op & 0xF000) >> 12) as u8;
+        let op1 = ((op & 0x0F00) >> 8) as u8;
+        let op2 = ((op & 0x00F0) >> 4) as u8;
+        let op3 = (op & 0x000F) as u8;
+
+        let schip = match (op0, op1, op2, op3) {
+            (0x0, 0x0, 0xC, n) => Some(Instruction::ScrollDown { n }),
+            (0x0, 0x0, 0xF, 0xB) => Some(Instruction::ScrollRight),
+            (0x0, 0x0, 0xF, 0xC) => Some(Instruction::ScrollLeft),
+            (0x0, 0x0, 0xF, 0xD) => Some(Instruction::Exit),
+            (0x0, 0x0, 0xF, 0xE) => Some(Instruction::Lores),
+            (0x0, 0x0, 0xF, 0xF) => Some(Instruction::Hires),
+            (0xF, x, 0x3, 0x0) => Some(Instruction::LdHiFont { reg: x.into() }),
+            _ => None,
+        };
+        let xochip = match (op0, op1, op2, op3) {
+            (0x0, 0x0, 0xD, n) => Some(Instruction::ScrollUp { n }),
+            (0x5, x, y, 0x2) => Some(Instruction::StoreRange {
+                regx: x.into(),
+                regy: y.into(),
+            }),
+            (0x5, x, y, 0x3) => Some(Instruction::LoadRange {
+                regx: x.into(),
+                regy: y.into(),
+            }),
+            (0xF, 0x0, 0x0, 0x0) => Some(Instruction::LdILong),
+            (0xF, n, 0x0, 0x1) => Some(Instruction::Plane { mask: n }),
+            (0xF, 0x0, 0x0, 0x2) => Some(Instruction::Audio),
+            (0xF, x, 0x3, 0xA) => Some(Instruction::Pitch { reg: x.into() }),
+            _ => None,
+        };
+
+        let extended = match variant {
+            Variant::Chip8 => None,
+            Variant::Schip => schip,
+            Variant::XoChip => schip.or(xochip),
+        };
+        extended.unwrap_or(instr)
+    }
+
+    /// First variant with this instruction, `DRW` with a height of 0 draws a
+    /// 16x16 SCHIP sprite
+    pub fn variant(&self) -> Variant {
+        match self {
+            Instruction::ScrollDown { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Exit
+            | Instruction::Lores
+            | Instruction::Hires
+            | Instruction::LdHiFont { .. }
+            | Instruction::StoreFlags { .. }
+            | Instruction::LoadFlags { .. }
+            | Instruction::Drw { len: 0, .. } => Variant::Schip,
+            Instruction::ScrollUp { .. }
+            | Instruction::StoreRange { .. }
+            | Instruction::LoadRange { .. }
+            | Instruction::LdILong
+            | Instruction::Plane { .. }
+            | Instruction::Audio
+            | Instruction::Pitch { .. } => Variant::XoChip,
+            _ => Variant::Chip8,
+        }
+    }
+
+    /// Opcode of the instruction, the inverse of [`Instruction::parse_variant`].
+    /// For `F000 nnnn` this is only the first word.
+    pub fn encode(&self) -> u16 {
+        let x = |reg: &Register| (*reg as u16) << 8;
+        let xy = |regx: &Register, regy: &Register| x(regx) | ((*regy as u16) << 4);
+
+        match self {
+            Instruction::Cls => 0x00E0,
+            Instruction::Ret => 0x00EE,
+            Instruction::Jmp { addr } => 0x1000 | addr,
+            Instruction::Call { addr } => 0x2000 | addr,
+            Instruction::SkipEqImm { reg, byte } => 0x3000 | x(reg) | *byte as u16,
+            Instruction::SkipNEqImm { reg, byte } => 0x4000 | x(reg) | *byte as u16,
+            Instruction::SkipEqReg { regx, regy } => 0x5000 | xy(regx, regy),
+            Instruction::LdImm { reg, byte } => 0x6000 | x(reg) | *byte as u16,
+            Instruction::AddImm { reg, byte } => 0x7000 | x(reg) | *byte as u16,
+            Instruction::LdReg { regx, regy } => 0x8000 | xy(regx, regy),
+            Instruction::Or { regx, regy } => 0x8001 | xy(regx, regy),
+            Instruction::And { regx, regy } => 0x8002 | xy(regx, regy),
+            Instruction::Xor { regx, regy } => 0x8003 | xy(regx, regy),
+            Instruction::AddReg { regx, regy } => 0x8004 | xy(regx, regy),
+            Instruction::SubReg { regx, regy } => 0x8005 | xy(regx, regy),
+            Instruction::Shr { regx, regy } => 0x8006 | xy(regx, regy),
+            Instruction::SubN { regx, regy } => 0x8007 | xy(regx, regy),
+            Instruction::Shl { regx, regy } => 0x800E | xy(regx, regy),
+            Instruction::SkipNEqReg { regx, regy } => 0x9000 | xy(regx, regy),
+            Instruction::LdI { addr } => 0xA000 | addr,
+            Instruction::JmpReg { addr } => 0xB000 | addr,
+            Instruction::Rnd { reg, byte } => 0xC000 | x(reg) | *byte as u16,
+            Instruction::Drw { regx, regy, len } => 0xD000 | xy(regx, regy) | *len as u16,
+            Instruction::SkipPressed { reg } => 0xE09E | x(reg),
+            Instruction::SkipNotPressed { reg } => 0xE0A1 | x(reg),
+            Instruction::LdDelayTimer { reg } => 0xF007 | x(reg),
+            Instruction::LdKey { reg } => 0xF00A | x(reg),
+            Instruction::SetDelayTimer { reg } => 0xF015 | x(reg),
+            Instruction::SetSoundTimer { reg } => 0xF018 | x(reg),
+            Instruction::AddI { reg } => 0xF01E | x(reg),
+            Instruction::LdFont { reg } => 0xF029 | x(reg),
+            Instruction::Bcd { reg } => 0xF033 | x(reg),
+            Instruction::StoreRegs { reg } => 0xF055 | x(reg),
+            Instruction::LoadRegs { reg } => 0xF065 | x(reg),
+            Instruction::StoreFlags { reg } => 0xF075 | x(reg),
+            Instruction::LoadFlags { reg } => 0xF085 | x(reg),
+            Instruction::ScrollDown { n } => 0x00C0 | *n as u16,
+            Instruction::ScrollRight => 0x00FB,
+            Instruction::ScrollLeft => 0x00FC,
+            Instruction::Exit => 0x00FD,
+            Instruction::Lores => 0x00FE,
+            Instruction::Hires => 0x00FF,
+            Instruction::LdHiFont { reg } => 0xF030 | x(reg),
+            Instruction::ScrollUp { n } => 0x00D0 | *n as u16,
+            Instruction::StoreRange { regx, regy } => 0x5002 | xy(regx, regy),
+            Instruction::LoadRange { regx, regy } => 0x5003 | xy(regx, regy),
+            Instruction::LdILong => 0xF000,
+            Instruction::Plane { mask } => 0xF001 | (*mask as u16) << 8,
+            Instruction::Audio => 0xF002,
+            Instruction::Pitch { reg } => 0xF03A | x(reg),
+            Instruction::Unknown(op) => *op,
+        }
+    }
+
+    /// Size in bytes, including the operand word of `F000 nnnn`
+    pub fn size(&self) -> u16 {
+        match self {
+            Instruction::LdILong => 4,
+            _ => 2,
+        }
+    }
+
+    fn parse_chip8(op: u16) -> Instruction {
         let op0 = ((op & 0xF000) >> 12) as u8;
         let op1 = ((op & 0x0F00) >> 8) as u8;
         let op2 = ((op & 0x00F0) >> 4) as u8;
@@ -454,6 +896,8 @@ impl Instruction {
             (0xF, x, 0x3, 0x3) => Instruction::Bcd { reg: x.into() },
             (0xF, x, 0x5, 0x5) => Instruction::StoreRegs { reg: x.into() },
             (0xF, x, 0x6, 0x5) => Instruction::LoadRegs { reg: x.into() },
+            (0xF, x, 0x7, 0x5) => Instruction::StoreFlags { reg: x.into() },
+            (0xF, x, 0x8, 0x5) => Instruction::LoadFlags { reg: x.into() },
             _ => Instruction::Unknown(op),
         }
     }
@@ -603,10 +1047,106 @@ mod tests {
             (0xF133, Instruction::Bcd { reg: Register::V1 }),
             (0xF155, Instruction::StoreRegs { reg: Register::V1 }),
             (0xF165, Instruction::LoadRegs { reg: Register::V1 }),
+            (0xF175, Instruction::StoreFlags { reg: Register::V1 }),
+            (0xF185, Instruction::LoadFlags { reg: Register::V1 }),
         ];
 
         for (op, i) in tests {
             assert_eq!(Instruction::parse(op), i)
         }
     }
+
+    #[test]
+    fn test_parse_variant() {
+        assert_eq!(Instruction::parse(0x00FF), Instruction::Unknown(0x00FF));
+        assert_eq!(
+            Instruction::parse_variant(0x00FF, Variant::Schip),
+            Instruction::Hires
+        );
+        assert_eq!(
+            Instruction::parse_variant(0x00C3, Variant::Schip),
+            Instruction::ScrollDown { n: 3 }
+        );
+        assert_eq!(
+            Instruction::parse_variant(0x5122, Variant::Schip),
+            Instruction::Unknown(0x5122)
+        );
+        assert_eq!(
+            Instruction::parse_variant(0x5122, Variant::XoChip),
+            Instruction::StoreRange {
+                regx: Register::V1,
+                regy: Register::V2,
+            }
+        );
+        assert_eq!(
+            Instruction::parse_variant(0xF000, Variant::XoChip),
+            Instruction::LdILong
+        );
+        assert_eq!(Instruction::LdILong.size(), 4);
+        assert_eq!(Instruction::Cls.size(), 2);
+
+        for op in 0..=0xFFFF {
+            let instr = Instruction::parse_variant(op, Variant::XoChip);
+            assert_eq!(instr.to_string().parse(), Ok(instr), "{op:#06X}");
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        for op in 0..=0xFFFF {
+            let instr = Instruction::parse_variant(op, Variant::XoChip);
+            assert_eq!(instr.encode(), op, "{instr}");
+        }
+    }
+
+    #[test]
+    fn test_variant() {
+        for op in 0..=0xFFFF {
+            let instr = Instruction::parse_variant(op, Variant::XoChip);
+            assert_eq!(
+                Instruction::parse_variant(op, instr.variant()),
+                instr,
+                "{op:#06X}"
+            );
+        }
+
+        assert_eq!(Instruction::Cls.variant(), Variant::Chip8);
+        assert_eq!(Instruction::parse(0xD125).variant(), Variant::Chip8);
+        assert_eq!(Instruction::parse(0xD120).variant(), Variant::Schip);
+        assert_eq!(Instruction::parse(0xF075).variant(), Variant::Schip);
+        assert_eq!(Instruction::LdILong.variant(), Variant::XoChip);
+    }
+
+    #[test]
+    fn test_parse_asm() {
+        for op in 0..=0xFFFF {
+            let instr = Instruction::parse(op);
+            assert_eq!(instr.to_string().parse(), Ok(instr), "{op:#06X}");
+        }
+
+        assert_eq!(
+            "ld va, 12".parse(),
+            Ok(Instruction::LdImm {
+                reg: Register::VA,
+                byte: 12
+            })
+        );
+        assert_eq!(
+            "  JMP   0x300 ".parse(),
+            Ok(Instruction::Jmp { addr: 0x300 })
+        );
+        assert!("LD V0, 0x100".parse::<Instruction>().is_err());
+        assert!("JMP V1, 0x200".parse::<Instruction>().is_err());
+        assert!("DRW V0, V1, 16".parse::<Instruction>().is_err());
+        assert!("NOP".parse::<Instruction>().is_err());
+    }
+
+    #[test]
+    fn test_parse_register() {
+        assert_eq!("V0".parse(), Ok(Register::V0));
+        assert_eq!("va".parse(), Ok(Register::VA));
+        assert_eq!("VF".parse(), Ok(Register::VF));
+        assert!("V10".parse::<Register>().is_err());
+        assert!("I".parse::<Register>().is_err());
+    }
 }
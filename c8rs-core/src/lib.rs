@@ -1,193 +1,1802 @@
+// the threaded emulator can't be started in the browser, frontends there only
+// use `Chip8`
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
 use std::{
     cell::UnsafeCell,
-    collections::HashSet,
+    collections::{HashMap, HashSet, VecDeque},
+    io::{ErrorKind, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
     sync::{
-        mpsc::{channel, Receiver, Sender},
-        Arc,
+        mpsc::{channel, Receiver, SendError, Sender},
+        Arc, Mutex,
     },
-    thread,
     time::Duration,
 };
 
-pub use cpu::Cpu;
-pub use debug::DebugCommand;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::{self, JoinHandle};
+
+pub use alias::Aliases;
+pub use chip8::Chip8;
+#[cfg(not(target_arch = "wasm32"))]
+pub use clock::RealtimeClock;
+pub use clock::{Clock, VirtualClock};
+pub use command::{CommandError, CommandOutput, CommandResult, PendingReply};
+use command::{Reply, Request};
+pub use cpu::{Cpu, Fault};
+pub use debug::{BreakEvent, DebugCommand, InfoKind, KeyAction, Speed};
 use display::Display;
-pub use instructions::Instruction;
+pub use expr::Expr;
+pub use instructions::{Instruction, Variant};
 pub use memory::Memory;
+pub use memory_map::MemoryMap;
+pub use quirks::Quirks;
+pub use replay::{InputEvent, InputRecording};
+use screenshot::{ImageFormat, PngEncoder};
+pub use source_map::SourceMap;
+pub use trace::{InstructionTrace, OpcodeClass, TraceFilter, TraceFormat};
+use undo::{Edit, UndoStack};
 
+pub mod alias;
+pub mod chip8;
+pub mod clock;
+mod command;
 pub mod cpu;
 pub mod debug;
 pub mod display;
+pub mod expr;
+pub mod hash;
 pub mod instructions;
 pub mod memory;
+pub mod memory_map;
+pub mod quirks;
+pub mod replay;
+pub mod screenshot;
+pub mod source_map;
+pub mod trace;
+mod undo;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum EmulatorCommand {
     Stop,
     DebugCommand(DebugCommand),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EmulatorState {
     Running,
     Paused,
     Halted,
+    Faulted,
+}
+
+/// Notification broadcast to every subscriber, see
+/// [`EmulatorController::subscribe`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmulatorEvent {
+    StateChanged(EmulatorState),
+    BreakpointHit {
+        addr: u16,
+    },
+    Faulted {
+        fault: Fault,
+        pc: u16,
+    },
+    /// The instruction at `pc` matched an enabled [`BreakEvent`]
+    EventBreak {
+        event: BreakEvent,
+        pc: u16,
+    },
+    /// The instruction at `pc` overwrote `addr`, which had already been
+    /// executed
+    CodeModified {
+        addr: u16,
+        pc: u16,
+    },
+    /// A 60 Hz frame's worth of instructions has run
+    FrameCompleted,
+    /// The sound timer was set or ran out, the buzzer plays while it's
+    /// non-zero
+    SoundChanged {
+        playing: bool,
+    },
 }
 
 pub struct Chip8Emulator {
-    cmd_tx: Sender<EmulatorCommand>,
+    cmd_tx: Sender<Request>,
     inner: Arc<UnsafeCell<Chip8EmulatorInner>>,
 }
 
 impl Chip8Emulator {
     pub fn new(buf: &[u8]) -> Chip8Emulator {
+        Chip8Emulator::with_mem_size(buf, memory::MEM_SIZE)
+    }
+
+    pub fn with_mem_size(buf: &[u8], mem_size: usize) -> Chip8Emulator {
         let (cmd_tx, cmd_rx) = channel();
 
+        let mut machine = Chip8::with_mem_size(buf, mem_size);
+        machine.set_ips(10);
+        machine.cpu_mut().rng = random_seed();
+
         Chip8Emulator {
             cmd_tx,
             #[allow(clippy::arc_with_non_send_sync)]
             inner: Arc::new(UnsafeCell::new(Chip8EmulatorInner {
-                ips: 10,
                 state: EmulatorState::Paused,
-                cpu: Cpu::new(Memory::init(buf), Display::default()),
+                machine,
                 cmd_rx,
                 breakpoints: HashSet::new(),
+                flags_file: None,
+                aliases: Aliases::new(),
+                aliases_file: None,
+                read_rom: Box::new(read_rom),
+                encode_png: None,
+                source_map: SourceMap::new(),
+                slots: HashMap::new(),
+                taps: Vec::new(),
+                undo: UndoStack::default(),
+                clock: default_clock(),
+                speed: Speed::default(),
+                pause_on_code_write: false,
+                break_events: HashSet::new(),
+                tracepoints: HashMap::new(),
+                conditions: HashMap::new(),
+                run_to: None,
+                finish_sp: None,
+                steps: None,
+                script: VecDeque::new(),
+                frame: 0,
+                frame_cycles: 0,
+                frame_time: Duration::ZERO,
+                last_instruction: Duration::ZERO,
+                frame_completed: false,
+                instructions: 0,
+                sound: false,
+                recorder: None,
+                replay: None,
+                trace: None,
+                subscribers: Mutex::new(Vec::new()),
             })),
         }
     }
 
+    /// Persist RPL user flags (Fx75/Fx85) to `path`, loading previously saved
+    /// flags if the file exists.
+    pub fn with_flags_file(self, path: impl Into<PathBuf>) -> Chip8Emulator {
+        let path = path.into();
+        let inner = unsafe { &mut *self.inner.get() };
+
+        match std::fs::read(&path) {
+            Ok(bytes) => {
+                let flags = &mut inner.machine.cpu_mut().flags;
+                let n = bytes.len().min(flags.len());
+                flags[..n].copy_from_slice(&bytes[..n]);
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => (),
+            Err(err) => log::warn!("Failed to read flags file {}: {err}", path.display()),
+        }
+
+        inner.flags_file = Some(path);
+        self
+    }
+
+    /// Persist debugger aliases to `path`, loading previously saved aliases
+    /// if the file exists.
+    pub fn with_aliases_file(self, path: impl Into<PathBuf>) -> Chip8Emulator {
+        let path = path.into();
+        let inner = unsafe { &mut *self.inner.get() };
+
+        match std::fs::read_to_string(&path) {
+            Ok(s) => match Aliases::from_lines(&s) {
+                Ok(aliases) => inner.aliases = aliases,
+                Err(err) => log::warn!("Failed to parse aliases file {}: {err}", path.display()),
+            },
+            Err(err) if err.kind() == ErrorKind::NotFound => (),
+            Err(err) => log::warn!("Failed to read aliases file {}: {err}", path.display()),
+        }
+
+        inner.aliases_file = Some(path);
+        self
+    }
+
+    /// Read the ROMs of [`DebugCommand::Load`] with `read` instead of
+    /// [`std::fs::read`], e.g. to decompress archives
+    pub fn with_rom_reader(
+        self,
+        read: impl Fn(&Path) -> Result<Vec<u8>, String> + Send + 'static,
+    ) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.read_rom = Box::new(read);
+        self
+    }
+
+    /// Save [`DebugCommand::Screenshot`]s to PNG files with `encode`,
+    /// without one only `.pbm` and `.txt` screenshots can be taken
+    pub fn with_png_encoder(self, encode: PngEncoder) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.encode_png = Some(encode);
+        self
+    }
+
+    /// Let debugger expressions refer to the labels in `map`, e.g.
+    /// `break main_loop`
+    pub fn with_source_map(self, map: SourceMap) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.source_map = map;
+        self
+    }
+
+    /// See [`Chip8::add_opcode_hook`]
+    pub fn with_opcode_hook(
+        self,
+        opcodes: RangeInclusive<u16>,
+        handler: impl FnMut(&mut Cpu, u16) + Send + 'static,
+    ) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }
+            .machine
+            .add_opcode_hook(opcodes, handler);
+        self
+    }
+
+    /// Fault when the program writes outside its own memory, see
+    /// [`MemoryMap`]
+    pub fn with_memory_protection(self) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }
+            .machine
+            .cpu_mut()
+            .protect_memory = true;
+        self
+    }
+
+    /// Emulate the differences of another interpreter, see [`Quirks`]
+    pub fn with_quirks(self, quirks: Quirks) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.machine.cpu_mut().quirks = quirks;
+        self
+    }
+
+    /// Pause when the program overwrites code it has already executed
+    pub fn with_pause_on_code_write(self) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.pause_on_code_write = true;
+        self
+    }
+
+    /// Write the random number seed and every change of the keypad state to
+    /// `out`, see [`InputRecording`]
+    pub fn with_input_recording(self, out: impl Write + Send + 'static) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.recorder = Some(InputRecorder {
+            out: Box::new(out),
+            keys: None,
+        });
+        self
+    }
+
+    /// Play back the keypad input of `recording` instead of taking input
+    /// from [`DebugCommand::Key`], using its seed and IPS
+    pub fn with_input_replay(self, recording: InputRecording) -> Chip8Emulator {
+        let inner = unsafe { &mut *self.inner.get() };
+        inner.machine.cpu_mut().rng = recording.seed;
+        inner.machine.set_ips(recording.ips);
+        inner.replay = Some(recording.events.into());
+        self
+    }
+
+    /// Write every executed instruction to `trace`
+    pub fn with_instruction_trace(self, trace: InstructionTrace) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.trace = Some(trace);
+        self
+    }
+
+    /// Pace the emulator with `clock` instead of the wall clock
+    pub fn with_clock(self, clock: impl Clock + 'static) -> Chip8Emulator {
+        unsafe { &mut *self.inner.get() }.clock = Box::new(clock);
+        self
+    }
+
     pub fn controller(&self) -> EmulatorController {
         EmulatorController {
             cmd_tx: self.cmd_tx.clone(),
+            display: unsafe { &*self.inner.get() }.machine.shared_display(),
             emulator: self.inner.clone(),
         }
     }
 
-    pub fn start(self) {
-        let inner = unsafe { &mut *self.inner.get() };
-        thread::spawn(move || {
-            inner.run();
+    /// Run the emulator on its own thread until it receives
+    /// [`EmulatorCommand::Stop`]
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start(self) -> EmulatorHandle {
+        let state = EmulatorThread(self.inner.clone());
+        let thread = thread::spawn(move || state.run());
+
+        EmulatorHandle {
+            cmd_tx: self.cmd_tx,
+            thread,
+        }
+    }
+}
+
+/// Wall clock where there is one, the browser has no thread to sleep on
+fn default_clock() -> Box<dyn Clock> {
+    #[cfg(not(target_arch = "wasm32"))]
+    return Box::new(RealtimeClock::default());
+    #[cfg(target_arch = "wasm32")]
+    Box::new(VirtualClock::new())
+}
+
+/// Default reader of [`Chip8Emulator::with_rom_reader`]
+fn read_rom(path: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|err| format!("Failed to read {}: {err}", path.display()))
+}
+
+/// Seed for the RND instruction, the system time isn't available in the
+/// browser
+fn random_seed() -> u32 {
+    #[cfg(not(target_arch = "wasm32"))]
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    #[cfg(target_arch = "wasm32")]
+    0
+}
+
+/// Emulated time between two ticks of the delay and sound timers
+const FRAME: Duration = Duration::from_nanos(1_000_000_000 / chip8::TIMER_HZ as u64);
+
+/// Most emulated time one instruction moves on by, so a stalled host
+/// doesn't fast forward the timers when it resumes
+const MAX_INSTRUCTION_TIME: Duration = Duration::from_millis(250);
+
+/// Extension `load` recognizes as a save state, other files are ROMs
+const STATE_EXTENSION: &str = "c8state";
+
+/// Whether a save state name refers to a file rather than a slot
+fn is_path(name: &str) -> bool {
+    name.contains(['.', '/', std::path::MAIN_SEPARATOR])
+}
+
+/// Emulator state moved into its thread, which holds a reference so it
+/// outlives the [`Chip8Emulator`] and any controllers
+#[cfg(not(target_arch = "wasm32"))]
+struct EmulatorThread(Arc<UnsafeCell<Chip8EmulatorInner>>);
+
+// the thread is the only one mutating the state, controllers only read it
+#[cfg(not(target_arch = "wasm32"))]
+unsafe impl Send for EmulatorThread {}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EmulatorThread {
+    fn run(self) {
+        unsafe { &mut *self.0.get() }.run();
+    }
+}
+
+/// Handle to the thread of a started [`Chip8Emulator`]
+#[cfg(not(target_arch = "wasm32"))]
+pub struct EmulatorHandle {
+    cmd_tx: Sender<Request>,
+    thread: JoinHandle<()>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EmulatorHandle {
+    /// Wait for the emulator thread to exit
+    pub fn join(self) -> thread::Result<()> {
+        self.thread.join()
+    }
+
+    /// Send [`EmulatorCommand::Stop`] and wait for the emulator thread to exit
+    pub fn stop(self) -> thread::Result<()> {
+        let _ = self.cmd_tx.send(Request {
+            cmd: EmulatorCommand::Stop,
+            reply: Reply(None),
         });
+        self.join()
     }
 }
 
+/// See [`Chip8Emulator::with_rom_reader`]
+type RomReader = Box<dyn Fn(&Path) -> Result<Vec<u8>, String> + Send>;
+
 struct Chip8EmulatorInner {
-    ips: u32,
     state: EmulatorState,
-    cpu: Cpu,
-    cmd_rx: Receiver<EmulatorCommand>,
+    machine: Chip8,
+    cmd_rx: Receiver<Request>,
     breakpoints: HashSet<u16>,
+    flags_file: Option<PathBuf>,
+    aliases: Aliases,
+    aliases_file: Option<PathBuf>,
+    /// Reads the ROMs of [`DebugCommand::Load`]
+    read_rom: RomReader,
+    encode_png: Option<PngEncoder>,
+    /// Labels expressions may refer to
+    source_map: SourceMap,
+    /// Save states kept in memory, by name
+    slots: HashMap<String, Cpu>,
+    /// Tapped keys and the number of frames until they are released
+    taps: Vec<(u8, u32)>,
+    undo: UndoStack,
+    clock: Box<dyn Clock>,
+    speed: Speed,
+    pause_on_code_write: bool,
+    break_events: HashSet<BreakEvent>,
+    tracepoints: HashMap<u16, String>,
+    /// Conditions of conditional breakpoints
+    conditions: HashMap<u16, Expr>,
+    /// One-shot breakpoint set by [`DebugCommand::Until`]
+    run_to: Option<u16>,
+    /// Stack pointer of the subroutine [`DebugCommand::Finish`] runs out of
+    finish_sp: Option<u16>,
+    steps: Option<MultiStep>,
+    /// Commands from [`DebugCommand::Source`] still to run
+    script: VecDeque<DebugCommand>,
+    /// Frames completed since power on, the clock of input recordings
+    frame: u64,
+    /// Instructions executed in the current frame
+    frame_cycles: u32,
+    /// Emulated time passed in the current frame
+    frame_time: Duration,
+    /// Clock time of the last instruction
+    last_instruction: Duration,
+    /// The last instruction completed a frame
+    frame_completed: bool,
+    /// Instructions executed since power on
+    instructions: u64,
+    /// Whether the last [`EmulatorEvent::SoundChanged`] started the buzzer
+    sound: bool,
+    recorder: Option<InputRecorder>,
+    /// Input of [`Chip8Emulator::with_input_replay`] still to play back
+    replay: Option<VecDeque<InputEvent>>,
+    trace: Option<InstructionTrace>,
+    subscribers: Mutex<Vec<Sender<EmulatorEvent>>>,
+}
+
+/// Output of [`Chip8Emulator::with_input_recording`]
+struct InputRecorder {
+    out: Box<dyn Write + Send>,
+    /// Last recorded keypad state, `None` until the header is written
+    keys: Option<[bool; 16]>,
+}
+
+/// Progress of a `step N` command
+struct MultiStep {
+    count: u32,
+    done: u32,
+    reply: Reply,
 }
 
 impl Chip8EmulatorInner {
     fn run(&mut self) {
-        let mut interval = spin_sleep_util::interval(Duration::from_secs(1) / self.ips);
+        self.update_clock();
 
         loop {
+            let pc = self.machine.cpu().pc;
+            if self.state == EmulatorState::Running
+                && (self.breakpoint_hit(pc) || self.run_to == Some(pc))
             {
-                let pc = self.cpu.pc;
-                if self.breakpoints.contains(&pc) {
-                    self.state = EmulatorState::Paused;
-                    log::info!("Breakpoint hit: PC={pc:#06X}");
-                }
+                self.run_to = None;
+                self.finish_sp = None;
+                self.set_state(EmulatorState::Paused);
+                log::info!("Breakpoint hit: PC={pc:#06X}");
+                self.emit(EmulatorEvent::BreakpointHit { addr: pc });
             }
 
-            if let Some(cmd) = match self.state {
+            // like gdb, a script waits for the emulator to pause before
+            // running its next command
+            let request = match self.state {
                 EmulatorState::Running => self.cmd_rx.try_recv().ok(),
-                EmulatorState::Paused | EmulatorState::Halted => self.cmd_rx.recv().ok(),
-            } {
+                EmulatorState::Paused | EmulatorState::Halted | EmulatorState::Faulted => self
+                    .script
+                    .pop_front()
+                    .map(|cmd| Request {
+                        cmd: EmulatorCommand::DebugCommand(cmd),
+                        reply: Reply(None),
+                    })
+                    .or_else(|| self.cmd_rx.recv().ok()),
+            };
+
+            if let Some(Request { cmd, reply }) = request {
                 match cmd {
-                    EmulatorCommand::Stop => break,
+                    EmulatorCommand::Stop => {
+                        reply.send(Ok(CommandOutput::None));
+                        break;
+                    }
+                    EmulatorCommand::DebugCommand(DebugCommand::Step { count }) if count > 1 => {
+                        // replied to once the emulator pauses again
+                        self.steps = Some(MultiStep {
+                            count,
+                            done: 0,
+                            reply,
+                        });
+                        self.set_state(EmulatorState::Running);
+                    }
                     EmulatorCommand::DebugCommand(DebugCommand::IPS { ips }) => {
-                        self.ips = ips;
-                        interval = spin_sleep_util::interval(Duration::from_secs(1) / self.ips);
+                        self.machine.set_ips(ips);
+                        self.update_clock();
+                        reply.send(Ok(CommandOutput::None));
                         continue;
                     }
                     EmulatorCommand::DebugCommand(cmd) => {
-                        if !self.handle_debug_cmd(cmd) {
+                        let (step, result) = self.handle_debug_cmd(cmd);
+                        reply.send(result);
+                        if !step {
                             continue;
                         }
                     }
                 }
             }
 
-            if self.cpu.step() {
-                log::info!("CPU halted");
-                self.state = EmulatorState::Halted;
+            let pc = self.machine.cpu().pc;
+            if let Some(format) = self.tracepoints.get(&pc) {
+                log::info!(
+                    "Trace {pc:#06X}: {}",
+                    debug::format_trace(format, self.machine.cpu())
+                );
             }
 
-            interval.tick();
+            let instr = Instruction::parse(self.machine.memory().read_u16(pc));
+            let prev_i = self.machine.cpu().i;
+            let halted = self.cycle();
+
+            // a RET popping past the frame finish started in
+            if instr == Instruction::Ret
+                && self.finish_sp.is_some_and(|sp| self.machine.cpu().sp > sp)
+            {
+                self.finish_sp = None;
+                log::info!("Returned to PC={:#06X}", self.machine.cpu().pc);
+                self.set_state(EmulatorState::Paused);
+            }
+
+            if let Some(event) = self.break_event(instr, prev_i) {
+                log::info!("Break on {event}: PC={pc:#06X}");
+                self.set_state(EmulatorState::Paused);
+                self.emit(EmulatorEvent::EventBreak { event, pc });
+            }
+
+            if let Some(addr) = self.machine.last_code_write() {
+                log::info!("Self-modifying code: {addr:#06X} overwritten at PC={pc:#06X}");
+                self.emit(EmulatorEvent::CodeModified { addr, pc });
+                if self.pause_on_code_write {
+                    self.set_state(EmulatorState::Paused);
+                }
+            }
+
+            if halted {
+                let cpu = self.machine.cpu();
+                if let Some(fault) = cpu.fault {
+                    let pc = cpu.pc;
+                    log::error!("CPU faulted: {fault} at PC={pc:#06X}");
+                    self.set_state(EmulatorState::Faulted);
+                    self.emit(EmulatorEvent::Faulted { fault, pc });
+                } else {
+                    log::info!("CPU halted");
+                    self.set_state(EmulatorState::Halted);
+                }
+            }
+
+            if let Some(steps) = &mut self.steps {
+                steps.done += 1;
+                if steps.done >= steps.count {
+                    self.set_state(EmulatorState::Paused);
+                }
+            }
+
+            // show every step while debugging, not just completed frames
+            if self.state != EmulatorState::Running {
+                self.machine.present();
+            }
+
+            if self.frame_completed {
+                self.release_taps();
+                self.emit(EmulatorEvent::FrameCompleted);
+            }
+
+            let sound = self.machine.cpu().sound_timer > 0;
+            if sound != self.sound {
+                self.sound = sound;
+                self.emit(EmulatorEvent::SoundChanged { playing: sound });
+            }
+
+            if self.machine.cpu().flags_dirty {
+                self.save_flags();
+            }
+
+            self.clock.tick();
+        }
+
+        log::info!("Emulator stopped");
+        self.flush_trace();
+
+        // controllers can outlive the thread, drop the receiver so their
+        // later requests fail instead of waiting forever
+        self.cmd_rx = channel().1;
+    }
+
+    /// Execute an instruction with the keypad state being replayed or
+    /// recorded, see [`Chip8::cycle`]
+    fn cycle(&mut self) -> bool {
+        self.replay_input();
+        self.record_input();
+        self.write_trace();
+
+        let halted = self.machine.step();
+        self.instructions += 1;
+        self.frame_cycles += 1;
+        self.advance_time();
+        halted
+    }
+
+    /// Move emulated time on by one instruction, ticking the timers and
+    /// presenting the display every 1/60 s of it. Running, that's the
+    /// clock's time since the last instruction. Single steps, unlimited
+    /// speed and recorded or replayed input, which needs the same frames on
+    /// every run, take 1/IPS per instruction instead.
+    fn advance_time(&mut self) {
+        let now = self.clock.now();
+        let elapsed = match self.speed {
+            Speed::Percent(percent)
+                if self.state == EmulatorState::Running
+                    && self.recorder.is_none()
+                    && self.replay.is_none() =>
+            {
+                now.saturating_sub(self.last_instruction)
+                    .min(MAX_INSTRUCTION_TIME)
+                    * percent
+                    / 100
+            }
+            _ => Duration::from_secs(1) / self.machine.ips(),
+        };
+        self.last_instruction = now;
+
+        self.frame_completed = false;
+        self.frame_time += elapsed;
+        while self.frame_time >= FRAME {
+            self.frame_time -= FRAME;
+            self.machine.tick_timers();
+            self.frame += 1;
+            self.frame_cycles = 0;
+            self.frame_completed = true;
+        }
+        if self.frame_completed {
+            self.machine.present();
+            self.flush_trace();
+        }
+    }
+
+    fn replay_input(&mut self) {
+        let Some(replay) = &mut self.replay else {
+            return;
+        };
+
+        let now = (self.frame, self.frame_cycles);
+        while let Some(event) = replay
+            .front()
+            .copied()
+            .filter(|event| (event.frame, event.cycle) <= now)
+        {
+            self.machine.cpu_mut().keys = event.keys;
+            replay.pop_front();
+        }
+
+        if replay.is_empty() {
+            log::info!("Input replay finished at frame {}", self.frame);
+            self.replay = None;
+        }
+    }
+
+    /// Record the keypad state if it changed since the last instruction,
+    /// writing the header before the first one
+    fn record_input(&mut self) {
+        let Some(recorder) = &mut self.recorder else {
+            return;
+        };
+
+        let keys = self.machine.cpu().keys;
+        let line = match recorder.keys {
+            Some(recorded) if recorded == keys => return,
+            Some(_) => InputEvent {
+                frame: self.frame,
+                cycle: self.frame_cycles,
+                keys,
+            }
+            .to_string(),
+            None => InputRecording {
+                seed: self.machine.cpu().rng,
+                ips: self.machine.ips(),
+                rom: Some(hash::sha1_hex(self.machine.rom())),
+                events: vec![InputEvent {
+                    frame: self.frame,
+                    cycle: self.frame_cycles,
+                    keys,
+                }],
+            }
+            .to_string(),
+        };
+        recorder.keys = Some(keys);
+
+        if let Err(err) = writeln!(recorder.out, "{}", line.trim_end()) {
+            log::warn!("Failed to write input recording, stopping it: {err}");
+            self.recorder = None;
+        }
+    }
+
+    fn write_trace(&mut self) {
+        let Some(trace) = &mut self.trace else {
+            return;
+        };
+
+        let cycle = self.frame_cycles;
+        if let Err(err) = trace.write(self.frame, cycle, self.machine.cpu()) {
+            log::warn!("Failed to write instruction trace, stopping it: {err}");
+            self.trace = None;
+        }
+    }
+
+    fn flush_trace(&mut self) {
+        let Some(trace) = &mut self.trace else {
+            return;
+        };
+
+        if let Err(err) = trace.flush() {
+            log::warn!("Failed to write instruction trace, stopping it: {err}");
+            self.trace = None;
+        }
+    }
+
+    fn breakpoint_hit(&self, pc: u16) -> bool {
+        self.breakpoints.contains(&pc)
+            && self
+                .conditions
+                .get(&pc)
+                .is_none_or(|condition| condition.eval(self.machine.cpu()) != 0)
+    }
+
+    /// Enabled break event matched by `instr`, which just executed
+    fn break_event(&self, instr: Instruction, prev_i: u16) -> Option<BreakEvent> {
+        let cpu = self.machine.cpu();
+        self.break_events.iter().copied().find(|event| match event {
+            BreakEvent::Draw => matches!(instr, Instruction::Drw { .. }),
+            BreakEvent::Cls => {
+                instr == Instruction::Cls
+                    || (cpu.hires && instr == Instruction::Unknown(cpu::HIRES_CLS))
+            }
+            BreakEvent::I => cpu.i != prev_i,
+        })
+    }
+
+    /// Count tapped keys down by a frame, releasing the expired ones
+    fn release_taps(&mut self) {
+        let keys = &mut self.machine.cpu_mut().keys;
+        self.taps.retain_mut(|(key, frames)| {
+            *frames = frames.saturating_sub(1);
+            if *frames == 0 {
+                keys[*key as usize] = false;
+            }
+            *frames > 0
+        });
+    }
+
+    fn update_clock(&mut self) {
+        let period = match self.speed {
+            Speed::Percent(percent) => Duration::from_nanos(
+                1_000_000_000 * 100 / (self.machine.ips() as u64 * percent as u64),
+            ),
+            Speed::Unlimited => Duration::ZERO,
+        };
+        self.clock.set_period(period);
+    }
+
+    fn save_flags(&mut self) {
+        self.machine.cpu_mut().flags_dirty = false;
+
+        let Some(path) = &self.flags_file else {
+            return;
+        };
+
+        if let Err(err) = std::fs::write(path, self.machine.cpu().flags) {
+            log::warn!("Failed to write flags file {}: {err}", path.display());
+        }
+    }
+
+    fn save_state(&mut self, name: &str) -> Result<(), String> {
+        if !is_path(name) {
+            self.slots
+                .insert(name.to_string(), self.machine.cpu().clone());
+            return Ok(());
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(self.machine.cpu()).map_err(|err| err.to_string())?;
+            std::fs::write(name, json).map_err(|err| format!("Failed to write {name}: {err}"))
+        }
+        #[cfg(not(feature = "serde"))]
+        Err("Saving to a file requires the serde feature".to_string())
+    }
+
+    fn load_state(&self, name: &str) -> Result<Cpu, String> {
+        if !is_path(name) {
+            return self
+                .slots
+                .get(name)
+                .cloned()
+                .ok_or(format!("No saved state '{name}'"));
+        }
+
+        #[cfg(feature = "serde")]
+        {
+            let json = std::fs::read_to_string(name)
+                .map_err(|err| format!("Failed to read {name}: {err}"))?;
+            serde_json::from_str(&json).map_err(|err| format!("Invalid state file {name}: {err}"))
+        }
+        #[cfg(not(feature = "serde"))]
+        Err("Loading from a file requires the serde feature".to_string())
+    }
+
+    /// Write the display to `path` in the format of its extension
+    fn save_screenshot(&self, path: &Path) -> Result<(), String> {
+        let display = &self.machine.cpu().display;
+        let image = match (ImageFormat::from_path(path), self.encode_png) {
+            (Some(format), _) => screenshot::encode(display, format),
+            (None, Some(encode_png)) => encode_png(display)?,
+            (None, None) => return Err("Can only save .pbm and .txt screenshots".to_string()),
+        };
+        std::fs::write(path, image)
+            .map_err(|err| format!("Failed to write {}: {err}", path.display()))
+    }
+
+    /// Replace the program with the ROM at `path` and pause at its entry,
+    /// returns the ROM size
+    fn load_rom(&mut self, path: &Path) -> Result<usize, String> {
+        let rom = (self.read_rom)(path)?;
+
+        let max_len = self.machine.memory().size() - 0x200;
+        if rom.len() > max_len {
+            return Err(format!(
+                "ROM is {} bytes, at most {max_len} fit in memory",
+                rom.len()
+            ));
+        }
+
+        self.machine.load_rom(&rom);
+        self.taps.clear();
+        self.undo = UndoStack::default();
+        self.run_to = None;
+        self.finish_sp = None;
+        self.set_state(EmulatorState::Paused);
+        Ok(rom.len())
+    }
+
+    fn save_aliases(&self) {
+        let Some(path) = &self.aliases_file else {
+            return;
+        };
+
+        let res = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, self.aliases.to_string()));
+        if let Err(err) = res {
+            log::warn!("Failed to write aliases file {}: {err}", path.display());
         }
     }
 
-    fn handle_debug_cmd(&mut self, cmd: DebugCommand) -> bool {
+    /// Handle a debug command, returns whether the next instruction should
+    /// be executed and the result for the sender
+    fn handle_debug_cmd(&mut self, cmd: DebugCommand) -> (bool, CommandResult) {
         match cmd {
-            DebugCommand::Step => true,
+            DebugCommand::Step { count } => (count > 0, Ok(CommandOutput::None)),
             DebugCommand::Pause => {
-                self.state = EmulatorState::Paused;
-                false
+                self.run_to = None;
+                self.finish_sp = None;
+                self.set_state(EmulatorState::Paused);
+                (false, Ok(CommandOutput::None))
             }
             DebugCommand::Continue => {
-                self.state = EmulatorState::Running;
-                true
+                self.set_state(EmulatorState::Running);
+                (true, Ok(CommandOutput::None))
             }
-            DebugCommand::Breakpoint { addr } => {
-                if self.breakpoints.contains(&addr) {
-                    self.breakpoints.remove(&addr);
-                    log::info!("Breakpoint removed: {addr:#06X}");
-                } else {
-                    self.breakpoints.insert(addr);
-                    log::info!("Breakpoint set: {addr:#06X}");
+            DebugCommand::Finish => {
+                let sp = self.machine.cpu().sp;
+                if sp >= cpu::STACK_TOP {
+                    return (
+                        false,
+                        Err(CommandError::Failed("Not in a subroutine".to_string())),
+                    );
                 }
-                false
+
+                self.finish_sp = Some(sp);
+                self.set_state(EmulatorState::Running);
+                (true, Ok(CommandOutput::None))
+            }
+            DebugCommand::Until { addr } => {
+                self.run_to = Some(addr);
+                self.set_state(EmulatorState::Running);
+                (true, Ok(CommandOutput::None))
+            }
+            DebugCommand::Breakpoint { addr, condition } => {
+                let resolved = addr.resolve(&self.source_map).and_then(|addr| {
+                    let condition = condition
+                        .map(|condition| condition.resolve(&self.source_map))
+                        .transpose()?;
+                    Ok((addr, condition))
+                });
+                let (addr, condition) = match resolved {
+                    Ok(resolved) => resolved,
+                    Err(err) => return (false, Err(CommandError::Failed(err))),
+                };
+
+                let addr = addr.eval(self.machine.cpu());
+                match condition {
+                    Some(condition) => {
+                        if !self.breakpoints.contains(&addr) {
+                            self.edit(Edit::Breakpoint { addr });
+                        }
+                        self.conditions.insert(addr, condition);
+                    }
+                    None => {
+                        self.conditions.remove(&addr);
+                        self.edit(Edit::Breakpoint { addr });
+                    }
+                }
+                let msg = if self.breakpoints.contains(&addr) {
+                    format!("Breakpoint set: {addr:#06X}")
+                } else {
+                    format!("Breakpoint removed: {addr:#06X}")
+                };
+                (false, Ok(CommandOutput::Message(msg)))
+            }
+            DebugCommand::Trace {
+                addr,
+                format: Some(format),
+            } => {
+                self.tracepoints.insert(addr, format);
+                (
+                    false,
+                    Ok(CommandOutput::Message(format!(
+                        "Tracepoint set: {addr:#06X}"
+                    ))),
+                )
+            }
+            DebugCommand::Trace { addr, format: None } => {
+                let result = match self.tracepoints.remove(&addr) {
+                    Some(_) => Ok(CommandOutput::Message(format!(
+                        "Tracepoint removed: {addr:#06X}"
+                    ))),
+                    None => Err(CommandError::Failed(format!(
+                        "No tracepoint at {addr:#06X}"
+                    ))),
+                };
+                (false, result)
+            }
+            DebugCommand::BreakOn { event } => {
+                let msg = if self.break_events.insert(event) {
+                    format!("Break on {event} enabled")
+                } else {
+                    self.break_events.remove(&event);
+                    format!("Break on {event} disabled")
+                };
+                (false, Ok(CommandOutput::Message(msg)))
             }
             DebugCommand::Reset => {
-                self.cpu.reset();
-                false
+                self.machine.reset();
+                self.taps.clear();
+                if self.state == EmulatorState::Faulted {
+                    self.set_state(EmulatorState::Paused);
+                }
+                (false, Ok(CommandOutput::None))
             }
             DebugCommand::SetPc { addr } => {
-                self.cpu.pc = addr;
-                false
+                self.edit(Edit::Pc {
+                    old: self.machine.cpu().pc,
+                    new: addr,
+                });
+                (false, Ok(CommandOutput::None))
+            }
+            DebugCommand::SetReg { reg, value } => {
+                self.edit(Edit::Register {
+                    reg,
+                    old: self.machine.cpu().registers[reg],
+                    new: value,
+                });
+                (false, Ok(CommandOutput::None))
+            }
+            DebugCommand::WriteMem { addr, value } => {
+                self.edit(Edit::Memory {
+                    addr,
+                    old: self.machine.memory().read_u8(addr),
+                    new: value,
+                });
+                (false, Ok(CommandOutput::None))
+            }
+            DebugCommand::Patch { addr, instr } => {
+                let new = instr.encode();
+                self.edit(Edit::Word {
+                    addr,
+                    old: self.machine.memory().read_u16(addr),
+                    new,
+                });
+                (
+                    false,
+                    Ok(CommandOutput::Message(format!(
+                        "Patched {addr:#06X}: {new:04X} {instr}"
+                    ))),
+                )
+            }
+            DebugCommand::Undo => {
+                let result = match self
+                    .undo
+                    .undo(self.machine.cpu_mut(), &mut self.breakpoints)
+                {
+                    Some(edit) => Ok(CommandOutput::Message(format!("Undo: {edit}"))),
+                    None => Err(CommandError::Failed("Nothing to undo".to_string())),
+                };
+                (false, result)
+            }
+            DebugCommand::Redo => {
+                let result = match self
+                    .undo
+                    .redo(self.machine.cpu_mut(), &mut self.breakpoints)
+                {
+                    Some(edit) => Ok(CommandOutput::Message(format!("Redo: {edit}"))),
+                    None => Err(CommandError::Failed("Nothing to redo".to_string())),
+                };
+                (false, result)
             }
-            DebugCommand::IPS { .. } => false,
+            DebugCommand::IPS { .. } => (false, Ok(CommandOutput::None)),
+            DebugCommand::Speed { speed } => {
+                self.speed = speed;
+                self.update_clock();
+                (false, Ok(CommandOutput::None))
+            }
+            DebugCommand::Alias {
+                name: None,
+                expansion: _,
+            } => {
+                let rows = self
+                    .aliases
+                    .iter()
+                    .map(|(name, expansion)| [name.to_string(), expansion.to_string()])
+                    .collect();
+                (false, Ok(CommandOutput::table(["alias", "commands"], rows)))
+            }
+            DebugCommand::Info { what } => (false, Ok(self.info(what))),
+            DebugCommand::Alias {
+                name: Some(name),
+                expansion: None,
+            } => match self.aliases.get(&name) {
+                Some(expansion) => (
+                    false,
+                    Ok(CommandOutput::Message(format!("{name} = {expansion}"))),
+                ),
+                None => (
+                    false,
+                    Err(CommandError::Failed(format!("No alias '{name}'"))),
+                ),
+            },
+            DebugCommand::Alias {
+                name: Some(name),
+                expansion: Some(expansion),
+            } => match self.aliases.define(&name, &expansion) {
+                Ok(()) => {
+                    self.save_aliases();
+                    (
+                        false,
+                        Ok(CommandOutput::Message(format!("Alias defined: {name}"))),
+                    )
+                }
+                Err(err) => (false, Err(CommandError::Failed(err))),
+            },
+            DebugCommand::Unalias { name } => {
+                if self.aliases.remove(&name) {
+                    self.save_aliases();
+                    (
+                        false,
+                        Ok(CommandOutput::Message(format!("Alias removed: {name}"))),
+                    )
+                } else {
+                    (
+                        false,
+                        Err(CommandError::Failed(format!("No alias '{name}'"))),
+                    )
+                }
+            }
+            DebugCommand::Key { .. } if self.replay.is_some() => (
+                false,
+                Err(CommandError::Failed(
+                    "Keypad input comes from the replay".to_string(),
+                )),
+            ),
+            DebugCommand::Key { action } => {
+                let keys = &mut self.machine.cpu_mut().keys;
+                match action {
+                    KeyAction::Press { key } => keys[key as usize] = true,
+                    KeyAction::Release { key } => {
+                        keys[key as usize] = false;
+                        self.taps.retain(|&(tapped, _)| tapped != key);
+                    }
+                    KeyAction::Tap { key, frames } => {
+                        keys[key as usize] = true;
+                        self.taps.retain(|&(tapped, _)| tapped != key);
+                        self.taps.push((key, frames.max(1)));
+                    }
+                }
+                (false, Ok(CommandOutput::None))
+            }
+            DebugCommand::Screenshot { path } => {
+                let path = path.unwrap_or_else(|| {
+                    let secs = std::time::SystemTime::now()
+                        .duration_since(std::time::UNIX_EPOCH)
+                        .unwrap_or_default()
+                        .as_secs();
+                    let ext = if self.encode_png.is_some() {
+                        "png"
+                    } else {
+                        "pbm"
+                    };
+                    PathBuf::from(format!("c8rs-{secs}.{ext}"))
+                });
+
+                match self.save_screenshot(&path) {
+                    Ok(()) => (
+                        false,
+                        Ok(CommandOutput::Message(format!(
+                            "Screenshot saved: {}",
+                            path.display()
+                        ))),
+                    ),
+                    Err(err) => (false, Err(CommandError::Failed(err))),
+                }
+            }
+            DebugCommand::Save { name } => match self.save_state(&name) {
+                Ok(()) => (
+                    false,
+                    Ok(CommandOutput::Message(format!("State saved: {name}"))),
+                ),
+                Err(err) => (false, Err(CommandError::Failed(err))),
+            },
+            DebugCommand::Load { name }
+                if is_path(&name)
+                    && Path::new(&name).extension() != Some(STATE_EXTENSION.as_ref()) =>
+            {
+                match self.load_rom(Path::new(&name)) {
+                    Ok(len) => (
+                        false,
+                        Ok(CommandOutput::Message(format!(
+                            "Loaded ROM {name} ({len} bytes)"
+                        ))),
+                    ),
+                    Err(err) => (false, Err(CommandError::Failed(err))),
+                }
+            }
+            DebugCommand::Load { name } => match self.load_state(&name) {
+                Ok(cpu) => {
+                    self.machine.restore(cpu);
+                    self.undo = UndoStack::default();
+                    if self.state != EmulatorState::Running {
+                        let fault = self.machine.cpu().fault;
+                        self.set_state(match fault {
+                            Some(_) => EmulatorState::Faulted,
+                            None => EmulatorState::Paused,
+                        });
+                    }
+                    (
+                        false,
+                        Ok(CommandOutput::Message(format!("State loaded: {name}"))),
+                    )
+                }
+                Err(err) => (false, Err(CommandError::Failed(err))),
+            },
+            DebugCommand::Source { file } => {
+                let script = std::fs::read_to_string(&file)
+                    .map_err(|err| format!("Failed to read {}: {err}", file.display()))
+                    .and_then(|script| debug::parse_script(&script, &self.aliases));
+
+                match script {
+                    Ok(cmds) => {
+                        let msg =
+                            format!("Running {} commands from {}", cmds.len(), file.display());
+                        // ahead of anything queued, so nested scripts run in order
+                        for cmd in cmds.into_iter().rev() {
+                            self.script.push_front(cmd);
+                        }
+                        (false, Ok(CommandOutput::Message(msg)))
+                    }
+                    Err(err) => (false, Err(CommandError::Failed(err))),
+                }
+            }
+            DebugCommand::Print { expr } => {
+                let expr = match expr.resolve(&self.source_map) {
+                    Ok(expr) => expr,
+                    Err(err) => return (false, Err(CommandError::Failed(err))),
+                };
+
+                let value = expr.eval(self.machine.cpu());
+                (
+                    false,
+                    Ok(CommandOutput::Message(format!("{value:#06X} ({value})"))),
+                )
+            }
+            DebugCommand::Examine { count, unit, addr } => {
+                let addr = match addr.resolve(&self.source_map) {
+                    Ok(addr) => addr.eval(self.machine.cpu()),
+                    Err(err) => return (false, Err(CommandError::Failed(err))),
+                };
+                let output = debug::examine(self.machine.memory(), addr, count, unit);
+                (false, Ok(CommandOutput::Message(output)))
+            }
+        }
+    }
+
+    fn info(&self, what: InfoKind) -> CommandOutput {
+        match what {
+            InfoKind::Breakpoints => {
+                let mut addrs: Vec<_> = self.breakpoints.iter().copied().collect();
+                addrs.sort();
+                let rows = addrs
+                    .into_iter()
+                    .map(|addr| {
+                        let condition = self.conditions.get(&addr);
+                        [
+                            format!("{addr:#06X}"),
+                            condition.map_or(String::new(), |c| format!("{c}")),
+                        ]
+                    })
+                    .collect();
+                CommandOutput::table(["addr", "condition"], rows)
+            }
+            InfoKind::Tracepoints => {
+                let mut rows: Vec<_> = self
+                    .tracepoints
+                    .iter()
+                    .map(|(addr, format)| [format!("{addr:#06X}"), format.clone()])
+                    .collect();
+                rows.sort();
+                CommandOutput::table(["addr", "format"], rows)
+            }
+        }
+    }
+
+    fn set_state(&mut self, state: EmulatorState) {
+        if self.state == state {
+            return;
+        }
+
+        self.state = state;
+        self.emit(EmulatorEvent::StateChanged(state));
+
+        // time spent paused doesn't count towards the timers
+        if state == EmulatorState::Running {
+            self.last_instruction = self.clock.now();
         }
+
+        if state != EmulatorState::Running {
+            if let Some(steps) = self.steps.take() {
+                let msg = format!(
+                    "Stepped {} instructions, PC={:#06X}",
+                    steps.done,
+                    self.machine.cpu().pc
+                );
+                steps.reply.send(Ok(CommandOutput::Message(msg)));
+            }
+        }
+    }
+
+    /// Send `event` to all subscribers, dropping the ones that hung up
+    fn emit(&self, event: EmulatorEvent) {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|tx| tx.send(event).is_ok());
+    }
+
+    fn edit(&mut self, edit: Edit) {
+        self.undo
+            .apply(edit, self.machine.cpu_mut(), &mut self.breakpoints);
     }
 }
 
 pub struct EmulatorController {
-    cmd_tx: Sender<EmulatorCommand>,
+    cmd_tx: Sender<Request>,
+    /// Presented frame of the machine, locked instead of read through
+    /// `emulator` since its buffer is reallocated when the resolution changes
+    display: Arc<Mutex<Display>>,
     emulator: Arc<UnsafeCell<Chip8EmulatorInner>>,
 }
 
 impl EmulatorController {
-    pub fn send(
-        &self,
-        cmd: EmulatorCommand,
-    ) -> Result<(), std::sync::mpsc::SendError<EmulatorCommand>> {
-        self.cmd_tx.send(cmd)
+    /// Send a command without waiting for its result
+    pub fn send(&self, cmd: EmulatorCommand) -> Result<(), SendError<EmulatorCommand>> {
+        self.cmd_tx
+            .send(Request {
+                cmd,
+                reply: Reply(None),
+            })
+            .map_err(|err| SendError(err.0.cmd))
+    }
+
+    /// Send a command, the returned [`PendingReply`] receives its result once
+    /// the emulator has handled it
+    pub fn request(&self, cmd: EmulatorCommand) -> PendingReply {
+        let (reply_tx, rx) = channel();
+
+        if let Err(err) = self.cmd_tx.send(Request {
+            cmd,
+            reply: Reply(Some(reply_tx)),
+        }) {
+            err.0.reply.send(Err(CommandError::Disconnected));
+        }
+
+        PendingReply { rx }
+    }
+
+    pub fn pause(&self) -> Result<(), SendError<EmulatorCommand>> {
+        self.send_debug(DebugCommand::Pause)
+    }
+
+    pub fn resume(&self) -> Result<(), SendError<EmulatorCommand>> {
+        self.send_debug(DebugCommand::Continue)
+    }
+
+    /// Execute a single instruction, only useful while paused
+    pub fn step(&self) -> Result<(), SendError<EmulatorCommand>> {
+        self.send_debug(DebugCommand::Step { count: 1 })
+    }
+
+    pub fn set_ips(&self, ips: u32) -> Result<(), SendError<EmulatorCommand>> {
+        self.send_debug(DebugCommand::IPS { ips })
+    }
+
+    pub fn toggle_breakpoint(&self, addr: u16) -> Result<(), SendError<EmulatorCommand>> {
+        self.send_debug(DebugCommand::Breakpoint {
+            addr: Expr::Num(addr),
+            condition: None,
+        })
+    }
+
+    fn send_debug(&self, cmd: DebugCommand) -> Result<(), SendError<EmulatorCommand>> {
+        self.send(EmulatorCommand::DebugCommand(cmd))
+    }
+
+    /// Receive [`EmulatorEvent`]s from now on
+    pub fn subscribe(&self) -> Receiver<EmulatorEvent> {
+        let (tx, rx) = channel();
+        unsafe { &*self.emulator.get() }
+            .subscribers
+            .lock()
+            .unwrap()
+            .push(tx);
+        rx
     }
 
     pub fn ips(&self) -> u32 {
-        unsafe { &*self.emulator.get() }.ips
+        unsafe { &*self.emulator.get() }.machine.ips()
+    }
+
+    pub fn speed(&self) -> Speed {
+        unsafe { &*self.emulator.get() }.speed
     }
 
     pub fn state(&self) -> EmulatorState {
         unsafe { &*self.emulator.get() }.state
     }
 
+    /// Instructions executed since power on, sample it twice to measure the
+    /// actual speed
+    pub fn instructions(&self) -> u64 {
+        unsafe { &*self.emulator.get() }.instructions
+    }
+
     pub fn cpu(&self) -> &Cpu {
-        &unsafe { &*self.emulator.get() }.cpu
+        unsafe { &*self.emulator.get() }.machine.cpu()
     }
 
     pub fn memory(&self) -> &Memory {
-        &unsafe { &*self.emulator.get() }.cpu.mem
+        unsafe { &*self.emulator.get() }.machine.memory()
+    }
+
+    pub fn memory_map(&self) -> &MemoryMap {
+        unsafe { &*self.emulator.get() }.machine.memory_map()
     }
 
-    pub fn display(&self) -> &Display {
-        &unsafe { &*self.emulator.get() }.cpu.display
+    /// Copy of the last presented frame
+    pub fn display(&self) -> Display {
+        self.display.lock().unwrap().clone()
     }
 
     pub fn breakpoints(&self) -> &HashSet<u16> {
         &unsafe { &*self.emulator.get() }.breakpoints
     }
+
+    pub fn tracepoints(&self) -> &HashMap<u16, String> {
+        &unsafe { &*self.emulator.get() }.tracepoints
+    }
+
+    pub fn source_map(&self) -> &SourceMap {
+        &unsafe { &*self.emulator.get() }.source_map
+    }
+
+    pub fn aliases(&self) -> &Aliases {
+        &unsafe { &*self.emulator.get() }.aliases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_start_without_controller() {
+        // the thread keeps the state alive on its own
+        let handle = Chip8Emulator::new(&[0x12, 0x00]).start();
+        thread::sleep(Duration::from_millis(10));
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_subscribe() {
+        // NOP; RET
+        let emu = Chip8Emulator::new(&[0x00, 0x00, 0x00, 0xEE]);
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        let timeout = Duration::from_secs(1);
+        controller
+            .request(EmulatorCommand::DebugCommand(DebugCommand::Breakpoint {
+                addr: Expr::Num(0x202),
+                condition: None,
+            }))
+            .wait_timeout(timeout)
+            .unwrap();
+        controller.resume().unwrap();
+
+        let next = || loop {
+            match events.recv_timeout(timeout).unwrap() {
+                EmulatorEvent::FrameCompleted => continue,
+                event => break event,
+            }
+        };
+        assert_eq!(next(), EmulatorEvent::StateChanged(EmulatorState::Running));
+        assert_eq!(next(), EmulatorEvent::StateChanged(EmulatorState::Paused));
+        assert_eq!(next(), EmulatorEvent::BreakpointHit { addr: 0x202 });
+
+        controller.resume().unwrap();
+        assert_eq!(next(), EmulatorEvent::StateChanged(EmulatorState::Running));
+        assert_eq!(next(), EmulatorEvent::StateChanged(EmulatorState::Faulted));
+        assert_eq!(
+            next(),
+            EmulatorEvent::Faulted {
+                fault: Fault::StackUnderflow,
+                pc: 0x202
+            }
+        );
+
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_sound_changed() {
+        let rom = [
+            0x60, 0x02, // LD V0, 2
+            0xF0, 0x18, // LD ST, V0
+            0x12, 0x04, // JMP 0x204
+        ];
+        let emu = Chip8Emulator::new(&rom).with_clock(VirtualClock::new());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+        // a timer tick per instruction
+        controller.set_ips(60).unwrap();
+        controller.resume().unwrap();
+
+        let timeout = Duration::from_secs(1);
+        let next = || loop {
+            match events.recv_timeout(timeout).unwrap() {
+                event @ EmulatorEvent::SoundChanged { .. } => break event,
+                _ => continue,
+            }
+        };
+        assert_eq!(next(), EmulatorEvent::SoundChanged { playing: true });
+        assert_eq!(next(), EmulatorEvent::SoundChanged { playing: false });
+
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_display_snapshot() {
+        let emu = Chip8Emulator::new(&[0x12, 0x00]);
+        let controller = emu.controller();
+        let before = controller.display();
+        let handle = emu.start();
+
+        // a hi-res ROM reallocates the display at 64x64
+        let path = std::env::temp_dir().join("c8rs-test-hires.ch8");
+        std::fs::write(&path, [0x12, 0x60]).unwrap();
+        let cmd = DebugCommand::Load {
+            name: path.to_string_lossy().into_owned(),
+        };
+        controller
+            .request(EmulatorCommand::DebugCommand(cmd))
+            .wait()
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(before.get_dimensions(), (64, 32));
+        assert_eq!(controller.display().get_dimensions(), (64, 64));
+
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_finish() {
+        let rom = [
+            0x22, 0x04, // CALL 0x204
+            0x12, 0x02, // JMP 0x202
+            0x22, 0x08, // CALL 0x208
+            0x00, 0xEE, // RET
+            0x70, 0x01, // ADD V0, 1
+            0x00, 0xEE, // RET
+        ];
+        let emu = Chip8Emulator::new(&rom).with_clock(VirtualClock::new());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        let timeout = Duration::from_secs(1);
+        let finish = || {
+            controller
+                .request(EmulatorCommand::DebugCommand(DebugCommand::Finish))
+                .wait_timeout(timeout)
+        };
+        assert!(finish().is_err());
+
+        controller.step().unwrap();
+        assert_eq!(finish(), Ok(CommandOutput::None));
+
+        let paused = EmulatorEvent::StateChanged(EmulatorState::Paused);
+        while events.recv_timeout(timeout).unwrap() != paused {}
+
+        handle.stop().unwrap();
+        assert_eq!(controller.cpu().pc, 0x202);
+        assert_eq!(controller.cpu().registers[0], 1);
+    }
+
+    #[test]
+    fn test_step_count() {
+        // ADD V0, 1; JMP 0x200
+        let emu = Chip8Emulator::new(&[0x70, 0x01, 0x12, 0x00]).with_clock(VirtualClock::new());
+        let controller = emu.controller();
+        let handle = emu.start();
+
+        let result = controller
+            .request(EmulatorCommand::DebugCommand(DebugCommand::Step {
+                count: 5,
+            }))
+            .wait_timeout(Duration::from_secs(1));
+        assert_eq!(
+            result,
+            Ok(CommandOutput::Message(
+                "Stepped 5 instructions, PC=0x0202".to_string()
+            ))
+        );
+
+        handle.stop().unwrap();
+        assert_eq!(controller.cpu().registers[0], 3);
+        assert_eq!(controller.state(), EmulatorState::Paused);
+    }
+
+    #[test]
+    fn test_until() {
+        // ADD V0, 1; JMP 0x200
+        let emu = Chip8Emulator::new(&[0x70, 0x01, 0x12, 0x00]).with_clock(VirtualClock::new());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        controller
+            .send(EmulatorCommand::DebugCommand(DebugCommand::Until {
+                addr: 0x202,
+            }))
+            .unwrap();
+
+        let hit = EmulatorEvent::BreakpointHit { addr: 0x202 };
+        while events.recv_timeout(Duration::from_secs(1)).unwrap() != hit {}
+
+        handle.stop().unwrap();
+        assert_eq!(controller.cpu().registers[0], 1);
+        assert_eq!(controller.state(), EmulatorState::Paused);
+        assert!(controller.breakpoints().is_empty());
+    }
+
+    #[test]
+    fn test_conditional_breakpoint() {
+        // ADD V0, 1; JMP 0x200
+        let emu = Chip8Emulator::new(&[0x70, 0x01, 0x12, 0x00]).with_clock(VirtualClock::new());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        let cmd = DebugCommand::parse_from("break 0x200 if V0 == 5").unwrap();
+        controller
+            .request(EmulatorCommand::DebugCommand(cmd))
+            .wait()
+            .unwrap();
+        controller.resume().unwrap();
+
+        let hit = EmulatorEvent::BreakpointHit { addr: 0x200 };
+        while events.recv_timeout(Duration::from_secs(1)).unwrap() != hit {}
+
+        handle.stop().unwrap();
+        assert_eq!(controller.cpu().registers[0], 5);
+    }
+
+    #[test]
+    fn test_source() {
+        let path = std::env::temp_dir().join(format!("c8rs-test-{}.gdb", std::process::id()));
+        std::fs::write(
+            &path,
+            "# run to 0x202\nbreak 0x202\ncontinue\nset-reg V1 7\n",
+        )
+        .unwrap();
+
+        // ADD V0, 1; JMP 0x200
+        let emu = Chip8Emulator::new(&[0x70, 0x01, 0x12, 0x00]).with_clock(VirtualClock::new());
+        let controller = emu.controller();
+        let events = controller.subscribe();
+        let handle = emu.start();
+
+        let result = controller
+            .request(EmulatorCommand::DebugCommand(DebugCommand::Source {
+                file: path.clone(),
+            }))
+            .wait();
+        std::fs::remove_file(&path).unwrap();
+        assert!(result.is_ok());
+
+        let hit = EmulatorEvent::BreakpointHit { addr: 0x202 };
+        while events.recv_timeout(Duration::from_secs(1)).unwrap() != hit {}
+
+        handle.stop().unwrap();
+        assert_eq!(controller.cpu().registers[0], 1);
+        assert_eq!(controller.cpu().registers[1], 7);
+    }
+
+    #[test]
+    fn test_save_load() {
+        // ADD V0, 1; JMP 0x200
+        let emu = Chip8Emulator::new(&[0x70, 0x01, 0x12, 0x00]);
+        let controller = emu.controller();
+        let handle = emu.start();
+
+        let run = |line: &str| {
+            let cmd = DebugCommand::parse_from(line).unwrap();
+            controller
+                .request(EmulatorCommand::DebugCommand(cmd))
+                .wait()
+        };
+
+        run("step 2").unwrap();
+        run("save slot1").unwrap();
+        run("step 4").unwrap();
+        assert_eq!(controller.cpu().registers[0], 3);
+
+        run("load slot1").unwrap();
+        assert_eq!(controller.cpu().registers[0], 1);
+        assert!(run("load slot2").is_err());
+
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_key_tap() {
+        let emu = Chip8Emulator::new(&[]);
+        let inner = unsafe { &mut *emu.inner.get() };
+        let mut run = |line| {
+            let (_, result) = inner.handle_debug_cmd(DebugCommand::parse_from(line).unwrap());
+            result.unwrap();
+        };
+
+        run("key press 1");
+        run("key tap 0 2");
+        assert_eq!(inner.machine.cpu().keys[..2], [true, true]);
+
+        inner.release_taps();
+        assert!(inner.machine.cpu().keys[0]);
+
+        inner.release_taps();
+        assert_eq!(inner.machine.cpu().keys[..2], [false, true]);
+    }
+
+    #[test]
+    fn test_input_replay() {
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // sum random numbers into V2 and count frames key 0 is held in V3
+        let rom = [
+            0xC1, 0xFF, // RND V1, 0xFF
+            0x82, 0x14, // ADD V2, V1
+            0xE0, 0xA1, // SKNP V0
+            0x73, 0x01, // ADD V3, 0x01
+            0x12, 0x00, // JMP 0x200
+        ];
+        let run = |inner: &mut Chip8EmulatorInner, cycles| {
+            for _ in 0..cycles {
+                inner.cycle();
+                if inner.frame_completed {
+                    inner.release_taps();
+                }
+            }
+        };
+
+        let out = Arc::new(Mutex::new(Vec::new()));
+        let emu = Chip8Emulator::new(&rom).with_input_recording(Shared(out.clone()));
+        let inner = unsafe { &mut *emu.inner.get() };
+        inner.machine.set_ips(180);
+        run(inner, 31);
+        let (_, result) = inner.handle_debug_cmd(DebugCommand::parse_from("key tap 0 4").unwrap());
+        result.unwrap();
+        run(inner, 40);
+        let (_, result) = inner.handle_debug_cmd(DebugCommand::parse_from("key press 0").unwrap());
+        result.unwrap();
+        run(inner, 9);
+        let registers = inner.machine.cpu().registers;
+        assert_ne!(registers[instructions::Register::V3], 0);
+
+        let recording = String::from_utf8(out.lock().unwrap().clone()).unwrap();
+        let recording = InputRecording::parse(&recording).unwrap();
+        assert_eq!(recording.ips, 180);
+        assert_eq!(recording.events.len(), 4);
+
+        let emu = Chip8Emulator::new(&rom).with_input_replay(recording);
+        let inner = unsafe { &mut *emu.inner.get() };
+        run(inner, 80);
+        assert_eq!(inner.machine.cpu().registers, registers);
+
+        let (_, result) = inner.handle_debug_cmd(DebugCommand::parse_from("key press 1").unwrap());
+        assert!(result.is_ok(), "replay finished");
+    }
+
+    #[test]
+    fn test_break_label() {
+        let mut map = SourceMap::new();
+        map.add_label("main_loop", 0x204);
+        let emu = Chip8Emulator::new(&[]).with_source_map(map);
+        let inner = unsafe { &mut *emu.inner.get() };
+        let mut run = |line| {
+            let cmd = DebugCommand::parse_from(line).unwrap();
+            inner.handle_debug_cmd(cmd).1
+        };
+
+        run("break main_loop if V0 == main_loop - 0x200").unwrap();
+        assert!(run("break loop").is_err());
+        assert!(inner.breakpoints.contains(&0x204));
+        assert_eq!(inner.conditions[&0x204].eval(inner.machine.cpu()), 0);
+
+        inner.machine.cpu_mut().registers[0] = 4;
+        assert!(inner.breakpoint_hit(0x204));
+    }
+
+    #[test]
+    fn test_patch() {
+        // CLS; JMP 0x200
+        let emu = Chip8Emulator::new(&[0x00, 0xE0, 0x12, 0x00]);
+        let inner = unsafe { &mut *emu.inner.get() };
+        let run = |inner: &mut Chip8EmulatorInner, line| {
+            let cmd = DebugCommand::parse_from(line).unwrap();
+            inner.handle_debug_cmd(cmd).1.unwrap();
+        };
+
+        run(inner, "patch 0x202 JMP 0x202");
+        assert_eq!(inner.machine.memory().read_u16(0x202), 0x1202);
+
+        run(inner, "undo");
+        assert_eq!(inner.machine.memory().read_u16(0x202), 0x1200);
+    }
 }
@@ -1,4 +1,8 @@
-pub const MEM_SIZE: usize = 4096;
+/// Classic CHIP-8 memory size (4K)
+pub const MEM_SIZE: usize = 0x1000;
+
+/// XO-CHIP memory size (64K)
+pub const XO_MEM_SIZE: usize = 0x10000;
 
 pub const FONT_SPRITE_ADDR: u16 = 0x100;
 const FONT_SPRITES: [u8; 80] = [
@@ -20,53 +24,123 @@ const FONT_SPRITES: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80, // F
 ];
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Memory {
-    bytes: [u8; MEM_SIZE],
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "deserialize_bytes"))]
+    bytes: Box<[u8]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    generation: u64,
 }
 
 impl Default for Memory {
     fn default() -> Self {
-        Self { bytes: [0; 4096] }
+        Self::new(MEM_SIZE)
     }
 }
 
 impl Memory {
+    /// Create zeroed memory of `size` bytes.
+    ///
+    /// `size` must be a power of two no larger than [`XO_MEM_SIZE`] so that
+    /// addresses can be masked into range.
+    pub fn new(size: usize) -> Memory {
+        assert!(
+            size.is_power_of_two() && size <= XO_MEM_SIZE,
+            "invalid memory size: {size:#X}"
+        );
+
+        Memory {
+            bytes: vec![0; size].into_boxed_slice(),
+            generation: 0,
+        }
+    }
+
     pub fn init(buf: &[u8]) -> Memory {
-        let mut m = Memory::default();
+        Memory::init_with_size(MEM_SIZE, buf)
+    }
+
+    pub fn init_with_size(size: usize, buf: &[u8]) -> Memory {
+        let mut m = Memory::new(size);
         m.write(0x200, buf);
         m.write(FONT_SPRITE_ADDR, &FONT_SPRITES);
         m
     }
 
+    pub fn size(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Counter incremented on every write, for invalidating cached views of
+    /// memory contents
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Replace the contents with those of `other`, counting as a write
+    pub(crate) fn restore(&mut self, other: Memory) {
+        self.bytes = other.bytes;
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Highest valid address
+    pub fn max_addr(&self) -> u16 {
+        (self.bytes.len() - 1) as u16
+    }
+
+    fn index(&self, addr: u16) -> usize {
+        addr as usize & (self.bytes.len() - 1)
+    }
+
     pub fn read_u8(&self, addr: u16) -> u8 {
-        self.bytes[addr as usize]
+        self.bytes[self.index(addr)]
     }
 
     pub fn write_u8(&mut self, addr: u16, val: u8) {
-        self.bytes[addr as usize] = val;
+        let i = self.index(addr);
+        self.bytes[i] = val;
+        self.generation = self.generation.wrapping_add(1);
     }
 
     pub fn read_u16(&self, addr: u16) -> u16 {
-        ((self.bytes[addr as usize] as u16) << 8) | self.bytes[(addr + 1) as usize] as u16
+        ((self.read_u8(addr) as u16) << 8) | self.read_u8(addr.wrapping_add(1)) as u16
     }
 
     pub fn write_u16(&mut self, addr: u16, val: u16) {
         self.write_u8(addr, (val >> 8) as u8);
-        self.write_u8(addr + 1, val as u8);
+        self.write_u8(addr.wrapping_add(1), val as u8);
     }
 
-    pub fn read(&self, addr: u16, len: u16) -> &[u8] {
-        let addr = addr as usize;
-        &self.bytes[addr..addr + len as usize]
+    /// Read `len` bytes starting at `addr`, wrapping around at the end of
+    /// memory
+    pub fn read(&self, addr: u16, len: u16) -> Vec<u8> {
+        (0..len)
+            .map(|i| self.read_u8(addr.wrapping_add(i)))
+            .collect()
     }
 
+    /// Write `data` starting at `addr`, wrapping around at the end of memory
     pub fn write(&mut self, addr: u16, data: &[u8]) {
-        let addr = addr as usize;
-        self.bytes[addr..addr + data.len()].copy_from_slice(data);
+        for (i, b) in data.iter().enumerate() {
+            self.write_u8(addr.wrapping_add(i as u16), *b);
+        }
     }
 }
 
+#[cfg(feature = "serde")]
+fn deserialize_bytes<'de, D: serde::Deserializer<'de>>(d: D) -> Result<Box<[u8]>, D::Error> {
+    let bytes = <Box<[u8]> as serde::Deserialize>::deserialize(d)?;
+
+    if !bytes.len().is_power_of_two() || bytes.len() > XO_MEM_SIZE {
+        return Err(serde::de::Error::custom(format!(
+            "invalid memory size: {:#X}",
+            bytes.len()
+        )));
+    }
+
+    Ok(bytes)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +173,47 @@ mod tests {
 
         assert_eq!(m.read(0x200, 8), data);
     }
+
+    #[test]
+    fn test_size() {
+        assert_eq!(Memory::default().size(), MEM_SIZE);
+        assert_eq!(Memory::new(XO_MEM_SIZE).size(), XO_MEM_SIZE);
+        assert_eq!(Memory::new(XO_MEM_SIZE).max_addr(), 0xFFFF);
+    }
+
+    #[test]
+    fn test_wrap() {
+        let mut m = Memory::default();
+
+        m.write_u16(0xFFF, 0x1234);
+
+        assert_eq!(m.read_u8(0xFFF), 0x12);
+        assert_eq!(m.read_u8(0x000), 0x34);
+        assert_eq!(m.read_u8(0x1000), 0x34);
+        assert_eq!(m.read(0xFFE, 3), [0x00, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn test_generation() {
+        let mut m = Memory::default();
+        let generation = m.generation();
+
+        m.read_u16(0x200);
+        assert_eq!(m.generation(), generation);
+
+        m.write_u8(0x200, 0x12);
+        assert_ne!(m.generation(), generation);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        let m = Memory::init(&[0x12, 0x34]);
+
+        let json = serde_json::to_string(&m).unwrap();
+        let m2: Memory = serde_json::from_str(&json).unwrap();
+        assert_eq!(m2.read(0x200, 2), [0x12, 0x34]);
+
+        assert!(serde_json::from_str::<Memory>(r#"{"bytes":[1,2,3]}"#).is_err());
+    }
 }
@@ -0,0 +1,169 @@
+use crate::{
+    cpu::{HIRES_ENTRY, STACK_DEPTH, STACK_TOP},
+    memory::FONT_SPRITE_ADDR,
+};
+
+/// Size of the built-in font, 16 sprites of 5 bytes
+const FONT_SIZE: u16 = 16 * 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegionKind {
+    /// Memory used by the original interpreter
+    Reserved,
+    Font,
+    Stack,
+    /// Patch the hi-res interpreter loads ahead of the program
+    HiresPatch,
+    /// The loaded ROM
+    Program,
+    /// Free memory after the ROM
+    Free,
+}
+
+impl std::fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RegionKind::Reserved => "reserved",
+                RegionKind::Font => "font",
+                RegionKind::Stack => "stack",
+                RegionKind::HiresPatch => "hires",
+                RegionKind::Program => "program",
+                RegionKind::Free => "free",
+            }
+        )
+    }
+}
+
+/// Contiguous range of memory, `end` is inclusive
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Region {
+    pub kind: RegionKind,
+    pub start: u16,
+    pub end: u16,
+}
+
+impl Region {
+    pub fn contains(&self, addr: u16) -> bool {
+        (self.start..=self.end).contains(&addr)
+    }
+
+    /// Whether a program is expected to write to this region
+    pub fn writable(&self) -> bool {
+        matches!(self.kind, RegionKind::Program | RegionKind::Free)
+    }
+}
+
+/// Layout of memory after loading a ROM, regions are sorted and cover every
+/// address
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryMap {
+    regions: Vec<Region>,
+}
+
+impl MemoryMap {
+    /// Layout for a `rom_len` byte ROM loaded at 0x200 into `mem_size` bytes
+    /// of memory
+    pub fn new(mem_size: usize, rom_len: usize, hires: bool) -> MemoryMap {
+        let font_end = FONT_SPRITE_ADDR + FONT_SIZE - 1;
+        let stack_start = STACK_TOP - (STACK_DEPTH - 1) * 2;
+        let max_addr = (mem_size - 1) as u16;
+        let program_start = if hires { HIRES_ENTRY } else { 0x200 };
+        let program_end = (0x200 + rom_len).max(program_start as usize);
+
+        let mut bounds = vec![
+            (RegionKind::Reserved, 0x000),
+            (RegionKind::Font, FONT_SPRITE_ADDR),
+            (RegionKind::Reserved, font_end + 1),
+            (RegionKind::Stack, stack_start),
+        ];
+        if hires {
+            bounds.push((RegionKind::HiresPatch, 0x200));
+        }
+        bounds.push((RegionKind::Program, program_start));
+        if program_end < mem_size {
+            bounds.push((RegionKind::Free, program_end as u16));
+        }
+
+        let regions = bounds
+            .iter()
+            .enumerate()
+            .map(|(n, &(kind, start))| Region {
+                kind,
+                start,
+                end: bounds.get(n + 1).map_or(max_addr, |&(_, next)| next - 1),
+            })
+            .filter(|r| r.start <= r.end)
+            .collect();
+
+        MemoryMap { regions }
+    }
+
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+
+    pub fn region(&self, addr: u16) -> Option<&Region> {
+        self.regions.iter().find(|r| r.contains(addr))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::MEM_SIZE;
+
+    #[test]
+    fn test_memory_map() {
+        let map = MemoryMap::new(MEM_SIZE, 0x10, false);
+
+        let kind = |addr| map.region(addr).unwrap().kind;
+        assert_eq!(kind(0x000), RegionKind::Reserved);
+        assert_eq!(kind(0x100), RegionKind::Font);
+        assert_eq!(kind(0x14F), RegionKind::Font);
+        assert_eq!(kind(0x150), RegionKind::Reserved);
+        assert_eq!(kind(0x1E0), RegionKind::Stack);
+        assert_eq!(kind(0x1FF), RegionKind::Stack);
+        assert_eq!(kind(0x200), RegionKind::Program);
+        assert_eq!(kind(0x20F), RegionKind::Program);
+        assert_eq!(kind(0x210), RegionKind::Free);
+        assert_eq!(kind(0xFFF), RegionKind::Free);
+
+        for pair in map.regions().windows(2) {
+            assert_eq!(pair[0].end + 1, pair[1].start);
+        }
+    }
+
+    #[test]
+    fn test_memory_map_hires() {
+        let map = MemoryMap::new(MEM_SIZE, 0x100, true);
+
+        assert_eq!(map.region(0x200).unwrap().kind, RegionKind::HiresPatch);
+        assert_eq!(map.region(0x2C0).unwrap().kind, RegionKind::Program);
+        assert_eq!(map.region(0x300).unwrap().kind, RegionKind::Free);
+    }
+
+    #[test]
+    fn test_memory_map_empty() {
+        let map = MemoryMap::new(MEM_SIZE, 0, false);
+        assert_eq!(map.region(0x200).unwrap().kind, RegionKind::Free);
+
+        let map = MemoryMap::new(MEM_SIZE, 0x10, true);
+        assert_eq!(map.region(0x2BF).unwrap().kind, RegionKind::HiresPatch);
+        assert_eq!(map.region(0x2C0).unwrap().kind, RegionKind::Free);
+    }
+
+    #[test]
+    fn test_memory_map_full() {
+        let map = MemoryMap::new(MEM_SIZE, MEM_SIZE, false);
+
+        let last = map.regions().last().unwrap();
+        assert_eq!(last.kind, RegionKind::Program);
+        assert_eq!(last.end, 0xFFF);
+    }
+}
@@ -0,0 +1,41 @@
+use crate::Variant;
+
+/// Behaviours that differ between CHIP-8 interpreters, all off by default
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quirks {
+    /// `8xy6`/`8xyE` shift Vx in place instead of shifting Vy into Vx
+    pub shift: bool,
+    /// `Fx55`/`Fx65` leave I pointing past the last register copied
+    pub memory_increment: bool,
+    /// `Bnnn` jumps to `nnn + Vx` (`Bxnn`) instead of `nnn + V0`
+    pub jump: bool,
+    /// `8xy1`, `8xy2` and `8xy3` reset VF to 0
+    pub vf_reset: bool,
+}
+
+impl Quirks {
+    /// Quirks of the reference interpreter for `variant`
+    pub fn for_variant(variant: Variant) -> Quirks {
+        match variant {
+            Variant::Chip8 => Quirks {
+                shift: false,
+                memory_increment: true,
+                jump: false,
+                vf_reset: true,
+            },
+            Variant::Schip => Quirks {
+                shift: true,
+                memory_increment: false,
+                jump: true,
+                vf_reset: false,
+            },
+            Variant::XoChip => Quirks {
+                shift: false,
+                memory_increment: true,
+                jump: false,
+                vf_reset: false,
+            },
+        }
+    }
+}
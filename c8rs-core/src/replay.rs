@@ -0,0 +1,173 @@
+/// Keypad input of a run, written by [`Chip8Emulator::with_input_recording`]
+/// and played back by [`Chip8Emulator::with_input_replay`]
+///
+/// Together with the random number seed and IPS this makes a run
+/// reproducible from power on. The text form has one entry per line, each
+/// input line giving the keypad state from the `cycle`th instruction of a
+/// frame onwards as a mask with bit `n` set for key `n`:
+///
+/// ```text
+/// seed 0x1234ABCD
+/// ips 700
+/// rom 6a5f0c...
+/// input 120 3 0x0020
+/// ```
+///
+/// [`Chip8Emulator::with_input_recording`]: crate::Chip8Emulator::with_input_recording
+/// [`Chip8Emulator::with_input_replay`]: crate::Chip8Emulator::with_input_replay
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRecording {
+    pub seed: u32,
+    pub ips: u32,
+    /// SHA-1 of the ROM the input was recorded with
+    pub rom: Option<String>,
+    pub events: Vec<InputEvent>,
+}
+
+/// Change of the keypad state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputEvent {
+    /// Frames completed since power on
+    pub frame: u64,
+    /// Instructions executed in the frame before the change
+    pub cycle: u32,
+    pub keys: [bool; 16],
+}
+
+impl InputEvent {
+    pub fn mask(&self) -> u16 {
+        self.keys
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (key, &pressed)| mask | (pressed as u16) << key)
+    }
+
+    pub fn from_mask(frame: u64, cycle: u32, mask: u16) -> InputEvent {
+        InputEvent {
+            frame,
+            cycle,
+            keys: std::array::from_fn(|key| mask & (1 << key) != 0),
+        }
+    }
+}
+
+impl InputRecording {
+    /// Parse the text form written by the `Display` impl
+    pub fn parse(s: &str) -> Result<InputRecording, String> {
+        let mut seed = None;
+        let mut ips = None;
+        let mut rom = None;
+        let mut events: Vec<InputEvent> = Vec::new();
+
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |msg: &str| format!("line {}: {msg}", n + 1);
+            let mut words = line.split_whitespace();
+            let entry = words.next();
+            let mut number = |what: &str| {
+                words
+                    .next()
+                    .and_then(|s| clap_num::maybe_hex::<u64>(s).ok())
+                    .ok_or_else(|| err(&format!("expected {what}")))
+            };
+
+            match entry {
+                Some("seed") => seed = Some(number("a seed")? as u32),
+                Some("ips") => ips = Some(number("an IPS value")? as u32),
+                Some("rom") => rom = line.split_whitespace().nth(1).map(str::to_string),
+                Some("input") => {
+                    let event = InputEvent::from_mask(
+                        number("a frame")?,
+                        number("a cycle")? as u32,
+                        number("a key mask")? as u16,
+                    );
+                    if events
+                        .last()
+                        .is_some_and(|last| (last.frame, last.cycle) > (event.frame, event.cycle))
+                    {
+                        return Err(err("input is out of order"));
+                    }
+                    events.push(event);
+                }
+                Some(word) => return Err(err(&format!("unknown entry '{word}'"))),
+                None => unreachable!(),
+            }
+        }
+
+        Ok(InputRecording {
+            seed: seed.ok_or("missing seed")?,
+            ips: ips.ok_or("missing ips")?,
+            rom,
+            events,
+        })
+    }
+}
+
+impl std::fmt::Display for InputRecording {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "seed {:#010X}", self.seed)?;
+        writeln!(f, "ips {}", self.ips)?;
+        if let Some(rom) = &self.rom {
+            writeln!(f, "rom {rom}")?;
+        }
+        for event in &self.events {
+            writeln!(f, "{event}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for InputEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "input {} {} {:#06X}",
+            self.frame,
+            self.cycle,
+            self.mask()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let recording = InputRecording {
+            seed: 0xC0FFEE,
+            ips: 700,
+            rom: Some("da39a3ee".to_string()),
+            events: vec![
+                InputEvent::from_mask(3, 0, 0x0020),
+                InputEvent::from_mask(3, 7, 0x8021),
+                InputEvent::from_mask(10, 2, 0),
+            ],
+        };
+
+        let s = recording.to_string();
+        assert_eq!(
+            s,
+            "seed 0x00C0FFEE\nips 700\nrom da39a3ee\ninput 3 0 0x0020\n\
+             input 3 7 0x8021\ninput 10 2 0x0000\n"
+        );
+        assert_eq!(InputRecording::parse(&s), Ok(recording.clone()));
+        assert!(recording.events[1].keys[0xF]);
+        assert!(recording.events[1].keys[0x5]);
+        assert!(!recording.events[1].keys[0x1]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(InputRecording::parse("ips 700").is_err());
+        assert!(InputRecording::parse("seed 1").is_err());
+        assert!(InputRecording::parse("seed 1\nips 700\ninput 3 x 0x01").is_err());
+        assert!(InputRecording::parse("seed 1\nips 700\ninput 3 1 0x01\ninput 2 0 0").is_err());
+        assert!(InputRecording::parse("seed 1\nips 700\nkeys 0").is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::Path;
+
+use crate::display::Display;
+
+/// Text format of a display dump, PNG encoding is left to frontends, see
+/// [`Chip8Emulator::with_png_encoder`](crate::Chip8Emulator::with_png_encoder)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    /// Plain (ASCII) portable bitmap
+    Pbm,
+    /// `#` for lit pixels, `.` for unlit ones
+    Text,
+}
+
+/// Encodes a display as PNG, passed in by frontends with an image library
+pub type PngEncoder = fn(&Display) -> Result<Vec<u8>, String>;
+
+impl ImageFormat {
+    /// Format for the extension of `path`, if it's `.pbm` or `.txt`
+    pub fn from_path(path: &Path) -> Option<ImageFormat> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("pbm") => Some(ImageFormat::Pbm),
+            Some(ext) if ext.eq_ignore_ascii_case("txt") => Some(ImageFormat::Text),
+            _ => None,
+        }
+    }
+}
+
+/// Encode the contents of `display` as `format`
+pub fn encode(display: &Display, format: ImageFormat) -> Vec<u8> {
+    let (width, height) = display.get_dimensions();
+    encode_pixels(width, height, &display.get_pixels(), format)
+}
+
+/// Encode a `width` by `height` image of row-major `pixels` in `format`
+pub fn encode_pixels(width: usize, height: usize, pixels: &[bool], format: ImageFormat) -> Vec<u8> {
+    match format {
+        ImageFormat::Pbm => {
+            let mut out = format!("P1\n{width} {height}\n");
+            out.push_str(&rows(width, pixels, '1', '0'));
+            out.into_bytes()
+        }
+        ImageFormat::Text => rows(width, pixels, '#', '.').into_bytes(),
+    }
+}
+
+fn rows(width: usize, pixels: &[bool], on: char, off: char) -> String {
+    pixels
+        .chunks(width)
+        .map(|row| {
+            let mut line: String = row.iter().map(|&p| if p { on } else { off }).collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display() -> Display {
+        let mut display = Display::new(8, 2);
+        display.draw_sprite(0, 0, &[0b1000_0001]);
+        display
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(
+            encode(&display(), ImageFormat::Text),
+            b"#......#\n........\n"
+        );
+        assert_eq!(
+            encode(&display(), ImageFormat::Pbm),
+            b"P1\n8 2\n10000001\n00000000\n"
+        );
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ImageFormat::from_path(Path::new("a.PBM")),
+            Some(ImageFormat::Pbm)
+        );
+        assert_eq!(
+            ImageFormat::from_path(Path::new("a.txt")),
+            Some(ImageFormat::Text)
+        );
+        assert_eq!(ImageFormat::from_path(Path::new("a.png")), None);
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+/// Label names and source lines of an assembled program, written by
+/// `c8rs asm --source-map`
+///
+/// The text form has one entry per line:
+///
+/// ```text
+/// file pong.8o
+/// label 0x0200 main
+/// line 0x0200 12
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceMap {
+    /// Source file the program was assembled from
+    pub file: Option<String>,
+    labels: BTreeMap<String, u16>,
+    lines: BTreeMap<u16, usize>,
+}
+
+impl SourceMap {
+    pub fn new() -> SourceMap {
+        SourceMap::default()
+    }
+
+    pub fn add_label(&mut self, name: &str, addr: u16) {
+        self.labels.insert(name.to_string(), addr);
+    }
+
+    /// Record that the instruction at `addr` was assembled from `line`
+    pub fn add_line(&mut self, addr: u16, line: usize) {
+        self.lines.insert(addr, line);
+    }
+
+    /// Address of the label `name`
+    pub fn addr(&self, name: &str) -> Option<u16> {
+        self.labels.get(name).copied()
+    }
+
+    /// First label at `addr` in name order, if there is one
+    pub fn label(&self, addr: u16) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(_, a)| **a == addr)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Source line of the instruction at `addr`
+    pub fn line(&self, addr: u16) -> Option<usize> {
+        self.lines.get(&addr).copied()
+    }
+
+    pub fn labels(&self) -> impl Iterator<Item = (&str, u16)> {
+        self.labels
+            .iter()
+            .map(|(name, addr)| (name.as_str(), *addr))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty() && self.lines.is_empty()
+    }
+
+    /// Parse the text form written by the `Display` impl
+    pub fn parse(s: &str) -> Result<SourceMap, String> {
+        let mut map = SourceMap::new();
+
+        for (n, line) in s.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let err = |msg: &str| format!("line {}: {msg}", n + 1);
+            let addr = |s: Option<&str>| {
+                s.and_then(|s| clap_num::maybe_hex::<u16>(s).ok())
+                    .ok_or_else(|| err("expected an address"))
+            };
+
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("file") => map.file = Some(line["file".len()..].trim().to_string()),
+                Some("label") => {
+                    let addr = addr(words.next())?;
+                    let name = words.next().ok_or_else(|| err("expected a label name"))?;
+                    map.add_label(name, addr);
+                }
+                Some("line") => {
+                    let addr = addr(words.next())?;
+                    let line = words
+                        .next()
+                        .and_then(|s| s.parse().ok())
+                        .ok_or_else(|| err("expected a line number"))?;
+                    map.add_line(addr, line);
+                }
+                Some(word) => return Err(err(&format!("unknown entry '{word}'"))),
+                None => unreachable!(),
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+impl std::fmt::Display for SourceMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(file) = &self.file {
+            writeln!(f, "file {file}")?;
+        }
+        let mut labels: Vec<_> = self.labels().collect();
+        labels.sort_by_key(|(name, addr)| (*addr, *name));
+        for (name, addr) in labels {
+            writeln!(f, "label {addr:#06X} {name}")?;
+        }
+        for (addr, line) in &self.lines {
+            writeln!(f, "line {addr:#06X} {line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut map = SourceMap::new();
+        map.file = Some("pong game.8o".to_string());
+        map.add_label("main", 0x200);
+        map.add_label("draw", 0x20A);
+        map.add_line(0x200, 3);
+        map.add_line(0x202, 4);
+
+        let s = map.to_string();
+        assert_eq!(
+            s,
+            "file pong game.8o\nlabel 0x0200 main\nlabel 0x020A draw\n\
+             line 0x0200 3\nline 0x0202 4\n"
+        );
+        assert_eq!(SourceMap::parse(&s), Ok(map));
+    }
+
+    #[test]
+    fn test_lookup() {
+        let map = SourceMap::parse("# comment\nlabel 0x200 main\nline 0x200 7\n").unwrap();
+
+        assert_eq!(map.addr("main"), Some(0x200));
+        assert_eq!(map.addr("loop"), None);
+        assert_eq!(map.label(0x200), Some("main"));
+        assert_eq!(map.line(0x200), Some(7));
+        assert_eq!(map.line(0x202), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(SourceMap::parse("label main").is_err());
+        assert!(SourceMap::parse("label 0x200").is_err());
+        assert!(SourceMap::parse("line 0x200 x").is_err());
+        assert!(SourceMap::parse("sym 0x200 main").is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use std::{io::Write, ops::RangeInclusive};
+
+use clap::ValueEnum;
+
+use crate::{Cpu, Instruction, Variant};
+
+/// Layout of the lines written by [`InstructionTrace`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum TraceFormat {
+    /// Aligned columns for reading
+    #[default]
+    Text,
+    /// A JSON object per instruction
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// Rough grouping of instructions by what they act on, for filtering traces
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum OpcodeClass {
+    /// Jumps, calls, returns and skips not on the keypad
+    Flow,
+    /// Register loads and arithmetic
+    Alu,
+    /// Instructions using I
+    Memory,
+    /// Drawing, clearing, scrolling and resolution changes
+    Display,
+    /// Keypad skips and waits
+    Input,
+    /// Delay and sound timers and XO-CHIP audio
+    Timer,
+    /// Opcodes without a known instruction
+    Unknown,
+}
+
+impl OpcodeClass {
+    pub fn of(instr: &Instruction) -> OpcodeClass {
+        match instr {
+            Instruction::Ret
+            | Instruction::Jmp { .. }
+            | Instruction::Call { .. }
+            | Instruction::JmpReg { .. }
+            | Instruction::SkipEqImm { .. }
+            | Instruction::SkipNEqImm { .. }
+            | Instruction::SkipEqReg { .. }
+            | Instruction::SkipNEqReg { .. }
+            | Instruction::Exit => OpcodeClass::Flow,
+            Instruction::LdImm { .. }
+            | Instruction::AddImm { .. }
+            | Instruction::LdReg { .. }
+            | Instruction::Or { .. }
+            | Instruction::And { .. }
+            | Instruction::Xor { .. }
+            | Instruction::AddReg { .. }
+            | Instruction::SubReg { .. }
+            | Instruction::Shr { .. }
+            | Instruction::SubN { .. }
+            | Instruction::Shl { .. }
+            | Instruction::Rnd { .. } => OpcodeClass::Alu,
+            Instruction::LdI { .. }
+            | Instruction::AddI { .. }
+            | Instruction::LdFont { .. }
+            | Instruction::LdHiFont { .. }
+            | Instruction::Bcd { .. }
+            | Instruction::StoreRegs { .. }
+            | Instruction::LoadRegs { .. }
+            | Instruction::StoreFlags { .. }
+            | Instruction::LoadFlags { .. }
+            | Instruction::StoreRange { .. }
+            | Instruction::LoadRange { .. }
+            | Instruction::LdILong => OpcodeClass::Memory,
+            Instruction::Cls
+            | Instruction::Drw { .. }
+            | Instruction::ScrollDown { .. }
+            | Instruction::ScrollUp { .. }
+            | Instruction::ScrollRight
+            | Instruction::ScrollLeft
+            | Instruction::Lores
+            | Instruction::Hires
+            | Instruction::Plane { .. } => OpcodeClass::Display,
+            Instruction::SkipPressed { .. }
+            | Instruction::SkipNotPressed { .. }
+            | Instruction::LdKey { .. } => OpcodeClass::Input,
+            Instruction::LdDelayTimer { .. }
+            | Instruction::SetDelayTimer { .. }
+            | Instruction::SetSoundTimer { .. }
+            | Instruction::Audio
+            | Instruction::Pitch { .. } => OpcodeClass::Timer,
+            Instruction::Unknown(_) => OpcodeClass::Unknown,
+        }
+    }
+}
+
+/// Instructions an [`InstructionTrace`] writes, every one by default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TraceFilter {
+    /// Only instructions at these addresses
+    pub range: Option<RangeInclusive<u16>>,
+    /// Only instructions of these classes, any class if empty
+    pub classes: Vec<OpcodeClass>,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, pc: u16, instr: &Instruction) -> bool {
+        self.range.as_ref().is_none_or(|range| range.contains(&pc))
+            && (self.classes.is_empty() || self.classes.contains(&OpcodeClass::of(instr)))
+    }
+}
+
+/// Parse an inclusive address range, `START-END` or a single address
+pub fn parse_range(s: &str) -> Result<RangeInclusive<u16>, String> {
+    let addr =
+        |s: &str| clap_num::maybe_hex::<u16>(s.trim()).map_err(|_| format!("invalid address: {s}"));
+
+    let range = match s.split_once('-') {
+        Some((start, end)) => addr(start)?..=addr(end)?,
+        None => addr(s)?..=addr(s)?,
+    };
+    if range.is_empty() {
+        return Err(format!("empty range: {s}"));
+    }
+    Ok(range)
+}
+
+/// Writes every executed instruction with the registers before it ran, see
+/// [`Chip8Emulator::with_instruction_trace`]
+///
+/// [`Chip8Emulator::with_instruction_trace`]: crate::Chip8Emulator::with_instruction_trace
+pub struct InstructionTrace {
+    out: Box<dyn Write + Send>,
+    format: TraceFormat,
+    filter: TraceFilter,
+    header_written: bool,
+}
+
+impl InstructionTrace {
+    pub fn new(out: impl Write + Send + 'static, format: TraceFormat) -> InstructionTrace {
+        InstructionTrace {
+            out: Box::new(out),
+            format,
+            filter: TraceFilter::default(),
+            header_written: false,
+        }
+    }
+
+    pub fn with_filter(mut self, filter: TraceFilter) -> InstructionTrace {
+        self.filter = filter;
+        self
+    }
+
+    /// Write the instruction `cpu` is about to execute, the `cycle`th of
+    /// frame `frame`, if it passes the filter
+    pub(crate) fn write(&mut self, frame: u64, cycle: u32, cpu: &Cpu) -> std::io::Result<()> {
+        let op = cpu.mem.read_u16(cpu.pc);
+        if !self
+            .filter
+            .matches(cpu.pc, &Instruction::parse_variant(op, Variant::XoChip))
+        {
+            return Ok(());
+        }
+
+        if !self.header_written {
+            self.header_written = true;
+            if self.format == TraceFormat::Csv {
+                let registers: Vec<_> = (0..16).map(|n| format!("V{n:X}")).collect();
+                writeln!(
+                    self.out,
+                    "frame,cycle,pc,opcode,instruction,{},I,SP,DT,ST",
+                    registers.join(",")
+                )?;
+            }
+        }
+
+        writeln!(self.out, "{}", self.line(frame, cycle, cpu, op))
+    }
+
+    pub(crate) fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+
+    fn line(&self, frame: u64, cycle: u32, cpu: &Cpu, op: u16) -> String {
+        let asm = Instruction::parse_variant(op, Variant::XoChip).to_string();
+        let registers = cpu.registers.iter();
+
+        match self.format {
+            TraceFormat::Text => {
+                let registers: Vec<_> = registers.map(|v| format!("{v:02X}")).collect();
+                format!(
+                    "{frame:>6}:{cycle:<4} {:#06X}  {op:04X}  {asm:<20} V={} I={:#06X} SP={:#06X} DT={:02X} ST={:02X}",
+                    cpu.pc,
+                    registers.join(" "),
+                    cpu.i,
+                    cpu.sp,
+                    cpu.delay_timer,
+                    cpu.sound_timer
+                )
+            }
+            TraceFormat::Jsonl => {
+                let registers: Vec<_> = registers.map(u8::to_string).collect();
+                format!(
+                    "{{\"frame\":{frame},\"cycle\":{cycle},\"pc\":{},\"opcode\":\"{op:04X}\",\
+                     \"instruction\":\"{asm}\",\"v\":[{}],\"i\":{},\"sp\":{},\"dt\":{},\"st\":{}}}",
+                    cpu.pc,
+                    registers.join(","),
+                    cpu.i,
+                    cpu.sp,
+                    cpu.delay_timer,
+                    cpu.sound_timer
+                )
+            }
+            TraceFormat::Csv => {
+                let registers: Vec<_> = registers.map(|v| format!("{v:#04X}")).collect();
+                format!(
+                    "{frame},{cycle},{:#06X},{op:04X},\"{asm}\",{},{:#06X},{:#06X},{:#04X},{:#04X}",
+                    cpu.pc,
+                    registers.join(","),
+                    cpu.i,
+                    cpu.sp,
+                    cpu.delay_timer,
+                    cpu.sound_timer
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Chip8;
+
+    #[test]
+    fn test_line_formats() {
+        let mut chip8 = Chip8::new(&[0x6A, 0x02]);
+        chip8.cpu_mut().registers[0xF] = 0x01;
+        chip8.cpu_mut().i = 0x300;
+        let cpu = chip8.cpu();
+        let op = 0x6A02;
+
+        let text = InstructionTrace::new(std::io::sink(), TraceFormat::Text);
+        assert_eq!(
+            text.line(12, 3, cpu, op),
+            "    12:3    0x0200  6A02  LD VA, 0x02          \
+             V=00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 01 \
+             I=0x0300 SP=0x01FE DT=00 ST=00"
+        );
+
+        let jsonl = InstructionTrace::new(std::io::sink(), TraceFormat::Jsonl);
+        assert_eq!(
+            jsonl.line(12, 3, cpu, op),
+            "{\"frame\":12,\"cycle\":3,\"pc\":512,\"opcode\":\"6A02\",\
+             \"instruction\":\"LD VA, 0x02\",\"v\":[0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,1],\
+             \"i\":768,\"sp\":510,\"dt\":0,\"st\":0}"
+        );
+
+        let csv = InstructionTrace::new(std::io::sink(), TraceFormat::Csv);
+        assert_eq!(
+            csv.line(12, 3, cpu, op),
+            "12,3,0x0200,6A02,\"LD VA, 0x02\",0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x00,\
+             0x00,0x00,0x00,0x00,0x00,0x00,0x00,0x01,0x0300,0x01FE,0x00,0x00"
+        );
+    }
+
+    #[test]
+    fn test_filter() {
+        let drw = Instruction::parse(0xD125);
+        let jmp = Instruction::parse(0x1200);
+        assert_eq!(OpcodeClass::of(&drw), OpcodeClass::Display);
+        assert_eq!(
+            OpcodeClass::of(&Instruction::parse(0xF20A)),
+            OpcodeClass::Input
+        );
+        assert_eq!(
+            OpcodeClass::of(&Instruction::parse(0x0123)),
+            OpcodeClass::Unknown
+        );
+
+        let filter = TraceFilter {
+            range: Some(0x200..=0x2FF),
+            classes: vec![OpcodeClass::Display, OpcodeClass::Flow],
+        };
+        assert!(filter.matches(0x210, &drw));
+        assert!(filter.matches(0x2FF, &jmp));
+        assert!(!filter.matches(0x300, &drw));
+        assert!(!filter.matches(0x210, &Instruction::parse(0x6000)));
+        assert!(TraceFilter::default().matches(0x000, &Instruction::parse(0x6000)));
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("0x200-0x2FF"), Ok(0x200..=0x2FF));
+        assert_eq!(parse_range("512 - 0x300"), Ok(0x200..=0x300));
+        assert_eq!(parse_range("0x24A"), Ok(0x24A..=0x24A));
+        assert!(parse_range("0x300-0x200").is_err());
+        assert!(parse_range("0x200-").is_err());
+        assert!(parse_range("main").is_err());
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+use crate::{instructions::Register, Cpu};
+
+/// Manual state edit made through a debug command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Edit {
+    Register {
+        reg: Register,
+        old: u8,
+        new: u8,
+    },
+    Memory {
+        addr: u16,
+        old: u8,
+        new: u8,
+    },
+    /// Big-endian word, for patching a whole instruction in one step
+    Word {
+        addr: u16,
+        old: u16,
+        new: u16,
+    },
+    Pc {
+        old: u16,
+        new: u16,
+    },
+    Breakpoint {
+        addr: u16,
+    },
+}
+
+impl Edit {
+    fn apply(&self, cpu: &mut Cpu, breakpoints: &mut HashSet<u16>, undo: bool) {
+        match *self {
+            Edit::Register { reg, old, new } => cpu.registers[reg] = if undo { old } else { new },
+            Edit::Memory { addr, old, new } => cpu.mem.write_u8(addr, if undo { old } else { new }),
+            Edit::Word { addr, old, new } => {
+                let [hi, lo] = if undo { old } else { new }.to_be_bytes();
+                cpu.mem.write_u8(addr, hi);
+                cpu.mem.write_u8(addr.wrapping_add(1), lo);
+            }
+            Edit::Pc { old, new } => cpu.pc = if undo { old } else { new },
+            Edit::Breakpoint { addr } => {
+                if !breakpoints.remove(&addr) {
+                    breakpoints.insert(addr);
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::Register { reg, old, new } => write!(f, "{reg}: {old:#04X} -> {new:#04X}"),
+            Edit::Memory { addr, old, new } => write!(f, "[{addr:#06X}]: {old:#04X} -> {new:#04X}"),
+            Edit::Word { addr, old, new } => write!(f, "[{addr:#06X}]: {old:#06X} -> {new:#06X}"),
+            Edit::Pc { old, new } => write!(f, "PC: {old:#06X} -> {new:#06X}"),
+            Edit::Breakpoint { addr } => write!(f, "toggle breakpoint {addr:#06X}"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct UndoStack {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+}
+
+impl UndoStack {
+    /// Apply a new edit, discarding the redo history
+    pub(crate) fn apply(&mut self, edit: Edit, cpu: &mut Cpu, breakpoints: &mut HashSet<u16>) {
+        edit.apply(cpu, breakpoints, false);
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    pub(crate) fn undo(&mut self, cpu: &mut Cpu, breakpoints: &mut HashSet<u16>) -> Option<Edit> {
+        let edit = self.undo.pop()?;
+        edit.apply(cpu, breakpoints, true);
+        self.redo.push(edit);
+        Some(edit)
+    }
+
+    pub(crate) fn redo(&mut self, cpu: &mut Cpu, breakpoints: &mut HashSet<u16>) -> Option<Edit> {
+        let edit = self.redo.pop()?;
+        edit.apply(cpu, breakpoints, false);
+        self.undo.push(edit);
+        Some(edit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{display::Display, Memory};
+
+    #[test]
+    fn test_undo_redo() {
+        let mut cpu = Cpu::new(Memory::init(&[]), Display::default());
+        let mut breakpoints = HashSet::new();
+        let mut stack = UndoStack::default();
+
+        let edits = [
+            Edit::Register {
+                reg: Register::V3,
+                old: 0x00,
+                new: 0x12,
+            },
+            Edit::Memory {
+                addr: 0x300,
+                old: 0x00,
+                new: 0xAB,
+            },
+            Edit::Breakpoint { addr: 0x204 },
+        ];
+        for edit in edits {
+            stack.apply(edit, &mut cpu, &mut breakpoints);
+        }
+
+        assert_eq!(cpu.registers[Register::V3], 0x12);
+        assert_eq!(cpu.mem.read_u8(0x300), 0xAB);
+        assert!(breakpoints.contains(&0x204));
+
+        assert_eq!(stack.undo(&mut cpu, &mut breakpoints), Some(edits[2]));
+        assert_eq!(stack.undo(&mut cpu, &mut breakpoints), Some(edits[1]));
+        assert!(breakpoints.is_empty());
+        assert_eq!(cpu.mem.read_u8(0x300), 0x00);
+        assert_eq!(cpu.registers[Register::V3], 0x12);
+
+        assert_eq!(stack.redo(&mut cpu, &mut breakpoints), Some(edits[1]));
+        assert_eq!(cpu.mem.read_u8(0x300), 0xAB);
+
+        stack.apply(
+            Edit::Pc {
+                old: 0x200,
+                new: 0x300,
+            },
+            &mut cpu,
+            &mut breakpoints,
+        );
+        assert_eq!(stack.redo(&mut cpu, &mut breakpoints), None);
+
+        assert!(stack.undo(&mut cpu, &mut breakpoints).is_some());
+        assert_eq!(cpu.pc, 0x200);
+    }
+}
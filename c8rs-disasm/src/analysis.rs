@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use c8rs_core::{Instruction, Variant};
+
+/// Why an address is referenced, in order of precedence when it has
+/// several
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LabelKind {
+    Data,
+    Jump,
+    Sub,
+}
+
+/// Which bytes of a ROM are reachable code, found by following the control
+/// flow from the entry point
+pub struct Analysis {
+    rom: Vec<u8>,
+    base: u16,
+    entry: u16,
+    variant: Variant,
+    /// Set for offsets a reached instruction starts at
+    starts: Vec<bool>,
+    /// Referenced addresses inside the ROM
+    labels: BTreeMap<u16, LabelKind>,
+    /// Instructions referencing each label
+    xrefs: BTreeMap<u16, Vec<u16>>,
+    /// User-assigned names, replacing the generated ones
+    symbols: BTreeMap<u16, String>,
+}
+
+impl Analysis {
+    /// Trace `rom` written for `variant`, loaded at `base`, starting at
+    /// `entry`
+    pub fn new(rom: &[u8], base: u16, entry: u16, variant: Variant) -> Analysis {
+        let mut analysis = Analysis {
+            rom: rom.to_vec(),
+            base,
+            entry,
+            variant,
+            starts: vec![false; rom.len()],
+            labels: BTreeMap::new(),
+            xrefs: BTreeMap::new(),
+            symbols: BTreeMap::new(),
+        };
+
+        let mut pending = vec![entry];
+        while let Some(addr) = pending.pop() {
+            let Some(instr) = analysis.instruction(addr) else {
+                continue;
+            };
+            let offset = (addr - base) as usize;
+            if analysis.starts[offset] {
+                continue;
+            }
+
+            analysis.starts[offset] = true;
+
+            if let Some((target, kind)) = analysis.reference(instr, addr) {
+                analysis.add_label(target, kind, addr);
+            }
+            pending.extend(analysis.successors(instr, addr));
+        }
+
+        for xrefs in analysis.xrefs.values_mut() {
+            xrefs.sort_unstable();
+        }
+
+        analysis
+    }
+
+    /// Name addresses with `symbols` instead of generated labels, symbols
+    /// outside the ROM are ignored
+    pub fn with_symbols(mut self, symbols: BTreeMap<u16, String>) -> Analysis {
+        self.symbols = symbols
+            .into_iter()
+            .filter(|(addr, _)| self.offset(*addr).is_some())
+            .collect();
+        self
+    }
+
+    fn add_label(&mut self, addr: u16, kind: LabelKind, from: u16) {
+        if self.offset(addr).is_some() {
+            let label = self.labels.entry(addr).or_insert(kind);
+            *label = (*label).max(kind);
+            self.xrefs.entry(addr).or_default().push(from);
+        }
+    }
+
+    pub fn entry(&self) -> u16 {
+        self.entry
+    }
+
+    /// Addresses called as subroutines, in address order
+    pub fn subroutines(&self) -> impl Iterator<Item = u16> + '_ {
+        self.labels
+            .iter()
+            .filter(|(_, kind)| **kind == LabelKind::Sub)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Addresses reached instructions start at, in address order
+    pub fn instructions(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..self.starts.len())
+            .filter(|&offset| self.starts[offset])
+            .map(|offset| self.base + offset as u16)
+    }
+
+    /// Instruction at `addr`, `None` past the end of the ROM
+    pub fn instruction(&self, addr: u16) -> Option<Instruction> {
+        let offset = self.offset(addr)?;
+        let bytes = self.rom.get(offset..offset + 2)?;
+        Some(Instruction::parse_variant(
+            u16::from_be_bytes([bytes[0], bytes[1]]),
+            self.variant,
+        ))
+    }
+
+    /// Byte of the ROM at `offset`
+    pub fn byte(&self, offset: usize) -> Option<u8> {
+        self.rom.get(offset).copied()
+    }
+
+    /// Address loaded by the `F000 nnnn` at `addr`
+    pub fn long_addr(&self, addr: u16) -> Option<u16> {
+        let offset = self.offset(addr)? + 2;
+        let bytes = self.rom.get(offset..offset + 2)?;
+        Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Address referenced by `instr` at `addr`, if any
+    fn reference(&self, instr: Instruction, addr: u16) -> Option<(u16, LabelKind)> {
+        match instr {
+            Instruction::Jmp { addr } | Instruction::JmpReg { addr } => {
+                Some((addr, LabelKind::Jump))
+            }
+            Instruction::Call { addr } => Some((addr, LabelKind::Sub)),
+            Instruction::LdI { addr } => Some((addr, LabelKind::Data)),
+            Instruction::LdILong => Some((self.long_addr(addr)?, LabelKind::Data)),
+            _ => None,
+        }
+    }
+
+    /// Addresses execution can continue at after `instr` at `addr`
+    pub fn successors(&self, instr: Instruction, addr: u16) -> Vec<u16> {
+        let next = addr.wrapping_add(instr.size());
+        // skips step over a whole `F000 nnnn`
+        let after_next = || {
+            let len = self.instruction(next).map_or(2, |instr| instr.size());
+            next.wrapping_add(len)
+        };
+
+        match instr {
+            Instruction::Ret | Instruction::Exit => vec![],
+            // the target of `JP V0, addr` isn't known statically
+            Instruction::JmpReg { .. } => vec![],
+            Instruction::Jmp { addr } => vec![addr],
+            Instruction::Call { addr } => vec![addr, next],
+            Instruction::SkipEqImm { .. }
+            | Instruction::SkipNEqImm { .. }
+            | Instruction::SkipEqReg { .. }
+            | Instruction::SkipNEqReg { .. }
+            | Instruction::SkipPressed { .. }
+            | Instruction::SkipNotPressed { .. } => vec![next, after_next()],
+            _ => vec![next],
+        }
+    }
+
+    pub fn offset(&self, addr: u16) -> Option<usize> {
+        let offset = addr.checked_sub(self.base)? as usize;
+        (offset < self.starts.len()).then_some(offset)
+    }
+
+    /// Whether a reached instruction starts at `offset`
+    pub fn is_instruction(&self, offset: usize) -> bool {
+        self.starts.get(offset).copied().unwrap_or(false)
+    }
+
+    /// Name of the label at `addr`, if it has a symbol or anything inside the
+    /// ROM references it
+    pub fn label(&self, addr: u16) -> Option<String> {
+        if let Some(symbol) = self.symbols.get(&addr) {
+            return Some(symbol.clone());
+        }
+
+        let kind = self.labels.get(&addr)?;
+        let prefix = match kind {
+            LabelKind::Data => "data",
+            LabelKind::Jump => "label",
+            LabelKind::Sub => "sub",
+        };
+        Some(format!("{prefix}_{addr:04X}"))
+    }
+
+    /// Every label, in address order
+    pub fn labels(&self) -> BTreeMap<u16, String> {
+        self.labels
+            .keys()
+            .chain(self.symbols.keys())
+            .filter_map(|addr| Some((*addr, self.label(*addr)?)))
+            .collect()
+    }
+
+    /// Addresses of the instructions referencing `addr`, in address order
+    pub fn xrefs(&self, addr: u16) -> &[u16] {
+        self.xrefs.get(&addr).map_or(&[], Vec::as_slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analyse(rom: &[u8]) -> Analysis {
+        Analysis::new(rom, 0x200, 0x200, Variant::Chip8)
+    }
+
+    fn instructions(analysis: &Analysis) -> Vec<u16> {
+        analysis.instructions().collect()
+    }
+
+    #[test]
+    fn test_code_and_data() {
+        // LD I, 0x206; DRW V0, V0, 1; JMP 0x204; sprite
+        let analysis = analyse(&[0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xFF, 0x00]);
+        assert_eq!(instructions(&analysis), [0x200, 0x202, 0x204]);
+        assert!(!analysis.is_instruction(6));
+        assert_eq!(analysis.label(0x206).as_deref(), Some("data_0206"));
+        assert_eq!(analysis.label(0x204).as_deref(), Some("label_0204"));
+        assert_eq!(analysis.label(0x202), None);
+    }
+
+    #[test]
+    fn test_data_after_jump() {
+        // JMP 0x204; data; CLS; RET
+        let analysis = analyse(&[0x12, 0x04, 0x00, 0xE0, 0x00, 0xE0, 0x00, 0xEE]);
+        assert_eq!(instructions(&analysis), [0x200, 0x204, 0x206]);
+        assert!(!analysis.is_instruction(2));
+    }
+
+    #[test]
+    fn test_skips() {
+        // SE V0, 1; JMP 0x206; CLS; RET
+        let analysis = analyse(&[0x30, 0x01, 0x12, 0x06, 0x00, 0xE0, 0x00, 0xEE]);
+        assert_eq!(instructions(&analysis), [0x200, 0x202, 0x204, 0x206]);
+
+        // a skip steps over all of LD I, LONG 0x1234
+        let rom = [0x30, 0x01, 0xF0, 0x00, 0x12, 0x34, 0x00, 0xEE];
+        let analysis = Analysis::new(&rom, 0x200, 0x200, Variant::XoChip);
+        assert_eq!(instructions(&analysis), [0x200, 0x202, 0x206]);
+        assert!(!analysis.is_instruction(4));
+        assert_eq!(analysis.long_addr(0x202), Some(0x1234));
+    }
+
+    #[test]
+    fn test_call_ret() {
+        // CALL 0x204; JMP 0x202; RET
+        let analysis = analyse(&[0x22, 0x04, 0x12, 0x02, 0x00, 0xEE]);
+        assert_eq!(instructions(&analysis), [0x200, 0x202, 0x204]);
+        assert_eq!(analysis.subroutines().collect::<Vec<_>>(), [0x204]);
+        assert_eq!(analysis.label(0x204).as_deref(), Some("sub_0204"));
+    }
+
+    #[test]
+    fn test_self_recursive_call() {
+        let analysis = analyse(&[0x22, 0x00]);
+        assert_eq!(instructions(&analysis), [0x200]);
+        assert_eq!(analysis.subroutines().collect::<Vec<_>>(), [0x200]);
+        assert_eq!(analysis.xrefs(0x200), [0x200]);
+    }
+
+    #[test]
+    fn test_jump_v0() {
+        // JP V0, 0x204; data; CLS
+        let analysis = analyse(&[0xB2, 0x04, 0xFF, 0xFF, 0x00, 0xE0]);
+        assert_eq!(instructions(&analysis), [0x200]);
+        assert_eq!(analysis.label(0x204).as_deref(), Some("label_0204"));
+        assert!(!analysis.is_instruction(4));
+    }
+
+    #[test]
+    fn test_odd_length() {
+        // CLS; half an instruction
+        let analysis = analyse(&[0x00, 0xE0, 0x12]);
+        assert_eq!(instructions(&analysis), [0x200]);
+        assert_eq!(analysis.instruction(0x202), None);
+        assert_eq!(analysis.byte(2), Some(0x12));
+
+        assert!(instructions(&analyse(&[0x12])).is_empty());
+        assert!(instructions(&analyse(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_xrefs() {
+        // JMP 0x206; CALL 0x206; LD I, 0x206; RET; JMP 0x300
+        let rom = [0x22, 0x06, 0xA2, 0x06, 0x12, 0x06, 0x00, 0xEE, 0x13, 0x00];
+        let analysis = analyse(&rom);
+        assert_eq!(analysis.xrefs(0x206), [0x200, 0x202, 0x204]);
+        // calls take precedence over jumps and data
+        assert_eq!(analysis.label(0x206).as_deref(), Some("sub_0206"));
+        // outside the ROM
+        assert!(analysis.xrefs(0x300).is_empty());
+
+        let symbols = BTreeMap::from([
+            (0x206, "draw".to_string()),
+            (0x202, "load".to_string()),
+            (0x400, "outside".to_string()),
+        ]);
+        let analysis = analysis.with_symbols(symbols);
+        assert_eq!(
+            analysis.labels(),
+            BTreeMap::from([(0x202, "load".to_string()), (0x206, "draw".to_string())])
+        );
+    }
+}
@@ -0,0 +1,61 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+
+use c8rs_core::Instruction;
+
+use crate::analysis::Analysis;
+
+/// Write the subroutine call graph of the analysed ROM as Graphviz DOT, the
+/// entry point is called `main`
+pub(crate) fn write(out: &mut dyn Write, analysis: &Analysis) -> io::Result<()> {
+    let entry = analysis.entry();
+    let name = |addr: u16| match analysis.label(addr) {
+        _ if addr == entry => "main".to_string(),
+        Some(label) => label,
+        None => format!("sub_{addr:04X}"),
+    };
+
+    let mut graph = BTreeMap::new();
+    for sub in std::iter::once(entry).chain(analysis.subroutines()) {
+        graph.insert(sub, calls(analysis, sub));
+    }
+
+    writeln!(out, "digraph calls {{")?;
+    for (sub, calls) in &graph {
+        writeln!(out, "    {};", name(*sub))?;
+        for call in calls {
+            writeln!(out, "    {} -> {};", name(*sub), name(*call))?;
+        }
+    }
+    writeln!(out, "}}")
+}
+
+/// Subroutines called from the body of the one at `sub`, which ends at its
+/// returns
+fn calls(analysis: &Analysis, sub: u16) -> BTreeSet<u16> {
+    let mut calls = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+
+    let mut pending = vec![sub];
+    while let Some(addr) = pending.pop() {
+        let Some(instr) = analysis.instruction(addr) else {
+            continue;
+        };
+        if !visited.insert(addr) {
+            continue;
+        }
+
+        match instr {
+            // the callee's body belongs to its own node
+            Instruction::Call { addr: target } => {
+                calls.insert(target);
+                pending.push(addr.wrapping_add(2));
+            }
+            _ => pending.extend(analysis.successors(instr, addr)),
+        }
+    }
+
+    calls
+}
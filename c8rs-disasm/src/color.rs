@@ -0,0 +1,85 @@
+const RESET: &str = "\x1b[0m";
+const BOLD_CYAN: &str = "\x1b[1;36m";
+const YELLOW: &str = "\x1b[33m";
+const MAGENTA: &str = "\x1b[35m";
+const BLUE: &str = "\x1b[34m";
+const GREEN: &str = "\x1b[32m";
+const DIM: &str = "\x1b[2m";
+
+/// ANSI styling of disassembly, everything is passed through unchanged when
+/// disabled
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Colors {
+    enabled: bool,
+}
+
+impl Colors {
+    /// Colors if `enabled` and the `NO_COLOR` environment variable isn't set
+    pub fn new(enabled: bool) -> Colors {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Colors {
+            enabled: enabled && !no_color,
+        }
+    }
+
+    fn paint(&self, style: &str, s: &str) -> String {
+        if self.enabled {
+            format!("{style}{s}{RESET}")
+        } else {
+            s.to_string()
+        }
+    }
+
+    pub fn addr(&self, s: &str) -> String {
+        self.paint(BLUE, s)
+    }
+
+    pub fn comment(&self, s: &str) -> String {
+        self.paint(DIM, s)
+    }
+
+    /// `db` followed by data bytes
+    pub fn data(&self, literals: &[String]) -> String {
+        let bytes: Vec<String> = literals.iter().map(|b| self.paint(GREEN, b)).collect();
+        format!("{} {}", self.paint(BOLD_CYAN, "db"), bytes.join(", "))
+    }
+
+    /// An instruction in the c8rs syntax, e.g. `LD V1, 0x23`
+    pub fn instruction(&self, text: &str) -> String {
+        if !self.enabled {
+            return text.to_string();
+        }
+
+        let (mnemonic, operands) = text.split_once(' ').unwrap_or((text, ""));
+        let operands: Vec<String> = operands
+            .split(", ")
+            .filter(|op| !op.is_empty())
+            .map(|op| {
+                op.split(' ')
+                    .map(|word| self.operand(word))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+
+        let mnemonic = self.paint(BOLD_CYAN, mnemonic);
+        if operands.is_empty() {
+            mnemonic
+        } else {
+            format!("{mnemonic} {}", operands.join(", "))
+        }
+    }
+
+    fn operand(&self, op: &str) -> String {
+        let style = match op {
+            // the opcode of `unknown (0x0123)`
+            _ if op.starts_with('(') => MAGENTA,
+            // addresses are always printed with 4 digits, immediates with 2
+            _ if op.starts_with("0x") && op.len() == 6 => BLUE,
+            _ if op.starts_with("0x") || op.starts_with(|c: char| c.is_ascii_digit()) => MAGENTA,
+            "LONG" => BOLD_CYAN,
+            _ => YELLOW,
+        };
+        self.paint(style, op)
+    }
+}
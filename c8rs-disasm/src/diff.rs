@@ -0,0 +1,58 @@
+use std::{collections::BTreeMap, io::Write};
+
+use anyhow::Result;
+
+use crate::{analysis::Analysis, data_literals, items, offsets, text, DisassemblerArgs, Item};
+
+/// Width of the old column
+const WIDTH: usize = 28;
+
+/// Write the disassemblies of `old` and `new` side by side, aligned by
+/// address. Lines are marked `|` if they differ, `<` or `>` if only one side
+/// has something starting at that address.
+pub(crate) fn write(
+    out: &mut dyn Write,
+    old: &[u8],
+    new: &[u8],
+    args: &DisassemblerArgs,
+) -> Result<()> {
+    let old = lines(old, args)?;
+    let new = lines(new, args)?;
+
+    let mut addrs: Vec<u16> = old.keys().chain(new.keys()).copied().collect();
+    addrs.sort_unstable();
+    addrs.dedup();
+
+    for addr in addrs {
+        let (left, right) = (old.get(&addr), new.get(&addr));
+        let marker = match (left, right) {
+            (Some(left), Some(right)) if left == right => ' ',
+            (Some(_), Some(_)) => '|',
+            (Some(_), None) => '<',
+            (None, _) => '>',
+        };
+
+        let left = left.map_or("", String::as_str);
+        let right = right.map_or("", String::as_str);
+        let line = format!("{addr:#06X}| {left:<WIDTH$} {marker} {right}");
+        writeln!(out, "{}", line.trim_end())?;
+    }
+
+    Ok(())
+}
+
+/// Disassembly of `rom` by address
+fn lines(rom: &[u8], args: &DisassemblerArgs) -> Result<BTreeMap<u16, String>> {
+    let range = offsets(args, rom.len())?;
+    let analysis = Analysis::new(rom, args.base, args.base, args.variant);
+
+    let lines = items(rom, range, args.base, &analysis, 8)
+        .into_iter()
+        .map(|item| match item {
+            Item::Instruction { addr, bytes, instr } => (addr, text(instr, bytes)),
+            Item::Data { addr, bytes } => (addr, format!("db {}", data_literals(bytes).join(", "))),
+        })
+        .collect();
+
+    Ok(lines)
+}
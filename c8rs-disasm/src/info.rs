@@ -0,0 +1,133 @@
+use std::collections::BTreeSet;
+
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+
+use c8rs_core::{hash::sha1_hex, Instruction, Variant};
+
+use crate::{analysis::Analysis, lint};
+
+/// Memory a ROM can fill when loaded at 0x200 on a 4K machine
+const MAX_ROM_SIZE: usize = 0x1000 - 0x200;
+
+/// First opcode of ROMs for the two-page hi-res interpreter
+const HIRES_HEADER: [u8; 2] = [0x12, 0x60];
+
+#[derive(Parser, Debug)]
+pub struct InfoArgs {
+    /// chip-8 ROM file
+    file: String,
+}
+
+/// Print quick facts about a ROM: its size, hashes, the instruction set it
+/// needs and which hardware it uses
+pub fn info(args: InfoArgs, read_rom: impl Fn(&str) -> Result<Vec<u8>>) -> Result<()> {
+    let rom = read_rom(&args.file)?;
+    let analysis = Analysis::new(&rom, 0x200, 0x200, Variant::XoChip);
+    let instructions: Vec<(u16, Instruction)> = analysis
+        .instructions()
+        .filter_map(|addr| Some((addr, analysis.instruction(addr)?)))
+        .collect();
+
+    println!("file:        {}", args.file);
+    let fits = if rom.len() > MAX_ROM_SIZE {
+        ", too big for 4K memory"
+    } else {
+        ""
+    };
+    println!(
+        "size:        {} bytes ({:#06X}){fits}",
+        rom.len(),
+        rom.len()
+    );
+    println!("sha1:        {}", sha1_hex(&rom));
+    println!("crc32:       {:08x}", crc32fast::hash(&rom));
+
+    // the first instruction needing the variant, to show why it was picked
+    let variant = instructions
+        .iter()
+        .map(|&(addr, instr)| (instr.variant(), addr, instr))
+        .max_by_key(|&(variant, addr, _)| (variant, std::cmp::Reverse(addr)));
+    match variant {
+        Some((variant, addr, instr)) if variant != Variant::Chip8 => {
+            println!("variant:     {} ({instr} at {addr:#06X})", name(variant))
+        }
+        _ => println!("variant:     {}", name(Variant::Chip8)),
+    }
+    if rom.starts_with(&HIRES_HEADER) {
+        println!("display:     64x64, two-page hi-res interpreter");
+    }
+
+    let code_bytes: usize = instructions
+        .iter()
+        .map(|(_, instr)| instr.size() as usize)
+        .sum();
+    println!(
+        "code:        {} instructions reached, {} subroutines",
+        instructions.len(),
+        analysis.subroutines().count()
+    );
+    println!(
+        "data:        {} bytes not reached",
+        rom.len().saturating_sub(code_bytes)
+    );
+
+    let max_i = instructions
+        .iter()
+        .filter_map(|&(addr, instr)| match instr {
+            Instruction::LdI { addr: target } => Some((target, addr)),
+            Instruction::LdILong => Some((analysis.long_addr(addr)?, addr)),
+            _ => None,
+        })
+        .max();
+    match max_i {
+        Some((target, addr)) => println!("max I:       {target:#06X} (set at {addr:#06X})"),
+        None => println!("max I:       never set"),
+    }
+
+    let used: BTreeSet<_> = instructions
+        .iter()
+        .filter_map(|(_, instr)| feature(instr))
+        .collect();
+    let uses: Vec<_> = FEATURES.into_iter().filter(|f| used.contains(f)).collect();
+    if !uses.is_empty() {
+        println!("uses:        {}", uses.join(", "));
+    }
+
+    let warnings = lint::lint(&analysis).len();
+    if warnings > 0 {
+        println!("warnings:    {warnings}, see `c8rs dis --lint`");
+    }
+
+    Ok(())
+}
+
+/// Hardware listed on the `uses:` line, in order
+const FEATURES: [&str; 5] = [
+    "keypad",
+    "delay timer",
+    "sound",
+    "random numbers",
+    "RPL flags",
+];
+
+fn feature(instr: &Instruction) -> Option<&'static str> {
+    match instr {
+        Instruction::SkipPressed { .. }
+        | Instruction::SkipNotPressed { .. }
+        | Instruction::LdKey { .. } => Some("keypad"),
+        Instruction::SetDelayTimer { .. } | Instruction::LdDelayTimer { .. } => Some("delay timer"),
+        Instruction::SetSoundTimer { .. } | Instruction::Audio | Instruction::Pitch { .. } => {
+            Some("sound")
+        }
+        Instruction::Rnd { .. } => Some("random numbers"),
+        Instruction::StoreFlags { .. } | Instruction::LoadFlags { .. } => Some("RPL flags"),
+        _ => None,
+    }
+}
+
+fn name(variant: Variant) -> String {
+    variant
+        .to_possible_value()
+        .map_or_else(String::new, |value| value.get_name().to_string())
+}
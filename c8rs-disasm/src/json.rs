@@ -0,0 +1,52 @@
+use std::io::{self, Write};
+
+use serde_json::{json, Value};
+
+use crate::{analysis::Analysis, data_literals, hex, text, Item};
+
+/// Write `items` as a JSON array of records
+pub(crate) fn write(out: &mut dyn Write, items: &[Item], analysis: &Analysis) -> io::Result<()> {
+    let records: Vec<Value> = items.iter().map(|item| record(item, analysis)).collect();
+    serde_json::to_writer_pretty(&mut *out, &records)?;
+    writeln!(out)
+}
+
+fn record(item: &Item, analysis: &Analysis) -> Value {
+    let mut record = match item {
+        Item::Instruction { addr, bytes, instr } => {
+            // split the c8rs syntax, e.g. `LD V0, 0x12` or `unknown (0x0123)`
+            let text = text(*instr, bytes);
+            let (mnemonic, operands) = text.split_once(' ').unwrap_or((&text, ""));
+            let operands: Vec<&str> = operands
+                .trim_matches(['(', ')'])
+                .split(", ")
+                .filter(|op| !op.is_empty())
+                .collect();
+
+            json!({
+                "addr": addr,
+                "opcode": hex(bytes).replace(' ', ""),
+                "mnemonic": mnemonic,
+                "operands": operands,
+                "kind": "code",
+            })
+        }
+        Item::Data { addr, bytes } => json!({
+            "addr": addr,
+            "opcode": hex(bytes).replace(' ', ""),
+            "mnemonic": "db",
+            "operands": data_literals(bytes),
+            "kind": "data",
+        }),
+    };
+
+    let addr = match item {
+        Item::Instruction { addr, .. } | Item::Data { addr, .. } => *addr,
+    };
+    if let Some(label) = analysis.label(addr) {
+        record["label"] = label.into();
+        record["xrefs"] = analysis.xrefs(addr).into();
+    }
+
+    record
+}
@@ -1,63 +1,516 @@
-use std::{fs::File, io::Read};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    ops::Range,
+    path::Path,
+};
 
-use anyhow::Result;
-use clap::Parser;
+use anyhow::{anyhow, bail, Result};
+use clap::{Parser, ValueEnum};
+use clap_num::maybe_hex;
 
-use c8rs_core::Instruction;
+use c8rs_core::{hash::sha1_hex, Instruction, Variant};
+
+use analysis::Analysis;
+use color::Colors;
+pub use info::{info, InfoArgs};
+pub use sprites::{sprites, SpritesArgs};
+pub use symbols::parse as parse_symbols;
+
+mod analysis;
+mod callgraph;
+mod color;
+mod diff;
+mod info;
+mod json;
+mod lint;
+mod octo;
+mod sprites;
+mod symbols;
 
 #[derive(Parser, Debug)]
 pub struct DisassemblerArgs {
-    /// chip-8 ROM file
+    /// chip-8 ROM file, `.gz` and `.zip` files are decompressed
     file: String,
 
     #[arg(short = 'x')]
     /// show hexdump of file contents
     hexdump: bool,
+
+    #[arg(long, default_value_t = 16, value_parser = clap::value_parser!(u16).range(1..))]
+    /// bytes per hexdump row
+    width: u16,
+
+    #[arg(long, value_name = "ADDR", default_value_t = 0, value_parser = maybe_hex::<usize>)]
+    /// address of the first byte in the hexdump
+    offset: usize,
+
+    #[arg(long, value_enum, default_value_t = Format::C8rs)]
+    /// syntax of the disassembly
+    format: Format,
+
+    #[arg(long, conflicts_with = "format")]
+    /// same as `--format json`
+    json: bool,
+
+    #[arg(short, value_name = "FILE")]
+    /// write to a file instead of stdout
+    output: Option<String>,
+
+    #[arg(long)]
+    /// start with a comment naming the ROM, its size and SHA-1
+    header: bool,
+
+    #[arg(long)]
+    /// draw data bytes as sprite rows, one byte per line
+    sprites: bool,
+
+    #[arg(long, value_enum, default_value_t = Variant::Chip8)]
+    /// instruction set the ROM is written for
+    variant: Variant,
+
+    #[arg(long, value_name = "ADDR", default_value_t = 0x200, value_parser = maybe_hex::<u16>)]
+    /// address the ROM is loaded at, e.g. 0x600 for ETI-660 programs
+    base: u16,
+
+    #[arg(long, value_name = "ADDR", value_parser = maybe_hex::<u16>)]
+    /// first address to disassemble, defaults to the load address
+    start: Option<u16>,
+
+    #[arg(long, value_name = "ADDR", value_parser = maybe_hex::<u16>)]
+    /// address to stop disassembling at (exclusive), defaults to the end of
+    /// the ROM
+    end: Option<u16>,
+
+    #[arg(long, value_name = "FILE")]
+    /// also write the subroutine call graph as Graphviz DOT
+    callgraph: Option<String>,
+
+    #[arg(long, value_name = "FILE")]
+    /// name addresses with the `0x0240 draw_score` lines of a symbol file
+    symbols: Option<String>,
+
+    #[arg(long, value_name = "FILE")]
+    /// also write every label to a symbol file, for editing and passing to
+    /// `--symbols` later
+    save_symbols: Option<String>,
+
+    #[arg(long, value_name = "OLD", conflicts_with_all = ["format", "json", "hexdump", "header"])]
+    /// disassemble OLD and the ROM side by side, marking changed lines
+    diff: Option<String>,
+
+    #[arg(long, conflicts_with = "json")]
+    /// style the disassembly with terminal colors, unless NO_COLOR is set
+    color: bool,
+
+    #[arg(long, conflicts_with_all = ["json", "hexdump", "diff"])]
+    /// only report suspicious code, e.g. jumps outside the ROM
+    lint: bool,
 }
 
-pub fn disassemble(args: DisassemblerArgs) -> Result<()> {
-    let file_contents = read_file(args.file)?;
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// addresses and c8rs mnemonics
+    C8rs,
+    /// address, raw bytes and mnemonic columns
+    Listing,
+    /// Octo source, ready to be assembled again
+    Octo,
+    /// array of `{addr, opcode, mnemonic, operands, kind}` records
+    Json,
+}
 
-    if args.hexdump {
-        print_hexdump(file_contents);
+impl Format {
+    /// Line comment marker, `None` if the format has no comments
+    fn comment(&self) -> Option<&'static str> {
+        match self {
+            Format::C8rs | Format::Listing => Some(";"),
+            Format::Octo => Some("#"),
+            Format::Json => None,
+        }
+    }
+}
+
+/// Disassemble the ROM `read_rom` reads from `args.file`
+pub fn disassemble(
+    mut args: DisassemblerArgs,
+    read_rom: impl Fn(&str) -> Result<Vec<u8>>,
+) -> Result<()> {
+    let file_contents = read_rom(&args.file)?;
+    if args.json {
+        args.format = Format::Json;
+    }
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    if let Some(path) = &args.diff {
+        let old = read_rom(path)?;
+        diff::write(&mut out, &old, &file_contents, &args)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    if args.header {
+        let name = Path::new(&args.file)
+            .file_name()
+            .map_or(args.file.clone(), |name| {
+                name.to_string_lossy().into_owned()
+            });
+        let Some(comment) = args.format.comment() else {
+            bail!("--header can't be used with JSON output");
+        };
+        writeln!(out, "{comment} {name}")?;
+        writeln!(out, "{comment} size: {} bytes", file_contents.len())?;
+        writeln!(out, "{comment} sha1: {}", sha1_hex(&file_contents))?;
+    }
+
+    let range = offsets(&args, file_contents.len())?;
+    // the whole ROM is analysed so a slice is classified the same way
+    let mut analysis = Analysis::new(&file_contents, args.base, args.base, args.variant);
+    if let Some(path) = &args.symbols {
+        let symbols = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Failed to read {path}: {err}"))
+            .and_then(|s| symbols::parse(&s).map_err(|err| anyhow!("{path}: {err}")))?;
+        analysis = analysis.with_symbols(symbols);
+    }
+
+    if let Some(path) = &args.save_symbols {
+        fs::write(path, symbols::to_string(&analysis.labels()))?;
+    }
+
+    if let Some(path) = &args.callgraph {
+        let mut dot = BufWriter::new(File::create(path)?);
+        callgraph::write(&mut dot, &analysis)?;
+        dot.flush()?;
+    }
+
+    if args.lint {
+        for (addr, warning) in lint::lint(&analysis) {
+            writeln!(out, "{addr:#06X}: warning: {warning}")?;
+        }
+    } else if args.hexdump {
+        print_hexdump(&mut out, &file_contents, args.width as usize, args.offset)?;
     } else {
-        print_disassembly(file_contents);
+        print_disassembly(&mut out, &file_contents, &args, &analysis, range)?;
     }
 
+    out.flush()?;
     Ok(())
 }
 
-fn read_file(filename: String) -> Result<Vec<u8>> {
-    let mut file = File::open(filename)?;
+/// Offsets into a ROM of `len` bytes selected by `--start` and `--end`
+fn offsets(args: &DisassemblerArgs, len: usize) -> Result<Range<usize>> {
+    let base = args.base as usize;
+    if base + len > 0x10000 {
+        bail!("ROM doesn't fit in memory when loaded at {base:#06X}");
+    }
 
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
+    let offset = |addr: u16| (addr as usize).saturating_sub(base).min(len);
+    let start = args.start.map_or(0, offset);
+    let end = args.end.map_or(len, offset);
+    if start > end {
+        bail!("--start must not be after --end");
+    }
 
-    Ok(buf)
+    Ok(start..end)
 }
 
-fn print_hexdump(file_contents: Vec<u8>) {
-    let chunks = file_contents.chunks(16);
+fn print_hexdump(
+    out: &mut dyn Write,
+    file_contents: &[u8],
+    width: usize,
+    offset: usize,
+) -> io::Result<()> {
+    let chunks = file_contents.chunks(width);
 
     for (i, chunk) in chunks.enumerate() {
-        let offset = i * 16;
+        let offset = offset + i * width;
         let chunk_str = chunk.iter().fold(String::new(), |mut acc, b| {
             acc.push_str(format!("{b:02X} ").as_str());
             acc
         });
-        println!("|{offset:#06X}| {chunk_str}");
+        let ascii: String = chunk
+            .iter()
+            .map(|&b| {
+                if b.is_ascii_graphic() || b == b' ' {
+                    b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        writeln!(out, "|{offset:#06X}| {chunk_str:<0$} |{ascii}|", width * 3)?;
     }
+
+    Ok(())
 }
 
-fn print_disassembly(file_contents: Vec<u8>) {
-    let mut offset = 0x200;
+/// Piece of a disassembled ROM
+enum Item<'a> {
+    Instruction {
+        addr: u16,
+        bytes: &'a [u8],
+        instr: Instruction,
+    },
+    Data {
+        addr: u16,
+        bytes: &'a [u8],
+    },
+}
+
+/// Split `range` of `rom` into instructions and runs of at most `data_len`
+/// bytes of data, in address order
+fn items<'a>(
+    rom: &'a [u8],
+    range: Range<usize>,
+    base: u16,
+    analysis: &Analysis,
+    data_len: usize,
+) -> Vec<Item<'a>> {
+    let mut items = Vec::new();
+
+    let mut offset = range.start;
+    while offset < range.end {
+        let addr = base + offset as u16;
 
-    for inst in file_contents.chunks(2) {
-        let op = ((inst[0] as u16) << 8) | inst[1] as u16;
+        if let Some(instr) = analysis
+            .instruction(addr)
+            .filter(|_| analysis.is_instruction(offset))
+        {
+            let len = (instr.size() as usize).min(rom.len() - offset);
+            let bytes = &rom[offset..offset + len];
+            items.push(Item::Instruction { addr, bytes, instr });
+            offset += len;
+            continue;
+        }
 
-        let instr = Instruction::parse(op);
-        println!("{offset:#06X}| {instr}",);
+        // data runs until the next instruction or label
+        let len = (offset..range.end)
+            .take(data_len)
+            .take_while(|&n| {
+                n == offset
+                    || !(analysis.is_instruction(n) || analysis.label(base + n as u16).is_some())
+            })
+            .count();
+        items.push(Item::Data {
+            addr,
+            bytes: &rom[offset..offset + len],
+        });
+        offset += len;
+    }
+
+    items
+}
+
+fn print_disassembly(
+    out: &mut dyn Write,
+    rom: &[u8],
+    args: &DisassemblerArgs,
+    analysis: &Analysis,
+    range: Range<usize>,
+) -> io::Result<()> {
+    let (format, sprites, base) = (args.format, args.sprites, args.base);
+    let colors = Colors::new(args.color);
+
+    // sprites are at most 15 rows, longer runs are split into several
+    let data_len = if sprites { 15 } else { 8 };
+    let starts_at_entry = range.start == 0;
+    let items = items(rom, range, base, analysis, data_len);
+
+    match format {
+        Format::Json => return json::write(out, &items, analysis),
+        Format::Octo if starts_at_entry => writeln!(out, ": main")?,
+        _ => (),
+    }
+
+    for item in items {
+        match item {
+            Item::Instruction { addr, bytes, instr } => {
+                print_label(out, format, colors, analysis, addr)?;
+
+                let text = colors.instruction(&text(instr, bytes));
+                let addr_col = colors.addr(&format!("{addr:#06X}"));
+                match format {
+                    Format::C8rs => writeln!(out, "{addr_col}| {text}")?,
+                    Format::Listing => writeln!(out, "{addr_col}  {:<24} {text}", hex(bytes))?,
+                    Format::Octo => {
+                        writeln!(out, "  {}", octo::instruction(instr, addr, analysis))?
+                    }
+                    Format::Json => unreachable!(),
+                }
+            }
+            Item::Data { addr, bytes } => {
+                print_label(out, format, colors, analysis, addr)?;
+
+                if !sprites {
+                    print_data(out, format, colors, addr, bytes)?;
+                    writeln!(out)?;
+                    continue;
+                }
+
+                let comment = format.comment().unwrap_or_default();
+                for (n, byte) in bytes.iter().enumerate() {
+                    print_data(out, format, colors, addr + n as u16, &[*byte])?;
+                    let art = format!("{comment} {}", pixels(*byte));
+                    writeln!(out, "  {}", colors.comment(&art))?;
+                }
+                writeln!(out)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Write the label at `addr`, for Octo, and the addresses referencing it
+fn print_label(
+    out: &mut dyn Write,
+    format: Format,
+    colors: Colors,
+    analysis: &Analysis,
+    addr: u16,
+) -> io::Result<()> {
+    let Some(label) = analysis.label(addr) else {
+        return Ok(());
+    };
+
+    let xrefs: Vec<String> = analysis
+        .xrefs(addr)
+        .iter()
+        .map(|addr| format!("{addr:#06X}"))
+        .collect();
+    match format {
+        Format::C8rs | Format::Listing => {
+            writeln!(out, "{}", colors.comment(&format!("; {label}")))?
+        }
+        Format::Octo => writeln!(out, ": {label}")?,
+        Format::Json => unreachable!(),
+    }
+
+    // symbols can name addresses nothing references
+    if !xrefs.is_empty() {
+        let indent = if format == Format::Octo { "  " } else { "" };
+        let comment = format.comment().unwrap_or_default();
+        let xrefs = format!("{comment} xrefs: {}", xrefs.join(", "));
+        writeln!(out, "{indent}{}", colors.comment(&xrefs))?;
+    }
+
+    Ok(())
+}
+
+/// Write a data line for `bytes` at `addr`, without the line break
+fn print_data(
+    out: &mut dyn Write,
+    format: Format,
+    colors: Colors,
+    addr: u16,
+    bytes: &[u8],
+) -> io::Result<()> {
+    let literals = data_literals(bytes);
+    let addr_col = colors.addr(&format!("{addr:#06X}"));
+    match format {
+        Format::C8rs => write!(out, "{addr_col}| {}", colors.data(&literals)),
+        Format::Listing => write!(
+            out,
+            "{addr_col}  {:<24} {}",
+            hex(bytes),
+            colors.data(&literals)
+        ),
+        Format::Octo => write!(out, "  {}", literals.join(" ")),
+        Format::Json => unreachable!(),
+    }
+}
+
+/// Sprite row for `byte`, most significant bit first
+fn pixels(byte: u8) -> String {
+    (0..8)
+        .map(|bit| {
+            if byte & (0x80 >> bit) != 0 {
+                '█'
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// `instr` in the c8rs syntax, with the address following `F000`
+fn text(instr: Instruction, bytes: &[u8]) -> String {
+    match (instr, bytes) {
+        (Instruction::LdILong, &[_, _, hi, lo]) => {
+            format!("{instr} {:#06X}", u16::from_be_bytes([hi, lo]))
+        }
+        _ => instr.to_string(),
+    }
+}
+
+fn data_literals(bytes: &[u8]) -> Vec<String> {
+    bytes.iter().map(|b| format!("{b:#04X}")).collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let bytes: Vec<String> = bytes.iter().map(|b| format!("{b:02X}")).collect();
+    bytes.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// LD I, 0x206; DRW V0, V0, 1; JMP 0x204; sprite
+    const ROM: &[u8] = &[0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xFF, 0x00];
+
+    /// Output of `c8rs dis` with `flags` for `rom`
+    fn disassembly(rom: &[u8], flags: &[&str]) -> String {
+        let out = std::env::temp_dir().join(format!(
+            "c8rs-test-{}-{}.txt",
+            std::process::id(),
+            flags.concat()
+        ));
+        let out = out.to_str().unwrap();
+        let args = ["dis", "test.ch8", "-o", out]
+            .into_iter()
+            .chain(flags.iter().copied());
+        disassemble(DisassemblerArgs::parse_from(args), |_| Ok(rom.to_vec())).unwrap();
+        fs::read_to_string(out).unwrap()
+    }
+
+    #[test]
+    fn test_xrefs() {
+        assert_eq!(
+            disassembly(ROM, &[]),
+            "0x0200| LD I, 0x0206\n\
+             0x0202| DRW V0, V0, 0x01\n\
+             ; label_0204\n\
+             ; xrefs: 0x0204\n\
+             0x0204| JMP 0x0204\n\
+             ; data_0206\n\
+             ; xrefs: 0x0200\n\
+             0x0206| db 0xFF, 0x00\n"
+        );
+        assert_eq!(
+            disassembly(ROM, &["--format", "octo"]),
+            ": main\n  \
+               i := data_0206\n  \
+               sprite v0 v0 1\n\
+             : label_0204\n  \
+               # xrefs: 0x0204\n  \
+               jump label_0204\n\
+             : data_0206\n  \
+               # xrefs: 0x0200\n  \
+               0xFF 0x00\n"
+        );
+    }
 
-        offset += 2;
+    #[test]
+    fn test_lint_output() {
+        assert_eq!(disassembly(ROM, &["--lint"]), "");
+        assert_eq!(
+            disassembly(&[0x00, 0xEE], &["--lint"]),
+            "0x0200: warning: RET with an empty stack\n"
+        );
     }
 }
@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use c8rs_core::Instruction;
+
+use crate::analysis::Analysis;
+
+/// Nesting depth of the original interpreter's stack
+const STACK_SIZE: usize = 16;
+
+/// Paths explored before giving up, branchy ROMs can have a lot of them
+const MAX_STATES: usize = 100_000;
+
+/// Suspicious patterns in the analysed ROM, by address
+pub(crate) fn lint(analysis: &Analysis) -> BTreeSet<(u16, String)> {
+    let mut warnings = BTreeSet::new();
+
+    // quirk-sensitive opcodes are reported once per kind, at the first use
+    let mut quirks: BTreeMap<&str, (u16, usize)> = BTreeMap::new();
+
+    for addr in analysis.instructions() {
+        let Some(instr) = analysis.instruction(addr) else {
+            continue;
+        };
+
+        if let Instruction::Jmp { addr: target } | Instruction::Call { addr: target } = instr {
+            if target % 2 != 0 {
+                warnings.insert((addr, format!("jump to odd address {target:#06X}")));
+            }
+            if analysis.offset(target).is_none() {
+                warnings.insert((addr, format!("jump outside the ROM to {target:#06X}")));
+            }
+        }
+
+        if let Some(quirk) = quirk(instr) {
+            let (_, uses) = quirks.entry(quirk).or_insert((addr, 0));
+            *uses += 1;
+        }
+    }
+
+    for (quirk, (addr, uses)) in quirks {
+        warnings.insert((addr, format!("{quirk} (used {uses} times)")));
+    }
+
+    paths(analysis, &mut warnings);
+
+    warnings
+}
+
+/// Opcodes interpreters disagree on
+fn quirk(instr: Instruction) -> Option<&'static str> {
+    match instr {
+        Instruction::Shr { .. } | Instruction::Shl { .. } => {
+            Some("SHR/SHL depend on the shift quirk")
+        }
+        Instruction::StoreRegs { .. } | Instruction::LoadRegs { .. } => {
+            Some("LD [I], Vx/LD Vx, [I] depend on the load/store quirk")
+        }
+        Instruction::JmpReg { .. } => Some("JP V0 depends on the jump quirk"),
+        Instruction::Or { .. } | Instruction::And { .. } | Instruction::Xor { .. } => {
+            Some("OR/AND/XOR depend on the VF reset quirk")
+        }
+        _ => None,
+    }
+}
+
+/// Follow every path from the entry point with its call stack, for stack
+/// imbalances and drawing before I is set
+fn paths(analysis: &Analysis, warnings: &mut BTreeSet<(u16, String)>) {
+    let mut visited = HashSet::new();
+
+    let mut pending = vec![(analysis.entry(), false, Vec::new())];
+    while let Some((addr, i_set, mut stack)) = pending.pop() {
+        if visited.len() >= MAX_STATES || !visited.insert((addr, i_set, stack.clone())) {
+            continue;
+        }
+        let Some(instr) = analysis.instruction(addr) else {
+            continue;
+        };
+
+        let mut i_set = i_set;
+        match instr {
+            Instruction::LdI { .. }
+            | Instruction::LdILong
+            | Instruction::LdFont { .. }
+            | Instruction::LdHiFont { .. } => i_set = true,
+            Instruction::Drw { .. } if !i_set => {
+                warnings.insert((addr, "DRW before I is set".to_string()));
+            }
+            _ => (),
+        }
+
+        match instr {
+            Instruction::Call { addr: target } => {
+                if stack.len() >= STACK_SIZE {
+                    warnings.insert((addr, format!("calls nested over {STACK_SIZE} deep")));
+                    continue;
+                }
+                stack.push(addr.wrapping_add(2));
+                pending.push((target, i_set, stack));
+            }
+            Instruction::Ret => match stack.pop() {
+                Some(ret) => pending.push((ret, i_set, stack)),
+                None => {
+                    warnings.insert((addr, "RET with an empty stack".to_string()));
+                }
+            },
+            _ => {
+                for next in analysis.successors(instr, addr) {
+                    pending.push((next, i_set, stack.clone()));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c8rs_core::Variant;
+
+    fn warnings(rom: &[u8]) -> Vec<(u16, String)> {
+        let analysis = Analysis::new(rom, 0x200, 0x200, Variant::Chip8);
+        lint(&analysis).into_iter().collect()
+    }
+
+    fn warning(addr: u16, msg: &str) -> (u16, String) {
+        (addr, msg.to_string())
+    }
+
+    #[test]
+    fn test_clean() {
+        // LD I, 0x206; DRW V0, V0, 1; JMP 0x204; sprite
+        assert!(warnings(&[0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xFF, 0x00]).is_empty());
+    }
+
+    #[test]
+    fn test_jumps() {
+        assert_eq!(
+            warnings(&[0x13, 0x01]),
+            [
+                warning(0x200, "jump outside the ROM to 0x0301"),
+                warning(0x200, "jump to odd address 0x0301"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_quirks() {
+        // SHR V0; SHL V0; SHR V1; JMP 0x206
+        assert_eq!(
+            warnings(&[0x80, 0x06, 0x80, 0x0E, 0x81, 0x16, 0x12, 0x06]),
+            [warning(
+                0x200,
+                "SHR/SHL depend on the shift quirk (used 3 times)"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_stack() {
+        assert_eq!(
+            warnings(&[0x00, 0xEE]),
+            [warning(0x200, "RET with an empty stack")]
+        );
+        // the only path back is through the call, but it never returns
+        assert_eq!(
+            warnings(&[0x22, 0x00]),
+            [warning(0x200, "calls nested over 16 deep")]
+        );
+    }
+
+    #[test]
+    fn test_draw_before_i() {
+        // SE V0, 0; LD I, 0x206; DRW V0, V0, 1; JMP 0x208
+        assert_eq!(
+            warnings(&[0x30, 0x00, 0xA2, 0x06, 0xD0, 0x01, 0x12, 0x08, 0x12, 0x08]),
+            [warning(0x204, "DRW before I is set")]
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use c8rs_core::{instructions::Register, Instruction};
+
+use crate::analysis::Analysis;
+
+/// `instr` at `at` in Octo syntax, addresses are replaced with labels where
+/// known
+pub(crate) fn instruction(instr: Instruction, at: u16, analysis: &Analysis) -> String {
+    let addr = |addr: u16| analysis.label(addr).unwrap_or(format!("{addr:#05X}"));
+    let reg = |reg: Register| reg.to_string().to_lowercase();
+
+    match instr {
+        Instruction::Cls => "clear".to_string(),
+        Instruction::Ret => "return".to_string(),
+        Instruction::Jmp { addr: a } => format!("jump {}", addr(a)),
+        Instruction::Call { addr: a } => addr(a),
+        // Octo's `if ... then` skips the next instruction when the condition
+        // is false, so skip conditions are inverted
+        Instruction::SkipEqImm { reg: r, byte } => format!("if {} != {byte:#04X} then", reg(r)),
+        Instruction::SkipNEqImm { reg: r, byte } => format!("if {} == {byte:#04X} then", reg(r)),
+        Instruction::SkipEqReg { regx, regy } => format!("if {} != {} then", reg(regx), reg(regy)),
+        Instruction::SkipNEqReg { regx, regy } => {
+            format!("if {} == {} then", reg(regx), reg(regy))
+        }
+        Instruction::SkipPressed { reg: r } => format!("if {} -key then", reg(r)),
+        Instruction::SkipNotPressed { reg: r } => format!("if {} key then", reg(r)),
+        Instruction::LdImm { reg: r, byte } => format!("{} := {byte:#04X}", reg(r)),
+        Instruction::AddImm { reg: r, byte } => format!("{} += {byte:#04X}", reg(r)),
+        Instruction::LdReg { regx, regy } => format!("{} := {}", reg(regx), reg(regy)),
+        Instruction::Or { regx, regy } => format!("{} |= {}", reg(regx), reg(regy)),
+        Instruction::And { regx, regy } => format!("{} &= {}", reg(regx), reg(regy)),
+        Instruction::Xor { regx, regy } => format!("{} ^= {}", reg(regx), reg(regy)),
+        Instruction::AddReg { regx, regy } => format!("{} += {}", reg(regx), reg(regy)),
+        Instruction::SubReg { regx, regy } => format!("{} -= {}", reg(regx), reg(regy)),
+        Instruction::Shr { regx, regy } => format!("{} >>= {}", reg(regx), reg(regy)),
+        Instruction::SubN { regx, regy } => format!("{} =- {}", reg(regx), reg(regy)),
+        Instruction::Shl { regx, regy } => format!("{} <<= {}", reg(regx), reg(regy)),
+        Instruction::LdI { addr: a } => format!("i := {}", addr(a)),
+        Instruction::JmpReg { addr: a } => format!("jump0 {}", addr(a)),
+        Instruction::Rnd { reg: r, byte } => format!("{} := random {byte:#04X}", reg(r)),
+        Instruction::Drw { regx, regy, len } => {
+            format!("sprite {} {} {len}", reg(regx), reg(regy))
+        }
+        Instruction::LdDelayTimer { reg: r } => format!("{} := delay", reg(r)),
+        Instruction::LdKey { reg: r } => format!("{} := key", reg(r)),
+        Instruction::SetDelayTimer { reg: r } => format!("delay := {}", reg(r)),
+        Instruction::SetSoundTimer { reg: r } => format!("buzzer := {}", reg(r)),
+        Instruction::AddI { reg: r } => format!("i += {}", reg(r)),
+        Instruction::LdFont { reg: r } => format!("i := hex {}", reg(r)),
+        Instruction::Bcd { reg: r } => format!("bcd {}", reg(r)),
+        Instruction::StoreRegs { reg: r } => format!("save {}", reg(r)),
+        Instruction::LoadRegs { reg: r } => format!("load {}", reg(r)),
+        Instruction::StoreFlags { reg: r } => format!("saveflags {}", reg(r)),
+        Instruction::LoadFlags { reg: r } => format!("loadflags {}", reg(r)),
+        Instruction::ScrollDown { n } => format!("scroll-down {n}"),
+        Instruction::ScrollRight => "scroll-right".to_string(),
+        Instruction::ScrollLeft => "scroll-left".to_string(),
+        Instruction::Exit => "exit".to_string(),
+        Instruction::Lores => "lores".to_string(),
+        Instruction::Hires => "hires".to_string(),
+        Instruction::LdHiFont { reg: r } => format!("i := bighex {}", reg(r)),
+        Instruction::ScrollUp { n } => format!("scroll-up {n}"),
+        Instruction::StoreRange { regx, regy } => format!("save {} - {}", reg(regx), reg(regy)),
+        Instruction::LoadRange { regx, regy } => format!("load {} - {}", reg(regx), reg(regy)),
+        Instruction::LdILong => match analysis.long_addr(at) {
+            Some(a) => format!("i := long {}", addr(a)),
+            // truncated at the end of the ROM
+            None => "0xF0 0x00".to_string(),
+        },
+        Instruction::Plane { mask } => format!("plane {mask}"),
+        Instruction::Audio => "audio".to_string(),
+        Instruction::Pitch { reg: r } => format!("pitch := {}", reg(r)),
+        Instruction::Unknown(op) => format!("{:#04X} {:#04X}", op >> 8, op & 0xFF),
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, Result};
+use clap::Parser;
+
+use c8rs_core::{Instruction, Variant};
+
+use crate::analysis::Analysis;
+
+#[derive(Parser, Debug)]
+pub struct SpritesArgs {
+    /// chip-8 ROM file, `.gz` and `.zip` files are decompressed
+    file: String,
+
+    #[arg(long, value_name = "FILE")]
+    /// also write every sprite side by side to a PNG strip
+    png: Option<PathBuf>,
+}
+
+/// Sprite data drawn from one address
+struct Sprite {
+    addr: u16,
+    /// 8 for regular sprites, 16 for SCHIP `DRW n=0` ones
+    width: usize,
+    /// Tallest draw, in rows
+    height: usize,
+    /// Addresses of the `DRW`s drawing it
+    draws: Vec<u16>,
+}
+
+/// Value of I before an instruction, as far as the analysis can tell
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum I {
+    Known(u16),
+    Unknown,
+}
+
+/// Print the sprites a ROM draws, found by following the value of I from
+/// `LD I` instructions to the `DRW`s using it
+pub fn sprites(args: SpritesArgs, read_rom: impl Fn(&str) -> Result<Vec<u8>>) -> Result<()> {
+    let rom = read_rom(&args.file)?;
+    let analysis = Analysis::new(&rom, 0x200, 0x200, Variant::XoChip);
+    let sprites = find_sprites(&analysis);
+
+    for sprite in &sprites {
+        let draws: Vec<_> = sprite
+            .draws
+            .iter()
+            .map(|addr| format!("{addr:#06X}"))
+            .collect();
+        println!(
+            "; {:#06X}: {}x{}, drawn at {}",
+            sprite.addr,
+            sprite.width,
+            sprite.height,
+            draws.join(", ")
+        );
+        for row in pixels(&analysis, sprite).chunks(sprite.width) {
+            let row: String = row.iter().map(|&p| if p { '#' } else { '.' }).collect();
+            println!("{row}");
+        }
+        println!();
+    }
+
+    if let Some(path) = &args.png {
+        // one column of space between sprites
+        let width = sprites.iter().map(|s| s.width + 1).sum::<usize>().max(1);
+        let height = sprites.iter().map(|s| s.height).max().unwrap_or(1);
+        let mut strip = vec![false; width * height];
+
+        let mut x = 0;
+        for sprite in &sprites {
+            for (i, &pixel) in pixels(&analysis, sprite).iter().enumerate() {
+                strip[(i / sprite.width) * width + x + i % sprite.width] = pixel;
+            }
+            x += sprite.width + 1;
+        }
+
+        std::fs::write(path, encode_png(width, height, &strip)?)
+            .map_err(|err| anyhow!("Failed to write {}: {err}", path.display()))?;
+        println!("{} sprites written to {}", sprites.len(), path.display());
+    }
+
+    Ok(())
+}
+
+/// Sprites drawn by reached `DRW`s whose I is known, in address order
+fn find_sprites(analysis: &Analysis) -> Vec<Sprite> {
+    let mut sprites: BTreeMap<u16, Sprite> = BTreeMap::new();
+
+    for (addr, i) in i_values(analysis) {
+        let (Some(Instruction::Drw { len, .. }), I::Known(data)) = (analysis.instruction(addr), i)
+        else {
+            continue;
+        };
+        let (width, height) = if len == 0 {
+            (16, 16)
+        } else {
+            (8, len as usize)
+        };
+
+        let sprite = sprites.entry(data).or_insert(Sprite {
+            addr: data,
+            width,
+            height,
+            draws: Vec::new(),
+        });
+        sprite.width = sprite.width.max(width);
+        sprite.height = sprite.height.max(height);
+        sprite.draws.push(addr);
+    }
+
+    sprites
+        .into_values()
+        .filter(|sprite| analysis.offset(sprite.addr).is_some())
+        .map(|mut sprite| {
+            sprite.draws.sort_unstable();
+            sprite
+        })
+        .collect()
+}
+
+/// Value of I before each reached instruction, propagated along the control
+/// flow. Paths disagreeing on it, returns from calls and instructions
+/// computing it make it unknown.
+fn i_values(analysis: &Analysis) -> HashMap<u16, I> {
+    let mut values: HashMap<u16, I> = HashMap::new();
+    let mut pending = vec![(analysis.entry(), I::Unknown)];
+
+    while let Some((addr, i)) = pending.pop() {
+        let merged = match values.get(&addr) {
+            Some(&prev) if prev != i => I::Unknown,
+            _ => i,
+        };
+        if values.insert(addr, merged) == Some(merged) {
+            continue;
+        }
+
+        let Some(instr) = analysis.instruction(addr) else {
+            continue;
+        };
+        let after = match instr {
+            Instruction::LdI { addr } => I::Known(addr),
+            Instruction::LdILong => analysis.long_addr(addr).map_or(I::Unknown, I::Known),
+            Instruction::AddI { .. }
+            | Instruction::LdFont { .. }
+            | Instruction::LdHiFont { .. } => I::Unknown,
+            _ => merged,
+        };
+
+        let next = addr.wrapping_add(instr.size());
+        for succ in analysis.successors(instr, addr) {
+            // the subroutine may have changed I by the time it returns
+            let i = match instr {
+                Instruction::Call { .. } if succ == next => I::Unknown,
+                _ => after,
+            };
+            pending.push((succ, i));
+        }
+    }
+
+    values
+}
+
+/// Pixels of `sprite`, row by row, blank past the end of the ROM
+fn pixels(analysis: &Analysis, sprite: &Sprite) -> Vec<bool> {
+    let bytes_per_row = sprite.width / 8;
+    let offset = analysis.offset(sprite.addr).unwrap_or_default();
+
+    (0..sprite.height * bytes_per_row)
+        .flat_map(|n| {
+            let byte = analysis.byte(offset + n).unwrap_or(0);
+            (0..8).map(move |bit| byte & (0x80 >> bit) != 0)
+        })
+        .collect()
+}
+
+/// 1-bit grayscale PNG of a `width` by `height` image of row-major `pixels`
+fn encode_png(width: usize, height: usize, pixels: &[bool]) -> Result<Vec<u8>> {
+    let packed: Vec<u8> = pixels
+        .chunks(width)
+        .flat_map(|row| {
+            row.chunks(8).map(|bits| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (n, &bit)| byte | ((bit as u8) << (7 - n)))
+            })
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&packed)?;
+    writer.finish()?;
+    Ok(out)
+}
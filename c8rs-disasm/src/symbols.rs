@@ -0,0 +1,35 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, bail, Result};
+use clap_num::maybe_hex;
+
+/// Parse a symbol file, an address and a name per line, e.g.
+/// `0x0240 draw_score`. Blank lines and lines starting with `#` or `;` are
+/// skipped.
+pub fn parse(s: &str) -> Result<BTreeMap<u16, String>> {
+    let mut symbols = BTreeMap::new();
+
+    for (n, line) in s.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+
+        let mut parts = line.split_whitespace();
+        let (Some(addr), Some(name), None) = (parts.next(), parts.next(), parts.next()) else {
+            bail!("line {}: expected 'address name'", n + 1);
+        };
+        let addr = maybe_hex::<u16>(addr).map_err(|err| anyhow!("line {}: {err}", n + 1))?;
+        symbols.insert(addr, name.to_string());
+    }
+
+    Ok(symbols)
+}
+
+/// Format `symbols` the way [`parse`] reads them
+pub(crate) fn to_string(symbols: &BTreeMap<u16, String>) -> String {
+    symbols
+        .iter()
+        .map(|(addr, name)| format!("{addr:#06X} {name}\n"))
+        .collect()
+}
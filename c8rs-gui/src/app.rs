@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use c8rs_core::{DebugCommand, EmulatorCommand, EmulatorController, EmulatorState, KeyAction};
+use eframe::egui::{self, Key};
+
+use crate::{debugger::Debugger, display::DisplayView, memory::MemoryView, registers};
+
+/// How often the window redraws while the emulator runs, about once per
+/// 60 Hz frame
+const RUNNING_REPAINT: Duration = Duration::from_millis(16);
+
+/// How often the window redraws while paused, to pick up command results
+const PAUSED_REPAINT: Duration = Duration::from_millis(100);
+
+pub struct GuiApp {
+    controller: EmulatorController,
+    /// Key typing each keypad key, indexed by keypad key
+    keys: [Option<Key>; 16],
+    display: DisplayView,
+    memory: MemoryView,
+    debugger: Debugger,
+}
+
+impl GuiApp {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        controller: EmulatorController,
+        keys: [char; 16],
+    ) -> GuiApp {
+        GuiApp {
+            display: DisplayView::new(&cc.egui_ctx),
+            keys: keys.map(|c| Key::from_name(&c.to_ascii_uppercase().to_string())),
+            controller,
+            memory: MemoryView::default(),
+            debugger: Debugger::default(),
+        }
+    }
+
+    fn send(&self, cmd: DebugCommand) {
+        let _ = self.controller.send(EmulatorCommand::DebugCommand(cmd));
+    }
+
+    /// Press and release keypad keys as their keys are, unless a text field
+    /// takes the typing
+    fn handle_keypad(&self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let actions: Vec<KeyAction> = ctx.input(|input| {
+            input
+                .events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Key {
+                        key,
+                        pressed,
+                        repeat: false,
+                        ..
+                    } => {
+                        let keypad = self.keys.iter().position(|k| k == &Some(*key))? as u8;
+                        Some(if *pressed {
+                            KeyAction::Press { key: keypad }
+                        } else {
+                            KeyAction::Release { key: keypad }
+                        })
+                    }
+                    _ => None,
+                })
+                .collect()
+        });
+        for action in actions {
+            self.send(DebugCommand::Key { action });
+        }
+    }
+
+    fn controls(&self, ui: &mut egui::Ui) {
+        let state = self.controller.state();
+        ui.horizontal(|ui| {
+            if state == EmulatorState::Running {
+                if ui.button("Pause").clicked() {
+                    self.send(DebugCommand::Pause);
+                }
+            } else if ui.button("Run").clicked() {
+                self.send(DebugCommand::Continue);
+            }
+            if ui
+                .add_enabled(state != EmulatorState::Running, egui::Button::new("Step"))
+                .clicked()
+            {
+                self.send(DebugCommand::Step { count: 1 });
+            }
+            if ui.button("Reset").clicked() {
+                self.send(DebugCommand::Reset);
+            }
+
+            ui.separator();
+            let status = match state {
+                EmulatorState::Running => "running".to_string(),
+                EmulatorState::Paused => "paused".to_string(),
+                EmulatorState::Halted => "halted".to_string(),
+                EmulatorState::Faulted => match self.controller.cpu().fault {
+                    Some(fault) => format!("faulted: {fault}"),
+                    None => "faulted".to_string(),
+                },
+            };
+            ui.label(status);
+            ui.separator();
+            ui.label(format!("IPS: {}", self.controller.ips()));
+        });
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_keypad(ctx);
+
+        egui::TopBottomPanel::top("controls").show(ctx, |ui| self.controls(ui));
+
+        egui::SidePanel::right("registers")
+            .resizable(false)
+            .show(ctx, |ui| registers::show(ui, &self.controller));
+
+        egui::TopBottomPanel::bottom("memory_debugger")
+            .resizable(true)
+            .default_height(260.0)
+            .show(ctx, |ui| {
+                ui.columns(2, |columns| {
+                    self.memory.show(&mut columns[0], &self.controller);
+                    self.debugger.show(&mut columns[1], &self.controller);
+                });
+            });
+
+        egui::CentralPanel::default().show(ctx, |ui| self.display.show(ui, &self.controller));
+
+        ctx.request_repaint_after(if self.controller.state() == EmulatorState::Running {
+            RUNNING_REPAINT
+        } else {
+            PAUSED_REPAINT
+        });
+    }
+}
@@ -0,0 +1,107 @@
+use c8rs_core::{EmulatorCommand, EmulatorController, PendingReply};
+use eframe::egui::{self, Color32, RichText};
+
+/// Debugger command prompt with the results of past commands
+#[derive(Default)]
+pub(crate) struct Debugger {
+    history: Vec<HistoryEntry>,
+    input: String,
+    /// Submitted commands still waiting for the emulator to respond, with
+    /// the history entry their result belongs to. The emulator handles
+    /// commands in order so the results arrive in this order too.
+    pending: Vec<(usize, PendingReply)>,
+}
+
+/// Submitted input followed by the results of the commands it ran
+struct HistoryEntry {
+    input: String,
+    output: Vec<Result<String, String>>,
+}
+
+impl Debugger {
+    pub(crate) fn show(&mut self, ui: &mut egui::Ui, controller: &EmulatorController) {
+        self.poll_replies();
+
+        ui.heading("Debugger");
+
+        // the prompt at the bottom, the history takes the rest
+        egui::TopBottomPanel::bottom("debugger_input")
+            .show_separator_line(false)
+            .show_inside(ui, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.input)
+                        .hint_text("debugger command")
+                        .desired_width(f32::INFINITY)
+                        .font(egui::TextStyle::Monospace),
+                );
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    self.submit(controller);
+                    response.request_focus();
+                }
+            });
+
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for entry in &self.history {
+                    ui.monospace(RichText::new(format!("> {}", entry.input)).strong());
+                    for output in &entry.output {
+                        match output {
+                            Ok(msg) => ui.monospace(format!("  {msg}")),
+                            Err(err) => ui.monospace(
+                                RichText::new(format!("  {err}")).color(Color32::LIGHT_RED),
+                            ),
+                        };
+                    }
+                }
+            });
+    }
+
+    fn submit(&mut self, controller: &EmulatorController) {
+        let input = std::mem::take(&mut self.input);
+        if input.trim().is_empty() {
+            return;
+        }
+
+        let cmds = controller.aliases().parse(&input);
+        let mut entry = HistoryEntry {
+            input,
+            output: Vec::new(),
+        };
+        let cmds = match cmds {
+            Ok(cmds) => cmds,
+            Err(err) => {
+                entry
+                    .output
+                    .extend(err.lines().map(|line| Err(line.to_string())));
+                self.history.push(entry);
+                return;
+            }
+        };
+
+        self.history.push(entry);
+        let index = self.history.len() - 1;
+        for cmd in cmds {
+            self.pending.push((
+                index,
+                controller.request(EmulatorCommand::DebugCommand(cmd)),
+            ));
+        }
+    }
+
+    /// Move the results of handled commands under their input in the history
+    fn poll_replies(&mut self) {
+        while let Some((index, reply)) = self.pending.first() {
+            let Some(result) = reply.try_get() else {
+                break;
+            };
+            let output = &mut self.history[*index].output;
+            match result {
+                Ok(res) => output.extend(res.to_string().lines().map(|line| Ok(line.to_string()))),
+                Err(err) => output.push(Err(format!("error: {err}"))),
+            }
+            self.pending.remove(0);
+        }
+    }
+}
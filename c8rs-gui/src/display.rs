@@ -0,0 +1,47 @@
+use c8rs_core::EmulatorController;
+use eframe::egui::{self, Color32, ColorImage, TextureHandle, TextureOptions};
+
+const PIXEL_ON: Color32 = Color32::WHITE;
+const PIXEL_OFF: Color32 = Color32::BLACK;
+
+/// CHIP-8 screen scaled to fit its panel with square pixels
+pub(crate) struct DisplayView {
+    texture: TextureHandle,
+}
+
+impl DisplayView {
+    pub(crate) fn new(ctx: &egui::Context) -> DisplayView {
+        DisplayView {
+            texture: ctx.load_texture(
+                "display",
+                ColorImage::new([64, 32], PIXEL_OFF),
+                TextureOptions::NEAREST,
+            ),
+        }
+    }
+
+    pub(crate) fn show(&mut self, ui: &mut egui::Ui, controller: &EmulatorController) {
+        let display = controller.display();
+        let (width, height) = display.get_dimensions();
+        let image = ColorImage {
+            size: [width, height],
+            pixels: display
+                .get_pixels()
+                .into_iter()
+                .map(|on| if on { PIXEL_ON } else { PIXEL_OFF })
+                .collect(),
+        };
+        self.texture.set(image, TextureOptions::NEAREST);
+
+        // whole multiples of the resolution keep the pixels the same size
+        let available = ui.available_size();
+        let scale = (available.x / width as f32)
+            .min(available.y / height as f32)
+            .floor()
+            .max(1.0);
+        let size = egui::vec2(width as f32, height as f32) * scale;
+        ui.centered_and_justified(|ui| {
+            ui.add(egui::Image::new(&self.texture).fit_to_exact_size(size));
+        });
+    }
+}
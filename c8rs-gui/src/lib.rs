@@ -0,0 +1,37 @@
+//! Desktop frontend on egui, an alternative to the TUI with the same
+//! display, registers, memory and debugger on top of an
+//! [`EmulatorController`]
+
+use c8rs_core::EmulatorController;
+
+pub use app::GuiApp;
+
+mod app;
+mod debugger;
+mod display;
+mod memory;
+mod registers;
+
+/// Open a window for `controller`'s emulator and block until it's closed,
+/// typing one of `keys` presses the keypad key at its index
+pub fn run(
+    controller: EmulatorController,
+    title: Option<String>,
+    keys: [char; 16],
+) -> eframe::Result<()> {
+    let title = match title {
+        Some(title) => format!("c8rs - {title}"),
+        None => "c8rs".to_string(),
+    };
+    let options = eframe::NativeOptions {
+        viewport: eframe::egui::ViewportBuilder::default()
+            .with_title(&title)
+            .with_inner_size([1024.0, 720.0]),
+        ..Default::default()
+    };
+    eframe::run_native(
+        &title,
+        options,
+        Box::new(move |cc| Ok(Box::new(GuiApp::new(cc, controller, keys)))),
+    )
+}
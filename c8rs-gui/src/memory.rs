@@ -0,0 +1,67 @@
+use c8rs_core::EmulatorController;
+use eframe::egui::{self, Color32, RichText};
+
+/// Hex dump of memory with the bytes at PC and I highlighted
+#[derive(Default)]
+pub(crate) struct MemoryView {
+    input: String,
+    /// Row to scroll to at the next frame, set by goto
+    scroll_to: Option<usize>,
+}
+
+impl MemoryView {
+    pub(crate) fn show(&mut self, ui: &mut egui::Ui, controller: &EmulatorController) {
+        let mem = controller.memory();
+        let cpu = controller.cpu();
+
+        ui.horizontal(|ui| {
+            ui.heading("Memory");
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.input)
+                    .hint_text("goto address or label")
+                    .desired_width(160.0),
+            );
+            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                let input = self.input.trim();
+                let addr = controller
+                    .source_map()
+                    .addr(input)
+                    .or_else(|| u16::from_str_radix(input.trim_start_matches("0x"), 16).ok());
+                match addr {
+                    Some(addr) => self.scroll_to = Some(addr as usize / 16),
+                    None => log::warn!("Can't go to '{input}'"),
+                }
+            }
+        });
+
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+        let rows = mem.size().div_ceil(16);
+        let mut scroll = egui::ScrollArea::vertical().auto_shrink(false);
+        if let Some(row) = self.scroll_to.take() {
+            let spacing = ui.spacing().item_spacing.y;
+            scroll = scroll.vertical_scroll_offset(row as f32 * (row_height + spacing));
+        }
+        scroll.show_rows(ui, row_height, rows, |ui, visible| {
+            for row in visible {
+                let start = row * 16;
+                ui.horizontal(|ui| {
+                    ui.spacing_mut().item_spacing.x = 4.0;
+                    ui.monospace(RichText::new(format!("{start:#06X}")).weak());
+                    for addr in start..(start + 16).min(mem.size()) {
+                        let addr = addr as u16;
+                        let byte = mem.read_u8(addr);
+                        let mut text = RichText::new(format!("{byte:02X}")).monospace();
+                        if addr == cpu.pc || addr == cpu.pc.wrapping_add(1) {
+                            text = text.color(Color32::BLACK).background_color(Color32::YELLOW);
+                        } else if addr == cpu.i {
+                            text = text
+                                .color(Color32::BLACK)
+                                .background_color(Color32::LIGHT_BLUE);
+                        }
+                        ui.label(text);
+                    }
+                });
+            }
+        });
+    }
+}
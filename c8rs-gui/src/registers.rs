@@ -0,0 +1,68 @@
+use c8rs_core::{Cpu, EmulatorController, Instruction};
+use eframe::egui::{self, RichText};
+
+/// Address of the outermost stack slot, the stack grows down from here
+const STACK_TOP: u16 = 0x1FE;
+
+/// Registers, timers and the call stack
+pub(crate) fn show(ui: &mut egui::Ui, controller: &EmulatorController) {
+    let Cpu {
+        pc,
+        sp,
+        i,
+        delay_timer,
+        sound_timer,
+        registers,
+        ..
+    } = controller.cpu();
+    let mem = controller.memory();
+
+    ui.heading("CPU");
+    egui::Grid::new("special_registers")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            let rows = [
+                ("PC", format!("{pc:#06X}")),
+                ("SP", format!("{sp:#06X}")),
+                ("I", format!("{i:#06X}")),
+                ("DT", format!("{delay_timer:#04X} ({delay_timer:03})")),
+                ("ST", format!("{sound_timer:#04X} ({sound_timer:03})")),
+            ];
+            for (name, value) in rows {
+                ui.label(name);
+                ui.monospace(value);
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+    egui::Grid::new("registers")
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            for row in 0..8 {
+                for reg in [row, row + 8] {
+                    let val = registers[reg];
+                    ui.label(format!("V{reg:X}"));
+                    ui.monospace(format!("{val:#04X} ({val:03})"));
+                }
+                ui.end_row();
+            }
+        });
+
+    ui.separator();
+    ui.label("Call stack");
+    // each slot holds the address of a CALL, innermost first
+    let sites: Vec<u16> = (sp.saturating_add(2)..=STACK_TOP)
+        .step_by(2)
+        .map(|addr| mem.read_u16(addr))
+        .collect();
+    if sites.is_empty() {
+        ui.label(RichText::new("not in a subroutine").weak());
+    }
+    for (n, site) in sites.into_iter().enumerate() {
+        let inst = Instruction::parse(mem.read_u16(site));
+        ui.monospace(format!("#{n:<2} {:#06X} → {inst}", site.wrapping_add(2)));
+    }
+}
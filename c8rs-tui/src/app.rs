@@ -1,22 +1,44 @@
-use std::time::Duration;
+use std::{
+    cell::Cell,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
 use anyhow::Result;
-use c8rs_core::{EmulatorCommand, EmulatorController};
-use crossterm::event::KeyEvent;
+use c8rs_core::{
+    DebugCommand, EmulatorCommand, EmulatorController, EmulatorEvent, EmulatorState, KeyAction,
+    PendingReply, Speed,
+};
+use crossterm::event::{KeyEvent, KeyModifiers};
 use futures::{FutureExt, StreamExt};
 use ratatui::{
     crossterm::event::{Event, KeyCode, KeyEventKind},
-    layout::{Constraint, Direction, Layout},
+    layout::{Constraint, Direction, Layout, Rect},
+    text::Line,
     widgets::{Block, Borders},
     Frame,
 };
 use tokio_util::sync::CancellationToken;
 
 use crate::{
+    bindings::Action,
+    buzzer::Buzzer,
+    capabilities::{DisplayRenderer, InputMode, TerminalCapabilities},
+    command_line::CommandLine,
     components::{
         Component, CpuComponent, DebuggerComponent, DisassemblyComponent, DisplayComponent,
-        LogComponent, MemoryComponent,
+        LogComponent, MemoryComponent, KEY_TAP_FRAMES,
     },
+    config::{Panel, TuiConfig},
+    file_browser::{FileBrowser, Selection},
+    logger,
+    palette::Palette,
+    quit_dialog::{Choice, QuitDialog},
+    session::{load_session, SessionRecorder},
+    stats::Stats,
+    status_bar::StatusBar,
+    theme::Theme,
+    toasts::{ToastKind, Toasts},
     tui,
 };
 
@@ -24,24 +46,128 @@ pub struct App {
     state: AppState,
     cancellation_token: CancellationToken,
     panels: Vec<Box<dyn Component>>,
+    log_detached: bool,
+    /// Panels shown, starts out as the configured layout's
+    visible: [bool; 6],
+    replay: Vec<(Duration, KeyEvent)>,
+    /// ROM reloaded whenever it changes on disk
+    watch: Option<PathBuf>,
+    /// ROM being run, its state is saved next to it on quit
+    rom: Option<PathBuf>,
+    /// Keypad keys pressed in play mode and not released yet
+    held: Vec<u8>,
+    command_line: CommandLine,
+    file_browser: Option<FileBrowser>,
+    quit_dialog: Option<QuitDialog>,
+    /// When the executed instruction count was last sampled, and the count
+    ips_sample: (Instant, u64),
+    actual_ips: Option<u32>,
+    stats: Stats,
+    show_stats: bool,
+    toasts: Toasts,
+    /// The focused panel fills the terminal
+    zoomed: bool,
+    /// Opened when the app starts running
+    buzzer: Option<Buzzer>,
+    /// The sound timer is running
+    sound: bool,
 }
 
+const DISASM_PANEL: usize = 2;
+const LOG_PANEL: usize = 4;
+
+/// Shortest time between two draws, frames completed faster than this while
+/// the emulator runs are skipped
+const FRAME_INTERVAL: Duration = Duration::from_micros(1_000_000 / 30);
+
+/// IPS values the speed keys step through
+const IPS_STEPS: [u32; 16] = [
+    60, 120, 240, 360, 500, 700, 1000, 1500, 2000, 3000, 5000, 10_000, 20_000, 50_000, 100_000,
+    1_000_000,
+];
+
+/// Speed of slow motion, in percent
+const SLOW_MOTION: u32 = 25;
+
+/// How often a watched ROM is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
 #[derive(Debug, Clone)]
 enum AppEvent {
-    Tick,
+    /// Sent by a timer, with when it was due
+    Tick(Instant),
     Render,
     Key(KeyEvent),
+    Emulator(EmulatorEvent),
+    /// The watched ROM was rewritten
+    RomChanged,
     Error(String),
 }
 
 pub struct AppState {
     pub controller: EmulatorController,
+    /// Last state reported by the emulator
+    pub emulator_state: EmulatorState,
+    pub capabilities: TerminalCapabilities,
+    /// How the display panel draws pixels, see [`DisplayRenderer`]
+    pub renderer: DisplayRenderer,
+    pub config: TuiConfig,
+    /// Styles of [`AppState::config`]'s theme
+    pub theme: Theme,
+    /// Name of the game being run, if known
+    pub title: Option<String>,
+    /// Keyboard input goes to the keypad instead of the panels
+    pub play_mode: bool,
+    /// Why the emulator last stopped by itself, cleared when it runs again
+    pub stop_reason: Option<String>,
+    /// Address the disassembly panel moves to after the current event
+    disasm_target: Cell<Option<u16>>,
+    recorder: Option<SessionRecorder>,
+}
+
+impl AppState {
+    /// Send a command to the emulator, recording it if a session is being
+    /// recorded
+    pub fn send(&self, cmd: EmulatorCommand) {
+        if let Some(recorder) = &self.recorder {
+            recorder.record_command(&cmd);
+        }
+
+        let _ = self.controller.send(cmd);
+    }
+
+    /// Like [`AppState::send`], but returns a handle to the command's result
+    pub fn request(&self, cmd: EmulatorCommand) -> PendingReply {
+        if let Some(recorder) = &self.recorder {
+            recorder.record_command(&cmd);
+        }
+
+        self.controller.request(cmd)
+    }
+
+    /// Move the disassembly panel to `addr`, for panels that can't reach it
+    pub fn show_in_disassembly(&self, addr: u16) {
+        self.disasm_target.set(Some(addr));
+    }
 }
 
 impl App {
     pub fn new(controller: EmulatorController) -> Self {
+        let capabilities = TerminalCapabilities::detect();
         App {
-            state: AppState { controller },
+            state: AppState {
+                emulator_state: controller.state(),
+                controller,
+                renderer: capabilities.display_renderer(),
+                capabilities,
+                config: TuiConfig::default(),
+                theme: Theme::default(),
+                title: None,
+                play_mode: false,
+                stop_reason: None,
+                disasm_target: Cell::new(None),
+                recorder: None,
+            },
             cancellation_token: CancellationToken::new(),
             panels: vec![
                 Box::new(DisplayComponent::default()),
@@ -51,19 +177,137 @@ impl App {
                 Box::new(LogComponent::default()),
                 Box::new(DebuggerComponent::default()),
             ],
+            log_detached: false,
+            visible: [true; 6],
+            replay: Vec::new(),
+            watch: None,
+            rom: None,
+            held: Vec::new(),
+            command_line: CommandLine::default(),
+            file_browser: None,
+            quit_dialog: None,
+            ips_sample: (Instant::now(), 0),
+            actual_ips: None,
+            stats: Stats::default(),
+            show_stats: false,
+            toasts: Toasts::default(),
+            zoomed: false,
+            buzzer: None,
+            sound: false,
         }
     }
 
+    /// Record key events and emulator commands to `path`
+    pub fn with_session_recording(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.state.recorder = Some(SessionRecorder::create(path.as_ref())?);
+        Ok(self)
+    }
+
+    /// Re-drive the UI with the key events of a session recorded with
+    /// [`App::with_session_recording`]
+    pub fn with_session_replay(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.replay = load_session(path.as_ref())?;
+        Ok(self)
+    }
+
+    /// Reload and reset the emulator whenever the ROM at `path` changes,
+    /// keeping breakpoints and whether it was running
+    pub fn with_rom_watch(mut self, path: impl Into<PathBuf>) -> Self {
+        self.watch = Some(path.into());
+        self
+    }
+
+    /// Save the state next to the ROM at `path` when asked to on quit
+    pub fn with_rom_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.rom = Some(path.into());
+        self
+    }
+
+    /// Use the key mapping, theme and panel layout in `config`
+    pub fn with_config(mut self, config: TuiConfig) -> Self {
+        self.state.theme = config.theme();
+        self.state.config = config;
+        self.apply_layout();
+        self
+    }
+
+    /// Show `title` in the status bar
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.state.title = Some(title.into());
+        self
+    }
+
+    /// Hide the log panel, for use with [`App::init_remote_logger`]
+    pub fn with_detached_log(mut self) -> Self {
+        self.log_detached = true;
+        self
+    }
+
     pub fn init_logger() {
-        tui_logger::init_logger(log::LevelFilter::Debug).unwrap();
-        tui_logger::set_default_level(log::LevelFilter::Debug);
+        logger::init(log::LevelFilter::Debug).unwrap();
     }
 
     pub async fn run(&mut self) -> Result<()> {
-        let mut terminal = tui::init()?;
+        self.state.capabilities.log_report();
+        self.buzzer = Some(Buzzer::open(self.state.config.audio_device.as_deref()));
+
+        let input_mode = self.state.capabilities.input_mode();
+        let mut terminal = tui::init(input_mode)?;
 
         let (event_tx, mut event_rx) = tokio::sync::mpsc::unbounded_channel();
 
+        if !self.replay.is_empty() {
+            let replay = std::mem::take(&mut self.replay);
+            let event_tx = event_tx.clone();
+            tokio::spawn(async move {
+                log::info!("Replaying {} recorded key events", replay.len());
+                let start = tokio::time::Instant::now();
+                for (delay, key) in replay {
+                    tokio::time::sleep_until(start + delay).await;
+                    if event_tx.send(AppEvent::Key(key)).is_err() {
+                        return;
+                    }
+                }
+                log::info!("Session replay finished");
+            });
+        }
+
+        if let Some(path) = self.watch.clone() {
+            let watch_tx = event_tx.clone();
+            std::thread::spawn(move || {
+                let modified = |path: &Path| {
+                    std::fs::metadata(path)
+                        .ok()
+                        .map(|meta| (meta.modified().ok(), meta.len()))
+                };
+                let mut loaded = modified(&path);
+                let mut last = loaded;
+                loop {
+                    std::thread::sleep(WATCH_INTERVAL);
+                    // wait for the file to stop changing, an assembler may
+                    // still be writing it
+                    let current = modified(&path);
+                    if current.is_some() && current == last && current != loaded {
+                        loaded = current;
+                        if watch_tx.send(AppEvent::RomChanged).is_err() {
+                            return;
+                        }
+                    }
+                    last = current;
+                }
+            });
+        }
+
+        let emulator_events = self.state.controller.subscribe();
+        let emulator_tx = event_tx.clone();
+        std::thread::spawn(move || {
+            for event in emulator_events {
+                if emulator_tx.send(AppEvent::Emulator(event)).is_err() {
+                    return;
+                }
+            }
+        });
+
         let cancellation_token = self.cancellation_token.clone();
         tokio::spawn(async move {
             let mut tick_interval = tokio::time::interval(Duration::from_secs_f64(1.0 / 4.0));
@@ -77,17 +321,18 @@ impl App {
                     _ = cancellation_token.cancelled() => {
                         break;
                     }
-                    _ = tick_delay => {
-                        event_tx.send(AppEvent::Tick).unwrap();
+                    due = tick_delay => {
+                        event_tx.send(AppEvent::Tick(due.into_std())).unwrap();
                     },
                     event_opt = event => {
                         match event_opt {
                             Some(Ok(event)) => {
                                 match event {
+                                    // releases and repeats are only reported
+                                    // with the kitty keyboard protocol, play
+                                    // mode holds keys until the release arrives
                                     Event::Key(key) => {
-                                        if key.kind == KeyEventKind::Press {
-                                            event_tx.send(AppEvent::Key(key)).unwrap();
-                                        }
+                                        event_tx.send(AppEvent::Key(key)).unwrap();
                                     },
                                     Event::Resize(_, _) => event_tx.send(AppEvent::Render).unwrap(),
                                     _ => (),
@@ -103,111 +348,618 @@ impl App {
             }
         });
 
+        // nothing is drawn until something happens, the tick covers what
+        // changes without an event such as new log lines and command results
+        let mut next_draw = tokio::time::Instant::now();
         while !self.cancellation_token.is_cancelled() {
-            while let Ok(event) = event_rx.try_recv() {
-                match event {
-                    AppEvent::Tick => (),
-                    AppEvent::Key(key) => self.handle_key_event(key),
-                    AppEvent::Error(err) => log::error!("{err}"),
-                    _ => (),
-                }
+            let Some(event) = event_rx.recv().await else {
+                break;
+            };
+            self.handle_event(event);
+
+            // events arriving before the next frame is due share its draw
+            while let Ok(Some(event)) = tokio::time::timeout_at(next_draw, event_rx.recv()).await {
+                self.handle_event(event);
+            }
+            if self.cancellation_token.is_cancelled() {
+                break;
             }
 
-            let now = std::time::Instant::now();
+            let start = Instant::now();
             if let Err(err) = terminal.draw(|frame| self.render(frame)) {
                 log::error!("Error rendering frame: {err}");
             }
-            let elapsed = now.elapsed().as_millis();
-            log::info!("draw: {elapsed}ms {}fps", 1000 / elapsed);
+            self.stats.record_draw(start);
+            next_draw = tokio::time::Instant::now() + FRAME_INTERVAL;
         }
 
-        tui::restore()?;
+        tui::restore(input_mode)?;
 
         Ok(())
     }
 
+    fn handle_event(&mut self, event: AppEvent) {
+        match event {
+            AppEvent::Tick(due) => {
+                self.stats.record_latency(due);
+                self.sample_ips();
+                self.toasts.expire();
+            }
+            AppEvent::Render => (),
+            AppEvent::Key(key) => self.handle_key_event(key),
+            AppEvent::Emulator(EmulatorEvent::StateChanged(state)) => {
+                self.state.emulator_state = state;
+                match state {
+                    EmulatorState::Running => self.state.stop_reason = None,
+                    EmulatorState::Halted => {
+                        self.state.stop_reason = Some("program exited".to_string())
+                    }
+                    EmulatorState::Paused | EmulatorState::Faulted => (),
+                }
+                self.update_buzzer();
+            }
+            AppEvent::Emulator(EmulatorEvent::BreakpointHit { addr }) => {
+                self.stopped_at(ToastKind::Info, format!("breakpoint at {addr:#06X}"), addr)
+            }
+            AppEvent::Emulator(EmulatorEvent::EventBreak { event, pc }) => self.stopped_at(
+                ToastKind::Info,
+                format!("break on {event} at {pc:#06X}"),
+                pc,
+            ),
+            AppEvent::Emulator(EmulatorEvent::Faulted { fault, pc }) => {
+                self.stopped_at(ToastKind::Error, format!("{fault} at {pc:#06X}"), pc)
+            }
+            AppEvent::Emulator(EmulatorEvent::SoundChanged { playing }) => {
+                self.sound = playing;
+                self.update_buzzer();
+            }
+            AppEvent::Emulator(_) => (),
+            AppEvent::RomChanged => self.reload_rom(),
+            AppEvent::Error(err) => log::error!("{err}"),
+        }
+
+        if let Some(addr) = self.state.disasm_target.take() {
+            self.panels[DISASM_PANEL].show_addr(addr);
+        }
+
+        if self
+            .quit_dialog
+            .as_mut()
+            .is_some_and(|dialog| dialog.poll_saved())
+        {
+            self.quit();
+        }
+    }
+
+    /// Tell about the emulator stopping at `addr` by itself and show the
+    /// instruction there
+    fn stopped_at(&mut self, kind: ToastKind, reason: String, addr: u16) {
+        self.toasts.push(kind, reason.clone());
+        self.state.stop_reason = Some(reason);
+        self.state.show_in_disassembly(addr);
+    }
+
+    /// Measure the instructions executed per second, about once a second
+    fn sample_ips(&mut self) {
+        let (at, count) = self.ips_sample;
+        let elapsed = at.elapsed();
+        if elapsed < Duration::from_secs(1) {
+            return;
+        }
+
+        let now = self.state.controller.instructions();
+        let ips = now.saturating_sub(count) as f64 / elapsed.as_secs_f64();
+        self.actual_ips = Some(ips.round() as u32);
+        self.ips_sample = (Instant::now(), now);
+    }
+
+    fn reload_rom(&mut self) {
+        let Some(path) = &self.watch else {
+            return;
+        };
+
+        log::info!("{} changed, reloading", path.display());
+        let running = self.state.emulator_state == EmulatorState::Running;
+        self.state
+            .send(EmulatorCommand::DebugCommand(DebugCommand::Load {
+                name: path.to_string_lossy().into_owned(),
+            }));
+        if running {
+            self.state
+                .send(EmulatorCommand::DebugCommand(DebugCommand::Continue));
+        }
+    }
+
+    /// Load the ROM or saved state at `path`
+    fn open_rom(&mut self, path: &Path) {
+        log::info!("Loading {}", path.display());
+        self.state
+            .send(EmulatorCommand::DebugCommand(DebugCommand::Load {
+                name: path.to_string_lossy().into_owned(),
+            }));
+        if let Some(name) = path.file_name() {
+            self.state.title = Some(name.to_string_lossy().into_owned());
+        }
+        self.rom = Some(path.to_path_buf());
+    }
+
     fn render(&mut self, frame: &mut Frame) {
-        let (display_width, display_height) = (64, 32);
+        self.render_panels(frame);
+        if self.show_stats {
+            let ips = (self.actual_ips, self.state.controller.ips());
+            self.stats
+                .render(frame, frame.area(), &self.state.theme, ips);
+        }
+        if let Some(browser) = &self.file_browser {
+            browser.render(frame, frame.area(), &self.state.theme);
+        }
+        let toast_area = Rect {
+            // clear of the status bar and the row under it
+            height: frame.area().height.saturating_sub(2),
+            ..frame.area()
+        };
+        self.toasts.render(frame, toast_area, &self.state.theme);
+        if let Some(dialog) = &self.quit_dialog {
+            dialog.render(frame, frame.area(), &self.state.theme);
+        }
+    }
 
-        let [top_area, bottom_area] = Layout::new(
+    fn render_panels(&mut self, frame: &mut Frame) {
+        let (display_width, display_height) = self.state.controller.display().get_dimensions();
+        let (cell_width, cell_height) = self.state.renderer.cell_size();
+        let (display_width, display_height) = (
+            (display_width as u16).div_ceil(cell_width),
+            (display_height as u16).div_ceil(cell_height),
+        );
+
+        let warnings = self.state.capabilities.warnings(Some(frame.area()));
+
+        let [main_area, status_area, warning_area] = Layout::new(
             Direction::Vertical,
             [
-                Constraint::Length(display_height / 2 + 2),
                 Constraint::Fill(1),
+                Constraint::Length(1),
+                Constraint::Length(if warnings.is_empty() && !self.command_line.is_visible() {
+                    0
+                } else {
+                    1
+                }),
             ],
         )
         .split(frame.area())[..] else {
             unreachable!()
         };
 
-        let [display_area, cpu_area] = Layout::new(
-            Direction::Horizontal,
-            [Constraint::Length(display_width + 2), Constraint::Fill(1)],
-        )
-        .split(top_area)[..] else {
-            unreachable!()
-        };
+        frame.render_widget(
+            StatusBar {
+                state: &self.state,
+                actual_ips: self.actual_ips,
+                focused: self.panels.iter().position(|p| p.has_focus()),
+            },
+            status_area,
+        );
 
-        let [disasm_area, mem_area, debug_area] = Layout::new(
-            Direction::Horizontal,
-            [
-                Constraint::Length(37),
-                Constraint::Length(62),
-                Constraint::Fill(1),
-            ],
-        )
-        .split(bottom_area)[..] else {
-            unreachable!()
+        if let Some(focused) = self.zoomed_panel() {
+            self.panels[focused].render(frame, main_area, &self.state);
+            self.render_bottom_row(frame, warning_area, &warnings);
+            return;
+        }
+
+        // display and CPU on top, the rest below with the log and debugger
+        // sharing the last column. Hidden panels get no space, the last
+        // panel of a row takes what's left.
+        let shown = |panels: &[usize]| -> Vec<usize> {
+            panels
+                .iter()
+                .copied()
+                .filter(|&i| self.is_visible(i))
+                .collect()
+        };
+        let top = shown(&[0, 1]);
+        let side = shown(&[LOG_PANEL, 5]);
+        let bottom = shown(&[2, 3]);
+        let bottom_height = if bottom.is_empty() && side.is_empty() {
+            0
+        } else if top.is_empty() {
+            main_area.height
+        } else {
+            main_area.height.saturating_sub(display_height + 2)
         };
 
-        let [log_area, debugger_area] = Layout::new(
+        let [top_area, bottom_area] = Layout::new(
             Direction::Vertical,
-            Constraint::from_ratios([(1, 3), (2, 3)]),
+            [Constraint::Fill(1), Constraint::Length(bottom_height)],
         )
-        .split(debug_area)[..] else {
+        .split(main_area)[..] else {
             unreachable!()
         };
 
-        frame.render_widget(
-            Block::new().title("CHIP-8").borders(Borders::ALL),
-            display_area,
-        );
+        let mut areas = [Rect::default(); 6];
+        let widths = |panels: &[usize]| -> Vec<Constraint> {
+            panels
+                .iter()
+                .enumerate()
+                .map(|(n, &i)| match i {
+                    _ if n == panels.len() - 1 => Constraint::Fill(1),
+                    0 => Constraint::Length(display_width + 2),
+                    2 => Constraint::Length(self.state.config.layout.disasm_width),
+                    3 => Constraint::Length(self.state.config.layout.memory_width),
+                    _ => Constraint::Fill(1),
+                })
+                .collect()
+        };
+
+        let top_areas = Layout::new(Direction::Horizontal, widths(&top)).split(top_area);
+        for (&i, &area) in top.iter().zip(top_areas.iter()) {
+            areas[i] = area;
+        }
+
+        // the side column is the last of the bottom row
+        let mut columns = bottom.clone();
+        if !side.is_empty() {
+            columns.push(5);
+        }
+        let bottom_areas = Layout::new(Direction::Horizontal, widths(&columns)).split(bottom_area);
+        for (&i, &area) in bottom.iter().zip(bottom_areas.iter()) {
+            areas[i] = area;
+        }
+        if let Some(&side_area) = bottom_areas.get(bottom.len()) {
+            let side_areas = Layout::new(
+                Direction::Vertical,
+                if side.len() == 2 {
+                    Constraint::from_ratios([(1, 3), (2, 3)])
+                } else {
+                    vec![Constraint::Fill(1)]
+                },
+            )
+            .split(side_area);
+            for (&i, &area) in side.iter().zip(side_areas.iter()) {
+                areas[i] = area;
+            }
+        }
+
+        if self.is_visible(0) {
+            frame.render_widget(Block::new().title("CHIP-8").borders(Borders::ALL), areas[0]);
+        }
+
+        for (i, &area) in areas.iter().enumerate() {
+            if self.is_visible(i) {
+                self.panels[i].render(frame, area, &self.state);
+            }
+        }
+
+        self.render_bottom_row(frame, warning_area, &warnings);
+    }
+
+    /// Command line if it's in use, terminal warnings otherwise
+    fn render_bottom_row(&mut self, frame: &mut Frame, area: Rect, warnings: &[String]) {
+        if self.command_line.is_visible() {
+            self.command_line.render(frame, area, &self.state.theme);
+        } else {
+            frame.render_widget(
+                Line::from(warnings.join(" | ")).style(self.state.theme.warning),
+                area,
+            );
+        }
+    }
 
-        self.panels[0].render(frame, display_area, &self.state);
-        self.panels[1].render(frame, cpu_area, &self.state);
-        self.panels[2].render(frame, disasm_area, &self.state);
-        self.panels[3].render(frame, mem_area, &self.state);
-        self.panels[4].render(frame, log_area, &self.state);
-        self.panels[5].render(frame, debugger_area, &self.state);
+    /// Index of the panel drawn over the whole terminal, if any
+    fn zoomed_panel(&self) -> Option<usize> {
+        if !self.zoomed {
+            return None;
+        }
+        self.panels.iter().position(|p| p.has_focus())
     }
 
     fn handle_key_event(&mut self, event: KeyEvent) {
+        if let Some(recorder) = &self.state.recorder {
+            recorder.record_key(event);
+        }
+
+        let bindings = &self.state.config.bindings;
+        if bindings.key(Action::PlayMode).matches(&event) {
+            if event.kind == KeyEventKind::Press {
+                self.toggle_play_mode();
+            }
+            return;
+        }
+        if self.state.play_mode {
+            self.handle_play_key(event);
+            return;
+        }
+        // held keys repeat like they do without the kitty keyboard protocol
+        if event.kind == KeyEventKind::Release {
+            return;
+        }
+
+        if let Some(dialog) = &mut self.quit_dialog {
+            match dialog.handle_key_event(event) {
+                Some(Choice::Quit) => self.quit(),
+                Some(Choice::SaveAndQuit) => {
+                    let name = dialog.state_file().to_string();
+                    log::info!("Saving state to {name}");
+                    dialog.set_saving(
+                        self.state
+                            .request(EmulatorCommand::DebugCommand(DebugCommand::Save { name })),
+                    );
+                }
+                Some(Choice::Cancel) => self.quit_dialog = None,
+                None => (),
+            }
+            return;
+        }
+
+        if let Some(browser) = &mut self.file_browser {
+            match browser.handle_key_event(event) {
+                Some(Selection::File(path)) => {
+                    self.file_browser = None;
+                    self.open_rom(&path);
+                }
+                Some(Selection::Cancel) => self.file_browser = None,
+                None => (),
+            }
+            return;
+        }
+
+        if self.command_line.is_open() {
+            if let Some(input) = self.command_line.handle_key_event(event) {
+                match input.trim() {
+                    "q" | "quit" => self.quit(),
+                    input => self.command_line.run(input, &self.state),
+                }
+            }
+            return;
+        }
+
+        let mut capturing = false;
         if let Some(focused) = self.panels.iter_mut().find(|p| p.has_focus()) {
             if focused.handle_key_event(event, &self.state) {
                 return;
             }
+            capturing = focused.captures_input();
+        }
+
+        let bindings = &self.state.config.bindings;
+
+        // alt and a panel's focus key shows or hides it
+        if event.modifiers.contains(KeyModifiers::ALT) {
+            let mut unmodified = event;
+            unmodified.modifiers.remove(KeyModifiers::ALT);
+            if let Some(i) = Action::FOCUS
+                .iter()
+                .position(|&action| bindings.key(action).matches(&unmodified))
+            {
+                self.toggle_panel(i);
+                return;
+            }
         }
 
-        match event.code {
-            KeyCode::Char('1') => self.focus(0),
-            KeyCode::Char('2') => self.focus(1),
-            KeyCode::Char('3') => self.focus(2),
-            KeyCode::Char('4') => self.focus(3),
-            KeyCode::Char('5') => self.focus(4),
-            KeyCode::Char('6') => self.focus(5),
+        let Some(action) = bindings.action(&event, &Action::GLOBAL) else {
+            return;
+        };
+        match action {
+            Action::FocusDisplay => self.focus(0),
+            Action::FocusCpu => self.focus(1),
+            Action::FocusDisasm => self.focus(2),
+            Action::FocusMemory => self.focus(3),
+            Action::FocusLog => self.focus(4),
+            Action::FocusDebugger => self.focus(5),
 
-            KeyCode::Char('q') => {
-                self.cancellation_token.cancel();
-                let _ = self.state.controller.send(EmulatorCommand::Stop);
+            Action::Quit if !capturing => {
+                self.quit_dialog = Some(QuitDialog::new(self.state_file()));
             }
 
-            KeyCode::Tab => self.focus_next(),
-            KeyCode::Esc => self.unfocus(),
+            Action::CommandLine => self.command_line.open(),
+
+            Action::OpenRom => {
+                // start next to the ROM being watched, if any
+                let dir = match self.watch.as_deref().and_then(Path::parent) {
+                    Some(dir) => dir.to_path_buf(),
+                    None => PathBuf::from("."),
+                };
+                self.file_browser = Some(FileBrowser::new(&dir));
+            }
+
+            Action::NextRenderer => {
+                let default = self.state.capabilities.display_renderer();
+                self.state.renderer = self.state.renderer.next(default);
+                log::info!("Display renderer: {:?}", self.state.renderer);
+            }
+
+            Action::NextPalette => self.next_palette(),
+
+            Action::NextLayout => {
+                let layout = &mut self.state.config.layout;
+                layout.preset = layout.preset.next();
+                log::info!("Layout: {}", layout.preset);
+                self.apply_layout();
+            }
+
+            Action::Stats => self.show_stats = !self.show_stats,
+
+            Action::Mute => {
+                self.state.config.mute = !self.state.config.mute;
+                log::info!(
+                    "Sound {}",
+                    if self.state.config.mute {
+                        "muted"
+                    } else {
+                        "on"
+                    }
+                );
+                self.update_buzzer();
+            }
+
+            Action::SpeedUp => {
+                let ips = self.state.controller.ips();
+                if let Some(&next) = IPS_STEPS.iter().find(|&&step| step > ips) {
+                    self.set_ips(next);
+                }
+            }
+            Action::SpeedDown => {
+                let ips = self.state.controller.ips();
+                if let Some(&prev) = IPS_STEPS.iter().rev().find(|&&step| step < ips) {
+                    self.set_ips(prev);
+                }
+            }
+            Action::Turbo => self.toggle_speed(Speed::Unlimited),
+            Action::SlowMotion => self.toggle_speed(Speed::Percent(SLOW_MOTION)),
+
+            Action::Zoom => self.zoomed = !self.zoomed && self.panels.iter().any(|p| p.has_focus()),
+
+            Action::FocusNext => self.focus_next(),
+            Action::Unfocus => {
+                self.zoomed = false;
+                self.unfocus();
+            }
             _ => (),
         };
     }
 
+    /// Where quitting saves the state, next to the ROM if it's known
+    fn state_file(&self) -> String {
+        let path = match self.rom.as_deref().or(self.watch.as_deref()) {
+            Some(rom) => rom.with_extension("c8state"),
+            None => PathBuf::from("c8rs.c8state"),
+        };
+        path.to_string_lossy().into_owned()
+    }
+
+    fn quit(&mut self) {
+        self.cancellation_token.cancel();
+        self.state.send(EmulatorCommand::Stop);
+    }
+
+    /// Play the buzzer while the sound timer runs, unless paused or muted
+    fn update_buzzer(&mut self) {
+        let on = self.sound
+            && self.state.emulator_state == EmulatorState::Running
+            && !self.state.config.mute;
+        if let Some(buzzer) = &mut self.buzzer {
+            buzzer.set_on(on);
+        }
+    }
+
+    fn set_ips(&self, ips: u32) {
+        log::info!("IPS: {ips}");
+        self.state
+            .send(EmulatorCommand::DebugCommand(DebugCommand::IPS { ips }));
+    }
+
+    /// Switch to `speed`, or back to normal speed if it's already used
+    fn toggle_speed(&self, speed: Speed) {
+        let speed = if self.state.controller.speed() == speed {
+            Speed::default()
+        } else {
+            speed
+        };
+        log::info!("Speed: {speed}");
+        self.state
+            .send(EmulatorCommand::DebugCommand(DebugCommand::Speed { speed }));
+    }
+
+    fn toggle_play_mode(&mut self) {
+        self.state.play_mode = !self.state.play_mode;
+        if self.state.play_mode {
+            log::info!(
+                "Play mode: keys go to the keypad, {} to exit",
+                self.state.config.bindings.key(Action::PlayMode)
+            );
+            return;
+        }
+
+        for key in std::mem::take(&mut self.held) {
+            self.send_key(KeyAction::Release { key });
+        }
+        log::info!("Play mode exited");
+    }
+
+    /// Press the keypad key mapped to `event`'s key, holding it until the
+    /// release if the terminal reports releases
+    fn handle_play_key(&mut self, event: KeyEvent) {
+        let KeyCode::Char(c) = event.code else {
+            return;
+        };
+        if event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return;
+        }
+        let Some(key) = self.state.config.keypad_key(c) else {
+            return;
+        };
+
+        let action = match (self.state.capabilities.input_mode(), event.kind) {
+            (InputMode::Basic, _) => KeyAction::Tap {
+                key,
+                frames: KEY_TAP_FRAMES,
+            },
+            (InputMode::Enhanced, KeyEventKind::Press) => {
+                self.held.push(key);
+                KeyAction::Press { key }
+            }
+            (InputMode::Enhanced, KeyEventKind::Release) => {
+                self.held.retain(|&k| k != key);
+                KeyAction::Release { key }
+            }
+            (InputMode::Enhanced, KeyEventKind::Repeat) => return,
+        };
+        self.send_key(action);
+    }
+
+    fn send_key(&self, action: KeyAction) {
+        self.state
+            .send(EmulatorCommand::DebugCommand(DebugCommand::Key { action }));
+    }
+
+    /// Switch to the palette after the current one, the configured palette
+    /// comes first
+    fn next_palette(&mut self) {
+        let configured = self.state.config.theme().palette;
+        let mut palettes = vec![configured.clone()];
+        palettes.extend(Palette::builtin().into_iter().filter(|p| *p != configured));
+
+        let current = palettes
+            .iter()
+            .position(|p| *p == self.state.theme.palette)
+            .unwrap_or(0);
+        self.state.theme.palette = palettes[(current + 1) % palettes.len()].clone();
+        log::info!("Palette: {}", self.state.theme.palette.name);
+    }
+
+    /// Show the panels of the configured layout, dropping the focus if the
+    /// focused panel is hidden
+    fn apply_layout(&mut self) {
+        let layout = &self.state.config.layout;
+        self.visible = Panel::ALL.map(|panel| layout.shows(panel));
+        self.unfocus_hidden();
+    }
+
+    /// Show or hide panel `i`
+    fn toggle_panel(&mut self, i: usize) {
+        self.visible[i] = !self.visible[i];
+        self.unfocus_hidden();
+    }
+
+    fn unfocus_hidden(&mut self) {
+        for i in 0..self.panels.len() {
+            if !self.is_visible(i) && self.panels[i].has_focus() {
+                self.panels[i].set_focus(false);
+                self.zoomed = false;
+            }
+        }
+    }
+
+    fn is_visible(&self, i: usize) -> bool {
+        self.visible[i] && !(self.log_detached && i == LOG_PANEL)
+    }
+
     fn focus(&mut self, i: usize) {
+        if !self.is_visible(i) {
+            return;
+        }
+
         if !self.panels[i].has_focus() {
             self.unfocus();
         }
@@ -222,17 +974,17 @@ impl App {
 
     fn focus_next(&mut self) {
         let panel_count = self.panels.len();
-        if let Some((i, focused)) = self
-            .panels
-            .iter_mut()
-            .enumerate()
-            .find(|(_, p)| p.has_focus())
+        let first = match self.panels.iter().position(|p| p.has_focus()) {
+            Some(i) => i + 1,
+            None => 0,
+        };
+        self.unfocus();
+
+        if let Some(next) = (0..panel_count)
+            .map(|n| (first + n) % panel_count)
+            .find(|&i| self.is_visible(i))
         {
-            let next = (i + 1) % panel_count;
-            focused.set_focus(false);
             self.panels[next].set_focus(true);
-        } else {
-            self.panels[0].set_focus(true);
         }
     }
 }
@@ -0,0 +1,517 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Key with modifiers, written `j`, `ctrl+g`, `alt+1`, `f2`, `tab` etc. in
+/// the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl Key {
+    pub const fn new(code: KeyCode) -> Key {
+        Key {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    pub const fn ctrl(c: char) -> Key {
+        Key {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    /// Whether `event` is this key, shift is part of the character for
+    /// character keys
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        let mut modifiers = event.modifiers;
+        if let KeyCode::Char(_) = event.code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        event.code == self.code && modifiers == self.modifiers
+    }
+}
+
+/// Names of keys that aren't characters
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("tab", KeyCode::Tab),
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("backspace", KeyCode::Backspace),
+    ("space", KeyCode::Char(' ')),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+];
+
+impl std::str::FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            if let Some(r) = rest.strip_prefix("ctrl+") {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("alt+") {
+                modifiers |= KeyModifiers::ALT;
+                rest = r;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            // names are case insensitive, characters aren't
+            _ => match KEY_NAMES
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(rest))
+            {
+                Some(&(_, code)) => code,
+                None => match rest.strip_prefix(['f', 'F']).and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key '{s}'")),
+                },
+            },
+        };
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl std::fmt::Display for Key {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        match (
+            self.code,
+            KEY_NAMES.iter().find(|(_, code)| *code == self.code),
+        ) {
+            (_, Some((name, _))) => write!(f, "{name}"),
+            (KeyCode::Char(c), None) => write!(f, "{c}"),
+            (KeyCode::F(n), None) => write!(f, "F{n}"),
+            (code, None) => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// Something a key can be bound to, named like its `[bindings]` key in the
+/// config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    FocusDisplay,
+    FocusCpu,
+    FocusDisasm,
+    FocusMemory,
+    FocusLog,
+    FocusDebugger,
+    FocusNext,
+    Unfocus,
+    Quit,
+    CommandLine,
+    PlayMode,
+    NextRenderer,
+    NextPalette,
+    Zoom,
+    NextLayout,
+    /// Pick a ROM to load from a file browser
+    OpenRom,
+    /// Show or hide the draw time and speed overlay
+    Stats,
+    Mute,
+    /// Next step up or down the IPS ladder
+    SpeedUp,
+    SpeedDown,
+    /// Run as fast as possible, or at normal speed again
+    Turbo,
+    /// Run at a fraction of the speed, or at normal speed again
+    SlowMotion,
+    Down,
+    Up,
+    /// Moves the memory edit cursor
+    Left,
+    Right,
+    Goto,
+    Follow,
+    /// Patch the instruction or edit the memory under the cursor
+    Edit,
+    Breakpoint,
+    JumpToI,
+    SpriteView,
+    HexView,
+    /// Color the hex view by how often each byte was executed
+    Heatmap,
+    /// Show fewer log levels, wrapping around to all of them
+    LogLevel,
+    /// Show the records of the next crate that logged something, then all
+    LogTarget,
+    /// Type text the shown log records must contain
+    LogFilter,
+    /// Dim the instructions of the program that haven't run
+    Coverage,
+    /// Find an instruction by its text
+    Search,
+    NextMatch,
+    PrevMatch,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    /// Start of memory
+    Top,
+    /// End of memory
+    Bottom,
+}
+
+impl Action {
+    pub const ALL: [Action; 47] = [
+        Action::FocusDisplay,
+        Action::FocusCpu,
+        Action::FocusDisasm,
+        Action::FocusMemory,
+        Action::FocusLog,
+        Action::FocusDebugger,
+        Action::FocusNext,
+        Action::Unfocus,
+        Action::Quit,
+        Action::CommandLine,
+        Action::PlayMode,
+        Action::NextRenderer,
+        Action::NextPalette,
+        Action::Zoom,
+        Action::NextLayout,
+        Action::OpenRom,
+        Action::Stats,
+        Action::Mute,
+        Action::SpeedUp,
+        Action::SpeedDown,
+        Action::Turbo,
+        Action::SlowMotion,
+        Action::Down,
+        Action::Up,
+        Action::Left,
+        Action::Right,
+        Action::Goto,
+        Action::Follow,
+        Action::Edit,
+        Action::Breakpoint,
+        Action::JumpToI,
+        Action::SpriteView,
+        Action::HexView,
+        Action::Heatmap,
+        Action::LogLevel,
+        Action::LogTarget,
+        Action::LogFilter,
+        Action::Coverage,
+        Action::Search,
+        Action::NextMatch,
+        Action::PrevMatch,
+        Action::PageDown,
+        Action::PageUp,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::Top,
+        Action::Bottom,
+    ];
+
+    /// Focus keys of the panels, in panel order
+    pub const FOCUS: [Action; 6] = [
+        Action::FocusDisplay,
+        Action::FocusCpu,
+        Action::FocusDisasm,
+        Action::FocusMemory,
+        Action::FocusLog,
+        Action::FocusDebugger,
+    ];
+
+    /// Handled by the app when the focused panel doesn't use the key
+    pub const GLOBAL: [Action; 22] = [
+        Action::FocusDisplay,
+        Action::FocusCpu,
+        Action::FocusDisasm,
+        Action::FocusMemory,
+        Action::FocusLog,
+        Action::FocusDebugger,
+        Action::FocusNext,
+        Action::Unfocus,
+        Action::Quit,
+        Action::CommandLine,
+        Action::PlayMode,
+        Action::NextRenderer,
+        Action::NextPalette,
+        Action::Zoom,
+        Action::NextLayout,
+        Action::OpenRom,
+        Action::Stats,
+        Action::Mute,
+        Action::SpeedUp,
+        Action::SpeedDown,
+        Action::Turbo,
+        Action::SlowMotion,
+    ];
+
+    pub const DISASSEMBLY: [Action; 16] = [
+        Action::Down,
+        Action::Up,
+        Action::PageDown,
+        Action::PageUp,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::Top,
+        Action::Bottom,
+        Action::Goto,
+        Action::Follow,
+        Action::Edit,
+        Action::Breakpoint,
+        Action::Coverage,
+        Action::Search,
+        Action::NextMatch,
+        Action::PrevMatch,
+    ];
+
+    pub const MEMORY: [Action; 15] = [
+        Action::Down,
+        Action::Up,
+        Action::PageDown,
+        Action::PageUp,
+        Action::HalfPageDown,
+        Action::HalfPageUp,
+        Action::Top,
+        Action::Bottom,
+        Action::Goto,
+        Action::Follow,
+        Action::Edit,
+        Action::JumpToI,
+        Action::SpriteView,
+        Action::HexView,
+        Action::Heatmap,
+    ];
+
+    /// Stack frames in the CPU panel
+    pub const CPU: [Action; 3] = [Action::Down, Action::Up, Action::Goto];
+
+    /// Memory panel in edit mode, which takes every other key
+    pub const MEMORY_EDIT: [Action; 4] = [Action::Left, Action::Right, Action::Up, Action::Down];
+
+    pub const LOG: [Action; 5] = [
+        Action::Down,
+        Action::Up,
+        Action::LogLevel,
+        Action::LogTarget,
+        Action::LogFilter,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::FocusDisplay => "focus_display",
+            Action::FocusCpu => "focus_cpu",
+            Action::FocusDisasm => "focus_disasm",
+            Action::FocusMemory => "focus_memory",
+            Action::FocusLog => "focus_log",
+            Action::FocusDebugger => "focus_debugger",
+            Action::FocusNext => "focus_next",
+            Action::Unfocus => "unfocus",
+            Action::Quit => "quit",
+            Action::CommandLine => "command_line",
+            Action::PlayMode => "play_mode",
+            Action::NextRenderer => "next_renderer",
+            Action::NextPalette => "next_palette",
+            Action::Zoom => "zoom",
+            Action::NextLayout => "next_layout",
+            Action::OpenRom => "open_rom",
+            Action::Stats => "stats",
+            Action::Mute => "mute",
+            Action::SpeedUp => "speed_up",
+            Action::SpeedDown => "speed_down",
+            Action::Turbo => "turbo",
+            Action::SlowMotion => "slow_motion",
+            Action::Down => "down",
+            Action::Up => "up",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Goto => "goto",
+            Action::Follow => "follow",
+            Action::Edit => "edit",
+            Action::Breakpoint => "breakpoint",
+            Action::JumpToI => "jump_to_i",
+            Action::SpriteView => "sprite_view",
+            Action::HexView => "hex_view",
+            Action::Heatmap => "heatmap",
+            Action::LogLevel => "log_level",
+            Action::LogTarget => "log_target",
+            Action::LogFilter => "log_filter",
+            Action::Coverage => "coverage",
+            Action::Search => "search",
+            Action::NextMatch => "next_match",
+            Action::PrevMatch => "prev_match",
+            Action::PageDown => "page_down",
+            Action::PageUp => "page_up",
+            Action::HalfPageDown => "half_page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::Top => "top",
+            Action::Bottom => "bottom",
+        }
+    }
+
+    fn default_key(&self) -> Key {
+        Key::new(match self {
+            Action::FocusDisplay => KeyCode::Char('1'),
+            Action::FocusCpu => KeyCode::Char('2'),
+            Action::FocusDisasm => KeyCode::Char('3'),
+            Action::FocusMemory => KeyCode::Char('4'),
+            Action::FocusLog => KeyCode::Char('5'),
+            Action::FocusDebugger => KeyCode::Char('6'),
+            Action::FocusNext => KeyCode::Tab,
+            Action::Unfocus => KeyCode::Esc,
+            Action::Quit => KeyCode::Char('q'),
+            Action::CommandLine => KeyCode::Char(':'),
+            Action::PlayMode => KeyCode::F(2),
+            Action::NextRenderer => KeyCode::F(3),
+            Action::NextPalette => KeyCode::F(4),
+            Action::Zoom => KeyCode::F(5),
+            Action::NextLayout => KeyCode::F(6),
+            Action::OpenRom => KeyCode::Char('o'),
+            Action::Stats => KeyCode::F(7),
+            Action::Mute => KeyCode::Char('m'),
+            Action::SpeedUp => KeyCode::Char('+'),
+            Action::SpeedDown => KeyCode::Char('-'),
+            Action::Turbo => KeyCode::F(8),
+            Action::SlowMotion => KeyCode::F(9),
+            Action::Down => KeyCode::Char('j'),
+            Action::Up => KeyCode::Char('k'),
+            Action::Left => KeyCode::Char('h'),
+            Action::Right => KeyCode::Char('l'),
+            Action::Goto => KeyCode::Char('g'),
+            Action::Follow => KeyCode::Char('f'),
+            Action::Edit => KeyCode::Char('e'),
+            Action::Breakpoint => KeyCode::Char('b'),
+            Action::JumpToI => KeyCode::Char('i'),
+            Action::SpriteView => KeyCode::Char('s'),
+            Action::HexView => KeyCode::Char('h'),
+            Action::Heatmap => KeyCode::Char('x'),
+            Action::LogLevel => KeyCode::Char('v'),
+            Action::LogTarget => KeyCode::Char('t'),
+            Action::LogFilter => KeyCode::Char('/'),
+            Action::Coverage => KeyCode::Char('c'),
+            Action::Search => KeyCode::Char('/'),
+            Action::NextMatch => KeyCode::Char('n'),
+            Action::PrevMatch => KeyCode::Char('N'),
+            Action::PageDown => KeyCode::PageDown,
+            Action::PageUp => KeyCode::PageUp,
+            Action::HalfPageDown => return Key::ctrl('d'),
+            Action::HalfPageUp => return Key::ctrl('u'),
+            Action::Top => KeyCode::Home,
+            Action::Bottom => KeyCode::End,
+        })
+    }
+}
+
+impl std::str::FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .into_iter()
+            .find(|action| action.name() == s)
+            .ok_or_else(|| format!("unknown action '{s}'"))
+    }
+}
+
+/// Key bound to each [`Action`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyBindings {
+    keys: [Key; Action::ALL.len()],
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            keys: Action::ALL.map(|action| action.default_key()),
+        }
+    }
+}
+
+impl KeyBindings {
+    pub fn key(&self, action: Action) -> Key {
+        self.keys[action as usize]
+    }
+
+    pub fn set(&mut self, action: Action, key: Key) {
+        self.keys[action as usize] = key;
+    }
+
+    /// First of `actions` bound to `event`'s key
+    pub fn action(&self, event: &KeyEvent, actions: &[Action]) -> Option<Action> {
+        actions
+            .iter()
+            .copied()
+            .find(|&action| self.key(action).matches(event))
+    }
+
+    /// Check that no key is bound to two actions that can be triggered from
+    /// the same place, a panel's keys shadow the global ones, and that the
+    /// play mode key doesn't shadow one of the keyboard keys in `keypad`
+    pub fn validate(&self, keypad: &[char; 16]) -> Result<(), String> {
+        // each panel's actions, and whether it swallows the global keys
+        let contexts: [(&[Action], bool); 5] = [
+            (&Action::DISASSEMBLY, false),
+            (&Action::MEMORY, false),
+            (&Action::LOG, false),
+            (&Action::MEMORY_EDIT, true),
+            (&Action::CPU, false),
+        ];
+        for (context, swallows_global) in contexts {
+            let global: &[Action] = if swallows_global {
+                &[]
+            } else {
+                &Action::GLOBAL
+            };
+            let actions: Vec<Action> = context.iter().chain(global).copied().collect();
+            for (i, &a) in actions.iter().enumerate() {
+                if let Some(&b) = actions[i + 1..]
+                    .iter()
+                    .find(|&&b| b != a && self.key(b) == self.key(a))
+                {
+                    return Err(format!(
+                        "'{}' is bound to both {} and {}",
+                        self.key(a),
+                        a.name(),
+                        b.name()
+                    ));
+                }
+            }
+        }
+
+        // play mode checks its key before handing characters to the keypad
+        let play = self.key(Action::PlayMode);
+        if let KeyCode::Char(c) = play.code {
+            let plain = !play
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT);
+            if let Some(key) = keypad
+                .iter()
+                .position(|&k| k == c.to_ascii_lowercase())
+                .filter(|_| plain)
+            {
+                return Err(format!(
+                    "'{play}' is bound to both {} and keypad key {key:X}",
+                    Action::PlayMode.name()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
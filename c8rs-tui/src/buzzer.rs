@@ -0,0 +1,120 @@
+/// Sound output for the CHIP-8 buzzer. Built with the `audio` feature it
+/// plays a tone on an audio device for as long as it's on, otherwise (or when
+/// no device can be opened) it rings the terminal bell when it comes on.
+pub enum Buzzer {
+    #[cfg(feature = "audio")]
+    Tone(tone::Tone),
+    Bell {
+        on: bool,
+    },
+}
+
+impl Buzzer {
+    /// Open the output device named `device`, or the default one
+    pub fn open(device: Option<&str>) -> Buzzer {
+        #[cfg(feature = "audio")]
+        match tone::Tone::open(device) {
+            Ok(tone) => return Buzzer::Tone(tone),
+            Err(err) => log::warn!("No audio, using the terminal bell: {err}"),
+        }
+        #[cfg(not(feature = "audio"))]
+        if let Some(name) = device {
+            log::warn!("Can't play sound on '{name}', c8rs was built without the audio feature");
+        }
+
+        Buzzer::Bell { on: false }
+    }
+
+    pub fn set_on(&mut self, on: bool) {
+        match self {
+            #[cfg(feature = "audio")]
+            Buzzer::Tone(tone) => tone.set_on(on),
+            Buzzer::Bell { on: was_on } => {
+                if on && !*was_on {
+                    if let Err(err) = crate::tui::bell() {
+                        log::warn!("Failed to ring the bell: {err}");
+                    }
+                }
+                *was_on = on;
+            }
+        }
+    }
+}
+
+#[cfg(feature = "audio")]
+mod tone {
+    use anyhow::{anyhow, Result};
+    use rodio::{
+        cpal::traits::HostTrait, source::SquareWave, DeviceSinkBuilder, DeviceTrait,
+        MixerDeviceSink, Player, Source,
+    };
+
+    /// Pitch of the buzzer, close to the VIP's
+    const FREQUENCY: f32 = 440.0;
+
+    const VOLUME: f32 = 0.15;
+
+    /// Square wave playing on an audio device, paused while the buzzer is off
+    pub struct Tone {
+        // playback stops when the sink is dropped
+        _sink: MixerDeviceSink,
+        player: Player,
+    }
+
+    impl Tone {
+        pub fn open(device: Option<&str>) -> Result<Tone> {
+            let builder = match device {
+                Some(name) => {
+                    let host = rodio::cpal::default_host();
+                    let device = host
+                        .output_devices()?
+                        .find(|device| device_name(device).as_deref() == Some(name))
+                        .ok_or_else(|| {
+                            let names: Vec<String> = host
+                                .output_devices()
+                                .into_iter()
+                                .flatten()
+                                .filter_map(|device| device_name(&device))
+                                .collect();
+                            anyhow!("no audio device '{name}', there are: {}", names.join(", "))
+                        })?;
+                    DeviceSinkBuilder::from_device(device)?
+                }
+                None => DeviceSinkBuilder::from_default_device()?,
+            };
+            // rodio prints stream errors to stderr otherwise, over the TUI
+            let mut sink = builder
+                .with_error_callback(|err| log::warn!("Audio stream error: {err}"))
+                .open_stream()?;
+            sink.log_on_drop(false);
+
+            let player = Player::connect_new(sink.mixer());
+            player.pause();
+            player.append(SquareWave::new(FREQUENCY).amplify(VOLUME));
+            log::info!(
+                "Playing sound on {}",
+                device.unwrap_or("the default audio device")
+            );
+
+            Ok(Tone {
+                _sink: sink,
+                player,
+            })
+        }
+
+        pub fn set_on(&self, on: bool) {
+            if on {
+                self.player.play();
+            } else {
+                self.player.pause();
+            }
+        }
+    }
+
+    fn device_name(device: &rodio::Device) -> Option<String> {
+        device
+            .description()
+            .ok()
+            .map(|desc| desc.name().to_string())
+    }
+}
@@ -0,0 +1,162 @@
+use std::env;
+
+use ratatui::layout::Rect;
+
+/// Smallest terminal size that fits the default layout
+pub const MIN_WIDTH: u16 = 147;
+pub const MIN_HEIGHT: u16 = 37;
+
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum ColorDepth {
+    Monochrome,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputMode {
+    /// Press events only
+    Basic,
+    /// kitty keyboard protocol, reports key release events
+    Enhanced,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DisplayRenderer {
+    /// Two pixels per cell using half-block characters with fg/bg colors
+    HalfBlock,
+    /// Two pixels per cell using glyphs only, for terminals without color
+    Glyph,
+    /// 2x2 pixels per cell using quadrant block characters
+    Quadrant,
+    /// 2x4 pixels per cell using braille patterns
+    Braille,
+}
+
+impl DisplayRenderer {
+    /// Pixels drawn by one terminal cell, width by height
+    pub fn cell_size(&self) -> (u16, u16) {
+        match self {
+            DisplayRenderer::HalfBlock | DisplayRenderer::Glyph => (1, 2),
+            DisplayRenderer::Quadrant => (2, 2),
+            DisplayRenderer::Braille => (2, 4),
+        }
+    }
+
+    /// Renderer after this one, cycling back to `default`
+    pub fn next(&self, default: DisplayRenderer) -> DisplayRenderer {
+        match self {
+            DisplayRenderer::HalfBlock | DisplayRenderer::Glyph => DisplayRenderer::Quadrant,
+            DisplayRenderer::Quadrant => DisplayRenderer::Braille,
+            DisplayRenderer::Braille => default,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TerminalCapabilities {
+    pub color_depth: ColorDepth,
+    pub keyboard_enhancement: bool,
+    pub sixel: bool,
+}
+
+impl TerminalCapabilities {
+    pub fn detect() -> TerminalCapabilities {
+        TerminalCapabilities {
+            color_depth: detect_color_depth(),
+            keyboard_enhancement: crossterm::terminal::supports_keyboard_enhancement()
+                .unwrap_or(false),
+            sixel: detect_sixel(),
+        }
+    }
+
+    pub fn input_mode(&self) -> InputMode {
+        if self.keyboard_enhancement {
+            InputMode::Enhanced
+        } else {
+            InputMode::Basic
+        }
+    }
+
+    pub fn display_renderer(&self) -> DisplayRenderer {
+        if self.color_depth == ColorDepth::Monochrome {
+            DisplayRenderer::Glyph
+        } else {
+            DisplayRenderer::HalfBlock
+        }
+    }
+
+    pub fn log_report(&self) {
+        log::info!(
+            "Terminal: colors: {:?}, kitty keyboard: {}, sixel: {}",
+            self.color_depth,
+            self.keyboard_enhancement,
+            self.sixel
+        );
+        log::info!(
+            "Using display renderer: {:?}, input mode: {:?}",
+            self.display_renderer(),
+            self.input_mode()
+        );
+
+        if !self.keyboard_enhancement {
+            log::warn!("No kitty keyboard protocol support, key releases unavailable");
+        }
+
+        for warning in self.warnings(None) {
+            log::warn!("{warning}");
+        }
+    }
+
+    /// Problems affecting the experience, including the terminal size if known
+    pub fn warnings(&self, area: Option<Rect>) -> Vec<String> {
+        let mut warnings = Vec::new();
+
+        if let Some(area) = area {
+            if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+                warnings.push(format!(
+                    "terminal too small: {}x{} (need {MIN_WIDTH}x{MIN_HEIGHT})",
+                    area.width, area.height
+                ));
+            }
+        }
+
+        if self.color_depth == ColorDepth::Monochrome {
+            warnings.push("no color support, using monochrome display".to_string());
+        }
+        if !self.sixel {
+            warnings.push("no sixel support, using text display renderers".to_string());
+        }
+
+        warnings
+    }
+}
+
+fn detect_color_depth() -> ColorDepth {
+    if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return ColorDepth::Monochrome;
+    }
+
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorDepth::TrueColor;
+    }
+
+    match env::var("TERM").unwrap_or_default().as_str() {
+        "" | "dumb" => ColorDepth::Monochrome,
+        term if term.contains("256color") => ColorDepth::Ansi256,
+        _ => ColorDepth::Ansi16,
+    }
+}
+
+/// Best-effort guess based on the environment, terminals don't reliably
+/// answer device attribute queries in time
+fn detect_sixel() -> bool {
+    let term = env::var("TERM").unwrap_or_default();
+    let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+
+    term.contains("sixel")
+        || ["mlterm", "foot", "contour", "yaft-256color"].contains(&term.as_str())
+        || ["WezTerm", "iTerm.app", "mintty"].contains(&term_program.as_str())
+}
@@ -0,0 +1,108 @@
+use c8rs_core::{EmulatorCommand, PendingReply};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Position, Rect},
+    text::Line,
+    Frame,
+};
+
+use crate::{app::AppState, theme::Theme};
+
+/// Vim style `:` prompt on the bottom row, runs debugger commands without
+/// focusing the debugger panel
+#[derive(Default)]
+pub(crate) struct CommandLine {
+    /// Text typed after the `:`, `None` when the prompt is closed
+    input: Option<String>,
+    /// Commands still waiting for the emulator to respond
+    pending: Vec<PendingReply>,
+    /// Result of the last command, shown until the prompt is opened again
+    message: Option<Result<String, String>>,
+}
+
+impl CommandLine {
+    pub(crate) fn open(&mut self) {
+        self.input = Some(String::new());
+        self.message = None;
+    }
+
+    pub(crate) fn is_open(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// Whether the bottom row is needed
+    pub(crate) fn is_visible(&self) -> bool {
+        self.input.is_some() || self.message.is_some()
+    }
+
+    /// Edit the prompt, returns the input once it's submitted
+    pub(crate) fn handle_key_event(&mut self, event: KeyEvent) -> Option<String> {
+        let input = self.input.as_mut()?;
+        match event.code {
+            KeyCode::Char(c) => input.push(c),
+            // like vim, deleting the `:` closes the prompt
+            KeyCode::Backspace if input.pop().is_none() => self.input = None,
+            KeyCode::Esc => self.input = None,
+            KeyCode::Enter => return self.input.take(),
+            _ => (),
+        }
+        None
+    }
+
+    /// Parse `input` and send the commands to the emulator
+    pub(crate) fn run(&mut self, input: &str, state: &AppState) {
+        match state.controller.aliases().parse(input) {
+            Ok(cmds) => {
+                for cmd in cmds {
+                    self.pending
+                        .push(state.request(EmulatorCommand::DebugCommand(cmd)));
+                }
+            }
+            Err(err) => self.message = Some(Err(err.lines().next().unwrap_or("").to_string())),
+        }
+    }
+
+    pub(crate) fn render(&mut self, f: &mut Frame<'_>, area: Rect, theme: &Theme) {
+        self.poll_replies();
+
+        if let Some(input) = &self.input {
+            f.render_widget(Line::from(format!(":{input}")), area);
+            f.set_cursor_position(Position::new(
+                area.x + 1 + input.chars().count() as u16,
+                area.y,
+            ));
+        } else if let Some(message) = &self.message {
+            let line = match message {
+                Ok(msg) => Line::from(msg.as_str()),
+                Err(err) => Line::styled(err.as_str(), theme.log_error),
+            };
+            f.render_widget(line, area);
+        }
+    }
+
+    /// Show the result of the last handled command, output longer than the
+    /// row goes to the log
+    fn poll_replies(&mut self) {
+        while let Some(reply) = self.pending.first() {
+            let Some(result) = reply.try_get() else {
+                break;
+            };
+            self.message = match result {
+                Ok(output) => {
+                    let output = output.to_string();
+                    let mut lines = output.lines();
+                    match (lines.next(), lines.count()) {
+                        (None, _) => self.message.take(),
+                        (Some(line), 0) => Some(Ok(line.to_string())),
+                        (Some(line), more) => {
+                            log::info!("{output}");
+                            Some(Ok(format!("{line} (+{more} lines in the log)")))
+                        }
+                    }
+                }
+                Err(err) => Some(Err(format!("error: {err}"))),
+            };
+            self.pending.remove(0);
+        }
+    }
+}
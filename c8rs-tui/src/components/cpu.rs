@@ -1,35 +1,61 @@
-use c8rs_core::{Cpu, EmulatorState, Memory};
-use ratatui::{
-    prelude::*,
-    widgets::{block, Block},
-};
+use std::collections::VecDeque;
 
-use crate::app::AppState;
+use c8rs_core::{Cpu, EmulatorState, Instruction, Memory, SourceMap};
+use crossterm::event::KeyEvent;
+use ratatui::{prelude::*, widgets::Block};
 
-use super::Component;
+use crate::{app::AppState, bindings::Action, theme::Theme};
+
+use super::{disasm::instruction_text, Component};
+
+/// Address of the outermost stack slot, the stack grows down from here
+const STACK_TOP: u16 = 0x1FE;
+
+/// Timer samples kept for the history graphs
+const TIMER_HISTORY: usize = 64;
+
+/// Bars of the timer history graphs, lowest first
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Width of the DT and ST gauges
+const GAUGE_WIDTH: u16 = 10;
+
+/// Width of the sprite preview, room for a 16 pixel wide SCHIP sprite
+const SPRITE_WIDTH: u16 = 17;
 
 #[derive(Default)]
 pub struct CpuComponent {
     focused: bool,
+    /// DT and ST at each render while running, or at each change while
+    /// stepping, oldest first
+    timers: VecDeque<[u8; 2]>,
+    /// Selected stack frame, 0 is the innermost call
+    frame: usize,
 }
 
 impl Component for CpuComponent {
-    fn handle_key_event(&mut self, _: crossterm::event::KeyEvent, _: &AppState) -> bool {
-        false
+    fn handle_key_event(&mut self, event: KeyEvent, state: &AppState) -> bool {
+        let Some(action) = state.config.bindings.action(&event, &Action::CPU) else {
+            return false;
+        };
+        let sites = call_sites(state.controller.cpu(), state.controller.memory());
+        match action {
+            Action::Down => self.frame = (self.frame + 1).min(sites.len().saturating_sub(1)),
+            Action::Up => self.frame = self.frame.saturating_sub(1),
+            Action::Goto => match sites.get(self.frame) {
+                Some(&site) => state.show_in_disassembly(site),
+                None => log::info!("Not in a subroutine"),
+            },
+            _ => return false,
+        }
+        true
     }
 
     fn render(&mut self, f: &mut Frame<'_>, area: Rect, state: &AppState) {
-        let start = std::time::Instant::now();
-
-        let border_style = if self.focused {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
+        let border_style = state.theme.border_style(self.focused);
 
         let outer_block = Block::bordered()
             .title("[2: CPU]")
-            .title(block::Title::from(self.render_status_line(state)))
             .border_style(border_style);
 
         let [reg_area, stack_area] = Layout::default()
@@ -40,22 +66,51 @@ impl Component for CpuComponent {
             unreachable!()
         };
 
+        let [stack_area, sprite_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(SPRITE_WIDTH)])
+                .areas(stack_area);
+
         let cpu = state.controller.cpu();
         let mem = state.controller.memory();
 
-        f.render_widget(RegisterWidget { cpu }, reg_area);
-        f.render_widget(StackWidget { cpu, mem }, stack_area);
+        let timers = [cpu.delay_timer, cpu.sound_timer];
+        if state.emulator_state == EmulatorState::Running || self.timers.back() != Some(&timers) {
+            if self.timers.len() == TIMER_HISTORY {
+                self.timers.pop_front();
+            }
+            self.timers.push_back(timers);
+        }
 
         f.render_widget(
-            outer_block.title(
-                block::Title::from(format!(
-                    "[render: {:.02}ms]",
-                    start.elapsed().as_secs_f64() * 1000.0
-                ))
-                .alignment(Alignment::Right),
-            ),
-            area,
-        )
+            RegisterWidget {
+                cpu,
+                timers: &self.timers,
+                theme: &state.theme,
+            },
+            reg_area,
+        );
+        let sites = call_sites(cpu, mem);
+        self.frame = self.frame.min(sites.len().saturating_sub(1));
+        f.render_widget(
+            StackWidget {
+                sites: &sites,
+                mem,
+                source_map: state.controller.source_map(),
+                selected: self.focused.then_some(self.frame),
+                theme: &state.theme,
+            },
+            stack_area,
+        );
+        f.render_widget(
+            SpriteWidget {
+                cpu,
+                mem,
+                theme: &state.theme,
+            },
+            sprite_area,
+        );
+
+        f.render_widget(outer_block, area)
     }
 
     fn has_focus(&self) -> bool {
@@ -67,22 +122,10 @@ impl Component for CpuComponent {
     }
 }
 
-impl CpuComponent {
-    fn render_status_line(&self, state: &AppState) -> String {
-        let ips = state.controller.ips();
-        format!(
-            "[state: {} | IPS: {ips}]",
-            match state.controller.state() {
-                EmulatorState::Running => "running",
-                EmulatorState::Paused => "paused",
-                EmulatorState::Halted => "halted",
-            }
-        )
-    }
-}
-
 struct RegisterWidget<'a> {
     cpu: &'a Cpu,
+    timers: &'a VecDeque<[u8; 2]>,
+    theme: &'a Theme,
 }
 
 impl Widget for RegisterWidget<'_> {
@@ -119,18 +162,40 @@ impl Widget for RegisterWidget<'_> {
             area.width,
         );
 
-        buf.set_line(
-            area.x,
-            area.y + 4,
-            &Line::from(format!("DT: {delay_timer:#04X} ({delay_timer:03})")),
-            area.width,
-        );
-        buf.set_line(
-            area.x,
-            area.y + 5,
-            &Line::from(format!("ST: {sound_timer:#04X} ({sound_timer:03})")),
-            area.width,
-        );
+        let timers = [
+            ("DT", *delay_timer, self.theme.i),
+            ("ST", *sound_timer, self.theme.sp),
+        ];
+        for (n, (name, value, style)) in timers.into_iter().enumerate() {
+            let history = self.timers.iter().map(|timers| timers[n]);
+            // games set timers far below 255, scale to what this one reached
+            let scale = history.clone().chain([value, 1]).max().unwrap_or(1) as u16;
+            let filled = (value as u16 * GAUGE_WIDTH).div_ceil(scale);
+            let graph_width = area.width.saturating_sub(16 + GAUGE_WIDTH + 1) as usize;
+            let graph: String = history
+                .skip(self.timers.len().saturating_sub(graph_width))
+                .map(|value| match value {
+                    0 => ' ',
+                    value => BARS[(value as usize * BARS.len() - 1) / scale as usize],
+                })
+                .collect();
+
+            buf.set_line(
+                area.x,
+                area.y + 4 + n as u16,
+                &Line::from(vec![
+                    Span::raw(format!("{name}: {value:#04X} ({value:03}) ")),
+                    Span::styled("█".repeat(filled as usize), style),
+                    Span::styled(
+                        "░".repeat((GAUGE_WIDTH - filled) as usize),
+                        self.theme.comment,
+                    ),
+                    Span::raw(" "),
+                    Span::styled(graph, style),
+                ]),
+                area.width,
+            );
+        }
 
         for col in 0..2 {
             for row in 0..8 {
@@ -148,38 +213,123 @@ impl Widget for RegisterWidget<'_> {
     }
 }
 
-struct StackWidget<'a> {
+/// Bytes at I as pixels, as many rows as the DRW at PC draws or the most a
+/// DRW can draw otherwise
+struct SpriteWidget<'a> {
     cpu: &'a Cpu,
     mem: &'a Memory,
+    theme: &'a Theme,
 }
 
-impl Widget for StackWidget<'_> {
+impl Widget for SpriteWidget<'_> {
     fn render(self, area: Rect, buf: &mut Buffer)
     where
         Self: Sized,
     {
-        let Cpu { sp, .. } = self.cpu;
+        let Cpu { pc, i, .. } = self.cpu;
+
+        // DRW with a length of 0 draws a 16x16 sprite on SCHIP
+        let (title, title_style, rows, row_bytes) = match Instruction::parse(self.mem.read_u16(*pc))
+        {
+            Instruction::Drw { len: 0, .. } => ("DRW at I", self.theme.i, 16, 2),
+            Instruction::Drw { len, .. } => ("DRW at I", self.theme.i, len as u16, 1),
+            _ => ("sprite at I", self.theme.comment, 15, 1),
+        };
+        buf.set_line(
+            area.x,
+            area.y,
+            &Line::styled(title, title_style),
+            area.width,
+        );
+
+        let on = Style::new().fg(self.theme.palette.pixel_on());
+        let off = Style::new().fg(self.theme.palette.off);
+        for row in 0..rows.min(area.height.saturating_sub(1)) {
+            let addr = i.wrapping_add(row * row_bytes);
+            let spans: Vec<Span> = (0..row_bytes)
+                .flat_map(|n| {
+                    let byte = self.mem.read_u8(addr.wrapping_add(n));
+                    (0..8).map(move |bit| byte & (0x80 >> bit) != 0)
+                })
+                .map(|pixel| Span::styled("█", if pixel { on } else { off }))
+                .collect();
+            buf.set_line(area.x, area.y + 1 + row, &Line::from(spans), area.width);
+        }
+    }
+}
+
+/// Addresses of the CALLs on the stack, innermost first
+fn call_sites(cpu: &Cpu, mem: &Memory) -> Vec<u16> {
+    (cpu.sp.saturating_add(2)..=STACK_TOP)
+        .step_by(2)
+        .map(|addr| mem.read_u16(addr))
+        .collect()
+}
+
+/// Call frames as the address returned to and the call before it
+struct StackWidget<'a> {
+    sites: &'a [u16],
+    mem: &'a Memory,
+    source_map: &'a SourceMap,
+    /// Highlighted frame, while the panel is focused
+    selected: Option<usize>,
+    theme: &'a Theme,
+}
 
-        let stack_len = 0x1FEu16.saturating_sub(*sp) / 2;
+impl Widget for StackWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
         buf.set_line(
             area.x,
             area.y,
-            &Line::from(format!("stack    len: {stack_len}")),
+            &Line::from(format!("stack    depth: {}", self.sites.len())),
             area.width,
         );
 
-        let start_addr = sp.saturating_sub(area.height).min(0x0202 - area.height * 2) & 0xFFFE;
-        let end_addr = (start_addr + area.height * 2) & 0xFFFE;
+        if self.sites.is_empty() {
+            buf.set_line(
+                area.x,
+                area.y + 1,
+                &Line::styled("not in a subroutine", self.theme.comment),
+                area.width,
+            );
+            return;
+        }
+
+        // scroll just enough to keep the selected frame in view
+        let rows = area.height.saturating_sub(1) as usize;
+        let first = self
+            .selected
+            .map_or(0, |frame| (frame + 1).saturating_sub(rows));
+
+        for (row, (n, &site)) in self
+            .sites
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .enumerate()
+        {
+            let y = area.y + 1 + row as u16;
+            let style = if self.selected == Some(n) {
+                self.theme.cursor_line
+            } else {
+                Style::default()
+            };
+            buf.set_style(Rect::new(area.x, y, area.width, 1), style);
 
-        for (i, addr) in (start_addr..end_addr).step_by(2).enumerate() {
-            let val = self.mem.read_u16(addr);
+            let inst = Instruction::parse(self.mem.read_u16(site));
             buf.set_line(
                 area.x,
-                area.y + 1 + i as u16,
-                &Line::from(format!(
-                    "{}|{addr:#06X}| {val:#06X}",
-                    if *sp == addr { "SP->" } else { "    " }
-                )),
+                y,
+                &Line::from(vec![
+                    Span::styled(format!("#{n:<2} "), self.theme.comment),
+                    Span::raw(format!("{:#06X} ", site.wrapping_add(2))),
+                    Span::styled("→ ", self.theme.comment),
+                    Span::raw(instruction_text(inst, self.source_map)),
+                ]),
                 area.width,
             );
         }
@@ -1,9 +1,6 @@
-use c8rs_core::{DebugCommand, EmulatorCommand};
+use c8rs_core::{EmulatorCommand, PendingReply};
 use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::{
-    prelude::*,
-    widgets::{block, Block},
-};
+use ratatui::{prelude::*, widgets::Block};
 
 use crate::app::AppState;
 
@@ -13,9 +10,25 @@ use super::Component;
 pub struct DebuggerComponent {
     focused: bool,
 
-    history: Vec<String>,
+    history: Vec<HistoryEntry>,
     input: String,
     cursor_pos: usize,
+
+    /// Submitted commands still waiting for the emulator to respond, with
+    /// the history entry their result belongs to. The emulator handles
+    /// commands in order so the results arrive in this order too.
+    pending: Vec<(usize, PendingReply)>,
+}
+
+/// Submitted input followed by the results of the commands it ran
+struct HistoryEntry {
+    input: String,
+    output: Vec<Output>,
+}
+
+enum Output {
+    Message(String),
+    Error(String),
 }
 
 impl Component for DebuggerComponent {
@@ -42,13 +55,9 @@ impl Component for DebuggerComponent {
     }
 
     fn render(&mut self, f: &mut ratatui::Frame<'_>, area: Rect, state: &AppState) {
-        let start = std::time::Instant::now();
+        self.poll_replies();
 
-        let border_style = if self.focused {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
+        let border_style = state.theme.border_style(self.focused);
 
         let outer_block = Block::bordered()
             .title("[6: Debugger]")
@@ -64,14 +73,25 @@ impl Component for DebuggerComponent {
             unreachable!()
         };
 
-        let history = Text::from_iter((0..history_area.height as isize).rev().map(|i| {
-            let i = self.history.len() as isize - i - 1;
-            if i < 0 {
-                Line::default()
-            } else {
-                Line::from(self.history[i as usize].clone())
-            }
-        }));
+        let lines: Vec<Line> = self
+            .history
+            .iter()
+            .flat_map(|entry| {
+                std::iter::once(Line::styled(
+                    format!("> {}", entry.input),
+                    Style::new().add_modifier(Modifier::BOLD),
+                ))
+                .chain(entry.output.iter().map(|output| match output {
+                    Output::Message(msg) => Line::from(format!("  {msg}")),
+                    Output::Error(err) => Line::styled(format!("  {err}"), state.theme.log_error),
+                }))
+            })
+            .collect();
+        let skip = lines.len().saturating_sub(history_area.height as usize);
+        let padding = (history_area.height as usize).saturating_sub(lines.len());
+        let history = Text::from_iter(
+            std::iter::repeat_n(Line::default(), padding).chain(lines.into_iter().skip(skip)),
+        );
 
         let input_line = Line::from(self.input.to_string());
         let cursor_pos = Position::new(input_area.x + self.cursor_pos as u16, input_area.y);
@@ -83,16 +103,7 @@ impl Component for DebuggerComponent {
             f.set_cursor_position(cursor_pos);
         }
 
-        f.render_widget(
-            outer_block.title(
-                block::Title::from(format!(
-                    "[render: {:.02}ms]",
-                    start.elapsed().as_secs_f64() * 1000.0
-                ))
-                .alignment(Alignment::Right),
-            ),
-            area,
-        );
+        f.render_widget(outer_block, area);
     }
 
     fn has_focus(&self) -> bool {
@@ -102,6 +113,10 @@ impl Component for DebuggerComponent {
     fn set_focus(&mut self, focus: bool) {
         self.focused = focus
     }
+
+    fn captures_input(&self) -> bool {
+        true
+    }
 }
 
 impl DebuggerComponent {
@@ -145,21 +160,49 @@ impl DebuggerComponent {
     }
 
     fn submit(&mut self, state: &AppState) {
-        let input = self.input.clone();
-        self.history.push(input.clone());
-        self.input.clear();
+        let input = std::mem::take(&mut self.input);
         self.cursor_pos = 0;
 
-        let cmd = match DebugCommand::parse_from(&input) {
-            Ok(cmd) => cmd,
+        let cmds = state.controller.aliases().parse(&input);
+        let mut entry = HistoryEntry {
+            input,
+            output: Vec::new(),
+        };
+        let cmds = match cmds {
+            Ok(cmds) => cmds,
             Err(err) => {
-                for line in err.lines() {
-                    self.history.push(line.to_string());
-                }
+                entry
+                    .output
+                    .extend(err.lines().map(|line| Output::Error(line.to_string())));
+                self.history.push(entry);
                 return;
             }
         };
 
-        let _ = state.controller.send(EmulatorCommand::DebugCommand(cmd));
+        self.history.push(entry);
+        let index = self.history.len() - 1;
+        for cmd in cmds {
+            self.pending
+                .push((index, state.request(EmulatorCommand::DebugCommand(cmd))));
+        }
+    }
+
+    /// Move the results of handled commands under their input in the history
+    fn poll_replies(&mut self) {
+        while let Some((index, reply)) = self.pending.first() {
+            let Some(result) = reply.try_get() else {
+                break;
+            };
+            let output = &mut self.history[*index].output;
+            match result {
+                Ok(res) => output.extend(
+                    res.to_string()
+                        .lines()
+                        .map(|line| Output::Message(line.to_string())),
+                ),
+                Err(err) => output.push(Output::Error(format!("error: {err}"))),
+            }
+            self.pending.remove(0);
+        }
     }
 }
@@ -1,16 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use c8rs_core::{Cpu, DebugCommand, EmulatorCommand, Instruction, Memory};
+use c8rs_core::{
+    memory_map::RegionKind, Cpu, DebugCommand, EmulatorCommand, Expr, Instruction, Memory,
+    MemoryMap, SourceMap,
+};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     prelude::*,
     widgets::{block, Block},
 };
 
-use crate::app::AppState;
+use crate::{app::AppState, bindings::Action, theme::Theme};
 
 use super::Component;
 
+/// Longest label shown before the address, longer ones are cut off
+const MAX_LABEL_WIDTH: u16 = 16;
+
 #[derive(Default)]
 pub struct DisassemblyComponent {
     focused: bool,
@@ -18,6 +24,51 @@ pub struct DisassemblyComponent {
     addr: u16,
     input: String,
     prev_mode: Mode,
+    /// Last search, repeated by the next and previous match keys
+    search: String,
+    /// Instructions that fit in the panel at the last render
+    rows: u16,
+    /// Dim the program's instructions that haven't run
+    coverage: bool,
+    cache: LineCache,
+}
+
+/// Decoded lines of the visible window, reused until the window moves or
+/// memory is written to
+#[derive(Default)]
+struct LineCache {
+    key: Option<(u64, usize, usize)>,
+    lines: Vec<DisasmLine>,
+}
+
+struct DisasmLine {
+    addr: u16,
+    word: u16,
+    inst: Instruction,
+}
+
+impl LineCache {
+    fn update(&mut self, mem: &Memory, start_addr: usize, rows: usize) -> &[DisasmLine] {
+        let key = Some((mem.generation(), start_addr, rows));
+
+        if self.key != key {
+            self.key = key;
+            self.lines = (start_addr..start_addr + rows * 2)
+                .step_by(2)
+                .map(|addr| {
+                    let addr = addr as u16;
+                    let word = mem.read_u16(addr);
+                    DisasmLine {
+                        addr,
+                        word,
+                        inst: Instruction::parse(word),
+                    }
+                })
+                .collect();
+        }
+
+        &self.lines
+    }
 }
 
 #[derive(Default, Copy, Clone, PartialEq)]
@@ -26,32 +77,90 @@ enum Mode {
     Follow,
     Manual,
     GotoInput,
+    /// Typing an instruction to write over the one at `addr`
+    PatchInput,
+    SearchInput,
 }
 
 impl Component for DisassemblyComponent {
     fn handle_key_event(&mut self, event: KeyEvent, state: &AppState) -> bool {
         match self.mode {
             Mode::Follow | Mode::Manual => {
-                match event.code {
-                    KeyCode::Char('f') => self.mode = Mode::Follow,
-                    KeyCode::Char('j') => {
+                let Some(action) = state.config.bindings.action(&event, &Action::DISASSEMBLY)
+                else {
+                    return false;
+                };
+                match action {
+                    Action::Follow => self.mode = Mode::Follow,
+                    Action::Down => {
                         self.mode = Mode::Manual;
                         self.addr = self.addr.saturating_add(2);
                     }
-                    KeyCode::Char('k') => {
+                    Action::Up => {
                         self.mode = Mode::Manual;
                         self.addr = self.addr.saturating_sub(2);
                     }
-                    KeyCode::Char('b') => {
-                        let _ = state.controller.send(EmulatorCommand::DebugCommand(
-                            DebugCommand::Breakpoint { addr: self.addr },
-                        ));
+                    Action::PageDown => self.move_by(state.controller.memory(), self.page()),
+                    Action::PageUp => self.move_by(state.controller.memory(), -self.page()),
+                    Action::HalfPageDown => {
+                        self.move_by(state.controller.memory(), self.page() / 2)
+                    }
+                    Action::HalfPageUp => self.move_by(state.controller.memory(), -self.page() / 2),
+                    Action::Top => self.move_by(state.controller.memory(), -(self.addr as i32)),
+                    Action::Bottom => {
+                        let mem = state.controller.memory();
+                        self.move_by(mem, mem.size() as i32)
+                    }
+                    Action::Breakpoint => {
+                        state.send(EmulatorCommand::DebugCommand(DebugCommand::Breakpoint {
+                            addr: Expr::Num(self.addr),
+                            condition: None,
+                        }));
                     }
-                    KeyCode::Char('g') => {
+                    Action::Goto => {
                         self.prev_mode = self.mode;
                         self.mode = Mode::GotoInput;
                         self.input.clear();
                     }
+                    Action::Edit => {
+                        self.prev_mode = self.mode;
+                        self.mode = Mode::PatchInput;
+                        let word = state.controller.memory().read_u16(self.addr);
+                        self.input = Instruction::parse(word).to_string();
+                    }
+                    Action::Search => {
+                        self.prev_mode = self.mode;
+                        self.mode = Mode::SearchInput;
+                        self.input.clear();
+                    }
+                    Action::Coverage => self.coverage = !self.coverage,
+                    Action::NextMatch => self.jump_to_match(state.controller.memory(), true),
+                    Action::PrevMatch => self.jump_to_match(state.controller.memory(), false),
+                    _ => return false,
+                }
+                true
+            }
+            Mode::PatchInput => {
+                match event.code {
+                    KeyCode::Char(c) => self.input.push(c),
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Esc => {
+                        self.mode = self.prev_mode;
+                    }
+                    KeyCode::Enter => {
+                        self.mode = self.prev_mode;
+                        match self.input.parse::<Instruction>() {
+                            Ok(instr) => {
+                                state.send(EmulatorCommand::DebugCommand(DebugCommand::Patch {
+                                    addr: self.addr,
+                                    instr,
+                                }));
+                            }
+                            Err(err) => log::warn!("Can't patch {:#06X}: {err}", self.addr),
+                        }
+                    }
                     _ => return false,
                 }
                 true
@@ -68,7 +177,9 @@ impl Component for DisassemblyComponent {
                     KeyCode::Enter => {
                         self.mode = Mode::Manual;
                         let input = self.input.trim_start_matches("0x");
-                        if let Ok(addr) = u16::from_str_radix(input, 16) {
+                        if let Some(addr) = state.controller.source_map().addr(&self.input) {
+                            self.addr = addr;
+                        } else if let Ok(addr) = u16::from_str_radix(input, 16) {
                             self.addr = addr;
                         }
                     }
@@ -76,19 +187,33 @@ impl Component for DisassemblyComponent {
                 }
                 true
             }
+            Mode::SearchInput => {
+                match event.code {
+                    KeyCode::Char(c) => self.input.push(c),
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Esc => {
+                        self.mode = self.prev_mode;
+                    }
+                    KeyCode::Enter => {
+                        self.mode = self.prev_mode;
+                        self.search = std::mem::take(&mut self.input);
+                        self.jump_to_match(state.controller.memory(), true);
+                    }
+                    _ => return false,
+                }
+                true
+            }
         }
     }
 
     fn render(&mut self, f: &mut Frame<'_>, area: Rect, state: &AppState) {
-        let start = std::time::Instant::now();
-
-        let border_style = if self.focused {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
+        let border_style = state.theme.border_style(self.focused);
+        let cpu = state.controller.cpu();
+        let mem = state.controller.memory();
 
-        let outer_block = Block::bordered()
+        let mut outer_block = Block::bordered()
             .title("[3: Disassembly]")
             .title(
                 block::Title::from(self.render_status_line())
@@ -96,35 +221,45 @@ impl Component for DisassemblyComponent {
                     .alignment(Alignment::Right),
             )
             .border_style(border_style);
+        if self.coverage {
+            let (executed, total) = cpu.coverage();
+            let percent = executed as f64 * 100.0 / total.max(1) as f64;
+            outer_block = outer_block.title(
+                block::Title::from(format!("[coverage: {percent:.1}% of {total} bytes]"))
+                    .position(block::Position::Bottom)
+                    .alignment(Alignment::Left),
+            );
+        }
         let block_area = outer_block.inner(area);
-
-        let cpu = state.controller.cpu();
+        self.rows = block_area.height;
 
         if self.mode == Mode::Follow {
             self.addr = cpu.pc;
         }
 
+        let mem_size = mem.size();
+        let rows = (block_area.height as usize).min(mem_size / 2);
+        let start_addr =
+            (self.addr.saturating_sub(block_area.height) as usize).min(mem_size - rows * 2) & !1;
+
         f.render_widget(
             DisassemblyWidget {
                 cpu,
-                mem: state.controller.memory(),
+                lines: self.cache.update(mem, start_addr, rows),
                 addr: self.addr,
                 mode: self.mode,
                 breakpoints: state.controller.breakpoints(),
+                tracepoints: state.controller.tracepoints(),
+                map: state.controller.memory_map(),
+                source_map: state.controller.source_map(),
+                search: &normalize(&self.search),
+                coverage: self.coverage,
+                theme: &state.theme,
             },
             block_area,
         );
 
-        f.render_widget(
-            outer_block.title(
-                block::Title::from(format!(
-                    "[render: {:.02}ms]",
-                    start.elapsed().as_secs_f64() * 1000.0
-                ))
-                .alignment(Alignment::Right),
-            ),
-            area,
-        );
+        f.render_widget(outer_block, area);
     }
 
     fn has_focus(&self) -> bool {
@@ -134,6 +269,23 @@ impl Component for DisassemblyComponent {
     fn set_focus(&mut self, focus: bool) {
         self.focused = focus
     }
+
+    fn captures_input(&self) -> bool {
+        matches!(
+            self.mode,
+            Mode::GotoInput | Mode::PatchInput | Mode::SearchInput
+        )
+    }
+
+    fn show_addr(&mut self, addr: u16) {
+        match self.mode {
+            Mode::Follow | Mode::Manual => self.mode = Mode::Manual,
+            // the patch being typed is for the instruction under the cursor
+            Mode::PatchInput => return,
+            Mode::GotoInput | Mode::SearchInput => self.prev_mode = Mode::Manual,
+        }
+        self.addr = addr;
+    }
 }
 
 impl DisassemblyComponent {
@@ -142,16 +294,106 @@ impl DisassemblyComponent {
             Mode::Follow => "[addr: PC]".to_string(),
             Mode::Manual => format!("[addr: {:#06X}]", self.addr),
             Mode::GotoInput => format!("[goto: {}]", self.input),
+            Mode::PatchInput => format!("[patch {:#06X}: {}]", self.addr, self.input),
+            Mode::SearchInput => format!("[search: {}]", self.input),
+        }
+    }
+
+    fn page(&self) -> i32 {
+        self.rows.max(1) as i32
+    }
+
+    /// Move the cursor by `rows` instructions, stopping at the ends of memory
+    fn move_by(&mut self, mem: &Memory, rows: i32) {
+        self.mode = Mode::Manual;
+        let last = mem.size() as i32 - 2;
+        self.addr = (self.addr as i32 + rows * 2).clamp(0, last) as u16;
+    }
+
+    /// Move to the next or previous instruction after `addr` matching the
+    /// last search, wrapping around at the end of memory
+    fn jump_to_match(&mut self, mem: &Memory, forward: bool) {
+        if self.search.is_empty() {
+            return;
         }
+
+        let query = normalize(&self.search);
+        let count = mem.size() / 2;
+        let start = self.addr as usize / 2;
+        let found = (1..=count)
+            .map(|i| {
+                let n = if forward {
+                    start + i
+                } else {
+                    start + count - i
+                };
+                (n % count * 2) as u16
+            })
+            .find(|&addr| {
+                normalize(&Instruction::parse(mem.read_u16(addr)).to_string()).contains(&query)
+            });
+
+        match found {
+            Some(addr) => {
+                self.mode = Mode::Manual;
+                self.addr = addr;
+            }
+            None => log::info!("No instruction matches '{}'", self.search),
+        }
+    }
+}
+
+/// `inst` with its address operand replaced by the label there
+pub(super) fn instruction_text(inst: Instruction, source_map: &SourceMap) -> String {
+    let text = inst.to_string();
+    let (Instruction::Jmp { addr }
+    | Instruction::JmpReg { addr }
+    | Instruction::Call { addr }
+    | Instruction::LdI { addr }) = inst
+    else {
+        return text;
+    };
+    match source_map.label(addr) {
+        Some(label) => text.replacen(&format!("{addr:#06X}"), label, 1),
+        None => text,
     }
 }
 
+/// Lowercase with the leading zeros of hex numbers dropped, so `0x300` finds
+/// `LD I, 0x0300`
+fn normalize(text: &str) -> String {
+    let text = text.to_lowercase();
+    let mut normalized = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(i) = rest.find("0x") {
+        normalized.push_str(&rest[..i + 2]);
+        let digits = rest[i + 2..].trim_start_matches('0');
+        // all zeros
+        if digits.len() < rest[i + 2..].len()
+            && !digits.starts_with(|c: char| c.is_ascii_hexdigit())
+        {
+            normalized.push('0');
+        }
+        rest = digits;
+    }
+    normalized.push_str(rest);
+    normalized
+}
+
 struct DisassemblyWidget<'a> {
     cpu: &'a Cpu,
-    mem: &'a Memory,
+    lines: &'a [DisasmLine],
     addr: u16,
     mode: Mode,
     breakpoints: &'a HashSet<u16>,
+    tracepoints: &'a HashMap<u16, String>,
+    map: &'a MemoryMap,
+    source_map: &'a SourceMap,
+    /// Normalized last search, matching instructions are highlighted
+    search: &'a str,
+    /// Dim the program's instructions that haven't run
+    coverage: bool,
+    theme: &'a Theme,
 }
 
 impl Widget for DisassemblyWidget<'_> {
@@ -161,22 +403,31 @@ impl Widget for DisassemblyWidget<'_> {
     {
         let Cpu { pc, .. } = self.cpu;
 
-        let start_addr =
-            (self.addr.saturating_sub(area.height)).min(0x1000 - area.height * 2) & 0xFFFE;
-        let end_addr = (start_addr + area.height * 2) & 0xFFFE;
-
-        for (row, addr) in (start_addr..end_addr).step_by(2).enumerate() {
-            let word = self.mem.read_u16(addr);
+        // labels get a column before the address, as wide as the longest one
+        let label_width = self
+            .source_map
+            .labels()
+            .map(|(name, _)| name.len() as u16)
+            .max()
+            .map_or(0, |len| len.min(MAX_LABEL_WIDTH) + 2);
+        let x = area.x + label_width;
 
+        for (row, &DisasmLine { addr, word, inst }) in self.lines.iter().enumerate() {
             let high_byte = (word >> 8) as u8;
             let low_byte = (word & 0xFF) as u8;
 
-            let inst = Instruction::parse(word);
-
             let line_style = if addr == *pc {
-                Style::new().black().on_green()
-            } else if self.mode == Mode::Manual && addr == self.addr {
-                Style::new().black().on_blue()
+                self.theme.pc_line
+            } else if matches!(self.mode, Mode::Manual | Mode::PatchInput) && addr == self.addr {
+                self.theme.cursor_line
+            } else if self.coverage
+                && !self.cpu.was_executed(addr)
+                && self
+                    .map
+                    .region(addr)
+                    .is_some_and(|r| r.kind == RegionKind::Program)
+            {
+                self.theme.comment
             } else {
                 Style::default()
             };
@@ -185,30 +436,56 @@ impl Widget for DisassemblyWidget<'_> {
 
             buf.set_style(Rect::new(area.x, y, area.width, 1), line_style);
 
+            if let Some(label) = self.source_map.label(addr) {
+                buf.set_span(
+                    area.x + 2,
+                    y,
+                    &Span::styled(format!("{label}:"), self.theme.label),
+                    label_width - 1,
+                );
+            }
+
+            let width = area.width.saturating_sub(label_width);
+            buf.set_span(x + 2, y, &Span::from(format!("{addr:#06X}")), width);
+            buf.set_span(x + 9, y, &Span::from(format!("{high_byte:02X}")), width);
+            buf.set_span(x + 12, y, &Span::from(format!("{low_byte:02X}")), width);
+            // searches match what the instruction is without labels
+            let inst_style =
+                if !self.search.is_empty() && normalize(&inst.to_string()).contains(self.search) {
+                    self.theme.accent
+                } else {
+                    Style::default()
+                };
             buf.set_span(
-                area.x + 2,
-                y,
-                &Span::from(format!("{addr:#06X}")),
-                area.width,
-            );
-            buf.set_span(
-                area.x + 9,
-                y,
-                &Span::from(format!("{high_byte:02X}")),
-                area.width,
-            );
-            buf.set_span(
-                area.x + 12,
+                x + 15,
                 y,
-                &Span::from(format!("{low_byte:02X}")),
-                area.width,
+                &Span::styled(instruction_text(inst, self.source_map), inst_style),
+                width,
             );
-            buf.set_span(area.x + 15, y, &Span::from(format!("{inst}")), area.width);
+
+            // label where regions start, data there isn't meant to be code
+            if let Some(region) = self
+                .map
+                .regions()
+                .iter()
+                .rev()
+                .find(|r| r.start & !1 == addr || (row == 0 && r.contains(addr)))
+            {
+                buf.set_span(
+                    x + 34,
+                    y,
+                    &Span::styled(format!("; {}", region.kind), self.theme.comment),
+                    width.saturating_sub(34),
+                );
+            }
 
             if self.breakpoints.contains(&addr) {
                 if let Some(cell) = buf.cell_mut(Position { x: area.x, y }) {
-                    cell.set_symbol("●");
-                    cell.set_fg(Color::Red);
+                    cell.set_symbol("●").set_style(self.theme.breakpoint);
+                }
+            } else if self.tracepoints.contains_key(&addr) {
+                if let Some(cell) = buf.cell_mut(Position { x: area.x, y }) {
+                    cell.set_symbol("◆").set_style(self.theme.tracepoint);
                 }
             }
         }
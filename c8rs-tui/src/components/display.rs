@@ -1,34 +1,68 @@
+use c8rs_core::{DebugCommand, EmulatorCommand, EmulatorState, KeyAction};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
+    layout::Flex,
     prelude::*,
     widgets::{block, Block},
 };
 
-use crate::app::AppState;
+use crate::{app::AppState, bindings::Action, capabilities::DisplayRenderer, theme::Theme};
 
 use super::Component;
 
+/// Frames a mapped key is held for when typed
+pub(crate) const KEY_TAP_FRAMES: u32 = 5;
+
 #[derive(Default)]
 pub struct DisplayComponent {
     focused: bool,
 }
 
 impl Component for DisplayComponent {
-    fn handle_key_event(&mut self, _event: crossterm::event::KeyEvent, _: &AppState) -> bool {
-        false
+    fn handle_key_event(&mut self, event: KeyEvent, state: &AppState) -> bool {
+        let KeyCode::Char(c) = event.code else {
+            return false;
+        };
+        if event
+            .modifiers
+            .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+        {
+            return false;
+        }
+        let Some(key) = state.config.keypad_key(c) else {
+            return false;
+        };
+
+        // the terminal only reports presses, so hold the key for a few frames
+        state.send(EmulatorCommand::DebugCommand(DebugCommand::Key {
+            action: KeyAction::Tap {
+                key,
+                frames: KEY_TAP_FRAMES,
+            },
+        }));
+        true
     }
 
     fn render(&mut self, f: &mut Frame<'_>, area: Rect, state: &AppState) {
-        let start = std::time::Instant::now();
-
-        let border_style = if self.focused {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
+        let border_style = state.theme.border_style(self.focused);
 
         let outer_block = Block::bordered()
             .title("[1: CHIP-8]")
             .border_style(border_style);
+        let outer_block = if state.play_mode {
+            outer_block.title(
+                block::Title::from(Span::styled(
+                    format!(
+                        "[PLAY - {} to exit]",
+                        state.config.bindings.key(Action::PlayMode)
+                    ),
+                    state.theme.accent,
+                ))
+                .alignment(Alignment::Center),
+            )
+        } else {
+            outer_block
+        };
         let block_area = outer_block.inner(area);
 
         let display = state.controller.display();
@@ -39,20 +73,35 @@ impl Component for DisplayComponent {
             DisplayWidget {
                 pixels: &pixels,
                 width,
+                renderer: state.renderer,
+                theme: &state.theme,
             },
             block_area,
         );
 
-        f.render_widget(
-            outer_block.title(
-                block::Title::from(format!(
-                    "[render: {:.02} ms]",
-                    start.elapsed().as_secs_f64() * 1000.0
-                ))
-                .alignment(Alignment::Right),
-            ),
-            area,
-        );
+        // across the top of the screen, hiding as little of it as possible
+        let banner = match state.emulator_state {
+            EmulatorState::Running => None,
+            EmulatorState::Paused => Some(("PAUSED", state.theme.warning)),
+            EmulatorState::Halted => Some(("HALTED", state.theme.comment)),
+            EmulatorState::Faulted => Some(("FAULTED", state.theme.log_error)),
+        };
+        if let Some((status, style)) = banner {
+            let text = match &state.stop_reason {
+                Some(reason) => format!(" {status}: {reason} "),
+                None => format!(" {status} "),
+            };
+            let line = Line::styled(text, style.reversed());
+            let [banner_area] = Layout::horizontal([Constraint::Length(line.width() as u16)])
+                .flex(Flex::Center)
+                .areas(Rect {
+                    height: 1,
+                    ..block_area
+                });
+            f.render_widget(line, banner_area);
+        }
+
+        f.render_widget(outer_block, area);
     }
 
     fn has_focus(&self) -> bool {
@@ -67,6 +116,8 @@ impl Component for DisplayComponent {
 struct DisplayWidget<'a> {
     pixels: &'a [bool],
     width: usize,
+    renderer: DisplayRenderer,
+    theme: &'a Theme,
 }
 
 impl Widget for DisplayWidget<'_> {
@@ -74,6 +125,18 @@ impl Widget for DisplayWidget<'_> {
     where
         Self: Sized,
     {
+        match self.renderer {
+            DisplayRenderer::HalfBlock | DisplayRenderer::Glyph => {
+                self.render_half_cells(area, buf)
+            }
+            DisplayRenderer::Quadrant | DisplayRenderer::Braille => self.render_cells(area, buf),
+        }
+    }
+}
+
+impl DisplayWidget<'_> {
+    /// Draw two pixels per cell, one above the other
+    fn render_half_cells(self, area: Rect, buf: &mut Buffer) {
         for (i, pixel) in self.pixels.iter().enumerate() {
             let x = i % self.width;
             let y = i / self.width;
@@ -83,13 +146,89 @@ impl Widget for DisplayWidget<'_> {
                 continue;
             };
 
-            let color = if *pixel { Color::White } else { Color::Black };
+            match self.renderer {
+                DisplayRenderer::HalfBlock => {
+                    let color = if *pixel {
+                        self.theme.palette.pixel_on()
+                    } else {
+                        self.theme.palette.off
+                    };
+
+                    if y.is_multiple_of(2) {
+                        cell.set_bg(color);
+                    } else {
+                        cell.set_fg(color).set_symbol("▄");
+                    }
+                }
+                _ => {
+                    if y.is_multiple_of(2) {
+                        cell.set_symbol(if *pixel { "▀" } else { " " });
+                    } else {
+                        let top = cell.symbol() == "▀";
+                        cell.set_symbol(match (top, *pixel) {
+                            (true, true) => "█",
+                            (true, false) => "▀",
+                            (false, true) => "▄",
+                            (false, false) => " ",
+                        });
+                    }
+                }
+            }
+        }
+    }
 
-            if y % 2 == 0 {
-                cell.set_bg(color);
-            } else {
-                cell.set_fg(color).set_symbol("▄");
+    /// Draw several pixels per cell with a character showing which are on
+    fn render_cells(self, area: Rect, buf: &mut Buffer) {
+        let (cell_width, cell_height) = self.renderer.cell_size();
+        let (cell_width, cell_height) = (cell_width as usize, cell_height as usize);
+        let height = self.pixels.len() / self.width;
+
+        for row in 0..(area.height as usize).min(height.div_ceil(cell_height)) {
+            for col in 0..(area.width as usize).min(self.width.div_ceil(cell_width)) {
+                let mut dots = 0;
+                for dy in 0..cell_height {
+                    for dx in 0..cell_width {
+                        let (x, y) = (col * cell_width + dx, row * cell_height + dy);
+                        if x < self.width && y < height && self.pixels[y * self.width + x] {
+                            dots |= dot_bit(self.renderer, dx, dy);
+                        }
+                    }
+                }
+
+                let symbol = match self.renderer {
+                    DisplayRenderer::Quadrant => QUADRANTS[dots as usize],
+                    _ => char::from_u32(BRAILLE_BLANK + dots).unwrap_or(' '),
+                };
+                if let Some(cell) =
+                    buf.cell_mut((area.left() + col as u16, area.top() + row as u16))
+                {
+                    cell.set_char(symbol)
+                        .set_fg(self.theme.palette.pixel_on())
+                        .set_bg(self.theme.palette.off);
+                }
             }
         }
     }
 }
+
+/// Quadrant characters, indexed by the bits of the top left, top right,
+/// bottom left and bottom right pixels
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Braille pattern without dots, the dots are the low 8 bits
+const BRAILLE_BLANK: u32 = 0x2800;
+
+/// Bit of the pixel at `dx`, `dy` in a cell's character index
+fn dot_bit(renderer: DisplayRenderer, dx: usize, dy: usize) -> u32 {
+    match renderer {
+        DisplayRenderer::Quadrant => 1 << (dy * 2 + dx),
+        // braille numbers the dots down the left column first, the bottom
+        // row was added later and comes last
+        _ => match (dx, dy) {
+            (_, 3) => 0x40 << dx,
+            _ => 1 << (dx * 3 + dy),
+        },
+    }
+}
@@ -1,67 +1,46 @@
 use crossterm::event::{KeyCode, KeyEvent};
+use log::{Level, LevelFilter};
 use ratatui::{
     prelude::*,
     widgets::{block, Block},
 };
-use tui_logger::TuiLoggerWidget;
 
-use crate::app::AppState;
+use crate::{
+    app::AppState,
+    bindings::{Action, KeyBindings},
+    logger::{self, LogRecord},
+    theme::Theme,
+};
 
 use super::Component;
 
 #[derive(Default)]
 pub struct LogComponent {
     focused: bool,
-    state: tui_logger::TuiWidgetState,
+    view: LogView,
 }
 
 impl Component for LogComponent {
-    fn handle_key_event(&mut self, event: KeyEvent, _: &AppState) -> bool {
-        let log_event = match event.code {
-            KeyCode::Char('j') => tui_logger::TuiWidgetEvent::NextPageKey,
-            KeyCode::Char('k') => tui_logger::TuiWidgetEvent::PrevPageKey,
-            _ => return false,
-        };
-
-        self.state.transition(log_event);
-
-        true
+    fn handle_key_event(&mut self, event: KeyEvent, state: &AppState) -> bool {
+        self.view.handle_key_event(event, &state.config.bindings)
     }
 
-    fn render(&mut self, f: &mut Frame<'_>, area: Rect, _: &AppState) {
-        let start = std::time::Instant::now();
+    fn render(&mut self, f: &mut Frame<'_>, area: Rect, state: &AppState) {
+        let border_style = state.theme.border_style(self.focused);
 
-        let border_style = if self.focused {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
+        let outer_block = Block::bordered()
+            .title("[5: Log]")
+            .title(
+                block::Title::from(self.view.status_line())
+                    .position(block::Position::Bottom)
+                    .alignment(Alignment::Right),
+            )
+            .style(border_style);
 
-        let outer_block = Block::bordered().title("[5: Log]").style(border_style);
-
-        f.render_widget(
-            TuiLoggerWidget::default()
-                .output_separator('|')
-                .output_timestamp(Some("%H:%M:%S%.3f".to_string()))
-                .style_error(Style::default().fg(Color::Red))
-                .style_debug(Style::default().fg(Color::Green))
-                .style_warn(Style::default().fg(Color::Yellow))
-                .style_trace(Style::default().fg(Color::Magenta))
-                .style_info(Style::default().fg(Color::Cyan))
-                .state(&self.state),
-            outer_block.inner(area),
-        );
+        self.view
+            .render(f.buffer_mut(), outer_block.inner(area), &state.theme);
 
-        f.render_widget(
-            outer_block.title(
-                block::Title::from(format!(
-                    "[render: {:.02}ms]",
-                    start.elapsed().as_secs_f64() * 1000.0
-                ))
-                .alignment(Alignment::Right),
-            ),
-            area,
-        )
+        f.render_widget(outer_block, area)
     }
 
     fn has_focus(&self) -> bool {
@@ -72,7 +51,226 @@ impl Component for LogComponent {
         self.focused = focus;
 
         if !focus {
-            self.state.transition(tui_logger::TuiWidgetEvent::EscapeKey);
+            self.view.reset();
         }
     }
+
+    fn captures_input(&self) -> bool {
+        self.view.is_editing()
+    }
+}
+
+/// Scrollable list of log records that can be filtered by level, target and
+/// text, shared by the log panel and the remote log viewer
+pub(crate) struct LogView {
+    /// Newest record shown, `None` follows new records
+    anchor: Option<u64>,
+    /// Anchors half a page up and down, found while rendering
+    prev_page: Option<u64>,
+    next_page: Option<u64>,
+    /// Most verbose level shown
+    level: LevelFilter,
+    /// Crate whose records are shown, all of them if `None`
+    target: Option<String>,
+    /// Only records containing this are shown
+    filter: String,
+    /// Typing edits the filter
+    editing: bool,
+}
+
+impl Default for LogView {
+    fn default() -> LogView {
+        LogView {
+            anchor: None,
+            prev_page: None,
+            next_page: None,
+            level: LevelFilter::Trace,
+            target: None,
+            filter: String::new(),
+            editing: false,
+        }
+    }
+}
+
+impl LogView {
+    pub(crate) fn is_editing(&self) -> bool {
+        self.editing
+    }
+
+    /// Follow new records again and stop editing the filter
+    pub(crate) fn reset(&mut self) {
+        self.anchor = None;
+        self.editing = false;
+    }
+
+    pub(crate) fn handle_key_event(&mut self, event: KeyEvent, bindings: &KeyBindings) -> bool {
+        if self.editing {
+            match event.code {
+                KeyCode::Char(c) => self.filter.push(c),
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                }
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => {
+                    self.editing = false;
+                    self.filter.clear();
+                }
+                _ => return false,
+            }
+            self.anchor = None;
+            return true;
+        }
+
+        match bindings.action(&event, &Action::LOG) {
+            Some(Action::Down) => self.anchor = self.next_page,
+            Some(Action::Up) => self.anchor = self.prev_page,
+            Some(Action::LogLevel) => {
+                self.level = match self.level {
+                    LevelFilter::Trace => LevelFilter::Debug,
+                    LevelFilter::Debug => LevelFilter::Info,
+                    LevelFilter::Info => LevelFilter::Warn,
+                    LevelFilter::Warn => LevelFilter::Error,
+                    LevelFilter::Error | LevelFilter::Off => LevelFilter::Trace,
+                };
+                self.anchor = None;
+            }
+            Some(Action::LogTarget) => {
+                self.target = self.next_target();
+                self.anchor = None;
+            }
+            Some(Action::LogFilter) => self.editing = true,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Crate after the shown one among those that logged something, wrapping
+    /// around to all of them
+    fn next_target(&self) -> Option<String> {
+        let mut crates: Vec<String> = logger::with_records(|records| {
+            records
+                .iter()
+                .map(|record| crate_name(&record.target).to_string())
+                .collect()
+        });
+        crates.sort();
+        crates.dedup();
+
+        match &self.target {
+            Some(target) => crates.into_iter().find(|name| name > target),
+            None => crates.into_iter().next(),
+        }
+    }
+
+    fn shows(&self, record: &LogRecord) -> bool {
+        record.level <= self.level
+            && self
+                .target
+                .as_ref()
+                .is_none_or(|target| crate_name(&record.target) == target)
+            && record
+                .msg
+                .to_lowercase()
+                .contains(&self.filter.to_lowercase())
+    }
+
+    pub(crate) fn status_line(&self) -> String {
+        let mut status = match self.level {
+            LevelFilter::Trace => "level: all".to_string(),
+            level => format!("level: {}", level.as_str().to_lowercase()),
+        };
+        if let Some(target) = &self.target {
+            status.push_str(&format!(" | target: {target}"));
+        }
+        if self.editing || !self.filter.is_empty() {
+            status.push_str(&format!(" | filter: {}", self.filter));
+        }
+        if self.anchor.is_some() {
+            status.push_str(" | scrolled");
+        }
+        format!("[{status}]")
+    }
+
+    pub(crate) fn render(&mut self, buf: &mut Buffer, area: Rect, theme: &Theme) {
+        let width = area.width as usize;
+        let height = area.height as usize;
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        logger::with_records(|records| {
+            // newest first, each tagged with its record
+            let mut rows: Vec<(u64, Line)> = Vec::new();
+            for record in records
+                .iter()
+                .rev()
+                .filter(|record| self.anchor.is_none_or(|anchor| record.id <= anchor))
+                .filter(|record| self.shows(record))
+            {
+                let record_rows = format_record(record, width, theme);
+                rows.extend(record_rows.into_iter().rev().map(|row| (record.id, row)));
+                if rows.len() >= height {
+                    break;
+                }
+            }
+            rows.truncate(height);
+
+            // a page that isn't full already shows the oldest record
+            self.prev_page = match rows.get(height / 2) {
+                Some(&(id, _)) if rows.len() == height => Some(id),
+                _ => self.anchor,
+            };
+            self.next_page = self.anchor.and_then(|anchor| {
+                let mut count = 0;
+                records
+                    .iter()
+                    .filter(|record| record.id > anchor && self.shows(record))
+                    .find(|record| {
+                        count += format_record(record, width, theme).len();
+                        count >= height / 2
+                    })
+                    .map(|record| record.id)
+            });
+
+            for (y, (_, row)) in rows.iter().rev().enumerate() {
+                buf.set_line(area.x, area.y + y as u16, row, area.width);
+            }
+        });
+    }
+}
+
+/// First part of a record's target, `c8rs_core` for `c8rs_core::emulator`
+fn crate_name(target: &str) -> &str {
+    target.split("::").next().unwrap_or(target)
+}
+
+/// Rows of `record`, with the message's lines wrapped to `width` and indented
+/// past the timestamp
+fn format_record(record: &LogRecord, width: usize, theme: &Theme) -> Vec<Line<'static>> {
+    let style = match record.level {
+        Level::Error => theme.log_error,
+        Level::Warn => theme.log_warn,
+        Level::Info => theme.log_info,
+        Level::Debug => theme.log_debug,
+        Level::Trace => theme.log_trace,
+    };
+
+    let timestamp = record.time.format("%H:%M:%S%.3f|").to_string();
+    let indent = timestamp.len();
+    let mut rows = Vec::new();
+    for (i, line) in record.msg.lines().enumerate() {
+        let text: Vec<char> = if i == 0 {
+            format!("{timestamp}{line}").chars().collect()
+        } else {
+            format!("{:indent$}{line}", "").chars().collect()
+        };
+        rows.extend(
+            text.chunks(width)
+                .map(|chunk| Line::styled(chunk.iter().collect::<String>(), style)),
+        );
+    }
+    if rows.is_empty() {
+        rows.push(Line::styled(timestamp, style));
+    }
+    rows
 }
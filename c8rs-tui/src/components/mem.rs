@@ -1,14 +1,23 @@
-use c8rs_core::{Cpu, Memory};
+use std::collections::HashMap;
+
+use c8rs_core::{Cpu, DebugCommand, EmulatorCommand, Memory, MemoryMap};
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::{
     prelude::*,
     widgets::{block, Block},
 };
 
-use crate::app::AppState;
+use crate::{app::AppState, bindings::Action, theme::Theme};
 
 use super::Component;
 
+/// Memory changes a written byte stays highlighted for
+const FADE_STEPS: usize = 3;
+
+/// Columns of the hex view's ASCII column and region labels
+const ASCII_COLUMN: u16 = 61;
+const LABEL_COLUMN: u16 = ASCII_COLUMN + 17;
+
 #[derive(Default)]
 pub struct MemoryComponent {
     focused: bool,
@@ -16,6 +25,101 @@ pub struct MemoryComponent {
     mode: Mode,
     view: View,
     input: String,
+    /// Byte being edited in [`Mode::Edit`]
+    cursor: u16,
+    changes: ChangeTracker,
+    /// Register the view scrolls to on every render
+    follow: Option<Follow>,
+    /// Rows of bytes that fit in the panel at the last render
+    rows: u16,
+    /// Color the hex view by execution counts instead
+    heatmap: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Follow {
+    Pc,
+    I,
+    Sp,
+}
+
+impl Follow {
+    /// Target after this one, `None` after the last
+    fn next(follow: Option<Follow>) -> Option<Follow> {
+        match follow {
+            None => Some(Follow::Pc),
+            Some(Follow::Pc) => Some(Follow::I),
+            Some(Follow::I) => Some(Follow::Sp),
+            Some(Follow::Sp) => None,
+        }
+    }
+
+    fn addr(&self, cpu: &Cpu) -> u16 {
+        match self {
+            Follow::Pc => cpu.pc,
+            Follow::I => cpu.i,
+            Follow::Sp => cpu.sp,
+        }
+    }
+}
+
+impl std::fmt::Display for Follow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Follow::Pc => "PC",
+                Follow::I => "I",
+                Follow::Sp => "SP",
+            }
+        )
+    }
+}
+
+/// Finds written bytes by comparing memory with a snapshot whenever its
+/// generation changes
+#[derive(Default)]
+struct ChangeTracker {
+    generation: Option<u64>,
+    snapshot: Vec<u8>,
+    /// Number of snapshots each byte changed in, by address
+    changed: HashMap<u16, u32>,
+    snapshots: u32,
+}
+
+impl ChangeTracker {
+    fn update(&mut self, mem: &Memory) {
+        if self.generation == Some(mem.generation()) {
+            return;
+        }
+
+        let bytes: Vec<u8> = (0..mem.size())
+            .map(|addr| mem.read_u8(addr as u16))
+            .collect();
+        // nothing to compare with at first or after the memory size changes
+        if self.generation.is_some() && self.snapshot.len() == bytes.len() {
+            self.snapshots += 1;
+            for (addr, (old, new)) in self.snapshot.iter().zip(&bytes).enumerate() {
+                if old != new {
+                    self.changed.insert(addr as u16, self.snapshots);
+                }
+            }
+        }
+
+        let fade = FADE_STEPS as u32;
+        self.changed
+            .retain(|_, snapshot| self.snapshots - *snapshot < fade);
+        self.generation = Some(mem.generation());
+        self.snapshot = bytes;
+    }
+
+    /// Snapshots since `addr` was last written, if recently
+    fn age(&self, addr: u16) -> Option<usize> {
+        self.changed
+            .get(&addr)
+            .map(|snapshot| (self.snapshots - snapshot) as usize)
+    }
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
@@ -23,6 +127,8 @@ enum Mode {
     #[default]
     Normal,
     GotoInput,
+    /// Typing hex digits over the byte under the cursor
+    Edit,
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
@@ -36,27 +142,49 @@ impl Component for MemoryComponent {
     fn handle_key_event(&mut self, event: KeyEvent, state: &AppState) -> bool {
         match self.mode {
             Mode::Normal => {
-                match event.code {
-                    KeyCode::Char('j') => {
-                        let diff = if self.view == View::Sprite { 1 } else { 16 };
-                        self.offset = self.offset.saturating_add(diff).min(0xFF0)
-                    }
-                    KeyCode::Char('k') => {
-                        let diff = if self.view == View::Sprite { 1 } else { 16 };
-                        self.offset = self.offset.saturating_sub(diff)
+                let Some(action) = state.config.bindings.action(&event, &Action::MEMORY) else {
+                    return false;
+                };
+                let mem = state.controller.memory();
+                match action {
+                    Action::Down => self.scroll(mem, 1),
+                    Action::Up => self.scroll(mem, -1),
+                    Action::PageDown => self.scroll(mem, self.page()),
+                    Action::PageUp => self.scroll(mem, -self.page()),
+                    Action::HalfPageDown => self.scroll(mem, self.page() / 2),
+                    Action::HalfPageUp => self.scroll(mem, -self.page() / 2),
+                    Action::Top => self.scroll_to(mem, 0),
+                    Action::Bottom => {
+                        // last page, not just the last row
+                        let last = (mem.max_addr() & !0xF) as i32;
+                        self.scroll_to(mem, last - (self.page() - 1) * self.row_size())
                     }
-                    KeyCode::Char('g') => {
+                    Action::Goto => {
                         self.mode = Mode::GotoInput;
                         self.input.clear();
                     }
-                    KeyCode::Char('i') => {
+                    Action::JumpToI => {
+                        self.follow = None;
                         let Cpu { i, .. } = state.controller.cpu();
-                        self.offset = i & 0xFF0;
+                        self.offset = i & !0xF;
+                    }
+                    Action::Follow => self.follow = Follow::next(self.follow),
+                    Action::Edit if self.view == View::Hex => {
+                        self.mode = Mode::Edit;
+                        self.input.clear();
+                        if !(self.offset..self.offset.saturating_add(16)).contains(&self.cursor) {
+                            self.cursor = self.offset;
+                        }
+                    }
+                    Action::SpriteView => self.view = View::Sprite,
+                    Action::HexView => {
+                        self.view = View::Hex;
+                        self.offset &= !0xF;
                     }
-                    KeyCode::Char('s') => self.view = View::Sprite,
-                    KeyCode::Char('h') => {
+                    Action::Heatmap => {
                         self.view = View::Hex;
-                        self.offset &= 0xFF0;
+                        self.offset &= !0xF;
+                        self.heatmap = !self.heatmap;
                     }
                     _ => return false,
                 }
@@ -74,7 +202,11 @@ impl Component for MemoryComponent {
                     KeyCode::Enter => {
                         self.mode = Mode::Normal;
                         let input = self.input.trim_start_matches("0x");
-                        if let Ok(offset) = u16::from_str_radix(input, 16) {
+                        if let Some(addr) = state.controller.source_map().addr(&self.input) {
+                            self.follow = None;
+                            self.offset = addr & !0xF;
+                        } else if let Ok(offset) = u16::from_str_radix(input, 16) {
+                            self.follow = None;
                             self.offset = offset;
                         }
                     }
@@ -82,17 +214,54 @@ impl Component for MemoryComponent {
                 }
                 true
             }
+            Mode::Edit => {
+                let cursor = self.cursor;
+                let action = state.config.bindings.action(&event, &Action::MEMORY_EDIT);
+                let move_to = match (action, event.code) {
+                    (Some(Action::Left), _) | (_, KeyCode::Left) => Some(cursor.saturating_sub(1)),
+                    (Some(Action::Right), _) | (_, KeyCode::Right) => {
+                        Some(cursor.saturating_add(1))
+                    }
+                    (Some(Action::Up), _) | (_, KeyCode::Up) => Some(cursor.saturating_sub(16)),
+                    (Some(Action::Down), _) | (_, KeyCode::Down) => Some(cursor.saturating_add(16)),
+                    (_, KeyCode::Char(c)) if c.is_ascii_hexdigit() => {
+                        self.input.push(c.to_ascii_uppercase());
+                        if self.input.len() < 2 {
+                            return true;
+                        }
+                        let value = u8::from_str_radix(&self.input, 16).unwrap();
+                        state.send(EmulatorCommand::DebugCommand(DebugCommand::WriteMem {
+                            addr: cursor,
+                            value,
+                        }));
+                        Some(cursor.saturating_add(1))
+                    }
+                    (_, KeyCode::Backspace) => {
+                        self.input.pop();
+                        None
+                    }
+                    (_, KeyCode::Esc | KeyCode::Enter) => {
+                        self.mode = Mode::Normal;
+                        self.input.clear();
+                        None
+                    }
+                    // not a digit, but keeps `q` from quitting mid-edit
+                    (_, KeyCode::Char(_)) => None,
+                    _ => return false,
+                };
+
+                // moving gives up on a half typed byte
+                if let Some(addr) = move_to {
+                    self.cursor = addr.min(state.controller.memory().max_addr());
+                    self.input.clear();
+                }
+                true
+            }
         }
     }
 
     fn render(&mut self, f: &mut Frame<'_>, area: Rect, state: &AppState) {
-        let start = std::time::Instant::now();
-
-        let border_style = if self.focused {
-            Style::default().fg(Color::Green)
-        } else {
-            Style::default()
-        };
+        let border_style = state.theme.border_style(self.focused);
 
         let outer_block = Block::bordered()
             .title("[4: Memory]")
@@ -103,9 +272,33 @@ impl Component for MemoryComponent {
             )
             .border_style(border_style);
         let block_area = outer_block.inner(area);
+        // the hex view has a column header
+        self.rows = match self.view {
+            View::Hex => block_area.height.saturating_sub(1),
+            View::Sprite => block_area.height,
+        };
 
         let cpu = state.controller.cpu();
         let mem = state.controller.memory();
+        self.changes.update(mem);
+
+        // one row of context above the register in the hex view
+        match (self.follow, self.view) {
+            (Some(follow), View::Hex) => self.offset = (follow.addr(cpu) & !0xF).saturating_sub(16),
+            (Some(follow), View::Sprite) => self.offset = follow.addr(cpu),
+            (None, _) => (),
+        }
+
+        // keep the cursor on screen, the first row is the column header
+        if self.mode == Mode::Edit {
+            let rows = block_area.height.saturating_sub(1).max(1);
+            let row = self.cursor & !0xF;
+            if row < self.offset {
+                self.offset = row;
+            } else if row >= self.offset.saturating_add(rows * 16) {
+                self.offset = row - (rows - 1) * 16;
+            }
+        }
 
         match self.view {
             View::Hex => f.render_widget(
@@ -113,24 +306,21 @@ impl Component for MemoryComponent {
                     offset: self.offset,
                     cpu,
                     mem,
+                    map: state.controller.memory_map(),
+                    theme: &state.theme,
+                    cursor: (self.mode == Mode::Edit).then_some((self.cursor, &self.input)),
+                    changes: &self.changes,
+                    heat: self.heatmap.then(|| Heat::new(cpu.exec_counts())),
                 },
                 block_area,
             ),
-            View::Sprite => {
-                f.render_widget(self.render_sprite(cpu, mem, block_area.height), block_area)
-            }
+            View::Sprite => f.render_widget(
+                self.render_sprite(cpu, mem, block_area.height, &state.theme),
+                block_area,
+            ),
         }
 
-        f.render_widget(
-            outer_block.title(
-                block::Title::from(format!(
-                    "[render: {:.02}ms]",
-                    start.elapsed().as_secs_f64() * 1000.0
-                ))
-                .alignment(Alignment::Right),
-            ),
-            area,
-        );
+        f.render_widget(outer_block, area);
     }
 
     fn has_focus(&self) -> bool {
@@ -140,31 +330,62 @@ impl Component for MemoryComponent {
     fn set_focus(&mut self, focus: bool) {
         self.focused = focus
     }
+
+    fn captures_input(&self) -> bool {
+        matches!(self.mode, Mode::GotoInput | Mode::Edit)
+    }
 }
 
 impl MemoryComponent {
+    /// Bytes per row of the view
+    fn row_size(&self) -> i32 {
+        match self.view {
+            View::Hex => 16,
+            View::Sprite => 1,
+        }
+    }
+
+    fn page(&self) -> i32 {
+        self.rows.max(1) as i32
+    }
+
+    /// Scroll by `rows` rows of the view
+    fn scroll(&mut self, mem: &Memory, rows: i32) {
+        self.scroll_to(mem, self.offset as i32 + rows * self.row_size());
+    }
+
+    /// Scroll to `offset`, kept within memory
+    fn scroll_to(&mut self, mem: &Memory, offset: i32) {
+        self.follow = None;
+        let max_offset = (mem.max_addr() & !0xF) as i32;
+        self.offset = offset.clamp(0, max_offset) as u16;
+    }
+
     fn render_status_line(&self) -> String {
         let view = match self.view {
+            View::Hex if self.heatmap => "view: heatmap",
             View::Hex => "view: hex",
             View::Sprite => "view: sprite",
         };
 
-        match self.mode {
-            Mode::Normal => format!("[{view} | offset: {:#06X}]", self.offset),
-            Mode::GotoInput => format!("[{view} | goto: {}]", self.input),
+        match (self.mode, self.follow) {
+            (Mode::Normal, Some(follow)) => format!("[{view} | follow: {follow}]"),
+            (Mode::Normal, None) => format!("[{view} | offset: {:#06X}]", self.offset),
+            (Mode::GotoInput, _) => format!("[{view} | goto: {}]", self.input),
+            (Mode::Edit, _) => format!("[{view} | edit: {:#06X}]", self.cursor),
         }
     }
 
-    fn render_sprite(&self, cpu: &Cpu, mem: &Memory, height: u16) -> Text {
+    fn render_sprite(&self, cpu: &Cpu, mem: &Memory, height: u16, theme: &Theme) -> Text<'_> {
         let Cpu { i, .. } = cpu;
         Text::from_iter((0..height).map(|row| {
-            let addr = self.offset + row;
+            let addr = self.offset.wrapping_add(row);
 
             let i_str = if *i == addr { "I" } else { " " };
             let mut spans = vec![Span::styled(
                 format!(" {i_str} |{addr:#06X}| "),
                 if *i == addr {
-                    Style::new().green()
+                    theme.i
                 } else {
                     Style::default()
                 },
@@ -173,9 +394,9 @@ impl MemoryComponent {
             let byte = mem.read_u8(addr);
             for j in 0..8 {
                 if (byte >> (7 - j)) & 0x1 == 1 {
-                    spans.push(Span::styled("█", Style::new().white()))
+                    spans.push(Span::styled("█", Style::new().fg(theme.palette.pixel_on())))
                 } else {
-                    spans.push(Span::styled("█", Style::new().black()))
+                    spans.push(Span::styled("█", Style::new().fg(theme.palette.off)))
                 }
             }
 
@@ -188,6 +409,42 @@ struct MemoryHexView<'a> {
     offset: u16,
     cpu: &'a Cpu,
     mem: &'a Memory,
+    map: &'a MemoryMap,
+    theme: &'a Theme,
+    /// Address being edited and the digits typed so far
+    cursor: Option<(u16, &'a str)>,
+    changes: &'a ChangeTracker,
+    heat: Option<Heat<'a>>,
+}
+
+/// Execution counts scaled to the heatmap styles
+struct Heat<'a> {
+    counts: &'a [u32],
+    max: u32,
+}
+
+impl Heat<'_> {
+    fn new(counts: &[u32]) -> Heat<'_> {
+        Heat {
+            counts,
+            max: counts.iter().copied().max().unwrap_or(0),
+        }
+    }
+
+    /// Heatmap style index of the byte at `addr`, `None` if it never ran.
+    /// Counts are only kept for the first byte of an instruction, the
+    /// second gets the same color
+    fn level(&self, addr: u16, levels: usize) -> Option<usize> {
+        let count = |addr: u16| self.counts.get(addr as usize).copied().unwrap_or(0);
+        let count = count(addr).max(count(addr.wrapping_sub(1)));
+        if count == 0 {
+            return None;
+        }
+        // logarithmic, a main loop runs orders of magnitude more often than
+        // the setup code before it
+        let scale = (count as f64).ln_1p() / (self.max as f64).ln_1p();
+        Some(((scale * levels as f64) as usize).min(levels - 1))
+    }
 }
 
 impl Widget for MemoryHexView<'_> {
@@ -205,34 +462,35 @@ impl Widget for MemoryHexView<'_> {
         );
 
         for row in 1..area.height {
-            let offset = self.offset + ((row - 1) * 16);
-            if offset > 0xFF0 {
+            let offset = self.offset as usize + ((row as usize - 1) * 16);
+            if offset >= self.mem.size() {
                 break;
             }
+            let offset = offset as u16;
 
-            let row_has_pc = offset == *pc & 0xFF0;
-            let row_has_sp = offset == *sp & 0xFF0;
-            let row_has_i = offset == *i & 0xFF0;
+            let row_has_pc = offset == *pc & !0xF;
+            let row_has_sp = offset == *sp & !0xF;
+            let row_has_i = offset == *i & !0xF;
 
             if row_has_pc {
                 buf.set_span(
                     area.x,
                     area.y + row,
-                    &Span::styled("PC", Style::default().fg(Color::Yellow)),
+                    &Span::styled("PC", self.theme.pc),
                     area.width,
                 );
             } else if row_has_sp {
                 buf.set_span(
                     area.x,
                     area.y + row,
-                    &Span::styled("SP", Style::default().fg(Color::Magenta)),
+                    &Span::styled("SP", self.theme.sp),
                     area.width,
                 );
             } else if row_has_i {
                 buf.set_span(
                     area.x + 1,
                     area.y + row,
-                    &Span::styled("I", Style::default().fg(Color::Green)),
+                    &Span::styled("I", self.theme.i),
                     area.width,
                 );
             }
@@ -244,6 +502,38 @@ impl Widget for MemoryHexView<'_> {
                 area.width,
             );
 
+            // printable ASCII, like a hex editor
+            let ascii: String = (0..16)
+                .map(|byte_offset| match self.mem.read_u8(offset + byte_offset) {
+                    byte @ 0x20..=0x7E => byte as char,
+                    _ => '.',
+                })
+                .collect();
+            buf.set_span(
+                area.x + ASCII_COLUMN,
+                area.y + row,
+                &Span::from(ascii),
+                area.width.saturating_sub(ASCII_COLUMN),
+            );
+
+            // label regions starting in this row, and the one the view starts in
+            let label = self
+                .map
+                .regions()
+                .iter()
+                .filter(|r| {
+                    (offset..=offset + 15).contains(&r.start) || (row == 1 && r.contains(offset))
+                })
+                .map(|r| r.kind.to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            buf.set_span(
+                area.x + LABEL_COLUMN,
+                area.y + row,
+                &Span::styled(label, self.theme.comment),
+                area.width.saturating_sub(LABEL_COLUMN),
+            );
+
             for byte_offset in 0..16 {
                 let addr = offset + byte_offset;
                 let byte = self.mem.read_u8(addr);
@@ -255,12 +545,26 @@ impl Widget for MemoryHexView<'_> {
                 if let Some(cell) = buf.cell_mut(position) {
                     cell.set_symbol(&format!("{byte:02X}"));
 
-                    if addr.saturating_sub(1) == *pc || addr == *pc {
-                        cell.set_fg(Color::Yellow);
+                    if let Some((_, input)) = self.cursor.filter(|(cursor, _)| *cursor == addr) {
+                        if !input.is_empty() {
+                            cell.set_symbol(&format!("{input:_<2}"));
+                        }
+                        cell.set_style(self.theme.cursor_line);
+                        continue;
+                    }
+
+                    if let Some(heat) = &self.heat {
+                        if let Some(level) = heat.level(addr, self.theme.heat.len()) {
+                            cell.set_style(self.theme.heat[level]);
+                        }
+                    } else if let Some(age) = self.changes.age(addr) {
+                        cell.set_style(self.theme.changed[age.min(FADE_STEPS - 1)]);
+                    } else if addr.saturating_sub(1) == *pc || addr == *pc {
+                        cell.set_style(self.theme.pc);
                     } else if addr.saturating_sub(1) == *sp || addr == *sp {
-                        cell.set_fg(Color::Magenta);
+                        cell.set_style(self.theme.sp);
                     } else if addr.saturating_sub(1) == *i || addr == *i {
-                        cell.set_fg(Color::Green);
+                        cell.set_style(self.theme.i);
                     }
                 };
             }
@@ -11,7 +11,9 @@ pub use cpu::CpuComponent;
 pub use debug::DebuggerComponent;
 pub use disasm::DisassemblyComponent;
 pub use display::DisplayComponent;
+pub(crate) use display::KEY_TAP_FRAMES;
 pub use log::LogComponent;
+pub(crate) use log::LogView;
 pub use mem::MemoryComponent;
 
 use crate::app::AppState;
@@ -24,4 +26,13 @@ pub trait Component {
     fn has_focus(&self) -> bool;
 
     fn set_focus(&mut self, focus: bool);
+
+    /// Whether typed characters go to the panel, global keys such as quit
+    /// are ignored while they do
+    fn captures_input(&self) -> bool {
+        false
+    }
+
+    /// Move the view to `addr`, for panels that show addresses
+    fn show_addr(&mut self, _addr: u16) {}
 }
@@ -0,0 +1,248 @@
+use ratatui::style::Color;
+
+use crate::{
+    bindings::KeyBindings,
+    palette::Palette,
+    theme::{Theme, ThemeName},
+};
+
+/// Keyboard keys of the COSMAC VIP keypad layout, `1234`/`qwer`/`asdf`/`zxcv`,
+/// indexed by keypad key
+pub const DEFAULT_KEYS: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// User settings for the TUI, see [`App::with_config`](crate::App::with_config)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuiConfig {
+    /// Keyboard key for each keypad key, indexed by keypad key. Typed while
+    /// the display panel is focused.
+    pub keys: [char; 16],
+    pub theme: ThemeName,
+    /// Display colors to use instead of the theme's
+    pub palette: Option<Palette>,
+    pub colors: Colors,
+    pub layout: LayoutConfig,
+    pub bindings: KeyBindings,
+    /// Don't play the buzzer
+    pub mute: bool,
+    /// Name of the audio device the buzzer plays on, the default output
+    /// device if unset. Needs the `audio` feature.
+    pub audio_device: Option<String>,
+}
+
+impl Default for TuiConfig {
+    fn default() -> TuiConfig {
+        TuiConfig {
+            keys: DEFAULT_KEYS,
+            theme: ThemeName::default(),
+            palette: None,
+            colors: Colors::default(),
+            layout: LayoutConfig::default(),
+            bindings: KeyBindings::default(),
+            mute: false,
+            audio_device: None,
+        }
+    }
+}
+
+impl TuiConfig {
+    /// Keypad key typed with `c`
+    pub fn keypad_key(&self, c: char) -> Option<u8> {
+        let c = c.to_ascii_lowercase();
+        self.keys.iter().position(|&k| k == c).map(|key| key as u8)
+    }
+
+    /// The selected theme and palette with the colors set in
+    /// [`TuiConfig::colors`]
+    pub fn theme(&self) -> Theme {
+        let mut theme = Theme::new(self.theme);
+        if let Some(palette) = &self.palette {
+            theme.palette = palette.clone();
+        }
+
+        let palette = &mut theme.palette;
+        let [plane1, plane2, both] = &mut palette.on;
+        let overrides = [
+            (self.colors.pixel_off, &mut palette.off),
+            (self.colors.pixel_on, plane1),
+            (self.colors.plane2, plane2),
+            (self.colors.planes_both, both),
+        ];
+        let mut custom = false;
+        for (color, slot) in overrides {
+            if let Some(color) = color {
+                *slot = color;
+                custom = true;
+            }
+        }
+        if custom {
+            palette.name = "custom".to_string();
+        }
+
+        if let Some(color) = self.colors.focus {
+            theme.focus = theme.focus.fg(color);
+        }
+        theme
+    }
+}
+
+/// Colors overriding the ones of the theme
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Colors {
+    pub pixel_on: Option<Color>,
+    pub pixel_off: Option<Color>,
+    /// XO-CHIP pixels set in plane 2 only
+    pub plane2: Option<Color>,
+    /// XO-CHIP pixels set in both planes
+    pub planes_both: Option<Color>,
+    /// Border of the focused panel
+    pub focus: Option<Color>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutConfig {
+    pub preset: LayoutPreset,
+    /// Panels of the preset to leave out
+    pub hidden: Vec<Panel>,
+    pub disasm_width: u16,
+    pub memory_width: u16,
+    /// Show the log panel above the debugger
+    pub show_log: bool,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> LayoutConfig {
+        LayoutConfig {
+            preset: LayoutPreset::default(),
+            hidden: Vec::new(),
+            disasm_width: 37,
+            memory_width: 79,
+            show_log: true,
+        }
+    }
+}
+
+impl LayoutConfig {
+    /// Whether `panel` is shown before any are toggled at runtime
+    pub fn shows(&self, panel: Panel) -> bool {
+        self.preset.panels().contains(&panel)
+            && !self.hidden.contains(&panel)
+            && (panel != Panel::Log || self.show_log)
+    }
+}
+
+/// The panels of the TUI, in the order of their focus keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Panel {
+    Display,
+    Cpu,
+    Disassembly,
+    Memory,
+    Log,
+    Debugger,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 6] = [
+        Panel::Display,
+        Panel::Cpu,
+        Panel::Disassembly,
+        Panel::Memory,
+        Panel::Log,
+        Panel::Debugger,
+    ];
+}
+
+impl std::str::FromStr for Panel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "display" => Ok(Panel::Display),
+            "cpu" => Ok(Panel::Cpu),
+            "disasm" => Ok(Panel::Disassembly),
+            "memory" => Ok(Panel::Memory),
+            "log" => Ok(Panel::Log),
+            "debugger" => Ok(Panel::Debugger),
+            _ => Err(format!("unknown panel '{s}'")),
+        }
+    }
+}
+
+/// Which panels are shown, the display and CPU panels go on top and the
+/// rest below them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutPreset {
+    /// Every panel
+    #[default]
+    Default,
+    /// Everything but the display, the code and memory panels get its space
+    Debug,
+    /// The display and the CPU state
+    Display,
+    /// The display and the debugger
+    Minimal,
+}
+
+impl LayoutPreset {
+    const ALL: [LayoutPreset; 4] = [
+        LayoutPreset::Default,
+        LayoutPreset::Debug,
+        LayoutPreset::Display,
+        LayoutPreset::Minimal,
+    ];
+
+    pub fn panels(&self) -> &'static [Panel] {
+        match self {
+            LayoutPreset::Default => &Panel::ALL,
+            LayoutPreset::Debug => &[
+                Panel::Cpu,
+                Panel::Disassembly,
+                Panel::Memory,
+                Panel::Log,
+                Panel::Debugger,
+            ],
+            LayoutPreset::Display => &[Panel::Display, Panel::Cpu],
+            LayoutPreset::Minimal => &[Panel::Display, Panel::Debugger],
+        }
+    }
+
+    /// Preset after this one, wrapping around
+    pub fn next(&self) -> LayoutPreset {
+        let i = LayoutPreset::ALL
+            .iter()
+            .position(|p| p == self)
+            .unwrap_or(0);
+        LayoutPreset::ALL[(i + 1) % LayoutPreset::ALL.len()]
+    }
+}
+
+impl std::str::FromStr for LayoutPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(LayoutPreset::Default),
+            "debug" => Ok(LayoutPreset::Debug),
+            "display" => Ok(LayoutPreset::Display),
+            "minimal" => Ok(LayoutPreset::Minimal),
+            _ => Err(format!("unknown layout preset '{s}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for LayoutPreset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LayoutPreset::Default => "default",
+                LayoutPreset::Debug => "debug",
+                LayoutPreset::Display => "display",
+                LayoutPreset::Minimal => "minimal",
+            }
+        )
+    }
+}
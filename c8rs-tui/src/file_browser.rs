@@ -0,0 +1,162 @@
+use std::path::{Path, PathBuf};
+
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Alignment, Constraint, Flex, Layout, Rect},
+    text::{Line, Text},
+    widgets::{block, Block, Clear},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Popup listing a directory to pick a ROM or saved state from, typing
+/// filters the entries
+pub(crate) struct FileBrowser {
+    dir: PathBuf,
+    /// Subdirectories first, then files, hidden ones left out
+    entries: Vec<Entry>,
+    filter: String,
+    /// Index into the filtered entries
+    selected: usize,
+    /// Couldn't list `dir`
+    error: Option<String>,
+}
+
+struct Entry {
+    name: String,
+    is_dir: bool,
+}
+
+/// How the browser was closed
+pub(crate) enum Selection {
+    Cancel,
+    File(PathBuf),
+}
+
+impl FileBrowser {
+    /// Browse `dir`, made absolute so loading the picked file doesn't depend
+    /// on the working directory
+    pub(crate) fn new(dir: &Path) -> FileBrowser {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        let mut browser = FileBrowser {
+            dir: PathBuf::new(),
+            entries: Vec::new(),
+            filter: String::new(),
+            selected: 0,
+            error: None,
+        };
+        browser.change_dir(dir);
+        browser
+    }
+
+    fn change_dir(&mut self, dir: PathBuf) {
+        self.filter.clear();
+        self.selected = 0;
+        self.entries.clear();
+        self.error = None;
+
+        match std::fs::read_dir(&dir) {
+            Ok(read_dir) => {
+                self.entries = read_dir
+                    .flatten()
+                    .map(|entry| Entry {
+                        name: entry.file_name().to_string_lossy().into_owned(),
+                        is_dir: entry.path().is_dir(),
+                    })
+                    .filter(|entry| !entry.name.starts_with('.'))
+                    .collect();
+                self.entries
+                    .sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then(a.name.cmp(&b.name)));
+            }
+            Err(err) => self.error = Some(format!("Failed to read {}: {err}", dir.display())),
+        }
+        self.dir = dir;
+    }
+
+    fn filtered(&self) -> Vec<&Entry> {
+        let filter = self.filter.to_lowercase();
+        self.entries
+            .iter()
+            .filter(|entry| entry.name.to_lowercase().contains(&filter))
+            .collect()
+    }
+
+    pub(crate) fn handle_key_event(&mut self, event: KeyEvent) -> Option<Selection> {
+        let count = self.filtered().len();
+        match event.code {
+            KeyCode::Esc => return Some(Selection::Cancel),
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Enter => {
+                let entry = self
+                    .filtered()
+                    .get(self.selected)
+                    .map(|entry| (self.dir.join(&entry.name), entry.is_dir));
+                match entry {
+                    Some((path, true)) => self.change_dir(path),
+                    Some((path, false)) => return Some(Selection::File(path)),
+                    None => (),
+                }
+            }
+            KeyCode::Backspace => {
+                if self.filter.pop().is_none() {
+                    if let Some(parent) = self.dir.parent() {
+                        self.change_dir(parent.to_path_buf());
+                    }
+                }
+                self.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                self.filter.push(c);
+                self.selected = 0;
+            }
+            _ => (),
+        }
+        None
+    }
+
+    pub(crate) fn render(&self, f: &mut Frame<'_>, area: Rect, theme: &Theme) {
+        let [area] = Layout::horizontal([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let block = Block::bordered()
+            .title(format!("[Open: {}]", self.dir.display()))
+            .title_bottom(format!("[filter: {}]", self.filter))
+            .title(
+                block::Title::from("[enter: open | backspace: up | esc: close]")
+                    .alignment(Alignment::Right),
+            )
+            .border_style(theme.focus);
+        let inner = block.inner(area);
+
+        let text = match &self.error {
+            Some(err) => Text::from(Line::styled(err.as_str(), theme.log_error)),
+            None => {
+                let entries = self.filtered();
+                // keep the selection on screen
+                let skip = (self.selected + 1).saturating_sub(inner.height as usize);
+                Text::from_iter(entries.iter().enumerate().skip(skip).map(|(i, entry)| {
+                    let name = if entry.is_dir {
+                        format!("{}/", entry.name)
+                    } else {
+                        entry.name.clone()
+                    };
+                    if i == self.selected {
+                        Line::styled(name, theme.cursor_line)
+                    } else {
+                        Line::from(name)
+                    }
+                }))
+            }
+        };
+
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        f.render_widget(text, inner);
+    }
+}
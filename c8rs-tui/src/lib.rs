@@ -1,5 +1,25 @@
 pub use app::App;
+pub use bindings::{Action, Key, KeyBindings};
+pub use config::{Colors, LayoutConfig, LayoutPreset, Panel, TuiConfig, DEFAULT_KEYS};
+pub use palette::Palette;
+pub use remote_log::{run_log_viewer, DEFAULT_LOG_ADDR};
+pub use theme::{Theme, ThemeName};
 
 mod app;
+mod bindings;
+mod buzzer;
+mod capabilities;
+mod command_line;
 mod components;
+mod config;
+mod file_browser;
+mod logger;
+mod palette;
+mod quit_dialog;
+mod remote_log;
+mod session;
+mod stats;
+mod status_bar;
+mod theme;
+mod toasts;
 mod tui;
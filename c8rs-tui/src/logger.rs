@@ -0,0 +1,75 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use chrono::{DateTime, Local};
+use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Records kept for the log panel, older ones are dropped
+const CAPACITY: usize = 10_000;
+
+static RECORDS: Mutex<Records> = Mutex::new(Records {
+    records: VecDeque::new(),
+    next_id: 0,
+});
+
+struct Records {
+    /// Oldest first
+    records: VecDeque<LogRecord>,
+    next_id: u64,
+}
+
+pub(crate) struct LogRecord {
+    /// Increases with every record, so a position in the log survives old
+    /// records being dropped
+    pub(crate) id: u64,
+    pub(crate) time: DateTime<Local>,
+    pub(crate) level: Level,
+    pub(crate) target: String,
+    pub(crate) msg: String,
+}
+
+/// Logger keeping the last records in memory for the log panel
+struct PanelLogger;
+
+impl Log for PanelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            push(record);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub(crate) fn init(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&PanelLogger)?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// Add a record that didn't go through the logger, like the ones received by
+/// the remote log viewer
+pub(crate) fn push(record: &Record) {
+    let mut records = RECORDS.lock().unwrap_or_else(|err| err.into_inner());
+    if records.records.len() == CAPACITY {
+        records.records.pop_front();
+    }
+    let id = records.next_id;
+    records.next_id += 1;
+    records.records.push_back(LogRecord {
+        id,
+        time: Local::now(),
+        level: record.level(),
+        target: record.target().to_string(),
+        msg: record.args().to_string(),
+    });
+}
+
+/// Run `f` on the records, oldest first, logging from `f` deadlocks
+pub(crate) fn with_records<T>(f: impl FnOnce(&VecDeque<LogRecord>) -> T) -> T {
+    let records = RECORDS.lock().unwrap_or_else(|err| err.into_inner());
+    f(&records.records)
+}
@@ -0,0 +1,45 @@
+use ratatui::style::Color;
+
+/// Colors of the display
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub off: Color,
+    /// Pixels set in plane 1, plane 2 and both, indexed by plane mask - 1.
+    /// Only plane 1 is drawn until XO-CHIP planes are emulated.
+    pub on: [Color; 3],
+}
+
+/// Built-in palettes: name, off, then the plane 1, plane 2 and both colors
+const PALETTES: &[(&str, u32, [u32; 3])] = &[
+    ("classic", 0x000000, [0xFFFFFF, 0xAAAAAA, 0x555555]),
+    ("inverted", 0xFFFFFF, [0x000000, 0x555555, 0xAAAAAA]),
+    ("amber", 0x1A1000, [0xFFB000, 0xB37B00, 0xFFD780]),
+    ("phosphor", 0x001A08, [0x33FF66, 0x1F9940, 0x99FFB3]),
+    ("lcd", 0x9BBC0F, [0x0F380F, 0x306230, 0x8BAC0F]),
+    ("octo", 0x996600, [0xFFCC00, 0xFF6600, 0x662200]),
+];
+
+impl Palette {
+    /// Built-in palette called `name`
+    pub fn named(name: &str) -> Option<Palette> {
+        Palette::builtin().into_iter().find(|p| p.name == name)
+    }
+
+    pub fn builtin() -> Vec<Palette> {
+        let rgb = |c: u32| Color::Rgb((c >> 16) as u8, (c >> 8) as u8, c as u8);
+        PALETTES
+            .iter()
+            .map(|&(name, off, on)| Palette {
+                name: name.to_string(),
+                off: rgb(off),
+                on: on.map(rgb),
+            })
+            .collect()
+    }
+
+    /// Color of pixels set in plane 1
+    pub fn pixel_on(&self) -> Color {
+        self.on[0]
+    }
+}
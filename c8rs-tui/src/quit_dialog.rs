@@ -0,0 +1,112 @@
+use c8rs_core::PendingReply;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Text},
+    widgets::{Block, Clear},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Popup asking whether to quit, optionally saving the machine state first
+pub(crate) struct QuitDialog {
+    /// File the state is saved to
+    state_file: String,
+    /// Save sent to the emulator and not answered yet
+    saving: Option<PendingReply>,
+    /// Why the last save failed
+    error: Option<String>,
+}
+
+/// How the dialog was answered
+pub(crate) enum Choice {
+    Quit,
+    SaveAndQuit,
+    Cancel,
+}
+
+impl QuitDialog {
+    pub(crate) fn new(state_file: String) -> QuitDialog {
+        QuitDialog {
+            state_file,
+            saving: None,
+            error: None,
+        }
+    }
+
+    pub(crate) fn state_file(&self) -> &str {
+        &self.state_file
+    }
+
+    pub(crate) fn handle_key_event(&mut self, event: KeyEvent) -> Option<Choice> {
+        // the answer is in once the save is sent
+        if self.saving.is_some() {
+            return None;
+        }
+
+        match event.code {
+            KeyCode::Char('y') | KeyCode::Enter => Some(Choice::Quit),
+            KeyCode::Char('s') => Some(Choice::SaveAndQuit),
+            KeyCode::Char('n') | KeyCode::Esc => Some(Choice::Cancel),
+            _ => None,
+        }
+    }
+
+    /// Wait for the result of `save` before quitting
+    pub(crate) fn set_saving(&mut self, save: PendingReply) {
+        self.error = None;
+        self.saving = Some(save);
+    }
+
+    /// Whether the state has been saved, a failed save is shown in the dialog
+    /// so it can be retried or the session quit without it
+    pub(crate) fn poll_saved(&mut self) -> bool {
+        let Some(result) = self.saving.as_ref().and_then(PendingReply::try_get) else {
+            return false;
+        };
+        self.saving = None;
+        match result {
+            Ok(_) => true,
+            Err(err) => {
+                self.error = Some(format!("Failed to save: {err}"));
+                false
+            }
+        }
+    }
+
+    pub(crate) fn render(&self, f: &mut Frame<'_>, area: Rect, theme: &Theme) {
+        let mut lines = vec![
+            Line::from("Quit c8rs?"),
+            Line::default(),
+            Line::from("y: quit"),
+            Line::from(format!("s: save to {} and quit", self.state_file)),
+            Line::from("n: cancel"),
+        ];
+        if self.saving.is_some() {
+            lines.push(Line::default());
+            lines.push(Line::styled("Saving...", theme.comment));
+        } else if let Some(err) = &self.error {
+            lines.push(Line::default());
+            lines.push(Line::styled(err.as_str(), theme.log_error));
+        }
+        let text = Text::from(lines);
+
+        let [area] = Layout::horizontal([Constraint::Length(text.width() as u16 + 4)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Length(text.height() as u16 + 2)])
+            .flex(Flex::Center)
+            .areas(area);
+
+        let block = Block::bordered().title("[Quit]").border_style(theme.focus);
+        let inner = block.inner(area);
+        let [inner] = Layout::horizontal([Constraint::Fill(1)])
+            .horizontal_margin(1)
+            .areas(inner);
+
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        f.render_widget(text, inner);
+    }
+}
@@ -0,0 +1,224 @@
+use std::{io::Write, net::TcpStream, sync::Mutex, time::Duration};
+
+use anyhow::{Context, Result};
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind};
+use futures::StreamExt;
+use log::{Level, Log, Metadata, Record};
+use ratatui::{
+    prelude::*,
+    widgets::{block, Block},
+};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::TcpListener,
+};
+
+use crate::{
+    bindings::KeyBindings, capabilities::InputMode, components::LogView, logger, theme::Theme, tui,
+    App,
+};
+
+/// Default address used by `c8rs logs` and `c8rs run --log-socket`
+pub const DEFAULT_LOG_ADDR: &str = "127.0.0.1:7341";
+
+/// Logger that forwards every record to a log viewer listening on a socket
+struct SocketLogger {
+    stream: Mutex<TcpStream>,
+}
+
+impl Log for SocketLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = encode(record.level(), record.target(), &record.args().to_string());
+        if let Ok(mut stream) = self.stream.lock() {
+            let _ = stream.write_all(line.as_bytes());
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut stream) = self.stream.lock() {
+            let _ = stream.flush();
+        }
+    }
+}
+
+impl App {
+    /// Forward log records to a `c8rs logs` viewer at `addr` instead of the
+    /// log panel
+    pub fn init_remote_logger(addr: &str) -> Result<()> {
+        let stream = TcpStream::connect(addr).with_context(|| {
+            format!("failed to connect to log viewer at {addr}, is `c8rs logs` running?")
+        })?;
+
+        log::set_boxed_logger(Box::new(SocketLogger {
+            stream: Mutex::new(stream),
+        }))?;
+        log::set_max_level(log::LevelFilter::Debug);
+
+        Ok(())
+    }
+}
+
+/// Full-screen log viewer receiving records from a detached TUI
+pub async fn run_log_viewer(addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("failed to listen on {addr}"))?;
+
+    App::init_logger();
+    log::info!("Listening for log records on {addr}");
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = match listener.accept().await {
+                Ok(conn) => conn,
+                Err(err) => {
+                    log::error!("Failed to accept connection: {err}");
+                    continue;
+                }
+            };
+
+            log::info!("Connected: {peer}");
+
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stream).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    if let Some((level, target, msg)) = decode(&line) {
+                        logger::push(
+                            &Record::builder()
+                                .level(level)
+                                .target(&target)
+                                .args(format_args!("{msg}"))
+                                .build(),
+                        );
+                    }
+                }
+                log::info!("Disconnected: {peer}");
+            });
+        }
+    });
+
+    let mut terminal = tui::init(InputMode::Basic)?;
+    let mut view = LogView::default();
+    let mut events = EventStream::new();
+    let mut redraw_interval = tokio::time::interval(Duration::from_millis(100));
+    let bindings = KeyBindings::default();
+
+    loop {
+        terminal.draw(|frame| {
+            let outer_block = Block::bordered()
+                .title(format!("[c8rs logs: {addr}]"))
+                .title(block::Title::from("[q: quit]").alignment(Alignment::Right))
+                .title(
+                    block::Title::from(view.status_line())
+                        .position(block::Position::Bottom)
+                        .alignment(Alignment::Right),
+                );
+            let area = outer_block.inner(frame.area());
+            view.render(frame.buffer_mut(), area, &Theme::default());
+            frame.render_widget(outer_block, frame.area());
+        })?;
+
+        tokio::select! {
+            _ = redraw_interval.tick() => (),
+            event = events.next() => match event {
+                Some(Ok(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    if key.code == KeyCode::Char('q') && !view.is_editing() {
+                        break;
+                    }
+                    view.handle_key_event(key, &bindings);
+                }
+                Some(Err(err)) => log::error!("{err}"),
+                None => break,
+                _ => (),
+            }
+        }
+    }
+
+    tui::restore(InputMode::Basic)
+}
+
+fn encode(level: Level, target: &str, msg: &str) -> String {
+    format!("{level}\t{}\t{}\n", escape(target), escape(msg))
+}
+
+fn decode(line: &str) -> Option<(Level, String, String)> {
+    let mut parts = line.splitn(3, '\t');
+    let level = parts.next()?.parse().ok()?;
+    let target = unescape(parts.next()?);
+    let msg = unescape(parts.next()?);
+    Some((level, target, msg))
+}
+
+/// `s` without the tabs and newlines that separate fields and records
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t")
+}
+
+fn unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => {
+                out.push('\n');
+                chars.next();
+            }
+            ('\\', Some('t')) => {
+                out.push('\t');
+                chars.next();
+            }
+            ('\\', Some('\\')) => {
+                out.push('\\');
+                chars.next();
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let records = [
+            (Level::Info, "c8rs_core", "plain message"),
+            (Level::Warn, "c8rs_tui::app", "two\nlines\n"),
+            (Level::Error, "tab\tin target", "tab\tin\tmessage"),
+            (Level::Debug, "back\\slash", "C:\\roms\\n\\t\\"),
+            (Level::Trace, "", ""),
+        ];
+        for (level, target, msg) in records {
+            let line = encode(level, target, msg);
+            assert_eq!(line.matches(['\t', '\n']).count(), 3, "{line:?}");
+            let line = line.strip_suffix('\n').unwrap();
+            assert_eq!(
+                decode(line),
+                Some((level, target.to_string(), msg.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid() {
+        assert_eq!(decode("LOUD\ttarget\tmsg"), None);
+        assert_eq!(decode("INFO\ttarget"), None);
+        // a lone backslash is kept as is
+        assert_eq!(
+            decode("INFO\ttarget\ta\\b\\"),
+            Some((Level::Info, "target".to_string(), "a\\b\\".to_string()))
+        );
+    }
+}
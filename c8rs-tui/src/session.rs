@@ -0,0 +1,98 @@
+use std::{
+    cell::RefCell,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use c8rs_core::EmulatorCommand;
+use crossterm::event::KeyEvent;
+use serde::{Deserialize, Serialize};
+
+/// Recorded key event or emulator command
+///
+/// Commands are stored for reference only, replaying the key events
+/// regenerates them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionEvent {
+    Key(KeyEvent),
+    Command(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub millis: u64,
+    #[serde(flatten)]
+    pub event: SessionEvent,
+}
+
+/// Writes session events as JSON lines with timestamps relative to the start
+/// of the recording
+pub struct SessionRecorder {
+    start: Instant,
+    writer: RefCell<BufWriter<File>>,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &Path) -> Result<SessionRecorder> {
+        let file = File::create(path)
+            .with_context(|| format!("failed to create session file {}", path.display()))?;
+
+        Ok(SessionRecorder {
+            start: Instant::now(),
+            writer: RefCell::new(BufWriter::new(file)),
+        })
+    }
+
+    pub fn record_key(&self, key: KeyEvent) {
+        self.record(SessionEvent::Key(key));
+    }
+
+    pub fn record_command(&self, cmd: &EmulatorCommand) {
+        self.record(SessionEvent::Command(format!("{cmd:?}")));
+    }
+
+    fn record(&self, event: SessionEvent) {
+        let record = SessionRecord {
+            millis: self.start.elapsed().as_millis() as u64,
+            event,
+        };
+
+        let mut writer = self.writer.borrow_mut();
+        let res = serde_json::to_writer(&mut *writer, &record)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writeln!(writer))
+            .and_then(|_| writer.flush());
+
+        if let Err(err) = res {
+            log::error!("Failed to record session event: {err}");
+        }
+    }
+}
+
+/// Load the key events of a recorded session with their delay since the
+/// start of the recording
+pub fn load_session(path: &Path) -> Result<Vec<(Duration, KeyEvent)>> {
+    let file = File::open(path)
+        .with_context(|| format!("failed to open session file {}", path.display()))?;
+
+    let mut keys = Vec::new();
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let record: SessionRecord = serde_json::from_str(&line)
+            .with_context(|| format!("{}:{}: invalid session record", path.display(), i + 1))?;
+
+        if let SessionEvent::Key(key) = record.event {
+            keys.push((Duration::from_millis(record.millis), key));
+        }
+    }
+
+    Ok(keys)
+}
@@ -0,0 +1,106 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Text},
+    widgets::{Block, Clear, Sparkline},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// Draw times kept for the sparkline
+const HISTORY: usize = 120;
+
+/// Timings of the UI, shown in an overlay
+#[derive(Default)]
+pub(crate) struct Stats {
+    /// How long the last draws took, oldest first
+    draw_times: VecDeque<Duration>,
+    /// When the draws of the last second started
+    draws: VecDeque<Instant>,
+    /// How late the last tick was handled
+    latency: Duration,
+}
+
+impl Stats {
+    pub(crate) fn record_draw(&mut self, start: Instant) {
+        if self.draw_times.len() == HISTORY {
+            self.draw_times.pop_front();
+        }
+        self.draw_times.push_back(start.elapsed());
+
+        self.draws.push_back(start);
+        while self
+            .draws
+            .front()
+            .is_some_and(|t| start.duration_since(*t) > Duration::from_secs(1))
+        {
+            self.draws.pop_front();
+        }
+    }
+
+    /// Record that an event sent at `sent` is being handled now
+    pub(crate) fn record_latency(&mut self, sent: Instant) {
+        self.latency = sent.elapsed();
+    }
+
+    /// `ips` is the measured and the target speed of the emulator
+    pub(crate) fn render(
+        &self,
+        f: &mut Frame<'_>,
+        area: Rect,
+        theme: &Theme,
+        ips: (Option<u32>, u32),
+    ) {
+        let [area] = Layout::horizontal([Constraint::Length(42)])
+            .flex(Flex::End)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Length(9)])
+            .flex(Flex::Start)
+            .areas(area);
+
+        let block = Block::bordered().title("[Stats]").border_style(theme.focus);
+        let inner = block.inner(area);
+        let [sparkline_area, text_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Fill(1)]).areas(inner);
+
+        let last = self.draw_times.back().copied().unwrap_or_default();
+        let max = self.draw_times.iter().max().copied().unwrap_or_default();
+        let (actual, target) = ips;
+        let actual = actual.map_or("-".to_string(), |ips| ips.to_string());
+        let lines = vec![
+            Line::from(format!(
+                "draw: {:.02} ms (max {:.02} ms)",
+                ms(last),
+                ms(max)
+            )),
+            Line::from(format!("fps: {}", self.draws.len())),
+            Line::from(format!("ips: {actual} of {target}")),
+            Line::from(format!("event latency: {:.02} ms", ms(self.latency))),
+        ];
+
+        let data: Vec<u64> = self
+            .draw_times
+            .iter()
+            .map(|t| t.as_micros() as u64)
+            .collect();
+        // newest on the right
+        let skip = data.len().saturating_sub(sparkline_area.width as usize);
+
+        f.render_widget(Clear, area);
+        f.render_widget(block, area);
+        f.render_widget(
+            Sparkline::default().data(&data[skip..]).style(theme.accent),
+            sparkline_area,
+        );
+        f.render_widget(Text::from(lines), text_area);
+    }
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
@@ -0,0 +1,138 @@
+use c8rs_core::{EmulatorState, Speed};
+use ratatui::{prelude::*, widgets::Widget};
+
+use crate::{app::AppState, bindings::Action};
+
+/// Panel titles, in panel order
+const PANEL_NAMES: [&str; 6] = ["CHIP-8", "CPU", "Disassembly", "Memory", "Log", "Debugger"];
+
+/// Keys worth knowing about in each panel
+const PANEL_HINTS: [&[(Action, &str)]; 6] = [
+    &[(Action::PlayMode, "play")],
+    &[(Action::Goto, "show call")],
+    &[
+        (Action::Follow, "follow"),
+        (Action::Goto, "goto"),
+        (Action::Breakpoint, "break"),
+        (Action::Edit, "patch"),
+        (Action::Search, "search"),
+        (Action::Coverage, "coverage"),
+    ],
+    &[
+        (Action::Follow, "follow"),
+        (Action::Goto, "goto"),
+        (Action::JumpToI, "to I"),
+        (Action::Edit, "edit"),
+        (Action::SpriteView, "sprites"),
+        (Action::HexView, "hex"),
+        (Action::Heatmap, "heatmap"),
+    ],
+    &[
+        (Action::LogLevel, "level"),
+        (Action::LogTarget, "target"),
+        (Action::LogFilter, "filter"),
+    ],
+    &[],
+];
+
+/// Keys shown while no panel is focused
+const GLOBAL_HINTS: &[(Action, &str)] = &[
+    (Action::FocusNext, "panels"),
+    (Action::CommandLine, "command"),
+    (Action::OpenRom, "open"),
+    (Action::PlayMode, "play"),
+    (Action::Quit, "quit"),
+];
+
+/// Bottom row with the emulator state and speed, the ROM, the focused panel
+/// and the keys it takes
+pub(crate) struct StatusBar<'a> {
+    pub(crate) state: &'a AppState,
+    /// Instructions per second over the last second
+    pub(crate) actual_ips: Option<u32>,
+    pub(crate) focused: Option<usize>,
+}
+
+impl Widget for StatusBar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let theme = &self.state.theme;
+        let bindings = &self.state.config.bindings;
+
+        let (status, style) = match self.state.emulator_state {
+            EmulatorState::Running => ("running".to_string(), theme.accent),
+            EmulatorState::Paused => ("paused".to_string(), theme.warning),
+            EmulatorState::Halted => ("halted".to_string(), theme.comment),
+            EmulatorState::Faulted => (
+                match self.state.controller.cpu().fault {
+                    Some(fault) => format!("faulted: {fault}"),
+                    None => "faulted".to_string(),
+                },
+                theme.log_error,
+            ),
+        };
+
+        let mut ips = match self.actual_ips {
+            Some(actual) => format!("IPS: {actual}/{}", self.state.controller.ips()),
+            None => format!("IPS: {}", self.state.controller.ips()),
+        };
+        match self.state.controller.speed() {
+            Speed::Percent(100) => (),
+            Speed::Percent(percent) => ips.push_str(&format!(" at {percent}%")),
+            Speed::Unlimited => ips.push_str(" turbo"),
+        }
+
+        let mut spans = vec![
+            Span::styled(format!(" {status} "), style),
+            Span::raw(format!(" {ips}")),
+        ];
+        if let Some(title) = &self.state.title {
+            spans.push(Span::raw(format!(" | {title}")));
+        }
+        if self.state.config.mute {
+            spans.push(Span::raw(" | muted"));
+        }
+
+        let hints: Vec<String> = match self.focused {
+            Some(i) => {
+                spans.push(Span::raw(format!(" | {}: {}", i + 1, PANEL_NAMES[i])));
+                // the scroll keys have a shared hint
+                let scroll = matches!(i, 1..=4).then(|| {
+                    format!(
+                        "{}/{} scroll",
+                        bindings.key(Action::Down),
+                        bindings.key(Action::Up)
+                    )
+                });
+                scroll
+                    .into_iter()
+                    .chain(
+                        PANEL_HINTS[i]
+                            .iter()
+                            .chain(&[(Action::Zoom, "zoom"), (Action::Unfocus, "back")])
+                            .map(|&(action, hint)| format!("{} {hint}", bindings.key(action))),
+                    )
+                    .collect()
+            }
+            None => GLOBAL_HINTS
+                .iter()
+                .map(|&(action, hint)| format!("{} {hint}", bindings.key(action)))
+                .collect(),
+        };
+
+        let hints = Line::styled(format!("{} ", hints.join("  ")), theme.comment);
+        let hints_width = hints.width() as u16;
+        let line = Line::from(spans);
+        let fits = line.width() as u16 + hints_width < area.width;
+        line.render(area, buf);
+        if fits {
+            hints.render(
+                Rect {
+                    x: area.right() - hints_width,
+                    width: hints_width,
+                    ..area
+                },
+                buf,
+            );
+        }
+    }
+}
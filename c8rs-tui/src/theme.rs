@@ -0,0 +1,236 @@
+use ratatui::style::{Color, Modifier, Style, Stylize};
+
+use crate::palette::Palette;
+
+/// Built-in themes, see [`Theme::new`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// For terminals with a light background
+    Light,
+    /// Modifiers only, for terminals without colors
+    Monochrome,
+    HighContrast,
+}
+
+impl std::str::FromStr for ThemeName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(ThemeName::Default),
+            "light" => Ok(ThemeName::Light),
+            "monochrome" => Ok(ThemeName::Monochrome),
+            "high-contrast" => Ok(ThemeName::HighContrast),
+            _ => Err(format!("unknown theme '{s}'")),
+        }
+    }
+}
+
+impl std::fmt::Display for ThemeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ThemeName::Default => "default",
+                ThemeName::Light => "light",
+                ThemeName::Monochrome => "monochrome",
+                ThemeName::HighContrast => "high-contrast",
+            }
+        )
+    }
+}
+
+/// Styles shared by every panel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Border of the focused panel
+    pub focus: Style,
+    /// Border of the other panels
+    pub border: Style,
+    /// Disassembly line of the instruction at PC
+    pub pc_line: Style,
+    /// Disassembly line under the cursor
+    pub cursor_line: Style,
+    /// PC, SP and I in the memory view
+    pub pc: Style,
+    pub sp: Style,
+    pub i: Style,
+    pub breakpoint: Style,
+    pub tracepoint: Style,
+    /// Memory bytes written by the last few changes, newest first
+    pub changed: [Style; 3],
+    /// Memory heatmap, from bytes executed least to most often
+    pub heat: [Style; 4],
+    /// Labels from a source map
+    pub label: Style,
+    /// Memory region names
+    pub comment: Style,
+    /// Play mode banner
+    pub accent: Style,
+    /// Terminal capability warnings
+    pub warning: Style,
+    pub log_error: Style,
+    pub log_warn: Style,
+    pub log_info: Style,
+    pub log_debug: Style,
+    pub log_trace: Style,
+    pub palette: Palette,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::new(ThemeName::Default)
+    }
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Theme {
+        let fg = |color| Style::new().fg(color);
+        // named colors, unlike the built-in palettes these work with 16
+        // color terminals
+        let palette = |off, on| Palette {
+            name: "theme".to_string(),
+            off,
+            on,
+        };
+
+        match name {
+            ThemeName::Default => Theme {
+                focus: fg(Color::Green),
+                border: Style::new(),
+                pc_line: Style::new().black().on_green(),
+                cursor_line: Style::new().black().on_blue(),
+                pc: fg(Color::Yellow),
+                sp: fg(Color::Magenta),
+                i: fg(Color::Green),
+                breakpoint: fg(Color::Red),
+                tracepoint: fg(Color::Yellow),
+                changed: [
+                    fg(Color::LightRed).bold(),
+                    fg(Color::Red),
+                    fg(Color::Red).dim(),
+                ],
+                heat: [
+                    fg(Color::Blue),
+                    fg(Color::Cyan),
+                    fg(Color::Yellow),
+                    fg(Color::LightRed).bold(),
+                ],
+                label: fg(Color::Yellow),
+                comment: fg(Color::DarkGray),
+                accent: fg(Color::Green),
+                warning: fg(Color::Yellow),
+                log_error: fg(Color::Red),
+                log_warn: fg(Color::Yellow),
+                log_info: fg(Color::Cyan),
+                log_debug: fg(Color::Green),
+                log_trace: fg(Color::Magenta),
+                palette: palette(Color::Black, [Color::White, Color::Gray, Color::DarkGray]),
+            },
+            ThemeName::Light => Theme {
+                focus: fg(Color::Blue),
+                border: fg(Color::DarkGray),
+                pc_line: Style::new().black().on_light_green(),
+                cursor_line: Style::new().black().on_light_blue(),
+                pc: fg(Color::Blue),
+                sp: fg(Color::Magenta),
+                i: fg(Color::Green),
+                breakpoint: fg(Color::Red),
+                tracepoint: fg(Color::Magenta),
+                changed: [fg(Color::Red).bold(), fg(Color::Red), fg(Color::LightRed)],
+                heat: [
+                    fg(Color::Blue),
+                    fg(Color::Green),
+                    fg(Color::Magenta),
+                    fg(Color::Red).bold(),
+                ],
+                label: fg(Color::Blue),
+                comment: fg(Color::Gray),
+                accent: fg(Color::Blue),
+                warning: fg(Color::Red),
+                log_error: fg(Color::Red),
+                log_warn: fg(Color::Magenta),
+                log_info: fg(Color::Blue),
+                log_debug: fg(Color::Green),
+                log_trace: fg(Color::DarkGray),
+                palette: palette(Color::White, [Color::Black, Color::DarkGray, Color::Gray]),
+            },
+            ThemeName::Monochrome => Theme {
+                focus: Style::new().add_modifier(Modifier::BOLD),
+                border: Style::new(),
+                pc_line: Style::new().add_modifier(Modifier::REVERSED),
+                cursor_line: Style::new().add_modifier(Modifier::UNDERLINED),
+                pc: Style::new().add_modifier(Modifier::BOLD),
+                sp: Style::new().add_modifier(Modifier::UNDERLINED),
+                i: Style::new().add_modifier(Modifier::ITALIC),
+                breakpoint: Style::new().add_modifier(Modifier::BOLD),
+                tracepoint: Style::new(),
+                changed: [
+                    Style::new().add_modifier(Modifier::REVERSED),
+                    Style::new().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                    Style::new().add_modifier(Modifier::UNDERLINED),
+                ],
+                heat: [
+                    Style::new().add_modifier(Modifier::DIM),
+                    Style::new().add_modifier(Modifier::UNDERLINED),
+                    Style::new().add_modifier(Modifier::BOLD),
+                    Style::new().add_modifier(Modifier::REVERSED),
+                ],
+                label: Style::new().add_modifier(Modifier::ITALIC),
+                comment: Style::new().add_modifier(Modifier::DIM),
+                accent: Style::new().add_modifier(Modifier::REVERSED),
+                warning: Style::new().add_modifier(Modifier::BOLD),
+                log_error: Style::new().add_modifier(Modifier::BOLD),
+                log_warn: Style::new().add_modifier(Modifier::UNDERLINED),
+                log_info: Style::new(),
+                log_debug: Style::new().add_modifier(Modifier::DIM),
+                log_trace: Style::new().add_modifier(Modifier::DIM),
+                palette: palette(Color::Black, [Color::White, Color::Gray, Color::DarkGray]),
+            },
+            ThemeName::HighContrast => Theme {
+                focus: fg(Color::LightYellow).bold(),
+                border: fg(Color::White),
+                pc_line: Style::new().black().on_light_green().bold(),
+                cursor_line: Style::new().black().on_light_cyan(),
+                pc: fg(Color::LightYellow).bold(),
+                sp: fg(Color::LightMagenta).bold(),
+                i: fg(Color::LightGreen).bold(),
+                breakpoint: fg(Color::LightRed).bold(),
+                tracepoint: fg(Color::LightYellow).bold(),
+                changed: [
+                    Style::new().black().on_light_red(),
+                    fg(Color::LightRed).bold(),
+                    fg(Color::Red),
+                ],
+                heat: [
+                    fg(Color::LightBlue),
+                    fg(Color::LightCyan),
+                    fg(Color::LightYellow).bold(),
+                    Style::new().black().on_light_red(),
+                ],
+                label: fg(Color::LightYellow),
+                comment: fg(Color::White),
+                accent: Style::new().black().on_light_yellow(),
+                warning: fg(Color::LightYellow).bold(),
+                log_error: fg(Color::LightRed).bold(),
+                log_warn: fg(Color::LightYellow),
+                log_info: fg(Color::LightCyan),
+                log_debug: fg(Color::LightGreen),
+                log_trace: fg(Color::LightMagenta),
+                palette: palette(Color::Black, [Color::White, Color::Gray, Color::DarkGray]),
+            },
+        }
+    }
+
+    /// Border style of a panel
+    pub fn border_style(&self, focused: bool) -> Style {
+        if focused {
+            self.focus
+        } else {
+            self.border
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::{Margin, Rect},
+    text::Line,
+    widgets::{Block, Clear},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(4);
+
+/// Toasts shown at once, older ones are dropped early
+const MAX_TOASTS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ToastKind {
+    Info,
+    Error,
+}
+
+/// Short lived notifications stacked in the bottom right corner
+#[derive(Default)]
+pub(crate) struct Toasts {
+    /// Oldest first
+    toasts: VecDeque<Toast>,
+}
+
+struct Toast {
+    kind: ToastKind,
+    message: String,
+    shown: Instant,
+}
+
+impl Toasts {
+    pub(crate) fn push(&mut self, kind: ToastKind, message: impl Into<String>) {
+        if self.toasts.len() == MAX_TOASTS {
+            self.toasts.pop_front();
+        }
+        self.toasts.push_back(Toast {
+            kind,
+            message: message.into(),
+            shown: Instant::now(),
+        });
+    }
+
+    /// Drop the toasts that have been up long enough
+    pub(crate) fn expire(&mut self) {
+        self.toasts
+            .retain(|toast| toast.shown.elapsed() < TOAST_DURATION);
+    }
+
+    pub(crate) fn render(&self, f: &mut Frame<'_>, area: Rect, theme: &Theme) {
+        // newest at the bottom
+        let mut bottom = area.bottom();
+        for toast in self.toasts.iter().rev() {
+            if bottom < area.y + 3 {
+                break;
+            }
+
+            let style = match toast.kind {
+                ToastKind::Info => theme.warning,
+                ToastKind::Error => theme.log_error,
+            };
+            let line = Line::styled(toast.message.as_str(), style);
+            let width = (line.width() as u16 + 4).min(area.width);
+            let toast_area = Rect::new(area.right() - width, bottom - 3, width, 3);
+            let block = Block::bordered().border_style(style);
+
+            f.render_widget(Clear, toast_area);
+            f.render_widget(line, block.inner(toast_area).inner(Margin::new(1, 0)));
+            f.render_widget(block, toast_area);
+            bottom -= 3;
+        }
+    }
+}
@@ -1,24 +1,49 @@
-use std::io::{stdout, Stdout};
+use std::io::{stdout, Stdout, Write};
 
 use anyhow::Result;
 use ratatui::{
     backend::CrosstermBackend,
     crossterm::{
+        event::{
+            KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+        },
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
     Terminal,
 };
 
+use crate::capabilities::InputMode;
+
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
-pub fn init() -> Result<Tui> {
+pub fn init(input_mode: InputMode) -> Result<Tui> {
     execute!(stdout(), EnterAlternateScreen)?;
     enable_raw_mode()?;
+    if input_mode == InputMode::Enhanced {
+        execute!(
+            stdout(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+            )
+        )?;
+    }
     Ok(Terminal::new(CrosstermBackend::new(stdout()))?)
 }
 
-pub fn restore() -> Result<()> {
+/// Ring the terminal bell, the buzzer when there's no audio device
+pub fn bell() -> Result<()> {
+    let mut out = stdout();
+    out.write_all(b"\x07")?;
+    out.flush()?;
+    Ok(())
+}
+
+pub fn restore(input_mode: InputMode) -> Result<()> {
+    if input_mode == InputMode::Enhanced {
+        execute!(stdout(), PopKeyboardEnhancementFlags)?;
+    }
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
@@ -0,0 +1,157 @@
+//! Browser frontend drawing a [`Chip8`] to a canvas, driven by the page's
+//! `requestAnimationFrame` loop.
+//!
+//! ```sh
+//! wasm-pack build c8rs-web --target web
+//! python3 -m http.server -d c8rs-web
+//! ```
+//!
+//! then open <http://localhost:8000> and pick a ROM.
+
+use c8rs_core::Chip8;
+use wasm_bindgen::{prelude::*, Clamped, JsCast};
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, ImageData};
+
+/// Keyboard key for each keypad key, indexed by keypad key, the same layout
+/// as the TUI's
+const KEYS: [char; 16] = [
+    'x', '1', '2', '3', 'q', 'w', 'e', 'a', 's', 'd', 'z', 'c', '4', 'r', 'f', 'v',
+];
+
+/// RGBA of lit and unlit pixels
+const PIXEL_ON: [u8; 4] = [0xFF, 0xFF, 0xFF, 0xFF];
+const PIXEL_OFF: [u8; 4] = [0x00, 0x00, 0x00, 0xFF];
+
+#[wasm_bindgen]
+pub struct WebEmulator {
+    chip8: Chip8,
+    canvas: HtmlCanvasElement,
+    ctx: CanvasRenderingContext2d,
+    /// The CPU halted or faulted, frames only redraw from now on
+    halted: bool,
+}
+
+#[wasm_bindgen]
+impl WebEmulator {
+    /// Run `rom` on the canvas with id `canvas_id`
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas_id: &str, rom: &[u8]) -> Result<WebEmulator, JsValue> {
+        let canvas: HtmlCanvasElement = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(canvas_id))
+            .ok_or_else(|| JsValue::from_str(&format!("No element '{canvas_id}'")))?
+            .dyn_into()?;
+        let ctx: CanvasRenderingContext2d = canvas
+            .get_context("2d")?
+            .ok_or_else(|| JsValue::from_str("No 2D canvas context"))?
+            .dyn_into()?;
+
+        let mut chip8 = Chip8::new(rom);
+        chip8.cpu_mut().rng = (js_sys::Math::random() * u32::MAX as f64) as u32;
+
+        Ok(WebEmulator {
+            chip8,
+            canvas,
+            ctx,
+            halted: false,
+        })
+    }
+
+    /// Run one 60 Hz frame and draw it
+    pub fn frame(&mut self) -> Result<(), JsValue> {
+        if !self.halted {
+            self.halted = self.chip8.frame();
+        }
+        self.draw()
+    }
+
+    pub fn reset(&mut self) {
+        self.chip8.reset();
+        self.halted = false;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ips(&self) -> u32 {
+        self.chip8.ips()
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_ips(&mut self, ips: u32) {
+        self.chip8.set_ips(ips);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn halted(&self) -> bool {
+        self.halted
+    }
+
+    /// Whether the buzzer should be playing
+    #[wasm_bindgen(getter)]
+    pub fn sound(&self) -> bool {
+        self.chip8.cpu().sound_timer > 0
+    }
+
+    /// Press the keypad key mapped to `key`, a `KeyboardEvent.key`. Returns
+    /// whether the key is mapped, so the page can leave other keys alone.
+    pub fn key_down(&mut self, key: &str) -> bool {
+        self.set_key(key, true)
+    }
+
+    pub fn key_up(&mut self, key: &str) -> bool {
+        self.set_key(key, false)
+    }
+}
+
+impl WebEmulator {
+    fn set_key(&mut self, key: &str, pressed: bool) -> bool {
+        let Some(key) = keypad_key(key) else {
+            return false;
+        };
+        self.chip8.cpu_mut().keys[key as usize] = pressed;
+        true
+    }
+
+    /// Put the display on the canvas at one canvas pixel per CHIP-8 pixel,
+    /// the page scales it up
+    fn draw(&self) -> Result<(), JsValue> {
+        let display = self.chip8.display();
+        let (width, height) = display.get_dimensions();
+        let (width, height) = (width as u32, height as u32);
+        if self.canvas.width() != width || self.canvas.height() != height {
+            self.canvas.set_width(width);
+            self.canvas.set_height(height);
+        }
+
+        let data: Vec<u8> = display
+            .get_pixels()
+            .into_iter()
+            .flat_map(|on| if on { PIXEL_ON } else { PIXEL_OFF })
+            .collect();
+        let image = ImageData::new_with_u8_clamped_array_and_sh(Clamped(&data), width, height)?;
+        self.ctx.put_image_data(&image, 0.0, 0.0)
+    }
+}
+
+/// Keypad key typed with `key`, shifted or not
+fn keypad_key(key: &str) -> Option<u8> {
+    let mut chars = key.chars();
+    let (Some(c), None) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    let c = c.to_ascii_lowercase();
+    KEYS.iter().position(|&k| k == c).map(|key| key as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keypad_key() {
+        assert_eq!(keypad_key("x"), Some(0x0));
+        assert_eq!(keypad_key("1"), Some(0x1));
+        assert_eq!(keypad_key("V"), Some(0xF));
+        assert_eq!(keypad_key("p"), None);
+        assert_eq!(keypad_key("Shift"), None);
+    }
+}
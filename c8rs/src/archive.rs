@@ -0,0 +1,117 @@
+use std::io::{Cursor, Read};
+
+use anyhow::{anyhow, bail, Result};
+use c8rs_core::memory::XO_MEM_SIZE;
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+/// Most bytes an archive may decompress to, no ROM fits in more than the
+/// largest memory
+const MAX_SIZE: usize = XO_MEM_SIZE;
+
+/// ROM contained in `data`, read from a file called `name`: the
+/// decompressed contents of a `.gz` file, the first `.ch8` entry of a `.zip`
+/// file, or `data` itself for anything else
+pub fn unpack(name: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    let name = name.to_ascii_lowercase();
+    if name.ends_with(".gz") {
+        read_capped(GzDecoder::new(&data[..]))
+    } else if name.ends_with(".zip") {
+        let mut zip = ZipArchive::new(Cursor::new(data))?;
+        let index = (0..zip.len())
+            .find(|&i| {
+                zip.name_for_index(i)
+                    .and_then(|name| name.ok())
+                    .is_some_and(|name| name.to_ascii_lowercase().ends_with(".ch8"))
+            })
+            .ok_or_else(|| anyhow!("no .ch8 file in archive"))?;
+        let rom = read_capped(zip.by_index(index)?);
+        rom
+    } else {
+        Ok(data)
+    }
+}
+
+/// Everything `reader` decompresses to, failing instead of going past
+/// [`MAX_SIZE`]
+fn read_capped(reader: impl Read) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    reader.take(MAX_SIZE as u64 + 1).read_to_end(&mut out)?;
+    if out.len() > MAX_SIZE {
+        bail!("decompresses to more than {MAX_SIZE} bytes");
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_gunzip() {
+        let data = [
+            0x1F, 0x8B, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, 0x03, 0x63, 0x78, 0x20, 0xC4,
+            0x00, 0x00, 0x6F, 0x7F, 0x52, 0xF0, 0x04, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(
+            unpack("pong.ch8.GZ", data.to_vec()).unwrap(),
+            vec![0x00, 0xE0, 0x12, 0x00]
+        );
+
+        let mut corrupt = data;
+        corrupt[16] ^= 1;
+        assert!(unpack("pong.ch8.gz", corrupt.to_vec()).is_err());
+        assert!(unpack("pong.ch8.gz", data[..12].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_zip() {
+        // readme.txt stored, then Game.CH8 deflated
+        let data = [
+            0x50, 0x4B, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x21, 0x00,
+            0xAC, 0x2A, 0x93, 0xD8, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0A, 0x00,
+            0x00, 0x00, 0x72, 0x65, 0x61, 0x64, 0x6D, 0x65, 0x2E, 0x74, 0x78, 0x74, 0x68, 0x69,
+            0x50, 0x4B, 0x03, 0x04, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x21, 0x00,
+            0x18, 0x70, 0x04, 0x71, 0x06, 0x00, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x08, 0x00,
+            0x00, 0x00, 0x47, 0x61, 0x6D, 0x65, 0x2E, 0x43, 0x48, 0x38, 0x63, 0x78, 0xC0, 0x80,
+            0x02, 0x01, 0x50, 0x4B, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x21, 0x00, 0xAC, 0x2A, 0x93, 0xD8, 0x02, 0x00, 0x00, 0x00, 0x02, 0x00,
+            0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x80, 0x01, 0x00, 0x00, 0x00, 0x00, 0x72, 0x65, 0x61, 0x64, 0x6D, 0x65, 0x2E, 0x74,
+            0x78, 0x74, 0x50, 0x4B, 0x01, 0x02, 0x14, 0x03, 0x14, 0x00, 0x00, 0x00, 0x08, 0x00,
+            0x00, 0x00, 0x21, 0x00, 0x18, 0x70, 0x04, 0x71, 0x06, 0x00, 0x00, 0x00, 0x10, 0x00,
+            0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x80, 0x01, 0x2A, 0x00, 0x00, 0x00, 0x47, 0x61, 0x6D, 0x65, 0x2E, 0x43, 0x48, 0x38,
+            0x50, 0x4B, 0x05, 0x06, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x02, 0x00, 0x6E, 0x00,
+            0x00, 0x00, 0x56, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+
+        assert_eq!(
+            unpack("roms.zip", data.to_vec()).unwrap(),
+            [0x00, 0xE0].repeat(8)
+        );
+        assert!(unpack("roms.zip", data[..100].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_size_cap() {
+        let gzip = |len: usize| {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+            encoder.write_all(&vec![0; len]).unwrap();
+            encoder.finish().unwrap()
+        };
+
+        assert_eq!(unpack("big.gz", gzip(MAX_SIZE)).unwrap().len(), MAX_SIZE);
+        let err = unpack("bomb.gz", gzip(100 * MAX_SIZE)).unwrap_err();
+        assert!(err.to_string().contains("decompresses to more than"));
+    }
+
+    #[test]
+    fn test_unpack_plain() {
+        assert_eq!(
+            unpack("pong.ch8", vec![0x12, 0x00]).unwrap(),
+            vec![0x12, 0x00]
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    collections::HashMap,
+    mem::Discriminant,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use c8rs_core::{chip8::TIMER_HZ, memory::MEM_SIZE, Chip8, Instruction, Quirks, Variant};
+use clap::Parser;
+
+/// Program benchmarked when no ROM is given: register arithmetic, a sprite
+/// draw and a subroutine call per iteration, roughly the mix of a game loop
+const SYNTHETIC_MIX: &[&str] = &[
+    "LD V2, 0x1F",
+    // 0x202: loop
+    "ADD V0, 0x05",
+    "ADD V1, 0x03",
+    "AND V1, V2",
+    "LD V3, V0",
+    "XOR V3, V1",
+    "SHR V3, V3",
+    "LD F, V3",
+    "DRW V0, V1, 0x05",
+    "CALL 0x021A",
+    "SE V4, 0x00",
+    "ADD V5, 0x01",
+    "JMP 0x0202",
+    // 0x21A: subroutine
+    "ADD V4, V3",
+    "RND V6, 0xFF",
+    "LD I, 0x0300",
+    "BCD V4",
+    "RET",
+];
+
+#[derive(Parser, Debug)]
+pub struct BenchArgs {
+    /// ROM file, `-` for stdin or an http(s) URL. Runs a synthetic
+    /// instruction mix if not given.
+    rom: Option<String>,
+
+    #[arg(long, default_value_t = 3.0)]
+    /// seconds to run each of the throughput and per-opcode passes for
+    seconds: f64,
+
+    #[arg(long, default_value_t = 700)]
+    /// instructions per frame are `ips / 60`, frames run back to back
+    ips: u32,
+
+    #[arg(long, value_enum, value_name = "PRESET")]
+    /// emulate the quirks of another interpreter
+    quirks: Option<Variant>,
+}
+
+/// Time spent in one kind of instruction during the per-opcode pass
+#[derive(Default)]
+struct OpcodeStats {
+    count: u64,
+    time: Duration,
+}
+
+/// Run a ROM as fast as possible and print instructions and frames per
+/// second, then time each instruction kind individually
+pub fn run(args: BenchArgs) -> Result<()> {
+    let (name, rom) = match &args.rom {
+        Some(source) => (source.clone(), crate::read_rom(source)?),
+        None => ("synthetic instruction mix".to_string(), synthetic_mix()?),
+    };
+    let duration = Duration::try_from_secs_f64(args.seconds)
+        .map_err(|_| anyhow!("--seconds must be a positive number"))?;
+    crate::check_rom_size(&rom, MEM_SIZE)?;
+
+    let mut chip8 = Chip8::new(&rom);
+    chip8.set_ips(args.ips);
+    if let Some(variant) = args.quirks {
+        chip8.cpu_mut().quirks = Quirks::for_variant(variant);
+    }
+
+    println!("rom:          {name}");
+
+    // throughput pass, checking the clock once per batch of frames to keep it
+    // out of the measurement
+    let mut frames = 0u64;
+    let mut restarts = 0u64;
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        for _ in 0..64 {
+            if chip8.frame() {
+                chip8.reset();
+                restarts += 1;
+            }
+        }
+        frames += 64;
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+    let ips = (frames * args.ips as u64 / TIMER_HZ as u64) as f64 / elapsed;
+    let fps = frames as f64 / elapsed;
+
+    println!(
+        "instructions: {:.0}/s, {:.0}x a {} ips machine",
+        ips,
+        ips / args.ips as f64,
+        args.ips
+    );
+    println!(
+        "frames:       {:.0}/s presented, {:.0}x realtime",
+        fps,
+        fps / TIMER_HZ as f64
+    );
+    if restarts > 0 {
+        println!("restarts:     {restarts}, the ROM halted or faulted");
+    }
+
+    // per-opcode pass, the timer overhead is included in every row
+    chip8.reset();
+    let per_frame = (args.ips / TIMER_HZ).max(1);
+    let mut stats: HashMap<Discriminant<Instruction>, (String, OpcodeStats)> = HashMap::new();
+    let mut present = OpcodeStats::default();
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        for _ in 0..per_frame {
+            let instr = Instruction::parse(chip8.memory().read_u16(chip8.cpu().pc));
+
+            let t = Instant::now();
+            let halted = chip8.step();
+            let elapsed = t.elapsed();
+
+            let (_, entry) = stats
+                .entry(std::mem::discriminant(&instr))
+                .or_insert_with(|| (kind(&instr), OpcodeStats::default()));
+            entry.time += elapsed;
+            entry.count += 1;
+
+            if halted {
+                chip8.reset();
+            }
+        }
+        chip8.tick_timers();
+
+        let t = Instant::now();
+        chip8.present();
+        present.time += t.elapsed();
+        present.count += 1;
+    }
+
+    let total: u64 = stats.values().map(|(_, s)| s.count).sum();
+    let mut rows: Vec<_> = stats.into_values().collect();
+    rows.sort_by(|(a, x), (b, y)| y.time.cmp(&x.time).then(a.cmp(b)));
+
+    if let Some(draws) = rows.iter().find(|(kind, _)| *kind == "Drw") {
+        println!(
+            "sprites:      {:.0}/s drawn",
+            draws.1.count as f64 / start.elapsed().as_secs_f64()
+        );
+    }
+
+    println!();
+    println!(
+        "{:<14} {:>12} {:>7} {:>9}",
+        "opcode", "count", "share", "ns each"
+    );
+    for (kind, s) in &rows {
+        print_row(kind, s, total);
+    }
+    print_row("(present)", &present, 0);
+
+    Ok(())
+}
+
+fn print_row(kind: &str, stats: &OpcodeStats, total: u64) {
+    let share = if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.1}%", stats.count as f64 * 100.0 / total as f64)
+    };
+    println!(
+        "{kind:<14} {:>12} {share:>7} {:>9.1}",
+        stats.count,
+        stats.time.as_nanos() as f64 / stats.count.max(1) as f64
+    );
+}
+
+/// Name of the instruction's variant, without its operands
+fn kind(instr: &Instruction) -> String {
+    let debug = format!("{instr:?}");
+    debug
+        .split([' ', '('])
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn synthetic_mix() -> Result<Vec<u8>> {
+    SYNTHETIC_MIX
+        .iter()
+        .map(|line| {
+            line.parse::<Instruction>()
+                .map(|instr| instr.encode().to_be_bytes())
+                .map_err(|err| anyhow!("{line}: {err}"))
+        })
+        .collect::<Result<Vec<_>>>()
+        .map(|words| words.concat())
+}
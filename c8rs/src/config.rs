@@ -0,0 +1,344 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{anyhow, bail, Result};
+use c8rs_core::Variant;
+use c8rs_tui::{Action, Palette, TuiConfig};
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// Settings from `config.toml`, command-line flags override them
+///
+/// ```toml
+/// ips = 1000
+/// # a power of two from 0x1000 to 0x10000
+/// memory_size = 0x10000
+/// quirks = "schip"
+/// # default, light, monochrome or high-contrast
+/// theme = "light"
+/// # classic, inverted, amber, phosphor, lcd or octo
+/// palette = "amber"
+/// # don't play the buzzer
+/// mute = true
+/// # the default output device if unset, needs the `audio` feature
+/// audio_device = "USB Audio"
+///
+/// [keys]
+/// # hex keypad key = keyboard key
+/// 5 = "w"
+///
+/// [colors]
+/// # override the theme's
+/// pixel_on = "#33ff66"
+/// pixel_off = "black"
+/// plane2 = "#ff6600"
+/// planes_both = "#662200"
+/// focus = "yellow"
+///
+/// [bindings]
+/// # see `c8rs_tui::Action` for the names, keys are characters, `tab`,
+/// # `esc`, `f1`-`f12` etc. with optional `ctrl+` or `alt+`
+/// quit = "ctrl+q"
+/// play_mode = "f8"
+///
+/// [layout]
+/// # default, debug, display or minimal
+/// preset = "debug"
+/// # display, cpu, disasm, memory, log or debugger
+/// hide = ["cpu", "log"]
+/// disasm_width = 37
+/// memory_width = 79
+/// show_log = false
+/// ```
+#[derive(Debug, Default)]
+pub struct Config {
+    pub ips: Option<u32>,
+    pub memory_size: Option<usize>,
+    pub quirks: Option<Variant>,
+    pub tui: TuiConfig,
+}
+
+/// `config.toml` as written, checked and applied to the defaults by
+/// [`ConfigFile::apply`]
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    ips: Option<u32>,
+    memory_size: Option<usize>,
+    quirks: Option<String>,
+    theme: Option<String>,
+    palette: Option<String>,
+    mute: Option<bool>,
+    audio_device: Option<String>,
+    keys: BTreeMap<String, String>,
+    colors: ColorsFile,
+    bindings: BTreeMap<String, String>,
+    layout: LayoutFile,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ColorsFile {
+    pixel_on: Option<String>,
+    pixel_off: Option<String>,
+    plane2: Option<String>,
+    planes_both: Option<String>,
+    focus: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LayoutFile {
+    preset: Option<String>,
+    hide: Option<Vec<String>>,
+    disasm_width: Option<u16>,
+    memory_width: Option<u16>,
+    show_log: Option<bool>,
+}
+
+impl Config {
+    /// Read `path`, a missing file gives the defaults unless `required`
+    pub fn load(path: &Path, required: bool) -> Result<Config> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => Config::parse(&s).map_err(|err| anyhow!("{}: {err}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => {
+                Ok(Config::default())
+            }
+            Err(err) => Err(anyhow!("Failed to read {}: {err}", path.display())),
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Config> {
+        let file: ConfigFile = toml::from_str(s).map_err(|err| anyhow!("{err}"))?;
+        let mut config = Config::default();
+        file.apply(&mut config)?;
+        config
+            .tui
+            .bindings
+            .validate(&config.tui.keys)
+            .map_err(|err| anyhow!(err))?;
+        Ok(config)
+    }
+}
+
+impl ConfigFile {
+    fn apply(self, config: &mut Config) -> Result<()> {
+        if let Some(ips) = self.ips {
+            config.ips = Some(ips);
+        }
+        if let Some(size) = self.memory_size {
+            let size =
+                crate::check_memory_size(size).map_err(|err| anyhow!("memory_size: {err}"))?;
+            config.memory_size = Some(size);
+        }
+        if let Some(name) = self.quirks {
+            let variant = Variant::from_str(&name, true)
+                .map_err(|_| anyhow!("quirks: unknown quirk preset '{name}'"))?;
+            config.quirks = Some(variant);
+        }
+
+        let tui = &mut config.tui;
+        if let Some(mute) = self.mute {
+            tui.mute = mute;
+        }
+        if let Some(device) = self.audio_device {
+            tui.audio_device = Some(device);
+        }
+        if let Some(name) = self.theme {
+            tui.theme = name.parse().map_err(|err| anyhow!("theme: {err}"))?;
+        }
+        if let Some(name) = self.palette {
+            let palette = Palette::named(&name)
+                .ok_or_else(|| anyhow!("palette: unknown palette '{name}'"))?;
+            tui.palette = Some(palette);
+        }
+
+        for (key, name) in self.keys {
+            let keypad = u8::from_str_radix(&key, 16)
+                .ok()
+                .filter(|&key| key <= 0xF)
+                .ok_or_else(|| anyhow!("keys: unknown keypad key '{key}'"))?;
+            let mut chars = name.chars();
+            let (Some(c), None) = (chars.next(), chars.next()) else {
+                bail!("keys.{key}: expected a single character, got '{name}'");
+            };
+            tui.keys[keypad as usize] = c.to_ascii_lowercase();
+        }
+
+        let c = self.colors;
+        let colors = [
+            ("pixel_on", c.pixel_on, &mut tui.colors.pixel_on),
+            ("pixel_off", c.pixel_off, &mut tui.colors.pixel_off),
+            ("plane2", c.plane2, &mut tui.colors.plane2),
+            ("planes_both", c.planes_both, &mut tui.colors.planes_both),
+            ("focus", c.focus, &mut tui.colors.focus),
+        ];
+        for (key, name, color) in colors {
+            if let Some(name) = name {
+                let parsed = name
+                    .parse()
+                    .map_err(|_| anyhow!("colors.{key}: unknown color '{name}'"))?;
+                *color = Some(parsed);
+            }
+        }
+
+        for (name, key) in self.bindings {
+            let action: Action = name.parse().map_err(|err| anyhow!("bindings: {err}"))?;
+            let key = key
+                .parse()
+                .map_err(|err| anyhow!("bindings.{name}: {err}"))?;
+            tui.bindings.set(action, key);
+        }
+
+        let layout = &mut tui.layout;
+        if let Some(name) = self.layout.preset {
+            layout.preset = name
+                .parse()
+                .map_err(|err| anyhow!("layout.preset: {err}"))?;
+        }
+        if let Some(hide) = self.layout.hide {
+            layout.hidden = hide
+                .iter()
+                .map(|name| name.parse().map_err(|err| anyhow!("layout.hide: {err}")))
+                .collect::<Result<_>>()?;
+        }
+        if let Some(width) = self.layout.disasm_width {
+            layout.disasm_width = width;
+        }
+        if let Some(width) = self.layout.memory_width {
+            layout.memory_width = width;
+        }
+        if let Some(show_log) = self.layout.show_log {
+            layout.show_log = show_log;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c8rs_tui::{LayoutPreset, Panel, ThemeName};
+
+    #[test]
+    fn test_overrides() {
+        let config = Config::parse(
+            r##"
+            ips = 1_000
+            memory_size = 0x10000
+            quirks = "schip"
+            theme = "light"
+            mute = true
+            audio_device = "USB Audio"
+
+            [keys]
+            5 = "W"
+            F = "v"
+
+            [colors]
+            pixel_on = "#33ff66"
+
+            [bindings]
+            quit = "ctrl+q"
+
+            [layout]
+            preset = "debug"
+            hide = ["cpu", "log"]
+            disasm_width = 40
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.ips, Some(1000));
+        assert_eq!(config.memory_size, Some(0x10000));
+        assert_eq!(config.quirks, Some(Variant::Schip));
+
+        let tui = &config.tui;
+        let defaults = TuiConfig::default();
+        assert_eq!(tui.theme, ThemeName::Light);
+        assert!(tui.mute);
+        assert_eq!(tui.audio_device.as_deref(), Some("USB Audio"));
+        assert_eq!(tui.keys[5], 'w');
+        assert_eq!(tui.keys[0xF], 'v');
+        assert_eq!(tui.keys[0], defaults.keys[0]);
+        assert!(tui.colors.pixel_on.is_some());
+        assert_eq!(tui.colors.pixel_off, None);
+        assert_eq!(tui.bindings.key(Action::Quit), "ctrl+q".parse().unwrap());
+        assert_eq!(tui.layout.preset, LayoutPreset::Debug);
+        assert_eq!(tui.layout.hidden, vec![Panel::Cpu, Panel::Log]);
+        assert_eq!(tui.layout.disasm_width, 40);
+        assert_eq!(tui.layout.memory_width, defaults.layout.memory_width);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let config = Config::parse("# nothing set\n").unwrap();
+        assert_eq!(config.ips, None);
+        assert_eq!(config.memory_size, None);
+        assert_eq!(config.quirks, None);
+        assert_eq!(config.tui, TuiConfig::default());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s| Config::parse(s).unwrap_err().to_string();
+
+        assert!(error("ips = \"fast\"").contains("line 1"));
+        assert!(error("ips = -1").contains("line 1"));
+        assert!(error("speed = 2").contains("unknown field `speed`"));
+        assert!(error("[layout]\nwidth = 2").contains("line 2"));
+        assert!(error("[sound]").contains("unknown field `sound`"));
+        assert!(error("[layout]\nhide = \"cpu, log\"").contains("invalid type"));
+        assert!(error("ips = 10\nips = 20").contains("duplicate key"));
+
+        assert_eq!(
+            error("memory_size = 0x1800"),
+            "memory_size: must be a power of two from 0x1000 to 0x10000, got 0x1800"
+        );
+        assert_eq!(
+            error("quirks = \"cosmac\""),
+            "quirks: unknown quirk preset 'cosmac'"
+        );
+        assert_eq!(error("[keys]\n10 = \"a\""), "keys: unknown keypad key '10'");
+        assert_eq!(
+            error("[keys]\n1 = \"ab\""),
+            "keys.1: expected a single character, got 'ab'"
+        );
+        assert_eq!(
+            error("[colors]\nfocus = \"blurple\""),
+            "colors.focus: unknown color 'blurple'"
+        );
+        assert_eq!(
+            error("[bindings]\nfly = \"f\""),
+            "bindings: unknown action 'fly'"
+        );
+        assert_eq!(
+            error("[layout]\nhide = [\"cpu\", \"sidebar\"]"),
+            "layout.hide: unknown panel 'sidebar'"
+        );
+    }
+
+    #[test]
+    fn test_binding_conflicts() {
+        let error = |s| Config::parse(s).unwrap_err().to_string();
+
+        assert_eq!(
+            error("[bindings]\nquit = \"j\""),
+            "'j' is bound to both down and quit"
+        );
+
+        assert_eq!(
+            error("[bindings]\nplay_mode = \"Q\""),
+            "'Q' is bound to both play_mode and keypad key 4"
+        );
+        assert_eq!(
+            error("[keys]\n4 = \"y\"\n[bindings]\nplay_mode = \"y\""),
+            "'y' is bound to both play_mode and keypad key 4"
+        );
+        assert!(Config::parse("[bindings]\nplay_mode = \"ctrl+q\"").is_ok());
+        assert!(Config::parse(
+            "[keys]\n4 = \"y\"\n[bindings]\nquit = \"ctrl+q\"\nplay_mode = \"q\""
+        )
+        .is_ok());
+    }
+}
@@ -1,14 +1,42 @@
-use std::{fs::File, io::Read};
+use std::{
+    fs::File,
+    io::{BufWriter, Read},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
 
-use anyhow::Result;
-use c8rs_core::Chip8Emulator;
-use c8rs_disasm::DisassemblerArgs;
+use anyhow::{anyhow, bail, Result};
+use bench::BenchArgs;
+use c8rs_asm::AssemblerArgs;
+use c8rs_core::{
+    display::Display,
+    hash::sha1_hex,
+    memory::{MEM_SIZE, XO_MEM_SIZE},
+    trace, Chip8Emulator, DebugCommand, EmulatorCommand, Expr, InputRecording, InstructionTrace,
+    OpcodeClass, Quirks, SourceMap, TraceFilter, TraceFormat, Variant,
+};
+use c8rs_disasm::{DisassemblerArgs, InfoArgs, SpritesArgs};
+use c8rs_tui::DEFAULT_LOG_ADDR;
 use clap::Parser;
+use clap_num::maybe_hex;
+use config::Config;
+use romdb::RomDatabase;
+use suite::TestArgs;
+
+mod archive;
+mod bench;
+mod config;
+mod romdb;
+mod suite;
 
 #[derive(Parser, Debug)]
 struct Args {
     #[clap(subcommand)]
     command: Command,
+
+    #[arg(long, global = true, value_name = "FILE")]
+    /// config file to use instead of ~/.config/c8rs/config.toml
+    config: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -19,11 +47,142 @@ enum Command {
     /// Disassemble chip-8 binary
     #[command(visible_alias = "dis")]
     Disassemble(DisassemblerArgs),
+
+    /// Show size, hashes and the instruction set of a chip-8 binary
+    Info(InfoArgs),
+
+    /// Find the sprites a chip-8 binary draws and print them as ASCII art
+    Sprites(SpritesArgs),
+
+    /// Assemble source into a chip-8 binary
+    #[command(visible_alias = "asm")]
+    Assemble(AssemblerArgs),
+
+    /// Run test ROMs headless and report which quirk presets pass
+    Test(TestArgs),
+
+    /// Run a ROM at unlimited speed and report emulator performance
+    Bench(BenchArgs),
+
+    /// Show logs of a `run --log-socket` session full-screen
+    Logs(LogsArgs),
 }
 
 #[derive(Parser, Debug)]
 struct RunArgs {
+    /// ROM file, `-` to read it from stdin or an http(s) URL to download it.
+    /// `.gz` and `.zip` files are decompressed.
     file: String,
+
+    #[arg(long)]
+    /// instructions executed per second
+    ips: Option<u32>,
+
+    #[arg(long, value_name = "BYTES", value_parser = parse_memory_size)]
+    /// memory size, a power of two from 0x1000 (4K, the default) to 0x10000
+    /// (XO-CHIP's 64K)
+    memory_size: Option<usize>,
+
+    #[arg(long)]
+    /// start running instead of paused
+    run: bool,
+
+    #[arg(long, value_name = "NAME")]
+    /// audio device to play the buzzer on, with the `audio` feature
+    audio_device: Option<String>,
+
+    #[arg(long = "break", value_name = "ADDR")]
+    /// set a breakpoint before starting, can be repeated
+    breakpoints: Vec<Expr>,
+
+    #[arg(long, value_enum, value_name = "PRESET")]
+    /// emulate the quirks of another interpreter
+    quirks: Option<Variant>,
+
+    #[arg(long)]
+    /// persist SCHIP RPL user flags next to the ROM (<file>.rpl)
+    persist_flags: bool,
+
+    #[arg(long)]
+    /// reload and reset when the ROM file changes, keeping breakpoints
+    watch: bool,
+
+    #[arg(long)]
+    /// pause when the ROM overwrites code it has already executed
+    pause_on_code_write: bool,
+
+    #[arg(long, value_name = "FILE")]
+    /// per-game settings in the chip-8-database `programs.json` format,
+    /// defaults to programs.json in the config directory
+    rom_db: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// labels from `c8rs asm --source-map`, for the disassembly and debugger
+    source_map: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// name addresses with the `0x0240 draw_score` lines of a symbol file,
+    /// like the ones `c8rs dis --save-symbols` writes
+    symbols: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// run debugger commands from a file at startup
+    script: Option<PathBuf>,
+
+    #[arg(long, value_name = "ADDR", num_args = 0..=1, default_missing_value = DEFAULT_LOG_ADDR)]
+    /// send logs to a `c8rs logs` viewer instead of the log panel
+    log_socket: Option<String>,
+
+    #[arg(long, value_name = "FILE")]
+    /// record keypad input and the random number seed to a file
+    record_input: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE", conflicts_with = "ips")]
+    /// play back keypad input recorded with --record-input, live input is
+    /// taken once it ends
+    replay: Option<PathBuf>,
+
+    #[arg(long, value_name = "FILE")]
+    /// write every executed instruction and the registers before it to a file
+    trace: Option<PathBuf>,
+
+    #[arg(long, value_enum, default_value_t, requires = "trace")]
+    /// layout of the --trace file
+    trace_format: TraceFormat,
+
+    #[arg(long, value_name = "START-END", value_parser = trace::parse_range, requires = "trace")]
+    /// only trace instructions at these addresses
+    trace_range: Option<RangeInclusive<u16>>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "CLASS",
+        value_delimiter = ',',
+        requires = "trace"
+    )]
+    /// only trace instructions of these classes, can be repeated
+    trace_class: Vec<OpcodeClass>,
+
+    #[cfg(feature = "gui")]
+    #[arg(long, conflicts_with_all = ["watch", "record_session", "replay_session", "audio_device"])]
+    /// open a window instead of the terminal UI
+    gui: bool,
+
+    #[arg(long, value_name = "FILE")]
+    /// record TUI key events and emulator commands to a file
+    record_session: Option<String>,
+
+    #[arg(long, value_name = "FILE")]
+    /// re-drive the TUI from a recorded session
+    replay_session: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+struct LogsArgs {
+    #[arg(long, default_value = DEFAULT_LOG_ADDR)]
+    /// address to listen on
+    addr: String,
 }
 
 #[tokio::main]
@@ -31,32 +190,388 @@ async fn main() {
     let args = Args::parse();
 
     let res = match args.command {
-        Command::Run(args) => run(args).await,
+        Command::Run(run_args) => match load_config(args.config.as_deref()) {
+            Ok(config) => run(run_args, config).await,
+            Err(err) => Err(err),
+        },
         Command::Disassemble(args) => disassemble(args),
+        Command::Info(args) => c8rs_disasm::info(args, read_rom),
+        Command::Sprites(args) => c8rs_disasm::sprites(args, read_rom),
+        Command::Assemble(args) => c8rs_asm::assemble(args),
+        Command::Test(args) => match suite::run(args) {
+            Ok(true) => Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(err) => Err(err),
+        },
+        Command::Bench(args) => bench::run(args),
+        Command::Logs(args) => c8rs_tui::run_log_viewer(&args.addr).await,
     };
 
     if let Err(err) = res {
         println!("{err:?}");
+        std::process::exit(1);
     }
 }
 
-async fn run(args: RunArgs) -> Result<()> {
-    let mut file = File::open(args.file)?;
+async fn run(args: RunArgs, mut config: Config) -> Result<()> {
+    let buf = read_rom(&args.file)?;
 
-    let mut buf = Vec::new();
-    file.read_to_end(&mut buf)?;
+    let db = match (&args.rom_db, config_dir()) {
+        (Some(path), _) => RomDatabase::load(path, true)?,
+        (None, Some(dir)) => RomDatabase::load(&dir.join("programs.json"), false)?,
+        (None, None) => None,
+    };
+    let rom_info = db.and_then(|db| db.find(&sha1_hex(&buf)));
+    if let Some(info) = &rom_info {
+        info.apply(&mut config);
+    }
 
-    let emu = Chip8Emulator::new(&buf);
+    let mem_size = args.memory_size.or(config.memory_size).unwrap_or(MEM_SIZE);
+    check_rom_size(&buf, mem_size).map_err(|err| anyhow!("{err}, see --memory-size"))?;
+
+    let mut emu = Chip8Emulator::with_mem_size(&buf, mem_size)
+        .with_rom_reader(|path| read_rom(&path.to_string_lossy()).map_err(|err| err.to_string()))
+        .with_png_encoder(encode_png);
+    if args.watch && (is_url(&args.file) || args.file == "-") {
+        bail!("--watch needs a ROM file to watch");
+    }
+    if args.persist_flags {
+        if is_url(&args.file) || args.file == "-" {
+            bail!("--persist-flags needs a ROM file to store the flags next to");
+        }
+        emu = emu.with_flags_file(Path::new(&args.file).with_extension("rpl"));
+    }
+    if args.pause_on_code_write {
+        emu = emu.with_pause_on_code_write();
+    }
+    if let Some(variant) = args.quirks.or(config.quirks) {
+        emu = emu.with_quirks(Quirks::for_variant(variant));
+    }
+    if let Some(dir) = config_dir() {
+        emu = emu.with_aliases_file(dir.join("aliases"));
+    }
+    if let Some(path) = &args.record_input {
+        let file = File::create(path)
+            .map_err(|err| anyhow!("Failed to create {}: {err}", path.display()))?;
+        emu = emu.with_input_recording(file);
+    }
+    if let Some(path) = &args.replay {
+        let recording = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Failed to read {}: {err}", path.display()))?;
+        let recording = InputRecording::parse(&recording)
+            .map_err(|err| anyhow!("{}: {err}", path.display()))?;
+        if recording
+            .rom
+            .as_ref()
+            .is_some_and(|rom| *rom != sha1_hex(&buf))
+        {
+            bail!("{} was recorded with a different ROM", path.display());
+        }
+        emu = emu.with_input_replay(recording);
+    }
+    if let Some(path) = &args.trace {
+        let file = File::create(path)
+            .map_err(|err| anyhow!("Failed to create {}: {err}", path.display()))?;
+        let trace = InstructionTrace::new(BufWriter::new(file), args.trace_format).with_filter(
+            TraceFilter {
+                range: args.trace_range.clone(),
+                classes: args.trace_class.clone(),
+            },
+        );
+        emu = emu.with_instruction_trace(trace);
+    }
+    let mut source_map = SourceMap::new();
+    if let Some(path) = &args.source_map {
+        let map = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Failed to read {}: {err}", path.display()))?;
+        source_map = SourceMap::parse(&map).map_err(|err| anyhow!("{}: {err}", path.display()))?;
+    }
+    if let Some(path) = &args.symbols {
+        let symbols = std::fs::read_to_string(path)
+            .map_err(|err| anyhow!("Failed to read {}: {err}", path.display()))?;
+        let symbols = c8rs_disasm::parse_symbols(&symbols)
+            .map_err(|err| anyhow!("{}: {err}", path.display()))?;
+        for (addr, name) in symbols {
+            source_map.add_label(&name, addr);
+        }
+    }
+    if !source_map.is_empty() {
+        emu = emu.with_source_map(source_map);
+    }
     let controller = emu.controller();
+    // a replay runs at the IPS it was recorded with
+    if let Some(ips) = args.ips.or(config.ips).filter(|_| args.replay.is_none()) {
+        controller.set_ips(ips)?;
+    }
+    for addr in args.breakpoints {
+        controller.send(EmulatorCommand::DebugCommand(DebugCommand::Breakpoint {
+            addr,
+            condition: None,
+        }))?;
+    }
+    if let Some(file) = args.script {
+        controller.send(EmulatorCommand::DebugCommand(DebugCommand::Source { file }))?;
+    }
+    if args.run {
+        controller.resume()?;
+    }
 
-    let mut app = c8rs_tui::App::new(controller);
-    c8rs_tui::App::init_logger();
+    #[cfg(feature = "gui")]
+    if args.gui {
+        if let Some(addr) = &args.log_socket {
+            c8rs_tui::App::init_remote_logger(addr)?;
+        }
+        let handle = emu.start();
+        let result = c8rs_gui::run(controller, rom_info.map(|info| info.title), config.tui.keys)
+            .map_err(|err| anyhow!("Failed to open a window: {err}"));
+        handle
+            .stop()
+            .map_err(|_| anyhow!("emulator thread panicked"))?;
+        return result;
+    }
 
-    emu.start();
+    if let Some(device) = args.audio_device {
+        config.tui.audio_device = Some(device);
+    }
+    let mut app = c8rs_tui::App::new(controller).with_config(config.tui);
+    if !is_url(&args.file) && args.file != "-" {
+        app = app.with_rom_path(&args.file);
+    }
+    if let Some(info) = rom_info {
+        app = app.with_title(info.title);
+    }
+    match &args.log_socket {
+        Some(addr) => {
+            c8rs_tui::App::init_remote_logger(addr)?;
+            app = app.with_detached_log();
+        }
+        None => c8rs_tui::App::init_logger(),
+    }
+    if args.watch {
+        // `load` only takes names that look like paths
+        let path = std::fs::canonicalize(&args.file)
+            .map_err(|err| anyhow!("Failed to read {}: {err}", args.file))?;
+        app = app.with_rom_watch(path);
+    }
+    if let Some(path) = &args.record_session {
+        app = app.with_session_recording(path)?;
+    }
+    if let Some(path) = &args.replay_session {
+        app = app.with_session_replay(path)?;
+    }
+
+    let handle = emu.start();
+
+    let result = app.run().await;
+    handle
+        .stop()
+        .map_err(|_| anyhow!("emulator thread panicked"))?;
+
+    result
+}
+
+/// Read a ROM from a file, stdin for `-`, or download it from an http(s) URL,
+/// decompressing `.gz` and `.zip` archives
+fn read_rom(source: &str) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+
+    if source == "-" {
+        std::io::stdin()
+            .read_to_end(&mut buf)
+            .map_err(|err| anyhow!("Failed to read ROM from stdin: {err}"))?;
+    } else if is_url(source) {
+        buf = download(source)?;
+    } else {
+        File::open(source)?.read_to_end(&mut buf)?;
+    }
+
+    if source == "-" {
+        return Ok(buf);
+    }
+    let name = source.split(['?', '#']).next().unwrap_or(source);
+    archive::unpack(name, buf).map_err(|err| anyhow!("{source}: {err}"))
+}
+
+/// Fail if `rom` doesn't fit in `mem_size` bytes of memory after 0x200,
+/// rather than wrapping over the font and interpreter area
+fn check_rom_size(rom: &[u8], mem_size: usize) -> Result<()> {
+    let max_len = mem_size - 0x200;
+    if rom.len() > max_len {
+        bail!(
+            "ROM is {} bytes, at most {max_len} fit in {mem_size:#X} bytes of memory",
+            rom.len()
+        );
+    }
+    Ok(())
+}
+
+fn parse_memory_size(s: &str) -> Result<usize, String> {
+    check_memory_size(maybe_hex(s)?)
+}
+
+/// `size` if it's a power of two between the classic 4K and XO-CHIP's 64K
+fn check_memory_size(size: usize) -> Result<usize, String> {
+    if !size.is_power_of_two() || !(MEM_SIZE..=XO_MEM_SIZE).contains(&size) {
+        return Err(format!(
+            "must be a power of two from {MEM_SIZE:#X} to {XO_MEM_SIZE:#X}, got {size:#X}"
+        ));
+    }
+    Ok(size)
+}
+
+/// Most bytes downloaded for a ROM, room for an archive of several
+#[cfg(feature = "download")]
+const MAX_DOWNLOAD_SIZE: u64 = 16 * 1024 * 1024;
+
+#[cfg(feature = "download")]
+fn download(url: &str) -> Result<Vec<u8>> {
+    ureq::get(url)
+        .call()
+        .and_then(|mut response| {
+            response
+                .body_mut()
+                .with_config()
+                .limit(MAX_DOWNLOAD_SIZE)
+                .read_to_vec()
+        })
+        .map_err(|err| anyhow!("Failed to download {url}: {err}"))
+}
+
+#[cfg(not(feature = "download"))]
+fn download(url: &str) -> Result<Vec<u8>> {
+    bail!("Can't download {url}, c8rs was built without the download feature")
+}
+
+fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// 1-bit grayscale PNG of `display`, for the `screenshot` command
+fn encode_png(display: &Display) -> Result<Vec<u8>, String> {
+    let (width, height) = display.get_dimensions();
+    let packed: Vec<u8> = display
+        .get_pixels()
+        .chunks(width)
+        .flat_map(|row| {
+            row.chunks(8).map(|bits| {
+                bits.iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (n, &bit)| byte | ((bit as u8) << (7 - n)))
+            })
+        })
+        .collect();
+
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    encoder
+        .write_header()
+        .and_then(|mut writer| {
+            writer.write_image_data(&packed)?;
+            writer.finish()
+        })
+        .map_err(|err| format!("Failed to encode PNG: {err}"))?;
+    Ok(out)
+}
+
+/// Load `path`, or `config.toml` in the config directory if it exists
+fn load_config(path: Option<&Path>) -> Result<Config> {
+    match path {
+        Some(path) => Config::load(path, true),
+        None => match config_dir() {
+            Some(dir) => Config::load(&dir.join("config.toml"), false),
+            None => Ok(Config::default()),
+        },
+    }
+}
 
-    app.run().await
+/// `$XDG_CONFIG_HOME/c8rs`, falling back to `~/.config/c8rs`
+fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("c8rs"))
 }
 
 fn disassemble(args: DisassemblerArgs) -> Result<()> {
-    c8rs_disasm::disassemble(args)
+    c8rs_disasm::disassemble(args, read_rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_args() {
+        let Command::Run(args) =
+            Args::try_parse_from(["c8rs", "run", "--run", "--quirks", "vip", "pong.ch8"])
+                .unwrap()
+                .command
+        else {
+            panic!("expected the run subcommand");
+        };
+        assert!(args.run);
+        assert_eq!(args.quirks, Some(Variant::Chip8));
+
+        assert!(Args::try_parse_from(["c8rs", "run", "--paused", "pong.ch8"]).is_err());
+    }
+
+    #[test]
+    fn test_memory_size() {
+        assert_eq!(parse_memory_size("0x10000"), Ok(XO_MEM_SIZE));
+        assert_eq!(parse_memory_size("8192"), Ok(0x2000));
+        assert!(parse_memory_size("0x800").is_err());
+        assert!(parse_memory_size("0x1800").is_err());
+        assert!(parse_memory_size("64K").is_err());
+    }
+
+    /// Serve `body` to one request on localhost, returns the URL
+    #[cfg(feature = "download")]
+    fn serve_once(body: Vec<u8>) -> String {
+        use std::{io::Write, net::TcpListener};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            let header = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len());
+            let _ = stream.write_all(header.as_bytes());
+            let _ = stream.write_all(&body);
+        });
+        format!("http://{addr}/pong.ch8")
+    }
+
+    #[cfg(feature = "download")]
+    #[test]
+    fn test_download() {
+        let url = serve_once(vec![0x12, 0x00]);
+        assert_eq!(read_rom(&url).unwrap(), [0x12, 0x00]);
+
+        let url = serve_once(vec![0; MAX_DOWNLOAD_SIZE as usize + 1]);
+        let err = read_rom(&url).unwrap_err().to_string();
+        assert!(err.contains("limit"), "{err}");
+    }
+
+    #[test]
+    fn test_encode_png() {
+        // LD I, 0x204; DRW V0, V0, 1; sprite 0x81
+        let mut chip8 = c8rs_core::Chip8::new(&[0xA2, 0x04, 0xD0, 0x01, 0x81]);
+        chip8.step();
+        chip8.step();
+        chip8.present();
+        let png = encode_png(&chip8.display()).unwrap();
+
+        let mut reader = png::Decoder::new(std::io::Cursor::new(png))
+            .read_info()
+            .unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size().unwrap()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (64, 32));
+        assert_eq!(info.bit_depth, png::BitDepth::One);
+        assert_eq!(pixels[..8], [0b1000_0001, 0, 0, 0, 0, 0, 0, 0]);
+        assert!(pixels[8..].iter().all(|&byte| byte == 0));
+    }
 }
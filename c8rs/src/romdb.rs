@@ -0,0 +1,197 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, Result};
+use c8rs_core::{memory::XO_MEM_SIZE, Variant};
+use serde::Deserialize;
+
+use crate::config::Config;
+
+/// Keyboard keys given to the game controls a database entry names, the
+/// keypad keys they replace get the keyboard key the control had
+const CONTROL_KEYS: &[(&str, char)] = &[
+    ("up", 'w'),
+    ("left", 'a'),
+    ("down", 's'),
+    ("right", 'd'),
+    ("a", 'q'),
+    ("b", 'e'),
+    ("player2Up", 'i'),
+    ("player2Left", 'j'),
+    ("player2Down", 'k'),
+    ("player2Right", 'l'),
+];
+
+/// Per-game settings in the `programs.json` layout of the CHIP-8 Archive's
+/// chip-8-database: a list of programs, each with its ROMs keyed by SHA-1
+#[derive(Debug, Deserialize)]
+pub struct RomDatabase(Vec<Program>);
+
+#[derive(Debug, Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    roms: HashMap<String, RomInfo>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RomInfo {
+    #[serde(skip)]
+    pub title: String,
+    #[serde(default)]
+    pub platforms: Vec<String>,
+    /// Instructions per frame
+    pub tickrate: Option<u32>,
+    /// Keypad key of each game control
+    #[serde(default)]
+    pub keys: HashMap<String, u8>,
+    pub colors: Option<RomColors>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RomColors {
+    /// Background first, then the color of each plane combination
+    #[serde(default)]
+    pub pixels: Vec<String>,
+}
+
+impl RomDatabase {
+    /// Read a database, `None` if the file doesn't exist and isn't `required`
+    pub fn load(path: &Path, required: bool) -> Result<Option<RomDatabase>> {
+        match std::fs::read_to_string(path) {
+            Ok(s) => serde_json::from_str(&s)
+                .map(Some)
+                .map_err(|err| anyhow!("{}: {err}", path.display())),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound && !required => Ok(None),
+            Err(err) => Err(anyhow!("Failed to read {}: {err}", path.display())),
+        }
+    }
+
+    /// Settings for the ROM with lowercase hex SHA-1 `sha1`
+    pub fn find(&self, sha1: &str) -> Option<RomInfo> {
+        self.0.iter().find_map(|program| {
+            let (_, rom) = program
+                .roms
+                .iter()
+                .find(|(hash, _)| hash.eq_ignore_ascii_case(sha1))?;
+            Some(RomInfo {
+                title: program.title.clone(),
+                ..rom.clone()
+            })
+        })
+    }
+}
+
+impl RomInfo {
+    /// Override `config` with the settings the database has for this ROM
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(variant) = self.platforms.first().and_then(|p| platform(p)) {
+            config.quirks = Some(variant);
+            if variant == Variant::XoChip {
+                config.memory_size = Some(XO_MEM_SIZE);
+            }
+        }
+        if let Some(tickrate) = self.tickrate {
+            config.ips = Some(tickrate * 60);
+        }
+
+        let keys = &mut config.tui.keys;
+        for &(control, c) in CONTROL_KEYS {
+            let Some(&key) = self.keys.get(control).filter(|&&key| key <= 0xF) else {
+                continue;
+            };
+            let key = key as usize;
+            if let Some(other) = keys.iter().position(|&k| k == c) {
+                keys[other] = keys[key];
+            }
+            keys[key] = c;
+        }
+
+        let colors = self.colors.as_ref().map_or(&[][..], |c| &c.pixels[..]);
+        let slots = [
+            &mut config.tui.colors.pixel_off,
+            &mut config.tui.colors.pixel_on,
+            &mut config.tui.colors.plane2,
+            &mut config.tui.colors.planes_both,
+        ];
+        for (slot, color) in slots.into_iter().zip(colors) {
+            if let Ok(color) = color.parse() {
+                *slot = Some(color);
+            }
+        }
+    }
+}
+
+/// Quirk preset of a chip-8-database platform id
+fn platform(id: &str) -> Option<Variant> {
+    match id {
+        "originalChip8" | "hybridVIP" | "modernChip8" => Some(Variant::Chip8),
+        "chip48" | "superchip1" | "superchip" | "megachip8" => Some(Variant::Schip),
+        "xochip" => Some(Variant::XoChip),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use c8rs_tui::DEFAULT_KEYS;
+
+    #[test]
+    fn test_platform() {
+        assert_eq!(platform("originalChip8"), Some(Variant::Chip8));
+        assert_eq!(platform("modernChip8"), Some(Variant::Chip8));
+        assert_eq!(platform("superchip"), Some(Variant::Schip));
+        assert_eq!(platform("megachip8"), Some(Variant::Schip));
+        assert_eq!(platform("xochip"), Some(Variant::XoChip));
+        assert_eq!(platform("XOCHIP"), None);
+        assert_eq!(platform(""), None);
+    }
+
+    #[test]
+    fn test_apply() {
+        let info = RomInfo {
+            platforms: vec!["xochip".to_string(), "superchip".to_string()],
+            tickrate: Some(100),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        info.apply(&mut config);
+        assert_eq!(config.quirks, Some(Variant::XoChip));
+        assert_eq!(config.memory_size, Some(XO_MEM_SIZE));
+        assert_eq!(config.ips, Some(6000));
+
+        let info = RomInfo {
+            platforms: vec!["unknown".to_string(), "superchip".to_string()],
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        info.apply(&mut config);
+        assert_eq!(config.quirks, None);
+        assert_eq!(config.memory_size, None);
+    }
+
+    #[test]
+    fn test_apply_keys() {
+        let info = RomInfo {
+            // `up` on 4 swaps `w` from 5 with 4's `q`, `left` on A swaps `a`
+            // from 7 with A's `z`, `a` already has `q` on 5 and `right` is out
+            // of range
+            keys: HashMap::from([
+                ("up".to_string(), 4),
+                ("left".to_string(), 0xA),
+                ("a".to_string(), 5),
+                ("right".to_string(), 0x10),
+            ]),
+            ..Default::default()
+        };
+        let mut config = Config::default();
+        info.apply(&mut config);
+
+        let mut expected = DEFAULT_KEYS;
+        expected[4] = 'w';
+        expected[5] = 'q';
+        expected[0xA] = 'a';
+        expected[7] = 'z';
+        assert_eq!(config.tui.keys, expected);
+    }
+}
@@ -0,0 +1,285 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use c8rs_core::{
+    memory::MEM_SIZE,
+    screenshot::{self, ImageFormat},
+    Chip8, Quirks, Variant,
+};
+use clap::{Parser, ValueEnum};
+use clap_num::maybe_hex;
+
+/// ROMs run when none are given, with the screen they show when every test
+/// passes
+const BUNDLED: &[(&str, &[u8], &str)] = &[(
+    "test_opcode.ch8",
+    include_bytes!("../../roms/test_opcode.ch8"),
+    include_str!("../../roms/test_opcode.txt"),
+)];
+
+/// Timendus' test suite reads the platform to test from here, skipping its
+/// menu: 1 for CHIP-8, 2 for SUPER-CHIP and 3 for XO-CHIP
+const PLATFORM_ADDR: u16 = 0x1FF;
+
+#[derive(Parser, Debug)]
+pub struct TestArgs {
+    /// test ROMs, expected screens are read from `<name>.<preset>.txt` or
+    /// `<name>.txt` next to each ROM, as written by the `screenshot` debug
+    /// command. Runs the bundled ROMs if none are given.
+    roms: Vec<PathBuf>,
+
+    #[arg(long, value_enum, value_name = "PRESET", value_delimiter = ',')]
+    /// quirk presets to run each ROM with, defaults to all of them
+    quirks: Vec<Variant>,
+
+    #[arg(long, default_value_t = 300)]
+    /// frames to run each ROM for, unless it halts first
+    frames: u32,
+
+    #[arg(long, default_value_t = 700)]
+    /// instructions executed per second
+    ips: u32,
+
+    #[arg(long = "expect-mem", value_name = "ADDR=VALUE", value_parser = parse_mem_check)]
+    /// also require the byte at ADDR to equal VALUE afterwards, can be
+    /// repeated
+    mem_checks: Vec<(u16, u8)>,
+}
+
+fn parse_mem_check(s: &str) -> Result<(u16, u8), String> {
+    let (addr, value) = s.split_once('=').ok_or("expected ADDR=VALUE")?;
+    Ok((maybe_hex(addr.trim())?, maybe_hex(value.trim())?))
+}
+
+/// Outcome of one ROM with one quirk preset
+#[derive(Debug, Clone, PartialEq)]
+enum Outcome {
+    Pass,
+    Fail,
+    /// Nothing to check against
+    Untested,
+}
+
+struct TestRom {
+    name: String,
+    rom: Vec<u8>,
+    path: Option<PathBuf>,
+    bundled_screen: Option<&'static str>,
+}
+
+impl TestRom {
+    /// Expected screen when run with `preset`
+    fn expected_screen(&self, preset: Variant) -> Option<String> {
+        if let Some(screen) = self.bundled_screen {
+            return Some(screen.to_string());
+        }
+
+        let path = self.path.as_ref()?;
+        let name = preset.to_possible_value()?.get_name().to_string();
+        [format!("{name}.txt"), "txt".to_string()]
+            .iter()
+            .find_map(|ext| std::fs::read_to_string(path.with_extension(ext)).ok())
+    }
+}
+
+/// Run every ROM with every preset and print a compliance report, returns
+/// whether nothing failed
+pub fn run(args: TestArgs) -> Result<bool> {
+    let roms = if args.roms.is_empty() {
+        BUNDLED
+            .iter()
+            .map(|(name, rom, screen)| TestRom {
+                name: name.to_string(),
+                rom: rom.to_vec(),
+                path: None,
+                bundled_screen: Some(screen),
+            })
+            .collect()
+    } else {
+        args.roms
+            .iter()
+            .map(|path| {
+                Ok(TestRom {
+                    name: path.display().to_string(),
+                    rom: std::fs::read(path)
+                        .map_err(|err| anyhow!("Failed to read {}: {err}", path.display()))?,
+                    path: Some(path.clone()),
+                    bundled_screen: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    for rom in &roms {
+        crate::check_rom_size(&rom.rom, MEM_SIZE).map_err(|err| anyhow!("{}: {err}", rom.name))?;
+    }
+
+    let presets = if args.quirks.is_empty() {
+        Variant::value_variants().to_vec()
+    } else {
+        args.quirks.clone()
+    };
+    let preset_names: Vec<_> = presets
+        .iter()
+        .filter_map(|preset| Some(preset.to_possible_value()?.get_name().to_string()))
+        .collect();
+
+    let width = roms.iter().map(|rom| rom.name.len()).max().unwrap_or(0);
+    println!("{:width$}  {}", "ROM", preset_names.join("  "));
+
+    let mut passed = true;
+    for rom in &roms {
+        let mut row = format!("{:width$}", rom.name);
+        for (preset, name) in presets.iter().zip(&preset_names) {
+            let outcome = run_rom(rom, *preset, &args);
+            passed &= outcome != Outcome::Fail;
+
+            let result = match outcome {
+                Outcome::Pass => "pass",
+                Outcome::Fail => "FAIL",
+                Outcome::Untested => "-",
+            };
+            row.push_str(&format!("  {result:<len$}", len = name.len()));
+        }
+        println!("{}", row.trim_end());
+    }
+
+    Ok(passed)
+}
+
+fn run_rom(rom: &TestRom, preset: Variant, args: &TestArgs) -> Outcome {
+    let expected = rom.expected_screen(preset);
+    if expected.is_none() && args.mem_checks.is_empty() {
+        return Outcome::Untested;
+    }
+
+    let mut chip8 = Chip8::new(&rom.rom);
+    chip8.set_ips(args.ips);
+    chip8.cpu_mut().quirks = Quirks::for_variant(preset);
+    let platform = match preset {
+        Variant::Chip8 => 1,
+        Variant::Schip => 2,
+        Variant::XoChip => 3,
+    };
+    chip8.memory_mut().write_u8(PLATFORM_ADDR, platform);
+
+    for _ in 0..args.frames {
+        if chip8.frame() {
+            break;
+        }
+    }
+
+    let screen = screenshot::encode(&chip8.display(), ImageFormat::Text);
+    let screen_ok =
+        expected.is_none_or(|expected| expected.trim_end().as_bytes() == screen.trim_ascii_end());
+    let mem_ok = args
+        .mem_checks
+        .iter()
+        .all(|&(addr, value)| chip8.memory().read_u8(addr) == value);
+
+    if screen_ok && mem_ok {
+        Outcome::Pass
+    } else {
+        Outcome::Fail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args() -> TestArgs {
+        TestArgs {
+            roms: Vec::new(),
+            quirks: Vec::new(),
+            frames: 10,
+            ips: 700,
+            mem_checks: Vec::new(),
+        }
+    }
+
+    /// `rom` written to a temp file, to put expected screens next to
+    fn test_rom(name: &str, rom: &[u8]) -> TestRom {
+        let dir = std::env::temp_dir().join(format!("c8rs-suite-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, rom).unwrap();
+        TestRom {
+            name: name.to_string(),
+            rom: rom.to_vec(),
+            path: Some(path),
+            bundled_screen: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_mem_check() {
+        assert_eq!(parse_mem_check("0x300=0x12"), Ok((0x300, 0x12)));
+        assert_eq!(parse_mem_check(" 768 = 18 "), Ok((0x300, 0x12)));
+        assert!(parse_mem_check("0x300").is_err());
+        assert!(parse_mem_check("0x300=0x100").is_err());
+        assert!(parse_mem_check("x=1").is_err());
+    }
+
+    #[test]
+    fn test_expected_screen() {
+        let rom = test_rom("screens.ch8", &[0x12, 0x00]);
+        let path = rom.path.clone().unwrap();
+        assert_eq!(rom.expected_screen(Variant::Chip8), None);
+
+        std::fs::write(path.with_extension("txt"), "any").unwrap();
+        std::fs::write(path.with_extension("schip.txt"), "schip").unwrap();
+        assert_eq!(
+            rom.expected_screen(Variant::Schip).as_deref(),
+            Some("schip")
+        );
+        assert_eq!(rom.expected_screen(Variant::Chip8).as_deref(), Some("any"));
+
+        let bundled = TestRom {
+            bundled_screen: Some("bundled"),
+            ..rom
+        };
+        assert_eq!(
+            bundled.expected_screen(Variant::Schip).as_deref(),
+            Some("bundled")
+        );
+    }
+
+    #[test]
+    fn test_outcomes() {
+        // LD V0, 0x12; LD I, 0x300; LD [I], V0; JP self
+        let rom = [0x60, 0x12, 0xA3, 0x00, 0xF0, 0x55, 0x12, 0x06];
+        let untested = test_rom("outcomes.ch8", &rom);
+        assert_eq!(
+            run_rom(&untested, Variant::Chip8, &args()),
+            Outcome::Untested
+        );
+
+        let blank = test_rom("blank.ch8", &rom);
+        let screen = format!("{}\n", ".".repeat(64)).repeat(32);
+        std::fs::write(blank.path.as_ref().unwrap().with_extension("txt"), screen).unwrap();
+        assert_eq!(run_rom(&blank, Variant::Chip8, &args()), Outcome::Pass);
+
+        let lit = TestRom {
+            bundled_screen: Some("#"),
+            ..test_rom("lit.ch8", &rom)
+        };
+        assert_eq!(run_rom(&lit, Variant::Chip8, &args()), Outcome::Fail);
+
+        let mut args = args();
+        args.mem_checks = vec![(0x300, 0x12)];
+        assert_eq!(run_rom(&untested, Variant::Chip8, &args), Outcome::Pass);
+        args.mem_checks = vec![(0x300, 0x13)];
+        assert_eq!(run_rom(&untested, Variant::Chip8, &args), Outcome::Fail);
+    }
+
+    #[test]
+    fn test_oversized_rom() {
+        let rom = test_rom("oversized.ch8", &[0; MEM_SIZE]);
+        let args = TestArgs {
+            roms: vec![rom.path.unwrap()],
+            ..args()
+        };
+        let err = run(args).unwrap_err().to_string();
+        assert!(err.contains("ROM is 4096 bytes, at most 3584 fit"), "{err}");
+    }
+}